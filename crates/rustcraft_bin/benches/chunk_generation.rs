@@ -0,0 +1,101 @@
+// Chunk generation benchmarks; run with `cargo bench -p rustcraft_bin`
+// The crate is binary-only, so the generator modules are compiled straight into the bench
+
+#![allow(dead_code, unused_imports)]
+
+#[path = "../src/consts.rs"]
+mod consts;
+#[path = "../src/terrain/mod.rs"]
+mod terrain;
+#[path = "../src/core/thread_pool.rs"]
+mod thread_pool;
+
+use std::hint::black_box;
+use std::sync::{Arc, mpsc};
+
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+
+use crate::consts::CHUNK_SEED;
+use crate::terrain::{ChunkGenerator, ChunkPos, FlatWorldGenerator, NoiseSettings, WorldGenerator};
+use crate::thread_pool::ChunkGenThreadPool;
+
+/// Generator with its height and biome maps already built, so only per-chunk work is measured
+fn noise_generator() -> Arc<ChunkGenerator> {
+    let generator = ChunkGenerator::new(CHUNK_SEED, NoiseSettings::default());
+    generator.prepare();
+    Arc::new(generator)
+}
+
+fn single_chunk(c: &mut Criterion) {
+    let mut group = c.benchmark_group("generate_chunk");
+
+    let noise = noise_generator();
+    group.bench_function("noise", |b| {
+        let mut i = 0;
+        b.iter(|| {
+            // Walk across the map so caching can't flatter the numbers
+            i = (i + 1) % 32;
+            black_box(noise.generate(black_box(ChunkPos::new(i, i / 2))))
+        })
+    });
+
+    let flat = FlatWorldGenerator::default();
+    group.bench_function("flat", |b| b.iter(|| black_box(flat.generate(black_box(ChunkPos::new(3, 7))))));
+
+    group.finish();
+}
+
+/// Building the height and biome maps, sampling every column against every 4th
+fn height_map(c: &mut Criterion) {
+    let mut group = c.benchmark_group("height_map");
+    group.sample_size(10);
+
+    for sample_step in [1, 4] {
+        let settings = NoiseSettings {
+            sample_step,
+            ..Default::default()
+        };
+        group.bench_with_input(BenchmarkId::new("sample_step", sample_step), &settings, |b, &settings| {
+            b.iter(|| ChunkGenerator::new(CHUNK_SEED, settings).prepare())
+        });
+    }
+
+    group.finish();
+}
+
+/// A pregeneration-sized batch pushed through the generation pool, as `ChunkStorage` does
+fn pool_batch(c: &mut Criterion) {
+    let pool = ChunkGenThreadPool::new();
+    let generator = noise_generator();
+
+    let mut group = c.benchmark_group("generate_batch");
+    group.sample_size(10);
+
+    for radius in [4, 8] {
+        let area: Vec<ChunkPos> = (0..2 * radius)
+            .flat_map(|x| (0..2 * radius).map(move |z| ChunkPos::new(x, z)))
+            .collect();
+        group.throughput(Throughput::Elements(area.len() as u64));
+
+        group.bench_with_input(BenchmarkId::new("pool", area.len()), &area, |b, area| {
+            b.iter(|| {
+                let (tx, rx) = mpsc::channel();
+                for &pos in area {
+                    let generator = Arc::clone(&generator);
+                    let tx = tx.clone();
+                    pool.execute(move || {
+                        let _ = tx.send(generator.generate(pos));
+                    })
+                    .unwrap();
+                }
+                drop(tx);
+                assert_eq!(rx.iter().count(), area.len());
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, single_chunk, height_map, pool_batch);
+criterion_main!(benches);
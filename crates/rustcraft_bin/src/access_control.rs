@@ -0,0 +1,412 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use parking_lot::RwLock;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+pub const BANLIST_FILE: &str = "banlist.json";
+pub const WHITELIST_FILE: &str = "whitelist.json";
+pub const OPS_FILE: &str = "ops.json";
+
+pub const DEFAULT_BAN_REASON: &str = "Banned by an operator";
+/// Highest op level; the console always has it
+pub const MAX_OP_LEVEL: u8 = 4;
+/// Level `op <name>` grants when none is given, as in vanilla
+pub const DEFAULT_OP_LEVEL: u8 = 4;
+pub const NOT_WHITELISTED_REASON: &str = "You are not whitelisted on this server!";
+/// Permission checked with `permits` that exempts a player from the AFK kick
+pub const AFK_BYPASS_PERMISSION: &str = "afk-bypass";
+
+/// Disconnect reason shown to a banned player
+pub fn ban_message(reason: &str) -> String {
+    format!("You are banned from this server.\nReason: {}", reason)
+}
+
+/// One entry of `banlist.json`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BanEntry {
+    pub uuid:   Uuid,
+    pub name:   String,
+    pub reason: String,
+}
+
+/// One entry of `whitelist.json`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WhitelistEntry {
+    pub uuid: Uuid,
+    pub name: String,
+}
+
+/// One entry of `ops.json`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OpEntry {
+    pub uuid:  Uuid,
+    pub name:  String,
+    /// 1 bypasses spawn protection and the AFK kick, 2 cheats (`/tp`, `/give`, ...), 3 moderates
+    /// players, 4 manages the server
+    pub level: u8,
+}
+
+/// Op level needed to run `command` (its name, without the `/`), or to hold a permission such as
+/// `AFK_BYPASS_PERMISSION`
+/// Unknown commands need none, so they reach the parser and get its "Unknown command" reply
+pub fn requires_op(command: &str) -> u8 {
+    match command {
+        AFK_BYPASS_PERMISSION => 1,
+        "tp" | "teleport" | "give" | "weather" | "seed" => 2,
+        "kick" | "ban" | "pardon" | "whitelist" | "op" | "deop" => 3,
+        "stop" | "save-all" | "regen" => 4,
+        _ => 0,
+    }
+}
+
+/// Reply for a player whose op level is too low for `command`
+pub fn insufficient_permission(command: &str) -> String {
+    format!("Insufficient permission: /{} requires op level {}", command, requires_op(command))
+}
+
+/// Whether a player may join, decided once their username is known
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AccessDecision {
+    Allowed,
+    Banned { reason: String },
+    NotWhitelisted,
+}
+
+impl AccessDecision {
+    /// Disconnect reason shown to a refused player
+    pub fn reason(&self) -> Option<String> {
+        match self {
+            AccessDecision::Allowed => None,
+            AccessDecision::Banned { reason } => Some(ban_message(reason)),
+            AccessDecision::NotWhitelisted => Some(NOT_WHITELISTED_REASON.to_string()),
+        }
+    }
+}
+
+/// Ban list, whitelist and ops, loaded at startup and written back whenever a command changes them
+pub struct AccessControl {
+    ban_path:          PathBuf,
+    whitelist_path:    PathBuf,
+    ops_path:          PathBuf,
+    bans:              RwLock<Vec<BanEntry>>,
+    whitelist:         RwLock<Vec<WhitelistEntry>>,
+    ops:               RwLock<Vec<OpEntry>>,
+    /// Only whitelisted players may join while set
+    whitelist_enabled: bool,
+}
+
+impl AccessControl {
+    /// Read the lists from `dir`, treating a missing file as an empty list
+    pub fn load<P: AsRef<Path>>(dir: P, whitelist_enabled: bool) -> Result<Self> {
+        let dir = dir.as_ref();
+        let (ban_path, whitelist_path, ops_path) =
+            (dir.join(BANLIST_FILE), dir.join(WHITELIST_FILE), dir.join(OPS_FILE));
+        let bans: Vec<BanEntry> = read_list(&ban_path)?;
+        let whitelist: Vec<WhitelistEntry> = read_list(&whitelist_path)?;
+        let ops: Vec<OpEntry> = read_list(&ops_path)?;
+        tracing::info!(
+            "[ACCESS] {} ban(s), {} whitelisted player(s), {} op(s), whitelist {}",
+            bans.len(),
+            whitelist.len(),
+            ops.len(),
+            if whitelist_enabled { "on" } else { "off" }
+        );
+
+        Ok(Self {
+            ban_path,
+            whitelist_path,
+            ops_path,
+            bans: RwLock::new(bans),
+            whitelist: RwLock::new(whitelist),
+            ops: RwLock::new(ops),
+            whitelist_enabled,
+        })
+    }
+
+    /// Bans win over the whitelist; names match case-insensitively, as usernames do in game
+    pub fn check(&self, name: &str, uuid: Uuid) -> AccessDecision {
+        if let Some(ban) = self
+            .bans
+            .read()
+            .iter()
+            .find(|ban| ban.uuid == uuid || ban.name.eq_ignore_ascii_case(name))
+        {
+            return AccessDecision::Banned {
+                reason: ban.reason.clone(),
+            };
+        }
+
+        if self.whitelist_enabled
+            && !self
+                .whitelist
+                .read()
+                .iter()
+                .any(|entry| entry.uuid == uuid || entry.name.eq_ignore_ascii_case(name))
+        {
+            return AccessDecision::NotWhitelisted;
+        }
+
+        AccessDecision::Allowed
+    }
+
+    /// Ban a player, or update the reason of an existing ban; returns false if already banned
+    pub fn ban(&self, name: &str, uuid: Uuid, reason: &str) -> Result<bool> {
+        let mut bans = self.bans.write();
+        let added = match bans.iter_mut().find(|ban| ban.name.eq_ignore_ascii_case(name)) {
+            Some(ban) => {
+                ban.reason = reason.to_string();
+                false
+            }
+            None => {
+                bans.push(BanEntry {
+                    uuid,
+                    name: name.to_string(),
+                    reason: reason.to_string(),
+                });
+                true
+            }
+        };
+        write_list(&self.ban_path, &bans)?;
+        Ok(added)
+    }
+
+    /// Lift a ban; returns false if the player wasn't banned
+    pub fn pardon(&self, name: &str) -> Result<bool> {
+        let mut bans = self.bans.write();
+        let before = bans.len();
+        bans.retain(|ban| !ban.name.eq_ignore_ascii_case(name));
+        if bans.len() == before {
+            return Ok(false);
+        }
+        write_list(&self.ban_path, &bans)?;
+        Ok(true)
+    }
+
+    /// Returns false if the player was already whitelisted
+    pub fn whitelist_add(&self, name: &str, uuid: Uuid) -> Result<bool> {
+        let mut whitelist = self.whitelist.write();
+        if whitelist
+            .iter()
+            .any(|entry| entry.name.eq_ignore_ascii_case(name))
+        {
+            return Ok(false);
+        }
+        whitelist.push(WhitelistEntry {
+            uuid,
+            name: name.to_string(),
+        });
+        write_list(&self.whitelist_path, &whitelist)?;
+        Ok(true)
+    }
+
+    /// Returns false if the player wasn't whitelisted
+    pub fn whitelist_remove(&self, name: &str) -> Result<bool> {
+        let mut whitelist = self.whitelist.write();
+        let before = whitelist.len();
+        whitelist.retain(|entry| !entry.name.eq_ignore_ascii_case(name));
+        if whitelist.len() == before {
+            return Ok(false);
+        }
+        write_list(&self.whitelist_path, &whitelist)?;
+        Ok(true)
+    }
+
+    /// Op level of a player; 0 for everyone not in `ops.json`
+    pub fn op_level(&self, uuid: Uuid) -> u8 {
+        self.ops
+            .read()
+            .iter()
+            .find(|op| op.uuid == uuid)
+            .map_or(0, |op| op.level)
+    }
+
+    /// Whether a player at `uuid` may run `command`
+    pub fn permits(&self, uuid: Uuid, command: &str) -> bool {
+        self.op_level(uuid) >= requires_op(command)
+    }
+
+    /// Make a player an op at `level` (clamped to `MAX_OP_LEVEL`), or change their level
+    pub fn op(&self, name: &str, uuid: Uuid, level: u8) -> Result<()> {
+        let level = level.min(MAX_OP_LEVEL);
+        let mut ops = self.ops.write();
+        match ops.iter_mut().find(|op| op.uuid == uuid) {
+            Some(op) => {
+                op.name = name.to_string();
+                op.level = level;
+            }
+            None => {
+                ops.push(OpEntry {
+                    uuid,
+                    name: name.to_string(),
+                    level,
+                })
+            }
+        }
+        write_list(&self.ops_path, &ops)
+    }
+
+    /// Matched by UUID like `op`, so a renamed op is still found; returns false if they weren't one
+    pub fn deop(&self, uuid: Uuid) -> Result<bool> {
+        let mut ops = self.ops.write();
+        let before = ops.len();
+        ops.retain(|op| op.uuid != uuid);
+        if ops.len() == before {
+            return Ok(false);
+        }
+        write_list(&self.ops_path, &ops)?;
+        Ok(true)
+    }
+}
+
+fn read_list<T: DeserializeOwned>(path: &Path) -> Result<Vec<T>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let contents = std::fs::read_to_string(path)?;
+    Ok(serde_json::from_str(&contents)?)
+}
+
+fn write_list<T: Serialize>(path: &Path, list: &[T]) -> Result<()> {
+    // Write then rename, so a crash mid-save leaves the previous list intact
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, serde_json::to_string_pretty(list)?)?;
+    std::fs::rename(tmp, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lists(whitelist_enabled: bool) -> (AccessControl, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("rustcraft_access_{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let access = AccessControl::load(&dir, whitelist_enabled).unwrap();
+        (access, dir)
+    }
+
+    #[test]
+    fn test_empty_lists_allow_everyone_unless_whitelisting() {
+        let (open, dir) = lists(false);
+        assert_eq!(open.check("Steve", Uuid::new_v4()), AccessDecision::Allowed);
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let (closed, dir) = lists(true);
+        assert_eq!(closed.check("Steve", Uuid::new_v4()), AccessDecision::NotWhitelisted);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_bans_match_name_or_uuid() {
+        let (access, dir) = lists(false);
+        let steve = Uuid::new_v4();
+
+        assert!(access.ban("Steve", steve, "Griefing").unwrap());
+        let banned = AccessDecision::Banned {
+            reason: "Griefing".to_string(),
+        };
+        assert_eq!(access.check("Steve", steve), banned);
+        assert_eq!(access.check("sTEVE", Uuid::new_v4()), banned);
+        assert_eq!(access.check("Renamed", steve), banned);
+        assert_eq!(access.check("Alex", Uuid::new_v4()), AccessDecision::Allowed);
+        assert!(banned.reason().unwrap().contains("Griefing"));
+
+        assert!(!access.ban("steve", steve, "Still griefing").unwrap());
+        assert!(access.pardon("STEVE").unwrap());
+        assert!(!access.pardon("Steve").unwrap());
+        assert_eq!(access.check("Steve", steve), AccessDecision::Allowed);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_ban_beats_whitelist() {
+        let (access, dir) = lists(true);
+        let (alex, steve) = (Uuid::new_v4(), Uuid::new_v4());
+
+        assert!(access.whitelist_add("Alex", alex).unwrap());
+        assert!(access.whitelist_add("Steve", steve).unwrap());
+        assert!(!access.whitelist_add("alex", alex).unwrap());
+        assert_eq!(access.check("alex", alex), AccessDecision::Allowed);
+
+        access.ban("Steve", steve, DEFAULT_BAN_REASON).unwrap();
+        assert!(matches!(access.check("Steve", steve), AccessDecision::Banned { .. }));
+
+        assert!(access.whitelist_remove("Alex").unwrap());
+        assert!(!access.whitelist_remove("Alex").unwrap());
+        assert_eq!(access.check("Alex", alex), AccessDecision::NotWhitelisted);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_lists_persist() {
+        let (access, dir) = lists(true);
+        let steve = Uuid::new_v4();
+        access.ban("Griefer", Uuid::new_v4(), "Lava").unwrap();
+        access.whitelist_add("Steve", steve).unwrap();
+        access.op("Steve", steve, 3).unwrap();
+        drop(access);
+        // Each list was renamed into place, with no temp file left behind
+        for entry in std::fs::read_dir(&dir).unwrap() {
+            assert_eq!(entry.unwrap().path().extension().unwrap(), "json");
+        }
+
+        let reloaded = AccessControl::load(&dir, true).unwrap();
+        assert_eq!(reloaded.check("Steve", steve), AccessDecision::Allowed);
+        assert_eq!(reloaded.op_level(steve), 3);
+        assert_eq!(
+            reloaded.check("griefer", Uuid::new_v4()),
+            AccessDecision::Banned {
+                reason: "Lava".to_string(),
+            }
+        );
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_permission_gate_by_op_level() {
+        let (access, dir) = lists(false);
+        let (player, moderator, admin) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        access.op("Mod", moderator, 3).unwrap();
+        access.op("Admin", admin, 9).unwrap();
+        assert_eq!(access.op_level(admin), MAX_OP_LEVEL);
+
+        for (command, allowed) in [
+            ("list", [true, true, true]),
+            ("fly", [true, true, true]),
+            ("tp", [false, true, true]),
+            ("weather", [false, true, true]),
+            ("seed", [false, true, true]),
+            ("ban", [false, true, true]),
+            ("stop", [false, false, true]),
+            ("regen", [false, false, true]),
+            (AFK_BYPASS_PERMISSION, [false, true, true]),
+        ] {
+            for (uuid, allowed) in [player, moderator, admin].into_iter().zip(allowed) {
+                assert_eq!(
+                    access.permits(uuid, command),
+                    allowed,
+                    "/{} at level {}",
+                    command,
+                    access.op_level(uuid)
+                );
+            }
+        }
+
+        // Lowering and removing ops takes effect straight away
+        access.op("Admin", admin, 2).unwrap();
+        assert!(!access.permits(admin, "stop"));
+        assert!(access.permits(admin, "give"));
+        assert!(access.deop(moderator).unwrap());
+        assert!(!access.deop(moderator).unwrap());
+        assert!(!access.permits(moderator, "tp"));
+        assert_eq!(insufficient_permission("tp"), "Insufficient permission: /tp requires op level 2");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -1,50 +1,96 @@
 #![allow(dead_code)]
 
 use std::collections::{HashMap, VecDeque};
-use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::{AtomicU64, AtomicUsize};
 use std::time::{Duration, Instant};
 
+/// Usage below which a grown cache starts counting towards a shrink
+pub(crate) const SHRINK_USAGE_THRESHOLD: f32 = 0.25;
+/// How long usage must stay below the threshold before each halving
+pub(crate) const SHRINK_AFTER: Duration = Duration::from_secs(300);
+
 #[derive(Debug)]
 struct CacheEntry<V> {
     value:          V,
     hits:           AtomicUsize,
     last_hit_reset: Instant,
+    /// Microseconds since the cache's `created_at` when this entry was last inserted/refreshed
+    last_access_us: AtomicU64,
 }
 
 /// LRU (Least Recently Used) Cache with dynamic growth and hit counting
 pub struct LruCache<K: Clone + Eq + std::hash::Hash, V: Sized> {
     current_capacity:   usize,
+    /// Floor for `try_shrink`
+    initial_capacity:   usize,
     max_capacity:       usize,
+    /// When usage last dropped below `SHRINK_USAGE_THRESHOLD`, while it stays there
+    low_usage_since:    Option<Instant>,
     cache:              HashMap<K, CacheEntry<V>>, // DashMap<K, CacheEntry<V>>,
     access_order:       VecDeque<K>,
     item_size:          usize,
     hit_reset_interval: Duration,
+    total_hits:         AtomicUsize,
+    total_misses:       AtomicUsize,
+    /// Entries not accessed for longer than this are expired, regardless of capacity
+    ttl:                Option<Duration>,
+    refresh_on_get:     bool,
+    created_at:         Instant,
 }
 
 impl<K: Clone + Eq + std::hash::Hash, V> LruCache<K, V> {
     pub fn new(initial_capacity: usize) -> Self {
         Self {
-            current_capacity:   initial_capacity,
-            max_capacity:       initial_capacity,
-            cache:              HashMap::new(), // dashmap::DashMap::new(),
-            access_order:       VecDeque::new(),
-            item_size:          0,
+            current_capacity: initial_capacity,
+            initial_capacity,
+            max_capacity: initial_capacity,
+            low_usage_since: None,
+            cache: HashMap::new(), // dashmap::DashMap::new(),
+            access_order: VecDeque::new(),
+            item_size: 0,
             hit_reset_interval: Duration::from_secs(300), // 5 minutes
+            total_hits: AtomicUsize::new(0),
+            total_misses: AtomicUsize::new(0),
+            ttl: None,
+            refresh_on_get: true,
+            created_at: Instant::now(),
         }
     }
 
     pub fn with_growth(initial_capacity: usize, max_capacity: usize, item_size: usize) -> Self {
         Self {
             current_capacity: initial_capacity,
+            initial_capacity,
             max_capacity,
+            low_usage_since: None,
             cache: HashMap::new(),
             // cache: DashMap::new(),
             access_order: VecDeque::new(),
             item_size,
             hit_reset_interval: Duration::from_secs(300), // 5 minutes
+            total_hits: AtomicUsize::new(0),
+            total_misses: AtomicUsize::new(0),
+            ttl: None,
+            refresh_on_get: true,
+            created_at: Instant::now(),
         }
     }
 
+    /// Fixed-capacity cache whose entries also expire after `ttl` without access
+    pub fn with_ttl(capacity: usize, ttl: Duration) -> Self {
+        Self {
+            ttl: Some(ttl),
+            ..Self::new(capacity)
+        }
+    }
+
+    /// Whether `get` resets an entry's age (defaults to true)
+    /// When false, entries expire `ttl` after they were inserted
+    pub fn refresh_on_get(mut self, refresh: bool) -> Self {
+        self.refresh_on_get = refresh;
+        self
+    }
+
     pub fn try_expand(&mut self) -> bool {
         if self.current_capacity < self.max_capacity && self.item_size > 0 {
             let new_capacity = std::cmp::min(self.current_capacity * 2, self.max_capacity);
@@ -56,9 +102,50 @@ impl<K: Clone + Eq + std::hash::Hash, V> LruCache<K, V> {
         false
     }
 
+    /// Halve capacity, down to the initial capacity, once usage has stayed below
+    /// `SHRINK_USAGE_THRESHOLD` for `SHRINK_AFTER`; meant to be called periodically
+    /// Returns whether it shrank and the keys evicted (fewest hits first) to fit
+    pub fn try_shrink(&mut self) -> (bool, Vec<K>) {
+        self.try_shrink_at(Instant::now())
+    }
+
+    fn try_shrink_at(&mut self, now: Instant) -> (bool, Vec<K>) {
+        if self.usage_ratio() >= SHRINK_USAGE_THRESHOLD {
+            self.low_usage_since = None;
+            return (false, Vec::new());
+        }
+
+        let since = *self.low_usage_since.get_or_insert(now);
+        let new_capacity = std::cmp::max(self.current_capacity / 2, self.initial_capacity);
+        if now.saturating_duration_since(since) < SHRINK_AFTER || new_capacity >= self.current_capacity {
+            return (false, Vec::new());
+        }
+
+        let mut evicted = Vec::new();
+        while self.cache.len() > new_capacity
+            && let Some(key) = self.evict_lowest_hits()
+        {
+            evicted.push(key);
+        }
+        self.current_capacity = new_capacity;
+        // The next halving needs another full period of low usage
+        self.low_usage_since = Some(now);
+        (true, evicted)
+    }
+
     pub fn get(&self, key: &K) -> Option<&V> {
-        if let Some(guard) = self.cache.get(key) {
+        self.get_at(key, Instant::now())
+    }
+
+    pub(crate) fn get_at(&self, key: &K, now: Instant) -> Option<&V> {
+        if let Some(guard) = self.cache.get(key).filter(|e| !self.is_expired(e, now)) {
             guard.hits.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            if self.refresh_on_get {
+                guard
+                    .last_access_us
+                    .store(self.micros_since_creation(now), std::sync::atomic::Ordering::Relaxed);
+            }
+            self.total_hits.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
             {
                 let mut order = self.access_order.clone();
                 order.retain(|k| k != key);
@@ -66,6 +153,8 @@ impl<K: Clone + Eq + std::hash::Hash, V> LruCache<K, V> {
             }
             Some(&guard.value)
         } else {
+            self.total_misses
+                .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
             None
         }
         // if self.cache.contains_key(key) {
@@ -85,6 +174,13 @@ impl<K: Clone + Eq + std::hash::Hash, V> LruCache<K, V> {
     }
 
     pub fn insert(&mut self, key: K, value: V) -> (Option<V>, bool, Option<K>) {
+        self.insert_at(key, value, Instant::now())
+    }
+
+    pub(crate) fn insert_at(&mut self, key: K, value: V, now: Instant) -> (Option<V>, bool, Option<K>) {
+        // Expired entries go first so they don't count against capacity
+        self.evict_expired_at(now);
+
         // Remove if already exists
         if self.cache.contains_key(&key) {
             self.access_order.retain(|k| k != &key);
@@ -106,19 +202,61 @@ impl<K: Clone + Eq + std::hash::Hash, V> LruCache<K, V> {
         }
 
         self.access_order.push_back(key.clone());
-        let now = Instant::now();
         let old_value = self.cache.insert(
             key,
             CacheEntry {
                 value,
                 hits: AtomicUsize::new(0),
                 last_hit_reset: now,
+                last_access_us: AtomicU64::new(self.micros_since_creation(now)),
             },
         );
 
         (old_value.map(|e| e.value), expanded, evicted_key)
     }
 
+    /// Remove every entry older than the TTL, returning the evicted keys
+    /// No-op when the cache has no TTL
+    pub fn evict_expired(&mut self) -> Vec<K> {
+        self.evict_expired_at(Instant::now())
+    }
+
+    fn evict_expired_at(&mut self, now: Instant) -> Vec<K> {
+        if self.ttl.is_none() {
+            return Vec::new();
+        }
+
+        let expired: Vec<K> = self
+            .cache
+            .iter()
+            .filter(|(_, e)| self.is_expired(e, now))
+            .map(|(k, _)| k.clone())
+            .collect();
+
+        for key in &expired {
+            self.access_order.retain(|k| k != key);
+            self.cache.remove(key);
+        }
+        expired
+    }
+
+    pub fn ttl(&self) -> Option<Duration> {
+        self.ttl
+    }
+
+    fn is_expired(&self, entry: &CacheEntry<V>, now: Instant) -> bool {
+        let Some(ttl) = self.ttl else {
+            return false;
+        };
+        let last_access = entry.last_access_us.load(std::sync::atomic::Ordering::Relaxed);
+        let age_us = self.micros_since_creation(now).saturating_sub(last_access);
+        age_us > ttl.as_micros() as u64
+    }
+
+    fn micros_since_creation(&self, now: Instant) -> u64 {
+        now.saturating_duration_since(self.created_at).as_micros() as u64
+    }
+
     pub fn contains(&self, key: &K) -> bool {
         self.cache.contains_key(key)
     }
@@ -202,6 +340,16 @@ impl<K: Clone + Eq + std::hash::Hash, V> LruCache<K, V> {
         }
     }
 
+    /// Total lookups served from the cache since creation
+    pub fn total_hits(&self) -> usize {
+        self.total_hits.load(std::sync::atomic::Ordering::Relaxed)
+    }
+
+    /// Total lookups that missed the cache since creation
+    pub fn total_misses(&self) -> usize {
+        self.total_misses.load(std::sync::atomic::Ordering::Relaxed)
+    }
+
     pub fn get_hit_count(&self, key: &K) -> Option<usize> {
         self.cache
             .get(key)
@@ -266,6 +414,137 @@ mod tests {
         assert!(cache.contains(&3));
     }
 
+    #[test]
+    fn test_cache_shrinks_after_sustained_low_usage() {
+        let mut cache = LruCache::with_growth(2, 16, 1);
+        for i in 0..9 {
+            cache.insert(i, i);
+        }
+        assert_eq!(cache.current_capacity(), 16);
+
+        // Busy enough: never shrinks
+        let start = Instant::now();
+        assert!(!cache.try_shrink_at(start).0);
+        assert!(!cache.try_shrink_at(start + SHRINK_AFTER * 2).0);
+
+        for i in 1..9 {
+            cache.remove(&i);
+        }
+        // Low usage starts the clock, and a brief dip isn't enough
+        let low = start + SHRINK_AFTER * 3;
+        assert!(!cache.try_shrink_at(low).0);
+        assert!(!cache.try_shrink_at(low + SHRINK_AFTER / 2).0);
+
+        let (shrunk, evicted) = cache.try_shrink_at(low + SHRINK_AFTER);
+        assert!(shrunk);
+        assert!(evicted.is_empty());
+        assert_eq!(cache.current_capacity(), 8);
+        assert!(cache.contains(&0));
+
+        // Each further halving waits for another period
+        assert!(!cache.try_shrink_at(low + SHRINK_AFTER + SHRINK_AFTER / 2).0);
+        assert!(cache.try_shrink_at(low + SHRINK_AFTER * 2).0);
+        assert_eq!(cache.current_capacity(), 4);
+        // 1 of 4 is no longer below the threshold
+        assert!(!cache.try_shrink_at(low + SHRINK_AFTER * 10).0);
+        assert_eq!(cache.current_capacity(), 4);
+
+        // Grows again under load
+        for i in 1..6 {
+            cache.insert(i, i);
+        }
+        assert_eq!(cache.current_capacity(), 8);
+        assert_eq!(cache.len(), 6);
+    }
+
+    #[test]
+    fn test_cache_never_shrinks_below_initial_capacity() {
+        let mut cache = LruCache::<i32, i32>::with_growth(4, 16, 1);
+        let start = Instant::now();
+        assert!(!cache.try_shrink_at(start).0);
+        assert!(!cache.try_shrink_at(start + SHRINK_AFTER * 2).0);
+        assert_eq!(cache.current_capacity(), 4);
+    }
+
+    #[test]
+    fn test_total_hits_and_misses() {
+        let mut cache = LruCache::new(4);
+        cache.insert(1, "a");
+
+        cache.get(&1);
+        cache.get(&1);
+        cache.get(&2);
+
+        assert_eq!(cache.total_hits(), 2);
+        assert_eq!(cache.total_misses(), 1);
+    }
+
+    #[test]
+    fn test_ttl_evict_expired() {
+        let mut cache = LruCache::with_ttl(8, Duration::from_secs(10));
+        let start = Instant::now();
+
+        cache.insert_at(1, "a", start);
+        cache.insert_at(2, "b", start + Duration::from_secs(5));
+
+        assert!(cache.evict_expired_at(start + Duration::from_secs(9)).is_empty());
+
+        let evicted = cache.evict_expired_at(start + Duration::from_secs(11));
+        assert_eq!(evicted, vec![1]);
+        assert!(!cache.contains(&1));
+        assert!(cache.contains(&2));
+    }
+
+    #[test]
+    fn test_ttl_expired_entries_miss_and_free_capacity() {
+        let mut cache = LruCache::with_ttl(2, Duration::from_secs(10));
+        let start = Instant::now();
+
+        cache.insert_at(1, "a", start);
+        cache.insert_at(2, "b", start);
+        assert_eq!(cache.get_at(&1, start + Duration::from_secs(11)), None);
+
+        // Both entries expired, so inserting doesn't evict by hit count
+        let later = start + Duration::from_secs(11);
+        let (_, expanded, evicted) = cache.insert_at(3, "c", later);
+        assert!(!expanded);
+        assert_eq!(evicted, None);
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn test_ttl_get_refresh() {
+        let start = Instant::now();
+
+        let mut refreshing = LruCache::with_ttl(4, Duration::from_secs(10));
+        refreshing.insert_at(1, "a", start);
+        assert!(refreshing.get_at(&1, start + Duration::from_secs(8)).is_some());
+        assert!(
+            refreshing
+                .evict_expired_at(start + Duration::from_secs(15))
+                .is_empty()
+        );
+
+        let mut fixed = LruCache::with_ttl(4, Duration::from_secs(10)).refresh_on_get(false);
+        fixed.insert_at(1, "a", start);
+        assert!(fixed.get_at(&1, start + Duration::from_secs(8)).is_some());
+        assert_eq!(fixed.evict_expired_at(start + Duration::from_secs(15)), vec![1]);
+    }
+
+    #[test]
+    fn test_no_ttl_never_expires() {
+        let mut cache = LruCache::new(4);
+        let start = Instant::now();
+        cache.insert_at(1, "a", start);
+
+        assert!(
+            cache
+                .evict_expired_at(start + Duration::from_secs(3600))
+                .is_empty()
+        );
+        assert_eq!(cache.get_at(&1, start + Duration::from_secs(3600)), Some(&"a"));
+    }
+
     #[test]
     fn test_hit_count_eviction() {
         let mut cache = LruCache::with_growth(2, 2, 1);
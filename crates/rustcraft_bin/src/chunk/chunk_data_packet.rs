@@ -2,15 +2,32 @@
 
 use anyhow::Result;
 use bytes::BytesMut;
-use tokio::io::AsyncWriteExt;
-use tokio::net::TcpStream;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
 
-use crate::network::{ByteWritable, PacketWriter, write_varint};
+use crate::consts::TERRAIN_SECTION_HEIGHT;
+use crate::network::{ByteWritable, ClientboundPlay, PacketWriter, write_varint};
 use crate::terrain::{BlockType, Chunk};
 
 /// Send a single chunk to the client using the Chunk Data packet
 /// This is the primary packet for sending terrain data
-pub async fn send_chunk_data_packet(socket: &mut TcpStream, chunk: &Chunk) -> Result<()> {
+pub async fn send_chunk_data_packet<S>(socket: &mut S, chunk: &Chunk) -> Result<()>
+where
+    S: AsyncWrite + Unpin,
+{
+    let frame = chunk_data_frame(chunk);
+
+    #[cfg(feature = "dev-sdk")]
+    let _ = &crate::LOGGER.log_server_packet(&frame);
+
+    socket.write_all(&frame).await?;
+    socket.flush().await?;
+
+    tracing::debug!("[CHUNK] Sent chunk data packet for ({}, {})", chunk.pos.x, chunk.pos.z);
+    Ok(())
+}
+
+/// Chunk Data frame for `chunk`, for queueing to players other than the one being handled
+pub fn chunk_data_frame(chunk: &Chunk) -> Vec<u8> {
     let mut writer = PacketWriter::new();
 
     // Chunk X coordinate
@@ -30,7 +47,7 @@ pub async fn send_chunk_data_packet(socket: &mut TcpStream, chunk: &Chunk) -> Re
     writer.write_bytes(&chunk_data_nbt);
 
     let packet_data = writer.finish();
-    let packet_id = write_varint(0x20); // Chunk Data packet ID in Play state (0x20 or 0x27)
+    let packet_id = write_varint(ClientboundPlay::ChunkDataAndUpdateLight.id());
     let packet_length = (packet_id.len() + packet_data.len()) as i32;
 
     // Write packet: [length][id][data]
@@ -38,15 +55,7 @@ pub async fn send_chunk_data_packet(socket: &mut TcpStream, chunk: &Chunk) -> Re
     frame.extend_from_slice(&write_varint(packet_length));
     frame.extend_from_slice(&packet_id);
     frame.extend_from_slice(&packet_data);
-
-    #[cfg(feature = "dev-sdk")]
-    let _ = &crate::LOGGER.log_server_packet(&frame);
-
-    socket.write_all(&frame).await?;
-    socket.flush().await?;
-
-    tracing::debug!("[CHUNK] Sent chunk data packet for ({}, {})", chunk.pos.x, chunk.pos.z);
-    Ok(())
+    frame
 }
 
 /// Create a minimal heightmap NBT compound
@@ -137,26 +146,14 @@ fn serialize_heightmap(_chunk: &Chunk) -> Vec<u8> {
 }
 
 /// Check if a chunk section (16x16x16 blocks) contains any non-air blocks
-fn has_section_data(chunk: &Chunk, section_y: usize) -> bool {
-    let base_y = section_y * 16;
-    for x in 0..16 {
-        for y in base_y..base_y + 16 {
-            for z in 0..16 {
-                let Some(block) = chunk.get_block(x, y, z) else {
-                    continue;
-                };
-                if block != BlockType::Air {
-                    return true;
-                }
-            }
-        }
-    }
-    false
+/// `section_index` counts from the bottom of the world, so section 0 is section Y -4
+fn has_section_data(chunk: &Chunk, section_index: usize) -> bool {
+    !chunk.is_section_empty(section_index)
 }
 
 /// Build a palette of block IDs present in this section
-fn build_palette(chunk: &Chunk, section_y: usize) -> Vec<i32> {
-    let base_y = section_y * 16;
+fn build_palette(chunk: &Chunk, section_index: usize) -> Vec<i32> {
+    let base_y = section_index * TERRAIN_SECTION_HEIGHT;
     let mut palette = vec![0i32]; // Air is always at index 0
     let mut seen = std::collections::HashSet::new();
     seen.insert(0i32);
@@ -164,8 +161,8 @@ fn build_palette(chunk: &Chunk, section_y: usize) -> Vec<i32> {
     for x in 0..16 {
         for y in base_y..base_y + 16 {
             for z in 0..16 {
-                if let Some(block) = chunk.get_block(x, y, z) {
-                    let block_id = block_type_to_id(block);
+                if let Some(block) = chunk.get_block_state(x, y, z) {
+                    let block_id = block.state_id();
                     if !seen.contains(&block_id) && block_id != 0 {
                         palette.push(block_id);
                         seen.insert(block_id);
@@ -179,6 +176,7 @@ fn build_palette(chunk: &Chunk, section_y: usize) -> Vec<i32> {
 }
 
 /// Convert block type to Minecraft block state ID
+/// Legacy 1.12-era numeric ids, kept for reference; serialization uses `BlockState::state_id`
 fn block_type_to_id(block: BlockType) -> i32 {
     // This maps our BlockType enum to Minecraft block state IDs
     match block {
@@ -194,5 +192,6 @@ fn block_type_to_id(block: BlockType) -> i32 {
         BlockType::Lava => 10,
         BlockType::Sand => 12,
         BlockType::Gravel => 13,
+        BlockType::Bedrock => 7,
     }
 }
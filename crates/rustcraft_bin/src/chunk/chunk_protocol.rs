@@ -2,16 +2,16 @@
 
 use bytes::BytesMut;
 
-use crate::network::{ByteWritable, PacketWriter};
-use crate::terrain::{BlockType, Chunk};
+use crate::consts::{TERRAIN_SECTION_COUNT, TERRAIN_SECTION_HEIGHT};
+use crate::network::{ByteWritable, ClientboundPlay, PacketWriter};
+use crate::terrain::{BlockState, BlockType, Chunk};
 
 /// Serialize a chunk into Minecraft protocol format (chunk data packet)
 /// This creates a basic chunk data packet that clients can render
 pub fn serialize_chunk(chunk: &Chunk) -> BytesMut {
     let mut writer = PacketWriter::new();
 
-    // Packet ID for Chunk Data packet (0x20 in Play state)
-    writer.write_varint(0x20);
+    writer.write_varint(ClientboundPlay::ChunkDataAndUpdateLight.id());
 
     // Chunk X coordinate
     writer.write_int(chunk.pos.x);
@@ -22,12 +22,12 @@ pub fn serialize_chunk(chunk: &Chunk) -> BytesMut {
     // Full chunk flag (true = full chunk with all sections)
     writer.write_bool(true);
 
-    // Primary Bit Mask - which sections are included (16 sections, bottom to top)
+    // Primary Bit Mask - which sections are included (bottom to top)
     // For now, send all sections that have data
-    let mut bitmask = 0u16;
-    for y in 0..16 {
-        if has_section_data(chunk, y) {
-            bitmask |= 1 << y;
+    let mut bitmask = 0u32;
+    for index in 0..TERRAIN_SECTION_COUNT {
+        if has_section_data(chunk, index) {
+            bitmask |= 1 << index;
         }
     }
     writer.write_varint(bitmask as i32);
@@ -41,13 +41,15 @@ pub fn serialize_chunk(chunk: &Chunk) -> BytesMut {
     writer.write_varint(0); // 0 biomes
 
     // Data section count (number of chunk sections with data)
-    let section_count = (0..16).filter(|&y| has_section_data(chunk, y)).count();
+    let section_count = (0..TERRAIN_SECTION_COUNT)
+        .filter(|&index| has_section_data(chunk, index))
+        .count();
     writer.write_varint(section_count as i32);
 
     // Serialize each section that has data
-    for section_y in 0..16 {
-        if has_section_data(chunk, section_y) {
-            serialize_section(&mut writer, chunk, section_y);
+    for index in 0..TERRAIN_SECTION_COUNT {
+        if has_section_data(chunk, index) {
+            serialize_section(&mut writer, chunk, index);
         }
     }
 
@@ -55,26 +57,14 @@ pub fn serialize_chunk(chunk: &Chunk) -> BytesMut {
 }
 
 /// Check if a chunk section (16x16x16 blocks) contains any non-air blocks
-fn has_section_data(chunk: &Chunk, section_y: usize) -> bool {
-    let base_y = section_y * 16;
-    for x in 0..16 {
-        for y in base_y..base_y + 16 {
-            for z in 0..16 {
-                let Some(block) = chunk.get_block(x, y, z) else {
-                    continue;
-                };
-                if block != BlockType::Air {
-                    return true;
-                }
-            }
-        }
-    }
-    false
+/// `section_index` counts from the bottom of the world, so section 0 is section Y -4
+fn has_section_data(chunk: &Chunk, section_index: usize) -> bool {
+    !chunk.is_section_empty(section_index)
 }
 
 /// Serialize a 16x16x16 section of blocks using Minecraft's block state palette format
-fn serialize_section(writer: &mut PacketWriter, chunk: &Chunk, section_y: usize) {
-    let base_y = section_y * 16;
+fn serialize_section(writer: &mut PacketWriter, chunk: &Chunk, section_index: usize) {
+    let base_y = section_index * TERRAIN_SECTION_HEIGHT;
 
     // Block count (number of non-air blocks) - simplified
     let mut block_count = 0i16;
@@ -95,21 +85,21 @@ fn serialize_section(writer: &mut PacketWriter, chunk: &Chunk, section_y: usize)
     // Palette (block state mapping)
     // Minecraft uses a palette system where block IDs are mapped to indices
     // For simplicity, we use a direct mapping
-    let palette = build_palette(chunk, section_y);
+    let palette = build_palette(chunk, section_index);
     writer.write_varint(palette.len() as i32);
     for block_id in &palette {
         writer.write_varint(*block_id);
     }
 
     // Data array (which palette index for each block)
-    let data = encode_block_data(chunk, section_y, &palette);
+    let data = encode_block_data(chunk, section_index, &palette);
     writer.write_varint((data.len() / 8) as i32); // Size in longs
     writer.write_bytes(&data);
 }
 
 /// Build a palette of block IDs present in this section
-fn build_palette(chunk: &Chunk, section_y: usize) -> Vec<i32> {
-    let base_y = section_y * 16;
+fn build_palette(chunk: &Chunk, section_index: usize) -> Vec<i32> {
+    let base_y = section_index * TERRAIN_SECTION_HEIGHT;
     let mut palette = vec![0i32]; // Air is always at index 0
     let mut seen = std::collections::HashSet::new();
     seen.insert(0i32);
@@ -117,8 +107,8 @@ fn build_palette(chunk: &Chunk, section_y: usize) -> Vec<i32> {
     for x in 0..16 {
         for y in base_y..base_y + 16 {
             for z in 0..16 {
-                if let Some(block) = chunk.get_block(x, y, z) {
-                    let block_id = block_type_to_id(block);
+                if let Some(block) = chunk.get_block_state(x, y, z) {
+                    let block_id = block.state_id();
                     if !seen.contains(&block_id) && block_id != 0 {
                         palette.push(block_id);
                         seen.insert(block_id);
@@ -132,16 +122,15 @@ fn build_palette(chunk: &Chunk, section_y: usize) -> Vec<i32> {
 }
 
 /// Encode block data as a byte array with palette indices
-fn encode_block_data(chunk: &Chunk, section_y: usize, palette: &[i32]) -> Vec<u8> {
-    let base_y = section_y * 16;
+fn encode_block_data(chunk: &Chunk, section_index: usize, palette: &[i32]) -> Vec<u8> {
+    let base_y = section_index * TERRAIN_SECTION_HEIGHT;
     let mut blocks = Vec::new();
 
     // Collect all blocks in the section
     for y in base_y..base_y + 16 {
         for z in 0..16 {
             for x in 0..16 {
-                let block = chunk.get_block(x, y, z).unwrap_or(BlockType::Air);
-                let block_id = block_type_to_id(block);
+                let block_id = chunk.get_block_state(x, y, z).map_or(0, BlockState::state_id);
 
                 // Find index in palette
                 let palette_idx = palette.iter().position(|&id| id == block_id).unwrap_or(0);
@@ -156,6 +145,7 @@ fn encode_block_data(chunk: &Chunk, section_y: usize, palette: &[i32]) -> Vec<u8
 }
 
 /// Convert block type to Minecraft block state ID
+/// Legacy 1.12-era numeric ids, kept for reference; serialization uses `BlockState::state_id`
 fn block_type_to_id(block: BlockType) -> i32 {
     // This maps our BlockType enum to Minecraft block state IDs
     // Format: blockid << 4 | metadata (for 1.12.x compatibility)
@@ -172,6 +162,7 @@ fn block_type_to_id(block: BlockType) -> i32 {
         BlockType::Lava => 10,
         BlockType::Sand => 12,
         BlockType::Gravel => 13,
+        BlockType::Bedrock => 7,
     }
 }
 
@@ -181,3 +172,59 @@ fn serialize_heightmap(_chunk: &Chunk) -> Vec<u8> {
     // For now, return a minimal heightmap
     vec![0; 36] // 36 bytes can hold 256 9-bit values (256 * 9 / 8 = 288 bits = 36 bytes)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::PacketReader;
+    use crate::terrain::{Axis, ChunkPos, block_state_id};
+
+    #[test]
+    fn test_negative_y_block_lands_in_its_section() {
+        let mut chunk = Chunk::new(ChunkPos::new(1, -2));
+        chunk.set_block(4, Chunk::local_y(-10).unwrap(), 9, BlockType::Stone);
+        let index = Chunk::section_index(-10).unwrap();
+        assert_eq!(index, 3);
+        assert_eq!(Chunk::section_y(index), -1);
+
+        let bytes = serialize_chunk(&chunk);
+        let mut reader = PacketReader::new(&bytes);
+        assert_eq!(reader.read_varint().unwrap(), ClientboundPlay::ChunkDataAndUpdateLight.id());
+        assert_eq!((reader.read_int().unwrap(), reader.read_int().unwrap()), (1, -2));
+        assert!(reader.read_bool().unwrap());
+        assert_eq!(reader.read_varint().unwrap(), 1 << index);
+        let heightmap_len = reader.read_varint().unwrap() as usize;
+        reader.read_bytes(heightmap_len).unwrap();
+        assert_eq!(reader.read_varint().unwrap(), 0); // biomes
+
+        // Only section -1 is sent, holding the one stone block
+        assert_eq!(reader.read_varint().unwrap(), 1);
+        assert_eq!(reader.read_short().unwrap(), 1);
+        assert_eq!(reader.read_varint().unwrap(), 2);
+        assert_eq!(reader.read_varint().unwrap(), block_state_id(BlockType::Air));
+        assert_eq!(reader.read_varint().unwrap(), block_state_id(BlockType::Stone));
+
+        // Y -10 is layer 6 of section -1, stored y, z, x
+        let data_len = reader.read_varint().unwrap() as usize * 8;
+        let data = reader.read_bytes(data_len).unwrap();
+        let stone_at = data.iter().position(|&b| b == 1).unwrap();
+        assert_eq!(stone_at, (6 * 16 + 9) * 16 + 4);
+        assert_eq!(data.iter().filter(|&&b| b != 0).count(), 1);
+        assert_eq!(reader.remaining(), 0);
+    }
+
+    #[test]
+    fn test_log_axis_reaches_the_palette() {
+        let mut chunk = Chunk::new(ChunkPos::new(0, 0));
+        chunk.set_block(0, 70, 0, BlockType::OakLog);
+        chunk.set_block(1, 70, 0, BlockState::log(BlockType::OakLog, Axis::X));
+        let index = 70 / TERRAIN_SECTION_HEIGHT;
+
+        let palette = build_palette(&chunk, index);
+        assert_eq!(palette, vec![block_state_id(BlockType::Air), 137, 136]);
+
+        let data = encode_block_data(&chunk, index, &palette);
+        let base = (70 % TERRAIN_SECTION_HEIGHT) * 256;
+        assert_eq!(&data[base..base + 2], &[1, 2]);
+    }
+}
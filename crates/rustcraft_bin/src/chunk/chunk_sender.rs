@@ -1,21 +1,27 @@
 #![allow(dead_code)]
 
 use anyhow::Result;
-use tokio::net::TcpStream;
+use tokio::io::AsyncWrite;
 use tracing::debug;
 
-use crate::chunk::{ChunkStorage, send_chunk_data_packet};
+use crate::chunk::{ChunkProvider, send_chunk_data_packet};
 use crate::terrain::{Chunk, ChunkPos};
 
 /// Send a single chunk to a player using the Chunk Data packet
-pub async fn send_chunk(socket: &mut TcpStream, chunk: &Chunk) -> Result<()> {
+pub async fn send_chunk<S>(socket: &mut S, chunk: &Chunk) -> Result<()>
+where
+    S: AsyncWrite + Unpin,
+{
     send_chunk_data_packet(socket, chunk).await?;
     debug!("[CHUNK] Sent chunk {} to player", chunk.pos);
     Ok(())
 }
 
 /// Send multiple chunks to a player
-pub async fn send_chunks(socket: &mut TcpStream, chunks: &[Chunk]) -> Result<()> {
+pub async fn send_chunks<S>(socket: &mut S, chunks: &[Chunk]) -> Result<()>
+where
+    S: AsyncWrite + Unpin,
+{
     for chunk in chunks {
         send_chunk(socket, chunk).await?;
     }
@@ -23,13 +29,16 @@ pub async fn send_chunks(socket: &mut TcpStream, chunks: &[Chunk]) -> Result<()>
 }
 
 /// Send chunks in a spiral pattern around player position
-pub async fn send_chunks_around_player(
-    socket: &mut TcpStream,
-    chunk_storage: &ChunkStorage,
+pub async fn send_chunks_around_player<S>(
+    socket: &mut S,
+    chunk_storage: &impl ChunkProvider,
     chunk_x: i32,
     chunk_z: i32,
     radius: i32,
-) -> Result<()> {
+) -> Result<()>
+where
+    S: AsyncWrite + Unpin,
+{
     // Spiral outward from player position
     for distance in 0..=radius {
         for dx in -distance..=distance {
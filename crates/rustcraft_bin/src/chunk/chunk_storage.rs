@@ -1,29 +1,45 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
 use std::ops::AddAssign;
-use std::path::PathBuf;
-use std::sync::atomic::AtomicUsize;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::{Arc, mpsc};
+use std::time::Duration;
 
 use anyhow::Result;
-use parking_lot::RwLock;
+use dashmap::DashMap;
+use futures::future::BoxFuture;
+use parking_lot::{Mutex, RwLock};
 use rayon::prelude::*;
+use tokio::sync::oneshot;
 use tracing::{debug, error, info, trace, warn};
 
-use crate::chunk::cache::LruCache;
+use crate::chunk::concurrent_cache::ConcurrentLruCache;
+use crate::chunk::provider::{ChunkProvider, edit_block};
 use crate::consts::{
     CHUNK_SIZE_BYTES,
     INITIAL_BUFFER_MB,
     INITIAL_CAPACITY,
     MAX_BUFFER_MB,
     MAX_CAPACITY,
-    WORLD_PATH,
+    TERRAIN_CHUNK_HEIGHT,
+    WORLD_MIN_Y,
 };
-use crate::core::ChunkGenThreadPool;
-use crate::terrain::{Chunk, ChunkGenerator, ChunkPos};
+use crate::core::{ChunkGenThreadPool, TaskPriority};
+use crate::player::PlayerRegistry;
+use crate::terrain::{BlockState, BlockType, Chunk, ChunkPos, WorldGenerator};
 use crate::world::{Region, RegionPos};
 
 const SLEEP_TIME_SECS: u64 = 300; // 5 minutes
 const SLEEP_TIME_DURATION: tokio::time::Duration = tokio::time::Duration::from_secs(SLEEP_TIME_SECS);
+const METRICS_LOG_SECS: u64 = 60;
+const METRICS_LOG_DURATION: tokio::time::Duration = tokio::time::Duration::from_secs(METRICS_LOG_SECS);
+const UNLOAD_SWEEP_SECS: u64 = 30;
+const UNLOAD_SWEEP_DURATION: tokio::time::Duration = tokio::time::Duration::from_secs(UNLOAD_SWEEP_SECS);
+/// Clean chunks nobody has read or written for this long are dropped by the shrink task
+const CHUNK_CACHE_TTL: Duration = Duration::from_secs(600);
+const PREGEN_PROGRESS_LOG_DURATION: Duration = Duration::from_secs(5);
+const PREGEN_PROGRESS_POLL_DURATION: Duration = Duration::from_millis(100);
 
 // Memory budget constants
 // const CHUNK_SIZE_BYTES: usize = 232 * 1024; // ~232 KB per chunk
@@ -33,32 +49,211 @@ const SLEEP_TIME_DURATION: tokio::time::Duration = tokio::time::Duration::from_s
 // const INITIAL_CAPACITY: usize = INITIAL_BUFFER_MB * 1024 * 1024 / CHUNK_SIZE_BYTES; // ~1130 chunks
 // const MAX_CAPACITY: usize = MAX_BUFFER_MB * 1024 * 1024 / CHUNK_SIZE_BYTES; // ~9033 chunks
 
-#[allow(dead_code)]
+/// Chunks cached and the cache's current capacity, taken at the same moment
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct CacheLenCapacity((usize, usize));
 
+#[allow(dead_code)]
+impl CacheLenCapacity {
+    pub fn len(&self) -> usize {
+        self.0.0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.0.1
+    }
+}
+
 impl From<(usize, usize)> for CacheLenCapacity {
     fn from(value: (usize, usize)) -> Self {
         CacheLenCapacity(value)
     }
 }
 
+/// Counters for how `get_chunk` requests were served
+#[derive(Default)]
+struct ChunkCounters {
+    hits:        AtomicUsize,
+    misses:      AtomicUsize,
+    disk_loads:  AtomicUsize,
+    generations: AtomicUsize,
+    evictions:   AtomicUsize,
+}
+
+/// Snapshot of the chunk cache counters
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CacheMetrics {
+    pub hits:        usize,
+    pub misses:      usize,
+    pub disk_loads:  usize,
+    pub generations: usize,
+    pub evictions:   usize,
+    pub len:         usize,
+    pub capacity:    usize,
+}
+
+impl CacheMetrics {
+    /// Fraction of lookups served from the cache (0.0 when nothing was requested yet)
+    pub fn hit_rate(&self) -> f64 {
+        let total = self.hits + self.misses;
+        if total == 0 {
+            0.0
+        } else {
+            self.hits as f64 / total as f64
+        }
+    }
+
+    /// Fraction of lookups that went to disk or the generator
+    pub fn miss_rate(&self) -> f64 {
+        let total = self.hits + self.misses;
+        if total == 0 {
+            0.0
+        } else {
+            self.misses as f64 / total as f64
+        }
+    }
+}
+
+impl std::fmt::Display for CacheMetrics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "hits: {}, misses: {} ({:.1}% hit / {:.1}% miss), disk loads: {}, generated: {}, evictions: {}, cache: {}/{}",
+            self.hits,
+            self.misses,
+            self.hit_rate() * 100.0,
+            self.miss_rate() * 100.0,
+            self.disk_loads,
+            self.generations,
+            self.evictions,
+            self.len,
+            self.capacity
+        )
+    }
+}
+
+/// What a `flush_cache` wrote
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FlushSummary {
+    pub chunks_saved:    usize,
+    pub regions_written: usize,
+    /// Size of the region files written, in bytes
+    pub bytes_written:   u64,
+    pub duration:        Duration,
+}
+
+impl std::fmt::Display for FlushSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} chunks in {} region files ({} bytes) in {:.2}s",
+            self.chunks_saved,
+            self.regions_written,
+            self.bytes_written,
+            self.duration.as_secs_f64()
+        )
+    }
+}
+
+/// How far a spawn area pregeneration has got
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PregenProgress {
+    pub done:    usize,
+    pub total:   usize,
+    pub elapsed: Duration,
+}
+
+impl PregenProgress {
+    /// Share of the area done, 0.0 to 100.0; an empty area is already complete
+    pub fn percent(&self) -> f64 {
+        if self.total == 0 {
+            100.0
+        } else {
+            self.done as f64 * 100.0 / self.total as f64
+        }
+    }
+
+    /// Time left at the rate so far, `None` until the first chunk is done
+    pub fn eta(&self) -> Option<Duration> {
+        if self.done == 0 {
+            return None;
+        }
+        let remaining = self.total.saturating_sub(self.done);
+        Some(self.elapsed.mul_f64(remaining as f64 / self.done as f64))
+    }
+}
+
+impl std::fmt::Display for PregenProgress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{} chunks ({:.1}%)", self.done, self.total, self.percent())?;
+        match self.eta() {
+            Some(eta) => write!(f, ", ETA {:.0}s", eta.as_secs_f64()),
+            None => write!(f, ", ETA unknown"),
+        }
+    }
+}
+
+/// Chunks pregenerated around `center`: a `(2 * radius)^2` square, `-radius..radius` on each axis
+pub fn pregen_area(center: ChunkPos, radius: i32) -> impl Iterator<Item = ChunkPos> {
+    (-radius..radius)
+        .flat_map(move |dx| (-radius..radius).map(move |dz| ChunkPos::new(center.x + dx, center.z + dz)))
+}
+
+/// Offsets covering a `(2 * radius + 1)^2` square, spiralling out one ring at a time so they are
+/// ordered by Chebyshev distance from the center, like vanilla sends chunks
+pub fn spiral_chunk_offsets(radius: i32) -> impl Iterator<Item = (i32, i32)> {
+    std::iter::once((0, 0)).chain((1..=radius.max(0)).flat_map(|r| {
+        let side = 0..2 * r;
+        side.clone()
+            .map(move |i| (r, -r + 1 + i))
+            .chain(side.clone().map(move |i| (r - 1 - i, r)))
+            .chain(side.clone().map(move |i| (-r, r - 1 - i)))
+            .chain(side.map(move |i| (-r + 1 + i, -r)))
+    }))
+}
+
 pub struct ChunkStorage {
-    // PERF: @locking : Is there a way to work around the use of a RwLock here?
-    cache:           Arc<RwLock<LruCache<ChunkPos, Chunk>>>,
+    /// Chunks are shared out of the cache, so a read is a reference count bump rather than a copy
+    cache:           Arc<ConcurrentLruCache<ChunkPos, Arc<Chunk>>>,
     world_dir:       PathBuf,
-    chunk_generator: Arc<ChunkGenerator>,
-    evictions:       AtomicUsize,
+    chunk_generator: Arc<dyn WorldGenerator>,
+    counters:        Arc<ChunkCounters>,
     chunk_gen_pool:  Arc<ChunkGenThreadPool>,
+    /// Cached chunks that differ from what is on disk: freshly generated or edited
+    /// Held while caching those chunks, so an unload can't drop an edit made mid-write
+    dirty:           Arc<Mutex<HashSet<ChunkPos>>>,
+    /// One lock per region file, so a load never reads a file another thread is half way through
+    /// writing and two saves of the same region don't drop each other's chunks; loads share it
+    region_locks:    Arc<DashMap<RegionPos, Arc<RwLock<()>>>>,
+    /// Block edits read a chunk, change it and cache it again, so they must not interleave
+    edit_lock:       Arc<Mutex<()>>,
 }
 
 impl ChunkStorage {
+    /// Open the world in `world_dir` (normally `WORLD_PATH`) and start the background cache tasks
+    /// Nothing is generated up front; see `pregenerate_spawn_area`
     pub fn new(
-        chunk_generator: Arc<ChunkGenerator>,
+        world_dir: PathBuf,
+        chunk_generator: Arc<dyn WorldGenerator>,
         chunk_gen_pool: Arc<ChunkGenThreadPool>,
     ) -> Result<Self> {
-        // let world_dir = PathBuf::from(WORLD_NAME);
-        let world_dir = PathBuf::from(WORLD_PATH);
+        let storage = Self::with_world_dir(world_dir, chunk_generator, chunk_gen_pool)?;
+
+        storage.start_hit_reset_task();
+        storage.start_metrics_log_task();
+        storage.start_cache_shrink_task();
+
+        storage.chunk_gen_pool.signal_init_complete();
+
+        Ok(storage)
+    }
 
+    /// Build the storage over `world_dir` without pregenerating or spawning background tasks
+    pub(crate) fn with_world_dir(
+        world_dir: PathBuf,
+        chunk_generator: Arc<dyn WorldGenerator>,
+        chunk_gen_pool: Arc<ChunkGenThreadPool>,
+    ) -> Result<Self> {
         // NOTE: Do not call world_dir.canonicalize() before checking existence,
         // This WILL crash if the directory does not exist yet.
 
@@ -75,33 +270,26 @@ impl ChunkStorage {
                 .unwrap();
         }
         info!("[STARTUP] World directory found at {:?}", world_dir.canonicalize()?);
+        Self::remove_interrupted_saves(&world_dir)?;
 
         info!(
             "[STARTUP] Initializing chunk cache: {}-{}MB ({}-{} chunks)",
             INITIAL_BUFFER_MB, MAX_BUFFER_MB, INITIAL_CAPACITY, MAX_CAPACITY
         );
 
-        let storage = Self {
-            cache: Arc::new(RwLock::new(LruCache::with_growth(
-                INITIAL_CAPACITY,
-                MAX_CAPACITY,
-                CHUNK_SIZE_BYTES,
-            ))),
+        Ok(Self {
+            cache: Arc::new(
+                ConcurrentLruCache::with_growth(INITIAL_CAPACITY, MAX_CAPACITY, CHUNK_SIZE_BYTES)
+                    .expire_after(CHUNK_CACHE_TTL),
+            ),
             world_dir,
             chunk_generator,
-            evictions: AtomicUsize::new(0),
+            counters: Arc::new(ChunkCounters::default()),
             chunk_gen_pool,
-        };
-
-        // Pregenerate 64x64 chunk area on startup
-        debug!("[STARTUP] Starting pregeneration of spawn area...");
-        storage.pregenerate_spawn_area()?;
-
-        storage.start_hit_reset_task();
-
-        storage.chunk_gen_pool.signal_init_complete();
-
-        Ok(storage)
+            dirty: Arc::new(Mutex::new(HashSet::new())),
+            region_locks: Arc::new(DashMap::new()),
+            edit_lock: Arc::new(Mutex::new(())),
+        })
     }
 
     /// Start hit count reset task (runs every 5 minutes)
@@ -110,7 +298,7 @@ impl ChunkStorage {
         tokio::spawn(async move {
             loop {
                 tokio::time::sleep(SLEEP_TIME_DURATION).await;
-                cache.write().reset_hit_counts();
+                cache.reset_hit_counts();
                 // let mut cache_lock = cache.write();
                 // cache_lock.reset_hit_counts();
                 // drop(cache_lock);
@@ -119,201 +307,435 @@ impl ChunkStorage {
         });
     }
 
-    fn pregenerate_spawn_area(&self) -> Result<()> {
-        info!("[STARTUP] Pregenerating spawn area (16x16 chunks)...");
-
-        let start = std::time::Instant::now();
-        let mut generated = 0;
-        let (tx, rx) = mpsc::channel();
-
-        // Generate a 16x16 area centered around origin using thread pool
+    /// Start cache metrics summary task (runs every minute)
+    pub fn start_metrics_log_task(&self) {
+        let storage = self.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(METRICS_LOG_DURATION).await;
+                info!("[CHUNK] Cache metrics: {}", storage.cache_metrics());
+            }
+        });
+    }
 
-        // PERF: @nested : Loop moved to thread engine
-        for cx in -8..8 {
-            for cz in -8..8 {
-                let chunk_pos = ChunkPos::new(cx, cz);
+    /// Start cache shrink task (runs every minute), dropping chunks unused for `CHUNK_CACHE_TTL` and
+    /// handing capacity back after a burst of chunks
+    pub fn start_cache_shrink_task(&self) {
+        let storage = self.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(METRICS_LOG_DURATION).await;
+                // Dirty chunks stay until a flush or unload writes them out
+                let (expired, (shrunk, evicted)) = {
+                    let dirty = storage.dirty.lock();
+                    (
+                        storage.cache.evict_expired_evicting(|pos| !dirty.contains(pos)),
+                        storage.cache.try_shrink_evicting(|pos| !dirty.contains(pos)),
+                    )
+                };
+                if !expired.is_empty() || !evicted.is_empty() {
+                    storage
+                        .counters
+                        .evictions
+                        .fetch_add(expired.len() + evicted.len(), Ordering::Relaxed);
+                }
+                if !expired.is_empty() {
+                    debug!("[CHUNK] Dropped {} chunks unused for {:?}", expired.len(), CHUNK_CACHE_TTL);
+                }
+                if shrunk {
+                    info!(
+                        "[CHUNK] Cache shrunk to {} chunks after low usage ({} evicted)",
+                        storage.cache.current_capacity(),
+                        evicted.len()
+                    );
+                }
+            }
+        });
+    }
 
-                // Check if chunk exists on disk
-                let region_pos = RegionPos::from(chunk_pos);
+    /// Start the unload sweep (runs every 30 seconds), writing out and dropping chunks more than
+    /// `distance` chunks from every player
+    pub fn start_unload_task(&self, players: Arc<PlayerRegistry>, distance: i32) {
+        let storage = self.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(UNLOAD_SWEEP_DURATION).await;
+                let centers = players.chunk_positions();
+                let sweep = storage.clone();
+                let result =
+                    tokio::task::spawn_blocking(move || sweep.unload_distant(&centers, distance)).await;
+                match result {
+                    Ok(Ok(0)) => {}
+                    Ok(Ok(unloaded)) => debug!("[CHUNK] Unloaded {} chunks away from every player", unloaded),
+                    Ok(Err(e)) => error!("[CHUNK] Unload sweep failed: {}", e),
+                    Err(e) => error!("[CHUNK] Unload sweep panicked: {}", e),
+                }
+            }
+        });
+    }
 
-                // if !self.chunk_exists_on_disk(region_pos)? {
-                if !self.world_dir.join(region_pos.filename()).exists() {
-                    // Clone needed data for thread pool task
-                    let generator = Arc::clone(&self.chunk_generator);
-                    let tx = tx.clone();
+    /// Load or generate the area around `center` (see `pregen_area`) on the generation pool, behind
+    /// any chunk a player is waiting on, logging progress every few seconds
+    /// Stops early once `cancel` is set, leaving what was generated to the next flush; returns how
+    /// far it got
+    pub fn pregenerate_spawn_area(
+        &self,
+        center: ChunkPos,
+        radius: i32,
+        cancel: Arc<AtomicBool>,
+    ) -> Result<PregenProgress> {
+        let start = std::time::Instant::now();
+        let (tx, rx) = mpsc::channel();
+        let mut progress = PregenProgress {
+            done:    0,
+            total:   0,
+            elapsed: Duration::ZERO,
+        };
 
-                    // Submit to thread pool
-                    self.chunk_gen_pool.execute(move || {
-                        let chunk = generator.generate(chunk_pos);
-                        let _ = tx.send((chunk_pos, chunk));
-                    })?;
+        // Chunks already stored were pregenerated or visited by an earlier run; each region file is
+        // read once to find out which of its chunks those are
+        let mut stored: HashMap<RegionPos, HashSet<ChunkPos>> = HashMap::new();
+        let missing: Vec<ChunkPos> = pregen_area(center, radius)
+            .filter(|pos| {
+                !stored
+                    .entry(RegionPos::from(*pos))
+                    .or_insert_with_key(|region_pos| self.stored_chunks(*region_pos))
+                    .contains(pos)
+            })
+            .collect();
+        progress.total = missing.len();
+        info!(
+            "[STARTUP] Pregenerating spawn area ({}x{} chunks around {}, {} missing)...",
+            2 * radius,
+            2 * radius,
+            center,
+            progress.total
+        );
 
-                    generated += 1;
+        for chunk_pos in missing {
+            let storage = self.clone();
+            let cancel = Arc::clone(&cancel);
+            let tx = tx.clone();
+            self.chunk_gen_pool.execute(move || {
+                // Queued work is dropped rather than generated once the run is cancelled
+                if !cancel.load(Ordering::Relaxed) {
+                    let _ = tx.send(storage.get_chunk(chunk_pos));
+                }
+            })?;
+        }
+        // Drop the original sender so the receiver knows when every task has finished
+        drop(tx);
 
-                    // Periodically receive and cache generated chunks
-                    if generated % 256 == 0 {
-                        trace!("[CHUNK] Submitted {} chunks to generation pool", generated);
-                        self.receive_and_cache_chunks(&rx)?;
-                    }
+        let mut last_log = start;
+        loop {
+            match rx.recv_timeout(PREGEN_PROGRESS_POLL_DURATION) {
+                Ok(chunk) => {
+                    chunk?;
+                    progress.done += 1;
                 }
+                Err(mpsc::RecvTimeoutError::Timeout) => {}
+                Err(mpsc::RecvTimeoutError::Disconnected) => break,
+            }
+            if last_log.elapsed() >= PREGEN_PROGRESS_LOG_DURATION {
+                last_log = std::time::Instant::now();
+                progress.elapsed = start.elapsed();
+                info!("[STARTUP] Pregenerating spawn area: {}", progress);
             }
         }
+        progress.elapsed = start.elapsed();
 
-        // Drop the original sender so receiver knows when all tasks are done
-        drop(tx);
-
-        // Receive all remaining chunks
-        self.receive_and_cache_all_chunks(&rx)?;
+        if cancel.load(Ordering::Relaxed) {
+            warn!("[STARTUP] Pregeneration cancelled at {}", progress);
+            return Ok(progress);
+        }
 
         self.flush_cache()?;
 
-        let elapsed = start.elapsed();
-        let cache = self.cache.read();
+        let cache = &self.cache;
         info!(
             "[STARTUP] Pregeneration complete: {} new chunks in {:.2}s ({:.0} chunks/sec), cache: {}/{}",
-            generated,
-            elapsed.as_secs_f64(),
-            generated as f64 / elapsed.as_secs_f64(),
+            progress.done,
+            progress.elapsed.as_secs_f64(),
+            progress.done as f64 / progress.elapsed.as_secs_f64(),
             cache.len(),
             cache.current_capacity()
         );
 
-        Ok(())
+        Ok(progress)
     }
 
-    fn receive_and_cache_chunks(&self, rx: &mpsc::Receiver<(ChunkPos, Chunk)>) -> Result<()> {
-        // Receive chunks with a short timeout to avoid blocking
-        while let Ok((pos, chunk)) = rx.try_recv() {
-            debug!("[CHUNK] Caching pregenerated chunk at {}", pos);
-            let (_, expanded, evicted) = {
-                let mut cache = self.cache.write();
-                cache.insert(pos, chunk)
-            };
+    /// The cached chunk, shared; edit a copy with `Arc::make_mut` and hand it to `update_chunk`
+    pub fn get_chunk(&self, chunk_pos: ChunkPos) -> Result<Arc<Chunk>> {
+        // Check cache first
+        if let Some(chunk) = self.cache.get(&chunk_pos) {
+            debug!("[CHUNK] Cache hit for {}", chunk_pos);
+            self.counters.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(chunk);
+        }
+        self.counters.misses.fetch_add(1, Ordering::Relaxed);
 
-            if expanded {
-                let cache = self.cache.read();
-                debug!("[CHUNK] Cache expanded to {} chunks during pregeneration", cache.current_capacity());
-            }
+        let region_pos = RegionPos::from_chunk(chunk_pos.x, chunk_pos.z);
+        let region_path = self.world_dir.join(region_pos.filename());
 
-            if let Some(evicted_pos) = evicted {
-                debug!("[CHUNK] Evicted {} during pregeneration", evicted_pos);
-            }
+        // Try to load from disk
+        // if let Ok(chunk) = self.load_chunk_from_disk(chunk_pos) {
+        if let Ok(chunk) = self.load_chunk_from_disk(chunk_pos.x, chunk_pos.z, region_path) {
+            debug!("[CHUNK] Loaded chunk {} from disk", chunk_pos);
+            self.counters.disk_loads.fetch_add(1, Ordering::Relaxed);
+            return Ok(self.cache_if_absent(chunk_pos, Arc::new(chunk), false));
         }
-        Ok(())
+
+        // Generate new chunk
+        debug!("[CHUNK] Generating new chunk at {}", chunk_pos);
+        let chunk = Arc::new(self.chunk_generator.generate(chunk_pos));
+        self.counters.generations.fetch_add(1, Ordering::Relaxed);
+
+        Ok(self.cache_if_absent(chunk_pos, chunk, true))
     }
 
-    fn receive_and_cache_all_chunks(&self, rx: &mpsc::Receiver<(ChunkPos, Chunk)>) -> Result<()> {
-        // Receive all remaining chunks from the channel
-        while let Ok((pos, chunk)) = rx.recv() {
-            let (_, expanded, evicted) = {
-                let mut cache = self.cache.write();
-                cache.insert(pos, chunk)
-            };
+    /// Load or generate the chunk on the chunk generation pool instead of the calling task
+    ///
+    /// The work is queued immediately, so several requests made before awaiting run concurrently
+    /// It is queued ahead of any pregeneration still waiting for a worker; like `get_chunk`, an edit
+    /// cached while it runs is returned instead of the loaded chunk
+    pub fn get_chunk_async(
+        &self,
+        chunk_pos: ChunkPos,
+    ) -> impl Future<Output = Result<Arc<Chunk>>> + Send + 'static {
+        let (tx, rx) = oneshot::channel();
+        let storage = self.clone();
+        let queued = self
+            .chunk_gen_pool
+            .execute_with_priority(TaskPriority::PlayerRequest, move || {
+                let _ = tx.send(storage.get_chunk(chunk_pos));
+            });
+
+        async move {
+            queued?;
+            rx.await
+                .map_err(|_| anyhow::anyhow!("Chunk generation pool dropped the request for {}", chunk_pos))?
+        }
+    }
 
-            if expanded {
-                let cache = self.cache.read();
-                debug!("[CHUNK] Cache expanded to {} chunks during pregeneration", cache.current_capacity());
-            }
+    /// Y a player can stand at in the column at block `x`/`z`: just above the highest solid block,
+    /// raised to the water surface (sea level) for columns under water
+    pub fn surface_height(&self, x: i32, z: i32) -> Result<i32> {
+        let chunk = self.get_chunk(ChunkPos::from_block_pos(x, z))?;
+        let (local_x, local_z) = (x.rem_euclid(16) as usize, z.rem_euclid(16) as usize);
+        let block_at = |y: usize| chunk.get_block(local_x, y, local_z).unwrap_or(BlockType::Air);
+        let above = |y: Option<usize>| y.map_or(WORLD_MIN_Y, |y| Chunk::world_y(y) + 1);
+
+        let solid = (0..TERRAIN_CHUNK_HEIGHT)
+            .rev()
+            .find(|&y| !matches!(block_at(y), BlockType::Air | BlockType::Water | BlockType::Lava));
+        let water = (0..TERRAIN_CHUNK_HEIGHT)
+            .rev()
+            .find(|&y| block_at(y) == BlockType::Water);
+
+        Ok(above(solid).max(above(water)))
+    }
 
-            if let Some(evicted_pos) = evicted {
-                debug!("[CHUNK] Evicted {} during pregeneration", evicted_pos);
-            }
+    /// Insert into the cache, counting any eviction it causes
+    /// Only clean chunks are evicted, so an unsaved chunk is never dropped; `dirty` is the held set
+    fn cache_chunk(&self, dirty: &HashSet<ChunkPos>, chunk_pos: ChunkPos, chunk: Arc<Chunk>) {
+        let (_, _, evicted) = self
+            .cache
+            .insert_evicting(chunk_pos, chunk, |pos| !dirty.contains(pos));
+        if let Some(evicted_pos) = evicted {
+            self.counters.evictions.fetch_add(1, Ordering::Relaxed);
+            debug!("[CHUNK] Evicted {} to make room for {}", evicted_pos, chunk_pos);
         }
-        Ok(())
     }
 
-    pub fn get_chunk(&self, chunk_pos: ChunkPos) -> Result<Chunk> {
-        // Check cache first
-        {
-            let cache = self.cache.write();
-            if let Some(chunk) = cache.get(&chunk_pos) {
-                debug!("[CHUNK] Cache hit for {}", chunk_pos);
-                return Ok(chunk.clone());
-            }
+    /// Cache a chunk `get_chunk` loaded or generated, unless one was cached while it did, e.g. by
+    /// `update_chunk`; returns whichever chunk ends up cached, so an edit is never overwritten
+    fn cache_if_absent(&self, chunk_pos: ChunkPos, chunk: Arc<Chunk>, is_dirty: bool) -> Arc<Chunk> {
+        // Every insert holds `dirty`, so nothing can be cached between the check and the insert
+        let mut dirty = self.dirty.lock();
+        if let Some(cached) = self.cache.get(&chunk_pos) {
+            debug!("[CHUNK] {} was cached while loading, keeping that copy", chunk_pos);
+            return cached;
         }
+        self.cache_chunk(&dirty, chunk_pos, Arc::clone(&chunk));
+        if is_dirty {
+            dirty.insert(chunk_pos);
+        }
+        chunk
+    }
 
-        let region_pos = RegionPos::from_chunk(chunk_pos.x, chunk_pos.z);
-        let region_path = self.world_dir.join(region_pos.filename());
+    /// Cache a chunk that still has to be written to disk
+    fn cache_dirty_chunk(&self, chunk_pos: ChunkPos, chunk: Arc<Chunk>) {
+        let mut dirty = self.dirty.lock();
+        self.cache_chunk(&dirty, chunk_pos, chunk);
+        dirty.insert(chunk_pos);
+    }
 
-        // Try to load from disk
-        // if let Ok(chunk) = self.load_chunk_from_disk(chunk_pos) {
-        if let Ok(chunk) = self.load_chunk_from_disk(chunk_pos.x, chunk_pos.z, region_path) {
-            debug!("[CHUNK] Loaded chunk {} from disk", chunk_pos);
-            self.cache.write().insert(chunk_pos, chunk.clone());
-            return Ok(chunk);
+    /// Folder the region files and world metadata live in
+    pub fn world_dir(&self) -> &Path {
+        &self.world_dir
+    }
+
+    /// Pool chunk generation runs on, for pausing and resuming it
+    pub fn generation_pool(&self) -> &Arc<ChunkGenThreadPool> {
+        &self.chunk_gen_pool
+    }
+
+    /// Replace the cached copy of a chunk after editing it; it is written out on the next flush
+    pub fn update_chunk(&self, chunk: Arc<Chunk>) {
+        self.cache_dirty_chunk(chunk.pos, chunk);
+    }
+
+    /// Throw away the chunk at `chunk_pos`, edits and all, for a freshly generated one
+    /// The new chunk is cached dirty, so the next flush overwrites the stored copy
+    pub fn regenerate_chunk(&self, chunk_pos: ChunkPos) -> Arc<Chunk> {
+        let _guard = self.edit_lock.lock();
+        let chunk = Arc::new(self.chunk_generator.generate(chunk_pos));
+        self.counters.generations.fetch_add(1, Ordering::Relaxed);
+        self.cache_dirty_chunk(chunk_pos, Arc::clone(&chunk));
+        info!("[CHUNK] Regenerated chunk {}", chunk_pos);
+        chunk
+    }
+
+    /// Write the chunk out if it changed since it was last saved, then drop it from the cache
+    /// Returns whether it was cached; the next `get_chunk` loads it back from disk
+    #[allow(dead_code)]
+    pub fn unload_chunk(&self, chunk_pos: ChunkPos) -> Result<bool> {
+        Ok(self.unload_chunks(&[chunk_pos])? == 1)
+    }
+
+    /// Unload every cached chunk more than `distance` chunks from all of `centers`, e.g. outside
+    /// every player's simulation distance; returns how many were unloaded
+    /// With no centers nothing is unloaded, so an empty server keeps the spawn area loaded
+    pub fn unload_distant(&self, centers: &[ChunkPos], distance: i32) -> Result<usize> {
+        if centers.is_empty() {
+            return Ok(0);
         }
+        let mut distant = Vec::new();
+        self.cache.for_each(|pos, _| {
+            if centers
+                .iter()
+                .all(|center| center.distance_chebyshev(pos) > distance)
+            {
+                distant.push(*pos);
+            }
+        });
+        self.unload_chunks(&distant)
+    }
 
-        // Generate new chunk
-        debug!("[CHUNK] Generating new chunk at {}", chunk_pos);
-        let chunk = self.chunk_generator.generate(chunk_pos);
-        self.cache.write().insert(chunk_pos, chunk.clone());
+    fn unload_chunks(&self, positions: &[ChunkPos]) -> Result<usize> {
+        // Snapshot the dirty chunks, then write them without holding up edits
+        let mut region_map: HashMap<RegionPos, Vec<Arc<Chunk>>> = HashMap::new();
+        let mut written: HashMap<ChunkPos, Arc<Chunk>> = HashMap::new();
+        {
+            let dirty = self.dirty.lock();
+            for pos in positions.iter().filter(|pos| dirty.contains(pos)) {
+                let region_pos = RegionPos::from_chunk(pos.x, pos.z);
+                if let Some(chunk) = self.cache.get(pos)
+                    && region_pos.is_valid()
+                {
+                    written.insert(*pos, Arc::clone(&chunk));
+                    region_map.entry(region_pos).or_default().push(chunk);
+                }
+            }
+        }
+        for (region_pos, chunks) in &region_map {
+            self.write_region(*region_pos, chunks)?;
+        }
 
-        Ok(chunk)
+        // A chunk edited while its region was written is dirty again and stays cached
+        let mut dirty = self.dirty.lock();
+        let mut unloaded = 0;
+        for pos in positions {
+            let saved = match written.get(pos) {
+                Some(chunk) => {
+                    self.cache
+                        .get(pos)
+                        .is_some_and(|cached| Arc::ptr_eq(&cached, chunk))
+                }
+                None => !dirty.contains(pos),
+            };
+            if saved && self.cache.remove(pos).is_some() {
+                dirty.remove(pos);
+                unloaded += 1;
+            }
+        }
+        trace!("[CHUNK] Unloaded {} chunks, {} of them written to disk", unloaded, written.len());
+        Ok(unloaded)
     }
 
     #[allow(dead_code)]
     pub fn save_chunk(&self, chunk: Chunk) -> Result<()> {
         // Update cache
-        let (_, expanded, evicted_key) = {
-            let mut cache = self.cache.write();
-            cache.insert(chunk.pos, chunk.clone())
-        };
+        let mut dirty = self.dirty.lock();
+        let chunk_pos = chunk.pos;
+        let (_, expanded, evicted_key) = self
+            .cache
+            .insert_evicting(chunk_pos, Arc::new(chunk), |pos| !dirty.contains(pos));
+        dirty.insert(chunk_pos);
+        drop(dirty);
 
         if expanded {
-            let cache = self.cache.read();
-            let usage = cache.usage_ratio();
-            let capacity = cache.current_capacity();
-            drop(cache);
+            let usage = self.cache.usage_ratio();
+            let capacity = self.cache.current_capacity();
             info!("[CHUNK] Cache expanded to {} chunks ({:.1}% usage)", capacity, usage * 100.0);
         }
 
         if let Some(evicted_pos) = evicted_key {
             // let mut evictions = self.evictions.write();
             // *evictions += 1;
-            self.evictions.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
-            warn!(
-                "[CHUNK] Evicted low-hit chunk {} (total evictions: {})",
-                evicted_pos,
-                self.evictions.load(std::sync::atomic::Ordering::SeqCst)
-            );
+            let evictions = self.counters.evictions.fetch_add(1, Ordering::Relaxed) + 1;
+            warn!("[CHUNK] Evicted low-hit chunk {} (total evictions: {})", evicted_pos, evictions);
         }
 
         // If cache is getting full, flush to disk
-        let cache = self.cache.read();
-        if cache.len() > cache.current_capacity() / 2 {
+        if self.cache.len() > self.cache.current_capacity() / 2 {
             warn!("[CHUNK] Cache over 50% full, flushing to disk...");
-            drop(cache);
             self.flush_cache()?;
         }
 
         Ok(())
     }
 
-    pub fn flush_cache(&self) -> Result<()> {
+    /// Write every cached chunk to its region file
+    pub fn flush_cache(&self) -> Result<FlushSummary> {
         warn!("[CHUNK] Flushing all cached chunks to disk...");
 
         let start = std::time::Instant::now();
 
-        let guard = self.cache.write();
-
-        let mut region_map: HashMap<RegionPos, Vec<Chunk>> = HashMap::new();
+        let mut region_map: HashMap<RegionPos, Vec<Arc<Chunk>>> = HashMap::new();
         let mut saved_count = 0;
         let mut skipped_count = 0;
 
-        self.fill_region_map(&guard, &mut skipped_count, &mut region_map, &mut saved_count);
-        // explicit drop after setting up flush_tracking
-        drop(guard);
-
-        self.par_gen_cache(region_map, self.world_dir.clone());
+        self.fill_region_map(&mut skipped_count, &mut region_map, &mut saved_count);
+
+        let flushed: Vec<(RegionPos, Arc<Chunk>)> = region_map
+            .iter()
+            .flat_map(|(region_pos, chunks)| chunks.iter().map(|chunk| (*region_pos, Arc::clone(chunk))))
+            .collect();
+        let (mut summary, written) = self.par_gen_cache(region_map, self.world_dir.clone());
+
+        // Chunks edited while the flush ran, or in a region that failed to write, are still dirty
+        let mut dirty = self.dirty.lock();
+        for (region_pos, chunk) in flushed {
+            let pos = chunk.pos;
+            if written.contains(&region_pos)
+                && self
+                    .cache
+                    .get(&pos)
+                    .is_none_or(|cached| Arc::ptr_eq(&cached, &chunk))
+            {
+                dirty.remove(&pos);
+            }
+        }
+        drop(dirty);
 
-        let duration = start.elapsed();
+        summary.duration = start.elapsed();
 
         info!(
-            "[CHUNK] Flushed {} chunks to disk in {:.2}s ({:.0} chunks/sec){}",
-            saved_count,
-            duration.as_secs_f64(),
-            if duration.as_secs_f64() > 0.0001 {
-                saved_count as f64 / duration.as_secs_f64()
+            "[CHUNK] Flushed {} ({:.0} chunks/sec){}{}",
+            summary,
+            if summary.duration.as_secs_f64() > 0.0001 {
+                summary.chunks_saved as f64 / summary.duration.as_secs_f64()
             } else {
                 0.0
             },
@@ -321,72 +743,148 @@ impl ChunkStorage {
                 format!(" (skipped {} invalid chunks)", skipped_count)
             } else {
                 "".to_string()
+            },
+            if summary.chunks_saved < saved_count {
+                format!(" ({} chunks failed to save)", saved_count - summary.chunks_saved)
+            } else {
+                "".to_string()
             }
         );
 
-        Ok(())
+        Ok(summary)
     }
 
     fn fill_region_map(
         &self,
-        guard: &parking_lot::RwLockWriteGuard<'_, LruCache<ChunkPos, Chunk>>,
         skipped_count: &mut usize,
-        region_map: &mut HashMap<RegionPos, Vec<Chunk>>,
+        region_map: &mut HashMap<RegionPos, Vec<Arc<Chunk>>>,
         saved_count: &mut usize,
     ) {
-        for (_, chunk) in guard.iter() {
+        self.cache.for_each(|_, chunk| {
             let region_pos = RegionPos::from_chunk(chunk.pos.x, chunk.pos.z);
 
             if !region_pos.is_valid() {
                 warn!("Skipping save for chunk outside bounds: ({}, {})", chunk.pos.x, chunk.pos.z);
                 skipped_count.add_assign(1);
-                continue;
+                return;
             }
 
-            region_map.entry(region_pos).or_default().push(chunk.clone());
+            region_map.entry(region_pos).or_default().push(Arc::clone(chunk));
             saved_count.add_assign(1);
-        }
+        });
     }
 
+    /// Write each region's chunks in parallel; failed regions are logged and left out of the
+    /// returned counts and the set of written regions
     fn par_gen_cache<P: AsRef<std::path::Path> + Send + Sync>(
         &self,
-        region_map: HashMap<RegionPos, Vec<Chunk>>,
+        region_map: HashMap<RegionPos, Vec<Arc<Chunk>>>,
         world_dir: P,
-    ) {
-        let groups: Vec<(RegionPos, Vec<Chunk>)> = region_map.into_par_iter().collect();
-        groups.par_iter().for_each(|(region_pos, chunks)| {
-            let region_path = world_dir.as_ref().join(region_pos.filename());
-
-            let result = (|| -> Result<()> {
-                let mut region = if region_path.exists() {
-                    let data = std::fs::read(&region_path)?;
-                    Region::deserialize(&data)?
-                } else {
-                    Region::new(*region_pos)
-                };
-
-                for chunk in chunks {
-                    region.insert(chunk.clone());
-                }
-
-                let serialized = region.serialize();
-                std::fs::write(&region_path, serialized)?;
-                Ok(())
-            })();
-
-            match result {
-                Ok(()) => {
-                    debug!(
-                        "Saved {} chunks to region file {:?}",
-                        chunks.len(),
-                        region_path.canonicalize().unwrap()
-                    );
+    ) -> (FlushSummary, HashSet<RegionPos>) {
+        let groups: Vec<(RegionPos, Vec<Arc<Chunk>>)> = region_map.into_par_iter().collect();
+        let written = Mutex::new(HashSet::new());
+        let summary = groups
+            .par_iter()
+            .filter_map(|(region_pos, chunks)| {
+                let region_path = world_dir.as_ref().join(region_pos.filename());
+
+                match self.write_region(*region_pos, chunks) {
+                    Ok(bytes) => {
+                        written.lock().insert(*region_pos);
+                        debug!(
+                            "Saved {} chunks to region file {:?}",
+                            chunks.len(),
+                            region_path.canonicalize().unwrap()
+                        );
+                        Some(FlushSummary {
+                            chunks_saved:    chunks.len(),
+                            regions_written: 1,
+                            bytes_written:   bytes,
+                            duration:        Duration::ZERO,
+                        })
+                    }
+                    Err(e) => {
+                        error!("Failed to save region: {:?} ({} chunks): {}", region_pos, chunks.len(), e);
+                        None
+                    }
                 }
-                Err(e) => {
-                    error!("Failed to save region: {:?} ({} chunks): {}", region_pos, chunks.len(), e);
+            })
+            .reduce(FlushSummary::default, |a, b| {
+                FlushSummary {
+                    chunks_saved:    a.chunks_saved + b.chunks_saved,
+                    regions_written: a.regions_written + b.regions_written,
+                    bytes_written:   a.bytes_written + b.bytes_written,
+                    duration:        Duration::ZERO,
                 }
+            });
+        (summary, written.into_inner())
+    }
+
+    /// Lock guarding the region's file; different regions lock independently
+    fn region_lock(&self, region_pos: RegionPos) -> Arc<RwLock<()>> {
+        Arc::clone(self.region_locks.entry(region_pos).or_default().value())
+    }
+
+    /// Chunks present in the region file, none if it is missing or unreadable (`get_chunk` would
+    /// generate those anyway)
+    fn stored_chunks(&self, region_pos: RegionPos) -> HashSet<ChunkPos> {
+        let lock = self.region_lock(region_pos);
+        let _guard = lock.read();
+
+        let region_path = self.world_dir.join(region_pos.filename());
+        if !region_path.exists() {
+            return HashSet::new();
+        }
+        match std::fs::read(&region_path)
+            .map_err(anyhow::Error::from)
+            .and_then(|data| Region::deserialize(&data))
+        {
+            Ok(region) => region.occupied_positions().collect(),
+            Err(e) => {
+                warn!("[CHUNK] Failed to read {:?}, treating its chunks as missing: {}", region_path, e);
+                HashSet::new()
             }
-        });
+        }
+    }
+
+    /// Merge the chunks into their region file, creating it if needed; returns the file's size
+    fn write_region(&self, region_pos: RegionPos, chunks: &[Arc<Chunk>]) -> Result<u64> {
+        let lock = self.region_lock(region_pos);
+        let _guard = lock.write();
+
+        let region_path = self.world_dir.join(region_pos.filename());
+        let mut region = if region_path.exists() {
+            let data = std::fs::read(&region_path)?;
+            Region::deserialize(&data)?
+        } else {
+            Region::new(region_pos)
+        };
+
+        for chunk in chunks {
+            region.insert(Chunk::clone(chunk));
+        }
+
+        // Write then rename, so a crash mid-save leaves the previous region intact; the data is
+        // synced first, or the rename could reach the disk before it and leave an empty region
+        let tmp = region_path.with_extension("dat.tmp");
+        let bytes = region.serialize();
+        let mut file = std::fs::File::create(&tmp)?;
+        file.write_all(&bytes)?;
+        file.sync_all()?;
+        std::fs::rename(tmp, region_path)?;
+        Ok(bytes.len() as u64)
+    }
+
+    /// Delete `.tmp` files left behind by saves that never got to their rename
+    fn remove_interrupted_saves(world_dir: &Path) -> Result<()> {
+        for entry in std::fs::read_dir(world_dir)? {
+            let path = entry?.path();
+            if path.is_file() && path.extension().is_some_and(|ext| ext == "tmp") {
+                warn!("[STARTUP] Removing {:?} left by an interrupted save", path);
+                std::fs::remove_file(&path)?;
+            }
+        }
+        Ok(())
     }
 
     // old impl.
@@ -428,6 +926,9 @@ impl ChunkStorage {
     // }
 
     fn load_chunk_from_disk(&self, chunk_x: i32, chunk_z: i32, region_path: PathBuf) -> Result<Chunk> {
+        let lock = self.region_lock(RegionPos::from_chunk(chunk_x, chunk_z));
+        let _guard = lock.read();
+
         if !region_path.exists() {
             return Err(anyhow::anyhow!("Region file not found"));
         }
@@ -443,7 +944,22 @@ impl ChunkStorage {
 
     #[allow(dead_code)]
     pub fn cache_stats(&self) -> CacheLenCapacity {
-        CacheLenCapacity::from((self.cache.read().len(), self.cache.read().current_capacity()))
+        CacheLenCapacity::from(self.cache.len_and_capacity())
+    }
+
+    /// Snapshot of the hit/miss counters along with the current cache size
+    pub fn cache_metrics(&self) -> CacheMetrics {
+        let (len, capacity) = self.cache.len_and_capacity();
+
+        CacheMetrics {
+            hits: self.counters.hits.load(Ordering::Relaxed),
+            misses: self.counters.misses.load(Ordering::Relaxed),
+            disk_loads: self.counters.disk_loads.load(Ordering::Relaxed),
+            generations: self.counters.generations.load(Ordering::Relaxed),
+            evictions: self.counters.evictions.load(Ordering::Relaxed),
+            len,
+            capacity,
+        }
     }
 }
 
@@ -453,8 +969,594 @@ impl Clone for ChunkStorage {
             cache:           self.cache.clone(),
             world_dir:       self.world_dir.clone(),
             chunk_generator: self.chunk_generator.clone(),
-            evictions:       AtomicUsize::from(self.evictions.load(std::sync::atomic::Ordering::SeqCst)),
+            counters:        self.counters.clone(),
             chunk_gen_pool:  self.chunk_gen_pool.clone(),
+            dirty:           self.dirty.clone(),
+            region_locks:    self.region_locks.clone(),
+            edit_lock:       self.edit_lock.clone(),
+        }
+    }
+}
+
+impl ChunkProvider for ChunkStorage {
+    fn get_chunk(&self, pos: ChunkPos) -> Result<Arc<Chunk>> {
+        ChunkStorage::get_chunk(self, pos)
+    }
+
+    /// Runs on the chunk generation pool
+    fn get_chunk_async(&self, pos: ChunkPos) -> BoxFuture<'static, Result<Arc<Chunk>>> {
+        Box::pin(ChunkStorage::get_chunk_async(self, pos))
+    }
+
+    fn set_block(&self, x: i32, y: i32, z: i32, block: BlockState) -> Result<bool> {
+        if Chunk::local_y(y).is_none() {
+            return Ok(false);
+        }
+
+        let _guard = self.edit_lock.lock();
+        let chunk = ChunkStorage::get_chunk(self, ChunkPos::from_block_pos(x, z))?;
+        let Some((chunk, placed)) = edit_block(&chunk, x, y, z, block) else {
+            return Ok(false);
+        };
+        self.update_chunk(chunk);
+        Ok(placed)
+    }
+
+    fn flush(&self) -> Result<FlushSummary> {
+        self.flush_cache()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::terrain::{ChunkGenerator, FlatWorldGenerator, NoiseSettings};
+
+    fn test_storage(world_dir: &std::path::Path) -> ChunkStorage {
+        ChunkStorage::with_world_dir(
+            world_dir.to_path_buf(),
+            Arc::new(ChunkGenerator::new::<u64>(12345, NoiseSettings::default())),
+            Arc::new(ChunkGenThreadPool::new()),
+        )
+        .unwrap()
+    }
+
+    /// Flat chunks, but each `generate` reports that it started and waits to be resumed, so a test
+    /// can act while a load is in flight
+    struct PausedGenerator {
+        started: Mutex<mpsc::Sender<()>>,
+        resume:  Mutex<mpsc::Receiver<()>>,
+    }
+
+    impl WorldGenerator for PausedGenerator {
+        fn generate(&self, pos: ChunkPos) -> Chunk {
+            self.started.lock().send(()).unwrap();
+            self.resume.lock().recv().unwrap();
+            FlatWorldGenerator::default().generate(pos)
+        }
+    }
+
+    /// Storage over `PausedGenerator`, with the ends that see generation start and resume it
+    fn paused_storage(world_dir: &std::path::Path) -> (ChunkStorage, mpsc::Receiver<()>, mpsc::Sender<()>) {
+        let (started_tx, started_rx) = mpsc::channel();
+        let (resume_tx, resume_rx) = mpsc::channel();
+        let generator = PausedGenerator {
+            started: Mutex::new(started_tx),
+            resume:  Mutex::new(resume_rx),
+        };
+        let storage = ChunkStorage::with_world_dir(
+            world_dir.to_path_buf(),
+            Arc::new(generator),
+            Arc::new(ChunkGenThreadPool::new()),
+        )
+        .unwrap();
+        (storage, started_rx, resume_tx)
+    }
+
+    /// The flat chunk at `pos` with gravel in its corner, as a player edit would leave it
+    fn edited_chunk(pos: ChunkPos) -> Arc<Chunk> {
+        let mut chunk = FlatWorldGenerator::default().generate(pos);
+        chunk.set_block(0, 0, 0, BlockType::Gravel);
+        Arc::new(chunk)
+    }
+
+    #[test]
+    fn test_pregen_area_bounds() {
+        let area: Vec<ChunkPos> = pregen_area(ChunkPos::new(0, 0), 8).collect();
+        assert_eq!(area.len(), 256);
+        assert!(
+            area.iter()
+                .all(|p| (-8..8).contains(&p.x) && (-8..8).contains(&p.z))
+        );
+
+        let area: Vec<ChunkPos> = pregen_area(ChunkPos::new(100, -40), 3).collect();
+        assert_eq!(area.len(), 36);
+        assert_eq!(area.first(), Some(&ChunkPos::new(97, -43)));
+        assert_eq!(area.last(), Some(&ChunkPos::new(102, -38)));
+
+        assert_eq!(pregen_area(ChunkPos::new(0, 0), 0).count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_new_storage_generates_on_first_access() {
+        let world_dir = std::env::temp_dir().join(format!("rustcraft_lazy_{}", uuid::Uuid::new_v4()));
+        let storage = ChunkStorage::new(
+            world_dir.clone(),
+            Arc::new(ChunkGenerator::new::<u64>(12345, NoiseSettings::default())),
+            Arc::new(ChunkGenThreadPool::new()),
+        )
+        .unwrap();
+
+        assert_eq!(storage.cache_metrics().len, 0);
+        assert_eq!(std::fs::read_dir(&world_dir).unwrap().count(), 0);
+
+        storage.get_chunk(ChunkPos::new(4, -9)).unwrap();
+        let metrics = storage.cache_metrics();
+        assert_eq!(metrics.generations, 1);
+        assert_eq!(metrics.len, 1);
+
+        let _ = std::fs::remove_dir_all(&world_dir);
+    }
+
+    #[test]
+    fn test_pregeneration_reports_progress_and_can_be_cancelled() {
+        let world_dir = std::env::temp_dir().join(format!("rustcraft_pregen_{}", uuid::Uuid::new_v4()));
+        let storage = test_storage(&world_dir);
+        let center = ChunkPos::new(0, 0);
+
+        let cancelled = storage
+            .pregenerate_spawn_area(center, 2, Arc::new(AtomicBool::new(true)))
+            .unwrap();
+        assert_eq!(cancelled.total, 16);
+        assert_eq!(cancelled.done, 0);
+        assert_eq!(storage.cache_metrics().generations, 0);
+
+        let progress = storage
+            .pregenerate_spawn_area(center, 2, Arc::new(AtomicBool::new(false)))
+            .unwrap();
+        assert_eq!((progress.done, progress.total), (16, 16));
+        assert_eq!(progress.percent(), 100.0);
+        assert_eq!(progress.eta(), Some(Duration::ZERO));
+        assert!(world_dir.join(RegionPos::from(center).filename()).exists());
+
+        // The region is on disk now, so a second run has nothing to do
+        let rerun = storage
+            .pregenerate_spawn_area(center, 2, Arc::new(AtomicBool::new(false)))
+            .unwrap();
+        assert_eq!(rerun.total, 0);
+
+        // A wider area reuses those regions, but only the new ring around them is stored yet
+        let wider = storage
+            .pregenerate_spawn_area(center, 3, Arc::new(AtomicBool::new(false)))
+            .unwrap();
+        assert_eq!((wider.done, wider.total), (20, 20));
+        assert_eq!(storage.cache_metrics().generations, 36);
+
+        let halfway = PregenProgress {
+            done:    25,
+            total:   100,
+            elapsed: Duration::from_secs(10),
+        };
+        assert_eq!(halfway.eta(), Some(Duration::from_secs(30)));
+        assert_eq!(halfway.to_string(), "25/100 chunks (25.0%), ETA 30s");
+
+        let _ = std::fs::remove_dir_all(&world_dir);
+    }
+
+    #[test]
+    fn test_spiral_offsets_by_distance() {
+        let offsets: Vec<(i32, i32)> = spiral_chunk_offsets(3).collect();
+        assert_eq!(offsets.len(), 49);
+        assert_eq!(offsets[0], (0, 0));
+
+        let distance = |&(dx, dz): &(i32, i32)| dx.abs().max(dz.abs());
+        assert!(offsets.windows(2).all(|w| distance(&w[0]) <= distance(&w[1])));
+        assert!(offsets.iter().all(|o| distance(o) <= 3));
+
+        let unique: std::collections::HashSet<_> = offsets.iter().collect();
+        assert_eq!(unique.len(), offsets.len());
+
+        assert_eq!(spiral_chunk_offsets(0).collect::<Vec<_>>(), vec![(0, 0)]);
+    }
+
+    #[tokio::test]
+    async fn test_get_chunk_async_returns_area_nearest_first() {
+        let world_dir = std::env::temp_dir().join(format!("rustcraft_async_{}", uuid::Uuid::new_v4()));
+        let storage = test_storage(&world_dir);
+        let center = ChunkPos::new(-7, 3);
+
+        let area: Vec<ChunkPos> = spiral_chunk_offsets(2)
+            .map(|(dx, dz)| ChunkPos::new(center.x + dx, center.z + dz))
+            .collect();
+        assert_eq!(area.len(), 25);
+
+        let requests: Vec<_> = area.iter().map(|&pos| storage.get_chunk_async(pos)).collect();
+        let chunks = futures::future::try_join_all(requests).await.unwrap();
+
+        assert_eq!(chunks[0].pos, center);
+        assert_eq!(chunks.iter().map(|c| c.pos).collect::<Vec<_>>(), area);
+        assert_eq!(storage.cache_metrics().generations, 25);
+
+        let _ = std::fs::remove_dir_all(&world_dir);
+    }
+
+    #[test]
+    fn test_cache_stats_consistent_under_concurrent_inserts() {
+        const THREADS: i32 = 4;
+        const PER_THREAD: i32 = 1_500;
+
+        let world_dir = std::env::temp_dir().join(format!("rustcraft_stats_{}", uuid::Uuid::new_v4()));
+        let storage = test_storage(&world_dir);
+        let done = Arc::new(std::sync::atomic::AtomicBool::new(false));
+
+        // Watch the stats while the cache grows past its initial capacity
+        let watcher = {
+            let storage = storage.clone();
+            let done = Arc::clone(&done);
+            std::thread::spawn(move || {
+                let mut snapshots = 0;
+                let mut last = storage.cache_stats();
+                while !done.load(Ordering::Relaxed) {
+                    let stats = storage.cache_stats();
+                    assert!(stats.len() <= stats.capacity(), "{:?}", stats);
+                    assert!(stats.len() >= last.len() && stats.capacity() >= last.capacity());
+                    last = stats;
+                    snapshots += 1;
+                }
+                snapshots
+            })
+        };
+
+        let writers: Vec<_> = (0..THREADS)
+            .map(|t| {
+                let storage = storage.clone();
+                std::thread::spawn(move || {
+                    for i in 0..PER_THREAD {
+                        let pos = ChunkPos::new(t, i);
+                        storage.cache_chunk(&HashSet::new(), pos, Arc::new(Chunk::new(pos)));
+                    }
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+        done.store(true, Ordering::Relaxed);
+        assert!(watcher.join().unwrap() > 0);
+
+        let stats = storage.cache_stats();
+        assert_eq!(stats.len(), (THREADS * PER_THREAD) as usize);
+        assert_eq!(stats.capacity(), INITIAL_CAPACITY * 2);
+
+        let _ = std::fs::remove_dir_all(&world_dir);
+    }
+
+    #[test]
+    fn test_surface_height_matches_generated_terrain() {
+        let world_dir = std::env::temp_dir().join(format!("rustcraft_surface_{}", uuid::Uuid::new_v4()));
+        let storage = test_storage(&world_dir);
+        let generator = ChunkGenerator::new::<u64>(12345, NoiseSettings::default());
+
+        let mut saw_land = false;
+        for (x, z) in [(0, 0), (5, 9), (37, 100), (200, 17), (511, 3), (64, 480)] {
+            let y = storage.surface_height(x, z).unwrap();
+            let chunk = generator.generate(ChunkPos::from_block_pos(x, z));
+            let (lx, lz) = (x.rem_euclid(16) as usize, z.rem_euclid(16) as usize);
+
+            // Standing in air, on top of the ground or the sea
+            let local_y = Chunk::local_y(y).unwrap();
+            assert_eq!(chunk.get_block(lx, local_y, lz), Some(BlockType::Air), "({}, {})", x, z);
+            let below = chunk.get_block(lx, local_y - 1, lz).unwrap();
+            assert_ne!(below, BlockType::Air, "({}, {})", x, z);
+            saw_land |= below != BlockType::Water;
         }
+        assert!(saw_land);
+
+        let _ = std::fs::remove_dir_all(&world_dir);
+    }
+
+    #[test]
+    fn test_get_chunk_shares_the_cached_chunk() {
+        let world_dir = std::env::temp_dir().join(format!("rustcraft_shared_{}", uuid::Uuid::new_v4()));
+        let storage = test_storage(&world_dir);
+        let pos = ChunkPos::new(2, -3);
+
+        let first = storage.get_chunk(pos).unwrap();
+        let second = storage.get_chunk(pos).unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+
+        // Editing copies, leaving readers of the old chunk untouched until it is replaced
+        let mut edited = storage.get_chunk(pos).unwrap();
+        Arc::make_mut(&mut edited).set_block(0, 0, 0, BlockType::Gravel);
+        assert!(!Arc::ptr_eq(&first, &edited));
+        assert_ne!(first.get_block(0, 0, 0), Some(BlockType::Gravel));
+
+        storage.update_chunk(edited);
+        let updated = storage.get_chunk(pos).unwrap();
+        assert_eq!(updated.get_block(0, 0, 0), Some(BlockType::Gravel));
+
+        let _ = std::fs::remove_dir_all(&world_dir);
+    }
+
+    #[test]
+    fn test_load_keeps_an_edit_made_while_it_ran() {
+        let world_dir = std::env::temp_dir().join(format!("rustcraft_load_race_{}", uuid::Uuid::new_v4()));
+        let (storage, started, resume) = paused_storage(&world_dir);
+        let pos = ChunkPos::new(4, -4);
+
+        let loader = {
+            let storage = storage.clone();
+            std::thread::spawn(move || storage.get_chunk(pos).unwrap())
+        };
+        started.recv().unwrap();
+        storage.update_chunk(edited_chunk(pos));
+        resume.send(()).unwrap();
+
+        let loaded = loader.join().unwrap();
+        assert_eq!(loaded.get_block(0, 0, 0), Some(BlockType::Gravel));
+        assert!(Arc::ptr_eq(&loaded, &storage.get_chunk(pos).unwrap()));
+        assert!(storage.dirty.lock().contains(&pos));
+
+        let _ = std::fs::remove_dir_all(&world_dir);
+    }
+
+    #[tokio::test]
+    async fn test_async_load_keeps_an_edit_made_while_it_ran() {
+        let world_dir = std::env::temp_dir().join(format!("rustcraft_async_race_{}", uuid::Uuid::new_v4()));
+        let (storage, started, resume) = paused_storage(&world_dir);
+        let pos = ChunkPos::new(-6, 2);
+
+        let request = storage.get_chunk_async(pos);
+        started.recv().unwrap();
+        storage.update_chunk(edited_chunk(pos));
+        resume.send(()).unwrap();
+
+        let loaded = request.await.unwrap();
+        assert_eq!(loaded.get_block(0, 0, 0), Some(BlockType::Gravel));
+        assert!(Arc::ptr_eq(&loaded, &storage.get_chunk(pos).unwrap()));
+
+        let _ = std::fs::remove_dir_all(&world_dir);
+    }
+
+    #[test]
+    fn test_regenerate_chunk_replaces_edits() {
+        let world_dir = std::env::temp_dir().join(format!("rustcraft_regen_{}", uuid::Uuid::new_v4()));
+        let storage = test_storage(&world_dir);
+        let generator = ChunkGenerator::new::<u64>(12345, NoiseSettings::default());
+        let pos = ChunkPos::new(3, -5);
+        let fresh = bincode::serialize(&generator.generate(pos)).unwrap();
+
+        let mut edited = storage.get_chunk(pos).unwrap();
+        Arc::make_mut(&mut edited).set_block(1, 2, 3, BlockType::Gravel);
+        storage.update_chunk(edited);
+        storage.flush_cache().unwrap();
+
+        let regenerated = storage.regenerate_chunk(pos);
+        assert_eq!(bincode::serialize(&*regenerated).unwrap(), fresh);
+        let cached = storage.get_chunk(pos).unwrap();
+        assert!(Arc::ptr_eq(&cached, &regenerated));
+        assert_ne!(cached.get_block(1, 2, 3), Some(BlockType::Gravel));
+
+        // The stored copy is overwritten too, rather than the edit coming back on reload
+        assert!(storage.unload_chunk(pos).unwrap());
+        assert_eq!(bincode::serialize(&*storage.get_chunk(pos).unwrap()).unwrap(), fresh);
+
+        let _ = std::fs::remove_dir_all(&world_dir);
+    }
+
+    #[test]
+    fn test_sweep_unloads_distant_chunks_and_reloads_them_from_disk() {
+        let world_dir = std::env::temp_dir().join(format!("rustcraft_unload_{}", uuid::Uuid::new_v4()));
+        let storage = test_storage(&world_dir);
+        let player = ChunkPos::new(0, 0);
+        let near = ChunkPos::new(2, -2);
+        let far = ChunkPos::new(40, -7);
+
+        storage.get_chunk(near).unwrap();
+        let mut edited = storage.get_chunk(far).unwrap();
+        Arc::make_mut(&mut edited).set_block(3, 100, 4, BlockType::Gravel);
+        storage.update_chunk(edited);
+
+        assert_eq!(storage.unload_distant(&[player], 4).unwrap(), 1);
+        assert!(storage.cache.contains(&near));
+        assert!(!storage.cache.contains(&far));
+
+        // Comes back from its region file with the edit, rather than being generated again
+        let generations = storage.cache_metrics().generations;
+        let reloaded = storage.get_chunk(far).unwrap();
+        assert_eq!(reloaded.get_block(3, 100, 4), Some(BlockType::Gravel));
+        assert_eq!(storage.cache_metrics().disk_loads, 1);
+        assert_eq!(storage.cache_metrics().generations, generations);
+
+        // A clean chunk is dropped without a write; unloading twice is a no-op
+        assert!(storage.unload_chunk(far).unwrap());
+        assert!(!storage.unload_chunk(far).unwrap());
+        assert_eq!(storage.get_chunk(far).unwrap().get_block(3, 100, 4), Some(BlockType::Gravel));
+
+        let _ = std::fs::remove_dir_all(&world_dir);
+    }
+
+    #[test]
+    fn test_sweep_without_players_keeps_everything() {
+        let world_dir = std::env::temp_dir().join(format!("rustcraft_unload_empty_{}", uuid::Uuid::new_v4()));
+        let storage = test_storage(&world_dir);
+        storage.get_chunk(ChunkPos::new(0, 0)).unwrap();
+
+        assert_eq!(storage.unload_distant(&[], 4).unwrap(), 0);
+        assert!(storage.cache.contains(&ChunkPos::new(0, 0)));
+
+        let _ = std::fs::remove_dir_all(&world_dir);
+    }
+
+    #[test]
+    fn test_eviction_skips_dirty_chunks() {
+        let world_dir = std::env::temp_dir().join(format!("rustcraft_evict_dirty_{}", uuid::Uuid::new_v4()));
+        let storage = test_storage(&world_dir);
+
+        // Fill the cache to its largest size with unsaved chunks
+        let capacity = storage.cache.max_capacity() as i32;
+        for i in 0..capacity {
+            let pos = ChunkPos::new(i % 64, i / 64);
+            storage.cache_dirty_chunk(pos, Arc::new(Chunk::new(pos)));
+        }
+
+        // A clean chunk goes over capacity rather than push one of them out
+        let extra = ChunkPos::new(-1, -1);
+        storage.cache_chunk(&storage.dirty.lock(), extra, Arc::new(Chunk::new(extra)));
+        assert_eq!(storage.cache.len(), capacity as usize + 1);
+        assert_eq!(storage.cache_metrics().evictions, 0);
+
+        let _ = std::fs::remove_dir_all(&world_dir);
+    }
+
+    #[test]
+    fn test_load_waits_for_a_save_of_the_same_region() {
+        let world_dir = std::env::temp_dir().join(format!("rustcraft_region_lock_{}", uuid::Uuid::new_v4()));
+        let storage = test_storage(&world_dir);
+        let chunks = vec![storage.get_chunk(ChunkPos::new(0, 0)).unwrap()];
+        let region_pos = RegionPos::from_chunk(0, 0);
+        let region_path = world_dir.join(region_pos.filename());
+        let mut region = Region::new(region_pos);
+        region.insert(Chunk::clone(&chunks[0]));
+        let bytes = region.serialize();
+
+        std::thread::scope(|scope| {
+            // A save caught half way through writing the file
+            let lock = storage.region_lock(region_pos);
+            let saving = lock.write();
+            std::fs::write(&region_path, &bytes[..bytes.len() / 2]).unwrap();
+
+            let loader = scope.spawn(|| storage.load_chunk_from_disk(0, 0, region_path.clone()));
+            std::thread::sleep(std::time::Duration::from_millis(50));
+            std::fs::write(&region_path, &bytes).unwrap();
+            drop(saving);
+
+            let loaded = loader.join().unwrap();
+            assert!(loaded.is_ok(), "Read the region mid-save: {:?}", loaded.err());
+
+            // Other regions aren't held up by the save
+            let _saving = lock.write();
+            let other = storage.region_lock(RegionPos::from_chunk(32, 0));
+            assert!(other.try_write().is_some());
+        });
+
+        // And saves through the storage take the same lock
+        storage.write_region(region_pos, &chunks).unwrap();
+        assert!(storage.load_chunk_from_disk(0, 0, region_path).is_ok());
+
+        let _ = std::fs::remove_dir_all(&world_dir);
+    }
+
+    #[test]
+    fn test_interrupted_save_keeps_the_old_region() {
+        let world_dir = std::env::temp_dir().join(format!("rustcraft_tmp_save_{}", uuid::Uuid::new_v4()));
+        let storage = test_storage(&world_dir);
+        let pos = ChunkPos::new(1, 1);
+        let region_pos = RegionPos::from_chunk(pos.x, pos.z);
+        storage
+            .write_region(region_pos, &[storage.get_chunk(pos).unwrap()])
+            .unwrap();
+
+        // A crash after the temp file was half written, before the rename
+        let region_path = world_dir.join(region_pos.filename());
+        let tmp = region_path.with_extension("dat.tmp");
+        std::fs::write(&tmp, b"half a region").unwrap();
+        drop(storage);
+
+        let storage = test_storage(&world_dir);
+        assert!(!tmp.exists());
+        assert!(storage.load_chunk_from_disk(pos.x, pos.z, region_path).is_ok());
+
+        let _ = std::fs::remove_dir_all(&world_dir);
+    }
+
+    #[test]
+    fn test_flush_summary_counts_what_was_written() {
+        let world_dir = std::env::temp_dir().join(format!("rustcraft_flush_{}", uuid::Uuid::new_v4()));
+        let storage = test_storage(&world_dir);
+
+        // Three chunks in region (0, 0) and one in region (-1, 0)
+        for pos in [
+            ChunkPos::new(0, 0),
+            ChunkPos::new(1, 0),
+            ChunkPos::new(31, 31),
+            ChunkPos::new(-1, 0),
+        ] {
+            storage.get_chunk(pos).unwrap();
+        }
+
+        let summary = storage.flush_cache().unwrap();
+        assert_eq!(summary.chunks_saved, 4);
+        assert_eq!(summary.regions_written, 2);
+        let on_disk: u64 = [RegionPos::from_chunk(0, 0), RegionPos::from_chunk(-1, 0)]
+            .iter()
+            .map(|region| {
+                std::fs::metadata(world_dir.join(region.filename()))
+                    .unwrap()
+                    .len()
+            })
+            .sum();
+        assert_eq!(summary.bytes_written, on_disk);
+        assert!(summary.duration > Duration::ZERO);
+
+        let _ = std::fs::remove_dir_all(&world_dir);
+    }
+
+    #[test]
+    fn test_failed_region_stays_dirty() {
+        let world_dir = std::env::temp_dir().join(format!("rustcraft_flush_fail_{}", uuid::Uuid::new_v4()));
+        let storage = test_storage(&world_dir);
+        let good = ChunkPos::new(0, 0);
+        let bad = ChunkPos::new(-1, 0);
+        storage.get_chunk(good).unwrap();
+        storage.get_chunk(bad).unwrap();
+
+        // A directory where the region file should be makes that region's write fail
+        let bad_region = RegionPos::from_chunk(bad.x, bad.z);
+        std::fs::create_dir(world_dir.join(bad_region.filename())).unwrap();
+
+        let summary = storage.flush_cache().unwrap();
+        assert_eq!(summary.regions_written, 1);
+        let dirty = storage.dirty.lock();
+        assert!(!dirty.contains(&good));
+        assert!(dirty.contains(&bad));
+        drop(dirty);
+
+        let _ = std::fs::remove_dir_all(&world_dir);
+    }
+
+    #[test]
+    fn test_cache_metrics_counts() {
+        let world_dir = std::env::temp_dir().join(format!("rustcraft_metrics_{}", uuid::Uuid::new_v4()));
+        let storage = test_storage(&world_dir);
+
+        let a = ChunkPos::new(0, 0);
+        let b = ChunkPos::new(1, 0);
+
+        storage.get_chunk(a).unwrap(); // generated
+        storage.get_chunk(a).unwrap(); // cached
+        storage.get_chunk(b).unwrap(); // generated
+        storage.get_chunk(a).unwrap(); // cached
+        storage.get_chunk(b).unwrap(); // cached
+
+        let metrics = storage.cache_metrics();
+        assert_eq!(metrics.hits, 3);
+        assert_eq!(metrics.misses, 2);
+        assert_eq!(metrics.generations, 2);
+        assert_eq!(metrics.disk_loads, 0);
+        assert_eq!(metrics.len, 2);
+        assert!((metrics.hit_rate() - 0.6).abs() < f64::EPSILON);
+
+        // A fresh storage over the flushed world loads from disk instead of generating
+        storage.flush_cache().unwrap();
+        let reloaded = test_storage(&world_dir);
+        reloaded.get_chunk(a).unwrap();
+
+        let metrics = reloaded.cache_metrics();
+        assert_eq!(metrics.misses, 1);
+        assert_eq!(metrics.disk_loads, 1);
+        assert_eq!(metrics.generations, 0);
+
+        let _ = std::fs::remove_dir_all(&world_dir);
     }
 }
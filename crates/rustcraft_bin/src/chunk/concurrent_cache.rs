@@ -0,0 +1,461 @@
+#![allow(dead_code)]
+
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use dashmap::DashMap;
+use parking_lot::Mutex;
+
+use crate::chunk::cache::{SHRINK_AFTER, SHRINK_USAGE_THRESHOLD};
+
+#[derive(Debug)]
+struct CacheEntry<V> {
+    value:          V,
+    hits:           AtomicUsize,
+    last_hit_reset: Instant,
+    /// Value of the cache's access clock when this entry was last touched
+    last_access:    AtomicU64,
+    /// Microseconds since the cache's `created_at` when this entry was last touched
+    touched_us:     AtomicU64,
+}
+
+/// Thread-safe counterpart to `LruCache`, backed by a sharded `DashMap`
+/// Reads only take a shard read lock; inserts are serialized so capacity checks and eviction stay
+/// consistent, without blocking concurrent `get`s
+pub struct ConcurrentLruCache<K: Clone + Eq + std::hash::Hash, V> {
+    current_capacity:   AtomicUsize,
+    initial_capacity:   usize,
+    max_capacity:       usize,
+    low_usage_since:    Mutex<Option<Instant>>,
+    item_size:          usize,
+    cache:              DashMap<K, CacheEntry<V>>,
+    insert_lock:        Mutex<()>,
+    access_clock:       AtomicU64,
+    hit_reset_interval: Duration,
+    total_hits:         AtomicUsize,
+    total_misses:       AtomicUsize,
+    /// Entries untouched for longer than this are removed by `evict_expired_evicting`
+    ttl:                Option<Duration>,
+    created_at:         Instant,
+}
+
+impl<K: Clone + Eq + std::hash::Hash, V: Clone> ConcurrentLruCache<K, V> {
+    pub fn new(initial_capacity: usize) -> Self {
+        Self::with_growth(initial_capacity, initial_capacity, 0)
+    }
+
+    pub fn with_growth(initial_capacity: usize, max_capacity: usize, item_size: usize) -> Self {
+        Self {
+            current_capacity: AtomicUsize::new(initial_capacity),
+            initial_capacity,
+            max_capacity,
+            low_usage_since: Mutex::new(None),
+            item_size,
+            cache: DashMap::new(),
+            insert_lock: Mutex::new(()),
+            access_clock: AtomicU64::new(0),
+            hit_reset_interval: Duration::from_secs(300), // 5 minutes
+            total_hits: AtomicUsize::new(0),
+            total_misses: AtomicUsize::new(0),
+            ttl: None,
+            created_at: Instant::now(),
+        }
+    }
+
+    /// Let entries expire after `ttl` without a `get` or `insert`, like `LruCache::with_ttl`
+    /// Nothing expires by itself; call `evict_expired_evicting` periodically
+    pub fn expire_after(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    pub fn get(&self, key: &K) -> Option<V> {
+        match self.cache.get(key) {
+            Some(entry) => {
+                entry.hits.fetch_add(1, Ordering::Relaxed);
+                entry.last_access.store(self.tick(), Ordering::Relaxed);
+                entry
+                    .touched_us
+                    .store(self.micros_since_creation(Instant::now()), Ordering::Relaxed);
+                self.total_hits.fetch_add(1, Ordering::Relaxed);
+                Some(entry.value.clone())
+            }
+            None => {
+                self.total_misses.fetch_add(1, Ordering::Relaxed);
+                None
+            }
+        }
+    }
+
+    /// Insert a value, returning `(old_value, expanded, evicted_key)` like `LruCache::insert`
+    pub fn insert(&self, key: K, value: V) -> (Option<V>, bool, Option<K>) {
+        self.insert_evicting(key, value, |_| true)
+    }
+
+    /// `insert`, but only entries `evictable` accepts may be evicted to make room
+    /// When none are, the cache goes over capacity rather than drop a pinned entry
+    pub fn insert_evicting(
+        &self,
+        key: K,
+        value: V,
+        evictable: impl Fn(&K) -> bool,
+    ) -> (Option<V>, bool, Option<K>) {
+        let _guard = self.insert_lock.lock();
+
+        let mut expanded = false;
+        let mut evicted_key = None;
+
+        // Replacing an existing key never needs room
+        if !self.cache.contains_key(&key) && self.cache.len() >= self.current_capacity() {
+            if self.try_expand() {
+                expanded = true;
+            } else {
+                evicted_key = self.evict_lowest_hits(&evictable);
+            }
+        }
+
+        let old_value = self.cache.insert(
+            key,
+            CacheEntry {
+                value,
+                hits: AtomicUsize::new(0),
+                last_hit_reset: Instant::now(),
+                last_access: AtomicU64::new(self.tick()),
+                touched_us: AtomicU64::new(self.micros_since_creation(Instant::now())),
+            },
+        );
+
+        (old_value.map(|e| e.value), expanded, evicted_key)
+    }
+
+    fn try_expand(&self) -> bool {
+        let current = self.current_capacity();
+        if current < self.max_capacity && self.item_size > 0 {
+            let new_capacity = std::cmp::min(current * 2, self.max_capacity);
+            if new_capacity > current {
+                self.current_capacity.store(new_capacity, Ordering::Relaxed);
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Shrink back towards the initial capacity after sustained low usage, like
+    /// `LruCache::try_shrink`; returns whether it shrank and the keys evicted to fit
+    pub fn try_shrink(&self) -> (bool, Vec<K>) {
+        self.try_shrink_evicting(|_| true)
+    }
+
+    /// `try_shrink`, evicting only entries `evictable` accepts; pinned entries that don't fit keep
+    /// the capacity up
+    pub fn try_shrink_evicting(&self, evictable: impl Fn(&K) -> bool) -> (bool, Vec<K>) {
+        self.try_shrink_at(Instant::now(), evictable)
+    }
+
+    fn try_shrink_at(&self, now: Instant, evictable: impl Fn(&K) -> bool) -> (bool, Vec<K>) {
+        let _guard = self.insert_lock.lock();
+        let mut low_usage_since = self.low_usage_since.lock();
+        if self.usage_ratio() >= SHRINK_USAGE_THRESHOLD {
+            *low_usage_since = None;
+            return (false, Vec::new());
+        }
+
+        let since = *low_usage_since.get_or_insert(now);
+        let current = self.current_capacity();
+        let new_capacity = std::cmp::max(current / 2, self.initial_capacity);
+        if now.saturating_duration_since(since) < SHRINK_AFTER || new_capacity >= current {
+            return (false, Vec::new());
+        }
+
+        let mut evicted = Vec::new();
+        while self.cache.len() > new_capacity
+            && let Some(key) = self.evict_lowest_hits(&evictable)
+        {
+            evicted.push(key);
+        }
+        let new_capacity = new_capacity.max(self.cache.len()).min(current);
+        self.current_capacity.store(new_capacity, Ordering::Relaxed);
+        *low_usage_since = Some(now);
+        (true, evicted)
+    }
+
+    /// Remove every entry `evictable` accepts that has gone the TTL without access, returning
+    /// their keys; a no-op when the cache has no TTL
+    pub fn evict_expired_evicting(&self, evictable: impl Fn(&K) -> bool) -> Vec<K> {
+        self.evict_expired_at(Instant::now(), evictable)
+    }
+
+    fn evict_expired_at(&self, now: Instant, evictable: impl Fn(&K) -> bool) -> Vec<K> {
+        let Some(ttl) = self.ttl else {
+            return Vec::new();
+        };
+        let _guard = self.insert_lock.lock();
+        let now_us = self.micros_since_creation(now);
+        let is_expired = |entry: &CacheEntry<V>| {
+            now_us.saturating_sub(entry.touched_us.load(Ordering::Relaxed)) > ttl.as_micros() as u64
+        };
+
+        let candidates: Vec<K> = self
+            .cache
+            .iter()
+            .filter(|entry| evictable(entry.key()) && is_expired(entry.value()))
+            .map(|entry| entry.key().clone())
+            .collect();
+        // A `get` since the scan refreshed the entry, so check again as it is removed
+        candidates
+            .into_iter()
+            .filter(|key| self.cache.remove_if(key, |_, entry| is_expired(entry)).is_some())
+            .collect()
+    }
+
+    fn micros_since_creation(&self, now: Instant) -> u64 {
+        now.saturating_duration_since(self.created_at).as_micros() as u64
+    }
+
+    /// Evict the evictable entry with the fewest hits, breaking ties by least recent access
+    fn evict_lowest_hits(&self, evictable: impl Fn(&K) -> bool) -> Option<K> {
+        let victim = self
+            .cache
+            .iter()
+            .filter(|entry| evictable(entry.key()))
+            .min_by_key(|entry| {
+                (entry.hits.load(Ordering::Relaxed), entry.last_access.load(Ordering::Relaxed))
+            })
+            .map(|entry| entry.key().clone())?;
+
+        self.cache.remove(&victim);
+        Some(victim)
+    }
+
+    fn tick(&self) -> u64 {
+        self.access_clock.fetch_add(1, Ordering::Relaxed)
+    }
+
+    pub fn contains(&self, key: &K) -> bool {
+        self.cache.contains_key(key)
+    }
+
+    pub fn remove(&self, key: &K) -> Option<V> {
+        let _guard = self.insert_lock.lock();
+        self.cache.remove(key).map(|(_, e)| e.value)
+    }
+
+    pub fn len(&self) -> usize {
+        self.cache.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cache.is_empty()
+    }
+
+    pub fn clear(&self) {
+        let _guard = self.insert_lock.lock();
+        self.cache.clear();
+    }
+
+    /// Visit every cached entry
+    /// Each shard is read-locked while it is visited, so `f` must not insert into this cache
+    pub fn for_each<F: FnMut(&K, &V)>(&self, mut f: F) {
+        for entry in self.cache.iter() {
+            f(entry.key(), &entry.value().value);
+        }
+    }
+
+    /// `(len, current_capacity)` read together; inserts and removals wait until both are read, so
+    /// the pair always describes one state of the cache
+    pub fn len_and_capacity(&self) -> (usize, usize) {
+        let _guard = self.insert_lock.lock();
+        (self.cache.len(), self.current_capacity())
+    }
+
+    pub fn current_capacity(&self) -> usize {
+        self.current_capacity.load(Ordering::Relaxed)
+    }
+
+    pub fn max_capacity(&self) -> usize {
+        self.max_capacity
+    }
+
+    pub fn usage_ratio(&self) -> f32 {
+        self.len() as f32 / self.current_capacity() as f32
+    }
+
+    pub fn reset_hit_counts(&self) {
+        let now = Instant::now();
+        for mut entry in self.cache.iter_mut() {
+            if now.duration_since(entry.last_hit_reset) >= self.hit_reset_interval {
+                entry.hits.store(0, Ordering::Relaxed);
+                entry.last_hit_reset = now;
+            }
+        }
+    }
+
+    pub fn get_hit_count(&self, key: &K) -> Option<usize> {
+        self.cache.get(key).map(|e| e.hits.load(Ordering::Relaxed))
+    }
+
+    /// Total lookups served from the cache since creation
+    pub fn total_hits(&self) -> usize {
+        self.total_hits.load(Ordering::Relaxed)
+    }
+
+    /// Total lookups that missed the cache since creation
+    pub fn total_misses(&self) -> usize {
+        self.total_misses.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+
+    #[test]
+    fn test_concurrent_cache_growth_and_eviction() {
+        let cache = ConcurrentLruCache::with_growth(2, 4, 1);
+
+        cache.insert(1, "a");
+        cache.insert(2, "b");
+        let (_, expanded, _) = cache.insert(3, "c");
+        assert!(expanded);
+        assert_eq!(cache.current_capacity(), 4);
+
+        cache.insert(4, "d");
+        cache.get(&1);
+        cache.get(&2);
+        cache.get(&4);
+
+        // 3 has no hits, so it's the one to go
+        let (_, expanded, evicted) = cache.insert(5, "e");
+        assert!(!expanded);
+        assert_eq!(evicted, Some(3));
+        assert_eq!(cache.len(), 4);
+        assert_eq!(cache.get(&1), Some("a"));
+    }
+
+    #[test]
+    fn test_concurrent_cache_ttl_expiry() {
+        let cache = ConcurrentLruCache::new(4).expire_after(Duration::from_secs(10));
+        let start = cache.created_at;
+        cache.insert(1, "a");
+        cache.insert(2, "b");
+        cache.insert(3, "c");
+
+        assert!(
+            cache
+                .evict_expired_at(start + Duration::from_secs(5), |_| true)
+                .is_empty()
+        );
+
+        // Pinned entries stay however old they are
+        let mut expired = cache.evict_expired_at(start + Duration::from_secs(11), |k| *k != 3);
+        expired.sort();
+        assert_eq!(expired, vec![1, 2]);
+        assert_eq!(cache.get(&3), Some("c"));
+
+        // Without a TTL nothing expires
+        let cache = ConcurrentLruCache::new(4);
+        cache.insert(1, "a");
+        assert!(
+            cache
+                .evict_expired_at(Instant::now() + Duration::from_secs(3600), |_| true)
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_concurrent_cache_shrink_and_regrow() {
+        let cache = ConcurrentLruCache::with_growth(2, 8, 1);
+        for i in 0..5 {
+            cache.insert(i, i);
+        }
+        assert_eq!(cache.current_capacity(), 8);
+        for i in 1..5 {
+            cache.remove(&i);
+        }
+
+        let start = Instant::now();
+        assert!(!cache.try_shrink_at(start, |_| true).0);
+        assert!(cache.try_shrink_at(start + SHRINK_AFTER, |_| true).0);
+        assert_eq!(cache.current_capacity(), 4);
+        assert_eq!(cache.get(&0), Some(0));
+
+        for i in 1..6 {
+            cache.insert(i, i);
+        }
+        assert_eq!(cache.current_capacity(), 8);
+        assert_eq!(cache.len(), 6);
+    }
+
+    #[test]
+    fn test_pinned_entries_are_never_evicted() {
+        let cache = ConcurrentLruCache::new(2);
+        cache.insert(1, "a");
+        cache.insert(2, "b");
+        cache.get(&2);
+
+        // 1 has the fewest hits but is pinned, so 2 goes instead
+        let (_, _, evicted) = cache.insert_evicting(3, "c", |&k| k != 1);
+        assert_eq!(evicted, Some(2));
+
+        // With everything pinned the cache overflows instead
+        let (_, _, evicted) = cache.insert_evicting(4, "d", |_| false);
+        assert_eq!(evicted, None);
+        assert_eq!(cache.len(), 3);
+        assert_eq!(cache.get(&1), Some("a"));
+    }
+
+    #[test]
+    fn test_concurrent_inserts_not_lost() {
+        const THREADS: usize = 8;
+        const PER_THREAD: usize = 500;
+
+        let cache = Arc::new(ConcurrentLruCache::new(THREADS * PER_THREAD));
+
+        let handles: Vec<_> = (0..THREADS)
+            .map(|t| {
+                let cache = Arc::clone(&cache);
+                std::thread::spawn(move || {
+                    for i in 0..PER_THREAD {
+                        let key = t * PER_THREAD + i;
+                        cache.insert(key, key * 2);
+                        // Interleave reads of other threads' keys
+                        cache.get(&((key * 7) % (THREADS * PER_THREAD)));
+                    }
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(cache.len(), THREADS * PER_THREAD);
+        for key in 0..THREADS * PER_THREAD {
+            assert_eq!(cache.get(&key), Some(key * 2));
+        }
+    }
+
+    #[test]
+    fn test_concurrent_inserts_respect_capacity() {
+        let cache = Arc::new(ConcurrentLruCache::new(64));
+
+        let handles: Vec<_> = (0..4)
+            .map(|t| {
+                let cache = Arc::clone(&cache);
+                std::thread::spawn(move || {
+                    for i in 0..1000 {
+                        cache.insert(t * 1000 + i, i);
+                    }
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(cache.len(), 64);
+    }
+}
@@ -3,7 +3,13 @@ mod chunk_data_packet;
 mod chunk_protocol;
 mod chunk_sender;
 mod chunk_storage;
+mod concurrent_cache;
+mod provider;
 
-pub use crate::chunk::chunk_data_packet::send_chunk_data_packet;
+pub use crate::chunk::cache::LruCache;
+pub use crate::chunk::chunk_data_packet::{chunk_data_frame, send_chunk_data_packet};
 pub use crate::chunk::chunk_sender::send_chunk;
-pub use crate::chunk::chunk_storage::ChunkStorage;
+pub use crate::chunk::chunk_storage::{CacheMetrics, ChunkStorage, FlushSummary, spiral_chunk_offsets};
+pub use crate::chunk::provider::ChunkProvider;
+#[cfg(test)]
+pub use crate::chunk::provider::InMemoryChunkProvider;
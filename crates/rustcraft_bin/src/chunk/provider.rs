@@ -0,0 +1,136 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use futures::future::BoxFuture;
+
+use crate::chunk::FlushSummary;
+use crate::terrain::{BlockState, Chunk, ChunkPos};
+
+/// Where handlers get chunks from and make block edits through
+/// `ChunkStorage` is the real one; tests use `InMemoryChunkProvider` to stay off disk
+pub trait ChunkProvider: Send + Sync {
+    /// The chunk at `pos`, loaded or generated if needed
+    fn get_chunk(&self, pos: ChunkPos) -> Result<Arc<Chunk>>;
+
+    /// Like `get_chunk`, without holding up the calling task; requests made before awaiting may run
+    /// concurrently
+    fn get_chunk_async(&self, pos: ChunkPos) -> BoxFuture<'static, Result<Arc<Chunk>>> {
+        Box::pin(std::future::ready(self.get_chunk(pos)))
+    }
+
+    /// Place a block at world coordinates; returns false if `y` is outside the world
+    fn set_block(&self, x: i32, y: i32, z: i32, block: BlockState) -> Result<bool>;
+
+    /// Persist every changed chunk, reporting what was written
+    fn flush(&self) -> Result<FlushSummary>;
+}
+
+/// Edit a copy of `chunk` so readers holding the shared one keep the old blocks; returns the
+/// edited chunk and whether the block was placed
+pub(crate) fn edit_block(
+    chunk: &Arc<Chunk>,
+    x: i32,
+    y: i32,
+    z: i32,
+    block: BlockState,
+) -> Option<(Arc<Chunk>, bool)> {
+    let y = Chunk::local_y(y)?;
+    let mut chunk = Arc::clone(chunk);
+    let placed =
+        Arc::make_mut(&mut chunk).set_block(x.rem_euclid(16) as usize, y, z.rem_euclid(16) as usize, block);
+    Some((chunk, placed))
+}
+
+#[cfg(test)]
+pub use in_memory::InMemoryChunkProvider;
+
+#[cfg(test)]
+mod in_memory {
+    use std::collections::HashMap;
+
+    use parking_lot::Mutex;
+
+    use super::*;
+    use crate::terrain::WorldGenerator;
+
+    /// Generates chunks on demand and keeps them in a map; nothing touches disk and no threads are
+    /// spawned
+    pub struct InMemoryChunkProvider {
+        generator: Arc<dyn WorldGenerator>,
+        chunks:    Mutex<HashMap<ChunkPos, Arc<Chunk>>>,
+    }
+
+    impl InMemoryChunkProvider {
+        pub fn new(generator: Arc<dyn WorldGenerator>) -> Self {
+            Self {
+                generator,
+                chunks: Mutex::new(HashMap::new()),
+            }
+        }
+
+        /// Chunks generated so far
+        pub fn len(&self) -> usize {
+            self.chunks.lock().len()
+        }
+    }
+
+    impl ChunkProvider for InMemoryChunkProvider {
+        fn get_chunk(&self, pos: ChunkPos) -> Result<Arc<Chunk>> {
+            let mut chunks = self.chunks.lock();
+            let chunk = chunks
+                .entry(pos)
+                .or_insert_with(|| Arc::new(self.generator.generate(pos)));
+            Ok(Arc::clone(chunk))
+        }
+
+        fn set_block(&self, x: i32, y: i32, z: i32, block: BlockState) -> Result<bool> {
+            let pos = ChunkPos::from_block_pos(x, z);
+            let chunk = self.get_chunk(pos)?;
+            let Some((chunk, placed)) = edit_block(&chunk, x, y, z, block) else {
+                return Ok(false);
+            };
+            self.chunks.lock().insert(pos, chunk);
+            Ok(placed)
+        }
+
+        fn flush(&self) -> Result<FlushSummary> {
+            Ok(FlushSummary::default())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consts::WORLD_MIN_Y;
+    use crate::terrain::{BlockType, FlatWorldGenerator};
+
+    #[test]
+    fn test_in_memory_provider_generates_and_edits() {
+        let provider = InMemoryChunkProvider::new(Arc::new(FlatWorldGenerator::default()));
+        let pos = ChunkPos::new(-3, 5);
+
+        let first = provider.get_chunk(pos).unwrap();
+        assert!(Arc::ptr_eq(&first, &provider.get_chunk(pos).unwrap()));
+        assert_eq!(first.get_block(0, 0, 0), Some(BlockType::Bedrock));
+
+        let (x, z) = pos.origin_world();
+        assert!(
+            provider
+                .set_block(x + 1, 70, z + 2, BlockType::Sand.into())
+                .unwrap()
+        );
+        assert!(
+            !provider
+                .set_block(x, WORLD_MIN_Y - 1, z, BlockType::Sand.into())
+                .unwrap()
+        );
+
+        let edited = provider.get_chunk(pos).unwrap();
+        assert_eq!(edited.get_block(1, Chunk::local_y(70).unwrap(), 2), Some(BlockType::Sand));
+        // Readers of the old chunk keep the old blocks
+        assert_eq!(first.get_block(1, Chunk::local_y(70).unwrap(), 2), Some(BlockType::Air));
+        assert_eq!(provider.len(), 1);
+        assert!(provider.flush().is_ok());
+    }
+}
@@ -0,0 +1,651 @@
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::{Result, anyhow};
+use serde::Deserialize;
+
+use crate::consts::{
+    CHUNK_SEED,
+    DEFAULT_FLAT_LAYERS,
+    DEFAULT_HEARTBEAT_INTERVAL_SECS,
+    DEFAULT_IDLE_TIMEOUT_SECS,
+    DEFAULT_MAX_CONNECTIONS,
+    DEFAULT_MAX_PLAYERS,
+    DEFAULT_METRICS_PORT,
+    DEFAULT_MOTD,
+    DEFAULT_PREGEN_RADIUS,
+    DEFAULT_RSA_KEY_BITS,
+    DEFAULT_SESSION_CACHE_TTL_SECS,
+    DEFAULT_SHUTDOWN_COUNTDOWN_SECS,
+    DEFAULT_SIMULATION_DISTANCE,
+    DEFAULT_SPAWN,
+    DEFAULT_TICK_RATE,
+    DEFAULT_VIEW_DISTANCE,
+    MAX_CHUNK_DISTANCE,
+    MAX_TICK_RATE,
+    MIN_CHUNK_DISTANCE,
+    MIN_TICK_RATE,
+    RATE_LIMIT_CONNECTIONS,
+    RATE_LIMIT_WINDOW_MS,
+    RSA_KEY_BITS_ALLOWED,
+    STAGE_TIMEOUT_AUTHENTICATING_MS,
+    STAGE_TIMEOUT_CONFIGURING_MS,
+    STAGE_TIMEOUT_CONNECTED_MS,
+    STAGE_TIMEOUT_HANDSHAKING_MS,
+    STAGE_WATCHDOG_INTERVAL_MS,
+};
+use crate::network::DimensionCompound;
+use crate::player::{ConnectionStage, GameMode, Vec3};
+use crate::terrain::{FlatWorldGenerator, NoiseSettings};
+
+/// Runtime server configuration
+/// Every field falls back to the values in `consts` when missing from the config file
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ServerConfig {
+    pub stage_timeouts:          StageTimeouts,
+    /// Server list description; `&` color codes are translated and up to two lines are shown
+    pub motd:                    String,
+    /// Players allowed in the world at once
+    pub max_players:             u32,
+    /// Concurrent connections of any kind; extra sockets are told the server is full and closed
+    pub max_connections:         u32,
+    pub rate_limit:              RateLimitConfig,
+    /// Path to a 64x64 PNG shown in the server list
+    pub favicon_path:            Option<String>,
+    /// Port the `/metrics` endpoint binds when built with the `metrics` feature
+    pub metrics_port:            u16,
+    /// Game mode players join in: `survival`, `creative`, `adventure` or `spectator`
+    pub gamemode:                GameMode,
+    /// World spawn; its Y is replaced by the surface height when `spawn_on_surface` is set
+    pub spawn:                   Vec3<f64>,
+    pub spawn_on_surface:        bool,
+    /// Generate the area around spawn in the background at startup; when false chunks are only
+    /// generated as players reach them
+    pub pregenerate:             bool,
+    /// Chunks pregenerated in each direction from the spawn chunk, a `(2 * radius)^2` area
+    pub pregen_radius:           i32,
+    /// Game loop ticks per second, between `MIN_TICK_RATE` and `MAX_TICK_RATE`
+    pub tick_rate:               u32,
+    /// Terrain for chunks that have never been saved
+    pub generator:               GeneratorKind,
+    /// Seed for a new world; a world that has saved its own seed keeps using that one
+    pub seed:                    u64,
+    /// Height map tuning for the `noise` generator: continent size, mountain strength, erosion
+    pub noise:                   NoiseSettings,
+    /// Layer spec for the `flat` generator, e.g. `"bedrock, 2 dirt, grass_block"`
+    pub flat_layers:             String,
+    /// `doDaylightCycle`; when false the time of day stays where it is
+    pub do_daylight_cycle:       bool,
+    /// Chunks sent in each direction around a player; a client asking for fewer gets fewer
+    pub view_distance:           u32,
+    /// Chunks in each direction around a player that are kept ticking, whatever the client's
+    /// view distance
+    pub simulation_distance:     u32,
+    /// Seconds of chat warnings before `stop` without a countdown, or Ctrl-C, disconnects players
+    pub shutdown_countdown_secs: u64,
+    /// Seconds a player can go without moving or sending input before they are marked idle, 0 to
+    /// never mark anyone idle
+    pub idle_timeout_secs:       u64,
+    /// Seconds without input before a player is kicked as AFK, 0 (the default) to never kick; must
+    /// be longer than `idle_timeout_secs`. Ops of level 1 and up are never kicked
+    pub afk_kick_secs:           u64,
+    /// Only players in `whitelist.json` may join
+    pub whitelist:               bool,
+    /// Seconds between `[HEARTBEAT]` vitals lines, 0 to disable; unchanged vitals are not logged
+    pub heartbeat_interval_secs: u64,
+    /// Verify players with the session server instead of trusting the name they send; not
+    /// supported until the login flow has the encryption handshake
+    pub online_mode:             bool,
+    /// Size of the RSA key generated for online-mode encryption: 1024, 2048 or 4096
+    pub rsa_key_bits:            u32,
+    /// Seconds a player's session server lookup is reused, 0 to always ask
+    pub session_cache_ttl_secs:  u64,
+    /// How a proxy in front of the server passes on players' real identities
+    pub forwarding:              ForwardingMode,
+    /// Secret shared with the proxy; required for `velocity` forwarding
+    pub forwarding_secret:       Option<String>,
+    /// Extra dimension types offered to clients after overworld, the nether and the end
+    pub dimensions:              Vec<DimensionCompound>,
+    /// `compact` lines for a terminal or `json` objects for a log aggregator; `--log-format`
+    /// overrides it
+    pub log_format:              LogFormat,
+    /// Most verbose level logged: `trace`, `debug`, `info`, `warn` or `error`
+    pub log_level:               LogLevel,
+    /// What the accept loop does once an error repeats `ERROR_THRESHOLD` times in the window
+    pub error_policy:            ErrorPolicy,
+    /// Hide coordinates and other details from the F3 screen
+    pub reduced_debug_info:      bool,
+    /// Show the death screen; when false players respawn straight away
+    pub enable_respawn_screen:   bool,
+    /// Tell clients the world is a debug world
+    pub is_debug:                bool,
+    /// Tell clients the world is superflat, which lowers the horizon to y 0; follows `generator`
+    /// when missing
+    pub is_flat:                 Option<bool>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            stage_timeouts:          StageTimeouts::default(),
+            motd:                    DEFAULT_MOTD.to_string(),
+            max_players:             DEFAULT_MAX_PLAYERS,
+            max_connections:         DEFAULT_MAX_CONNECTIONS,
+            rate_limit:              RateLimitConfig::default(),
+            favicon_path:            None,
+            metrics_port:            DEFAULT_METRICS_PORT,
+            gamemode:                GameMode::default(),
+            spawn:                   Vec3::from(DEFAULT_SPAWN),
+            spawn_on_surface:        true,
+            pregenerate:             true,
+            pregen_radius:           DEFAULT_PREGEN_RADIUS,
+            tick_rate:               DEFAULT_TICK_RATE,
+            generator:               GeneratorKind::default(),
+            seed:                    CHUNK_SEED,
+            noise:                   NoiseSettings::default(),
+            flat_layers:             DEFAULT_FLAT_LAYERS.to_string(),
+            do_daylight_cycle:       true,
+            view_distance:           DEFAULT_VIEW_DISTANCE,
+            simulation_distance:     DEFAULT_SIMULATION_DISTANCE,
+            shutdown_countdown_secs: DEFAULT_SHUTDOWN_COUNTDOWN_SECS,
+            idle_timeout_secs:       DEFAULT_IDLE_TIMEOUT_SECS,
+            afk_kick_secs:           0,
+            whitelist:               false,
+            heartbeat_interval_secs: DEFAULT_HEARTBEAT_INTERVAL_SECS,
+            online_mode:             false,
+            rsa_key_bits:            DEFAULT_RSA_KEY_BITS,
+            session_cache_ttl_secs:  DEFAULT_SESSION_CACHE_TTL_SECS,
+            forwarding:              ForwardingMode::default(),
+            forwarding_secret:       None,
+            dimensions:              Vec::new(),
+            log_format:              LogFormat::default(),
+            log_level:               LogLevel::default(),
+            error_policy:            ErrorPolicy::default(),
+            reduced_debug_info:      false,
+            enable_respawn_screen:   true,
+            is_debug:                false,
+            is_flat:                 None,
+        }
+    }
+}
+
+impl ServerConfig {
+    /// Load the config from a JSON file, using the defaults if the file does not exist
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        if !path.exists() {
+            tracing::info!("[CONFIG] No config at {}, using defaults", path.display());
+            return Ok(Self::default());
+        }
+
+        let contents = std::fs::read_to_string(path)?;
+        let config: Self = serde_json::from_str(&contents)?;
+        config.validate()?;
+        tracing::info!("[CONFIG] Loaded config from {}", path.display());
+        Ok(config)
+    }
+
+    /// Reject values the server can't run with
+    pub fn validate(&self) -> Result<()> {
+        if !(MIN_TICK_RATE..=MAX_TICK_RATE).contains(&self.tick_rate) {
+            return Err(anyhow!(
+                "tick_rate must be between {} and {}, got {}",
+                MIN_TICK_RATE,
+                MAX_TICK_RATE,
+                self.tick_rate
+            ));
+        }
+        for (key, distance) in [
+            ("view_distance", self.view_distance),
+            ("simulation_distance", self.simulation_distance),
+        ] {
+            if !(MIN_CHUNK_DISTANCE..=MAX_CHUNK_DISTANCE).contains(&distance) {
+                return Err(anyhow!(
+                    "{} must be between {} and {}, got {}",
+                    key,
+                    MIN_CHUNK_DISTANCE,
+                    MAX_CHUNK_DISTANCE,
+                    distance
+                ));
+            }
+        }
+        if self.afk_kick_secs > 0
+            && self.idle_timeout_secs > 0
+            && self.afk_kick_secs <= self.idle_timeout_secs
+        {
+            return Err(anyhow!(
+                "afk_kick_secs must be longer than idle_timeout_secs ({}), got {}",
+                self.idle_timeout_secs,
+                self.afk_kick_secs
+            ));
+        }
+        if self.forwarding == ForwardingMode::Velocity
+            && self.forwarding_secret.as_deref().is_none_or(str::is_empty)
+        {
+            return Err(anyhow!("forwarding \"velocity\" needs a forwarding_secret"));
+        }
+        if !RSA_KEY_BITS_ALLOWED.contains(&self.rsa_key_bits) {
+            return Err(anyhow!(
+                "rsa_key_bits must be one of {:?}, got {}",
+                RSA_KEY_BITS_ALLOWED,
+                self.rsa_key_bits
+            ));
+        }
+        let mut dimension_keys: Vec<_> = DimensionCompound::defaults()
+            .iter()
+            .map(DimensionCompound::key)
+            .collect();
+        for dimension in &self.dimensions {
+            dimension.validate()?;
+            if dimension_keys.contains(&dimension.key()) {
+                return Err(anyhow!("Dimension {} is defined more than once", dimension.key()));
+            }
+            dimension_keys.push(dimension.key());
+        }
+        self.noise.validate()?;
+        if self.generator == GeneratorKind::Flat {
+            self.flat_layers
+                .parse::<FlatWorldGenerator>()
+                .map_err(|e| anyhow!("Invalid flat_layers: {}", e))?;
+        }
+        Ok(())
+    }
+
+    /// View distance for a client that asked for `requested` chunks (from its Client Information)
+    /// The smaller of the two wins, but never below what the client can render
+    pub fn view_distance_for(&self, requested: Option<u8>) -> u32 {
+        match requested {
+            Some(requested) => self.view_distance.min(requested as u32).max(MIN_CHUNK_DISTANCE),
+            None => self.view_distance,
+        }
+    }
+
+    /// Whether clients are told the world is superflat
+    pub fn is_flat(&self) -> bool {
+        self.is_flat.unwrap_or(self.generator == GeneratorKind::Flat)
+    }
+
+    /// Time without input before a player is marked idle, `None` when idle detection is off
+    pub fn idle_timeout(&self) -> Option<Duration> {
+        (self.idle_timeout_secs > 0).then(|| Duration::from_secs(self.idle_timeout_secs))
+    }
+
+    /// Time without input before a player is kicked as AFK, `None` when the kick is off
+    pub fn afk_kick_timeout(&self) -> Option<Duration> {
+        (self.afk_kick_secs > 0).then(|| Duration::from_secs(self.afk_kick_secs))
+    }
+
+    /// Time between game loop ticks at the configured tick rate
+    pub fn tick_interval(&self) -> Duration {
+        Duration::from_nanos(1_000_000_000 / self.tick_rate.max(1) as u64)
+    }
+}
+
+/// World generator selected by the `generator` config key
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GeneratorKind {
+    /// Noise-based terrain with biomes
+    #[default]
+    Noise,
+    /// Superflat, layered as `flat_layers`
+    Flat,
+    /// All air
+    Void,
+}
+
+/// Player info forwarding selected by the `forwarding` config key
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ForwardingMode {
+    /// Players connect directly
+    #[default]
+    None,
+    /// Behind a Velocity proxy using modern forwarding; direct connections are refused
+    Velocity,
+}
+
+/// Reaction to an error crossing its threshold, selected by the `error_policy` config key
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorPolicy {
+    /// Save the world and stop the server
+    #[default]
+    ShutdownOnCritical,
+    /// Log it and carry on
+    LogOnly,
+    /// Restart the failing part, e.g. rebind the listener, and start counting again
+    RestartSubsystem,
+}
+
+/// Log output selected by the `log_format` config key
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// One human readable line per event
+    #[default]
+    Compact,
+    /// One JSON object per line
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "compact" => Ok(LogFormat::Compact),
+            "json" => Ok(LogFormat::Json),
+            other => Err(anyhow!("Unknown log format: {}", other)),
+        }
+    }
+}
+
+/// Maximum log level selected by the `log_level` config key
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Trace,
+    #[default]
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl LogLevel {
+    pub fn as_level(self) -> tracing::Level {
+        match self {
+            LogLevel::Trace => tracing::Level::TRACE,
+            LogLevel::Debug => tracing::Level::DEBUG,
+            LogLevel::Info => tracing::Level::INFO,
+            LogLevel::Warn => tracing::Level::WARN,
+            LogLevel::Error => tracing::Level::ERROR,
+        }
+    }
+}
+
+/// Per-stage limits (ms) on how long a connection may stay before reaching `InGame`
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct StageTimeouts {
+    pub connected_ms:         u64,
+    pub handshaking_ms:       u64,
+    pub authenticating_ms:    u64,
+    pub configuring_ms:       u64,
+    /// How often the watchdog checks the connection
+    pub watchdog_interval_ms: u64,
+}
+
+impl StageTimeouts {
+    /// Limit for the given stage, `None` for stages that are never timed out
+    pub fn limit_for(&self, stage: ConnectionStage) -> Option<u64> {
+        match stage {
+            ConnectionStage::Connected => Some(self.connected_ms),
+            ConnectionStage::Handshaking => Some(self.handshaking_ms),
+            ConnectionStage::Authenticating => Some(self.authenticating_ms),
+            ConnectionStage::Configuring => Some(self.configuring_ms),
+            ConnectionStage::InGame | ConnectionStage::Disconnecting | ConnectionStage::Disconnected => None,
+        }
+    }
+}
+
+impl Default for StageTimeouts {
+    fn default() -> Self {
+        Self {
+            connected_ms:         STAGE_TIMEOUT_CONNECTED_MS,
+            handshaking_ms:       STAGE_TIMEOUT_HANDSHAKING_MS,
+            authenticating_ms:    STAGE_TIMEOUT_AUTHENTICATING_MS,
+            configuring_ms:       STAGE_TIMEOUT_CONFIGURING_MS,
+            watchdog_interval_ms: STAGE_WATCHDOG_INTERVAL_MS,
+        }
+    }
+}
+
+/// Per-IP limit on how often new connections may be opened
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct RateLimitConfig {
+    /// Connections allowed per window, and the most that may be opened in a burst
+    pub connections: u32,
+    pub window_ms:   u64,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            connections: RATE_LIMIT_CONNECTIONS,
+            window_ms:   RATE_LIMIT_WINDOW_MS,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_partial_config_uses_defaults() {
+        let config: ServerConfig =
+            serde_json::from_str(r#"{ "stage_timeouts": { "configuring_ms": 42 } }"#).unwrap();
+
+        assert_eq!(config.stage_timeouts.configuring_ms, 42);
+        assert_eq!(config.stage_timeouts.authenticating_ms, STAGE_TIMEOUT_AUTHENTICATING_MS);
+        assert_eq!(config.stage_timeouts.limit_for(ConnectionStage::InGame), None);
+    }
+
+    #[test]
+    fn test_generator_selection() {
+        assert_eq!(ServerConfig::default().generator, GeneratorKind::Noise);
+
+        let config: ServerConfig =
+            serde_json::from_str(r#"{ "generator": "flat", "flat_layers": "bedrock, 3 sand" }"#).unwrap();
+        assert_eq!(config.generator, GeneratorKind::Flat);
+        assert!(config.validate().is_ok());
+
+        let config: ServerConfig =
+            serde_json::from_str(r#"{ "generator": "flat", "flat_layers": "3 cheese" }"#).unwrap();
+        assert!(config.validate().is_err());
+
+        assert!(serde_json::from_str::<ServerConfig>(r#"{ "generator": "amplified" }"#).is_err());
+
+        let config: ServerConfig =
+            serde_json::from_str(r#"{ "noise": { "mountain_strength": 0.4 } }"#).unwrap();
+        assert_eq!(config.noise.mountain_strength, 0.4);
+        assert_eq!(config.noise.continent_scale, NoiseSettings::default().continent_scale);
+        assert!(config.validate().is_ok());
+
+        let config: ServerConfig = serde_json::from_str(r#"{ "noise": { "hill_scale": -1.0 } }"#).unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_tick_interval_matches_rate() {
+        let config = ServerConfig::default();
+        assert_eq!(config.tick_interval(), Duration::from_millis(50));
+
+        let config: ServerConfig = serde_json::from_str(r#"{ "tick_rate": 100 }"#).unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(config.tick_interval(), Duration::from_millis(10));
+
+        let config: ServerConfig = serde_json::from_str(r#"{ "tick_rate": 3 }"#).unwrap();
+        assert_eq!(config.tick_interval(), Duration::from_nanos(333_333_333));
+
+        for rate in [0, 101] {
+            let config = ServerConfig {
+                tick_rate: rate,
+                ..ServerConfig::default()
+            };
+            assert!(config.validate().is_err(), "tick rate {}", rate);
+        }
+    }
+
+    #[test]
+    fn test_view_and_simulation_distance() {
+        let config: ServerConfig =
+            serde_json::from_str(r#"{ "view_distance": 12, "simulation_distance": 4 }"#).unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(config.view_distance, 12);
+        assert_eq!(config.simulation_distance, 4);
+
+        let config: ServerConfig = serde_json::from_str(r#"{ "simulation_distance": 6 }"#).unwrap();
+        assert_eq!(config.view_distance, DEFAULT_VIEW_DISTANCE);
+        assert_eq!(config.simulation_distance, 6);
+
+        // The client's request only ever lowers the view distance
+        let config = ServerConfig {
+            view_distance: 12,
+            simulation_distance: 4,
+            ..ServerConfig::default()
+        };
+        assert_eq!(config.view_distance_for(Some(8)), 8);
+        assert_eq!(config.view_distance_for(Some(32)), 12);
+        assert_eq!(config.view_distance_for(Some(0)), MIN_CHUNK_DISTANCE);
+        assert_eq!(config.view_distance_for(None), 12);
+        assert_eq!(config.simulation_distance, 4);
+
+        for (view, simulation) in [(1, 10), (10, 33)] {
+            let config = ServerConfig {
+                view_distance: view,
+                simulation_distance: simulation,
+                ..ServerConfig::default()
+            };
+            assert!(config.validate().is_err(), "{} / {}", view, simulation);
+        }
+    }
+
+    #[test]
+    fn test_velocity_forwarding_needs_secret() {
+        assert_eq!(ServerConfig::default().forwarding, ForwardingMode::None);
+
+        let config: ServerConfig =
+            serde_json::from_str(r#"{ "forwarding": "velocity", "forwarding_secret": "s3cret" }"#).unwrap();
+        assert_eq!(config.forwarding, ForwardingMode::Velocity);
+        assert!(config.validate().is_ok());
+
+        for json in [
+            r#"{ "forwarding": "velocity" }"#,
+            r#"{ "forwarding": "velocity", "forwarding_secret": "" }"#,
+        ] {
+            let config: ServerConfig = serde_json::from_str(json).unwrap();
+            assert!(config.validate().is_err(), "{}", json);
+        }
+    }
+
+    #[test]
+    fn test_custom_dimensions() {
+        assert!(ServerConfig::default().dimensions.is_empty());
+
+        let config: ServerConfig = serde_json::from_str(
+            r#"{ "dimensions": [{ "name": "skyblock_void", "height": 256, "min_y": 0, "ambient_light": 0.5 }] }"#,
+        )
+        .unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(config.dimensions[0].key(), "minecraft:skyblock_void");
+
+        for json in [
+            r#"{ "dimensions": [{ "name": "overworld", "height": 256, "min_y": 0 }] }"#,
+            r#"{ "dimensions": [{ "name": "void", "height": 100, "min_y": 0 }] }"#,
+            r#"{ "dimensions": [{ "name": "void", "height": 256, "min_y": 2000 }] }"#,
+            r#"{ "dimensions": [{ "name": "void", "height": 256, "min_y": 0, "ambient_light": 2.0 }] }"#,
+            r#"{ "dimensions": [{ "name": "Void!", "height": 256, "min_y": 0 }] }"#,
+            r#"{ "dimensions": [{ "name": "void", "height": 256, "min_y": 0, "infiniburn": "minecraft:x" }] }"#,
+            r#"{ "dimensions": [{ "name": "void", "height": 256, "min_y": 0, "monster_spawn_light_level": 16 }] }"#,
+        ] {
+            let config: ServerConfig = serde_json::from_str(json).unwrap();
+            assert!(config.validate().is_err(), "{}", json);
+        }
+    }
+
+    #[test]
+    fn test_idle_timeout() {
+        assert_eq!(ServerConfig::default().idle_timeout(), Some(Duration::from_secs(300)));
+
+        let config: ServerConfig = serde_json::from_str(r#"{ "idle_timeout_secs": 0 }"#).unwrap();
+        assert_eq!(config.idle_timeout(), None);
+    }
+
+    #[test]
+    fn test_gamemode_selection() {
+        assert_eq!(ServerConfig::default().gamemode, GameMode::Survival);
+
+        let config: ServerConfig = serde_json::from_str(r#"{ "gamemode": "creative" }"#).unwrap();
+        assert_eq!(config.gamemode, GameMode::Creative);
+        assert!(serde_json::from_str::<ServerConfig>(r#"{ "gamemode": "hardcore" }"#).is_err());
+    }
+
+    #[test]
+    fn test_error_policy_selection() {
+        assert_eq!(ServerConfig::default().error_policy, ErrorPolicy::ShutdownOnCritical);
+
+        for (value, policy) in [
+            ("shutdown_on_critical", ErrorPolicy::ShutdownOnCritical),
+            ("log_only", ErrorPolicy::LogOnly),
+            ("restart_subsystem", ErrorPolicy::RestartSubsystem),
+        ] {
+            let config: ServerConfig =
+                serde_json::from_str(&format!(r#"{{ "error_policy": "{}" }}"#, value)).unwrap();
+            assert_eq!(config.error_policy, policy);
+        }
+    }
+
+    #[test]
+    fn test_log_settings() {
+        let config = ServerConfig::default();
+        assert_eq!(config.log_format, LogFormat::Compact);
+        assert_eq!(config.log_level.as_level(), tracing::Level::DEBUG);
+
+        let config: ServerConfig =
+            serde_json::from_str(r#"{ "log_format": "json", "log_level": "warn" }"#).unwrap();
+        assert_eq!(config.log_format, LogFormat::Json);
+        assert_eq!(config.log_level.as_level(), tracing::Level::WARN);
+    }
+
+    #[test]
+    fn test_afk_kick_is_opt_in_and_after_idle() {
+        assert_eq!(ServerConfig::default().afk_kick_timeout(), None);
+
+        let config: ServerConfig = serde_json::from_str(r#"{ "afk_kick_secs": 900 }"#).unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(config.afk_kick_timeout(), Some(Duration::from_secs(900)));
+
+        let config: ServerConfig = serde_json::from_str(r#"{ "afk_kick_secs": 300 }"#).unwrap();
+        assert!(config.validate().is_err());
+
+        // Without idle detection any kick time goes
+        let config: ServerConfig =
+            serde_json::from_str(r#"{ "idle_timeout_secs": 0, "afk_kick_secs": 60 }"#).unwrap();
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_join_game_flags() {
+        let config = ServerConfig::default();
+        assert!(!config.reduced_debug_info);
+        assert!(config.enable_respawn_screen);
+        assert!(!config.is_debug);
+        assert!(!config.is_flat());
+
+        let config: ServerConfig =
+            serde_json::from_str(r#"{ "generator": "flat", "reduced_debug_info": true }"#).unwrap();
+        assert!(config.reduced_debug_info);
+        assert!(config.is_flat());
+
+        let config: ServerConfig =
+            serde_json::from_str(r#"{ "generator": "flat", "is_flat": false }"#).unwrap();
+        assert!(!config.is_flat());
+    }
+
+    #[test]
+    fn test_rsa_key_bits() {
+        assert_eq!(ServerConfig::default().rsa_key_bits, 1024);
+
+        let config: ServerConfig = serde_json::from_str(r#"{ "rsa_key_bits": 2048 }"#).unwrap();
+        assert!(config.validate().is_ok());
+
+        let config: ServerConfig = serde_json::from_str(r#"{ "rsa_key_bits": 512 }"#).unwrap();
+        assert!(config.validate().is_err());
+    }
+}
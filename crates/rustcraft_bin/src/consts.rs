@@ -1,3 +1,5 @@
+#![allow(dead_code)]
+
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 
 const SERVER_ADDR_LIT: [u8; 4] = [127, 0, 0, 1];
@@ -15,31 +17,125 @@ pub const CHUNK_SEED: u64 = 12345;
 /// dir.
 pub const WORLD_PATH: &str = "../../world";
 
-pub const NETWORK_VALID_PROTOCOL_VERSION: i32 = 772; // Minecraft 1.21.7
-
-pub const GAMELOOP_SLEEP_TICK: u64 = 50; // 20 ticks per second
-
-// pub const GAMEPLOOP_TICK_RATE: u64 = 1000 / GAMELOOP_SLEEP_TICK; // technically no?
-// What we're using atm
-pub const GAMELOOP_TICK_RATE: u64 = 20; // 20 ticks per second (50ms per tick)
-
-pub const GAMELOOP_DELTA_TIME: f32 = GAMELOOP_SLEEP_TICK as f32 / 1000.0; // in seconds
-pub const GAMELOOP_TICK_RATE_DURATION: std::time::Duration =
-    std::time::Duration::from_millis(GAMELOOP_SLEEP_TICK);
+/// Game loop ticks per second unless the config says otherwise (50ms per tick)
+pub const DEFAULT_TICK_RATE: u32 = 20;
+/// Tick rates the config may ask for
+pub const MIN_TICK_RATE: u32 = 1;
+pub const MAX_TICK_RATE: u32 = 100;
 
 pub const TERRAIN_CHUNK_SIZE: usize = 16;
-pub const TERRAIN_CHUNK_HEIGHT: usize = 256;
+/// World height in blocks, from `WORLD_MIN_Y` up; the overworld dimension type declares the same
+pub const TERRAIN_CHUNK_HEIGHT: usize = 384;
+/// Lowest block Y in the world, stored at chunk-local Y 0
+pub const WORLD_MIN_Y: i32 = -64;
+pub const TERRAIN_SECTION_HEIGHT: usize = 16;
+pub const TERRAIN_SECTION_COUNT: usize = TERRAIN_CHUNK_HEIGHT / TERRAIN_SECTION_HEIGHT;
+/// Lowest `min_y` and highest top a dimension type may declare; heights are at most the span
+pub const DIMENSION_MIN_Y: i32 = -2032;
+pub const DIMENSION_MAX_Y: i32 = 2032;
 
 pub const ERROR_THRESHOLD: usize = 5;
 const ERROR_WINDOW: u64 = 10;
 
 pub const ERROR_WINDOW_SECS: std::time::Duration = std::time::Duration::from_secs(ERROR_WINDOW);
 
-pub const CHUNK_SIZE_BYTES: usize = 232 * 1024;
+// Only non-air sections are stored (8 KB each); generated terrain fills about 10 of the 24
+pub const CHUNK_SIZE_BYTES: usize = 80 * 1024;
 pub const INITIAL_BUFFER_MB: usize = 256;
 pub const MAX_BUFFER_MB: usize = 2048; // 2 GB max
-pub const INITIAL_CAPACITY: usize = INITIAL_BUFFER_MB * 1024 * 1024 / CHUNK_SIZE_BYTES; // ~1130 chunks
-pub const MAX_CAPACITY: usize = MAX_BUFFER_MB * 1024 * 1024 / CHUNK_SIZE_BYTES; // ~9033 chunks
+pub const INITIAL_CAPACITY: usize = INITIAL_BUFFER_MB * 1024 * 1024 / CHUNK_SIZE_BYTES; // ~3276 chunks
+pub const MAX_CAPACITY: usize = MAX_BUFFER_MB * 1024 * 1024 / CHUNK_SIZE_BYTES; // ~26214 chunks
 
 pub const WORLD_MAX_CHUNKS: i32 = 10240;
 pub const WORLD_REGION_SIZE: i32 = 32;
+/// Vanilla's default world border, in blocks across
+pub const WORLD_BORDER_DIAMETER: f64 = 59_999_968.0;
+
+pub const SERVER_CONFIG_PATH: &str = "../../server_config.json";
+/// Holds `banlist.json`, `whitelist.json` and `ops.json`
+pub const SERVER_DIR: &str = "../..";
+
+// Max time (ms) a connection may sit in each pre-game stage before it is dropped
+pub const STAGE_TIMEOUT_CONNECTED_MS: u64 = 5_000;
+pub const STAGE_TIMEOUT_HANDSHAKING_MS: u64 = 5_000;
+pub const STAGE_TIMEOUT_AUTHENTICATING_MS: u64 = 10_000;
+pub const STAGE_TIMEOUT_CONFIGURING_MS: u64 = 10_000;
+pub const STAGE_WATCHDOG_INTERVAL_MS: u64 = 250;
+
+pub const DEFAULT_MOTD: &str = "A RustCraft Server";
+pub const DEFAULT_MAX_PLAYERS: u32 = 20;
+/// Open sockets allowed at once, counting status pings and logins still in progress
+pub const DEFAULT_MAX_CONNECTIONS: u32 = 64;
+/// New connections one IP may open per `RATE_LIMIT_WINDOW_MS`
+pub const RATE_LIMIT_CONNECTIONS: u32 = 10;
+pub const RATE_LIMIT_WINDOW_MS: u64 = 10_000;
+pub const STATUS_SAMPLE_MAX_PLAYERS: usize = 12;
+/// Pause after the first failed `accept()`, doubling with each failure in a row
+pub const ACCEPT_BACKOFF_BASE_MS: u64 = 5;
+pub const ACCEPT_BACKOFF_MAX_MS: u64 = 1_000;
+/// "Server full" disconnects sent at once past `max_connections`; beyond that sockets are just closed
+pub const MAX_PENDING_REJECTS: usize = 16;
+/// Time a refused client gets to take its "Server full" disconnect
+pub const REJECT_TIMEOUT_MS: u64 = 1_000;
+/// Largest frame accepted from a client, matching vanilla's 2 MiB limit (21-bit length)
+pub const MAX_PACKET_SIZE: usize = 2 * 1024 * 1024 - 1;
+
+/// Where players spawn unless the config says otherwise
+pub const DEFAULT_SPAWN: (f64, f64, f64) = (0.0, 64.0, 0.0);
+/// Chunks pregenerated in each direction from the spawn chunk
+pub const DEFAULT_PREGEN_RADIUS: i32 = 8;
+/// Chunks sent in each direction around a player (vanilla's `view-distance`)
+pub const DEFAULT_VIEW_DISTANCE: u32 = 10;
+/// Chunks in each direction around a player that tick (vanilla's `simulation-distance`)
+pub const DEFAULT_SIMULATION_DISTANCE: u32 = 10;
+/// Distances the client accepts for both
+pub const MIN_CHUNK_DISTANCE: u32 = 2;
+pub const MAX_CHUNK_DISTANCE: u32 = 32;
+/// Seconds players are warned for before `stop` (or Ctrl-C) disconnects them
+pub const DEFAULT_SHUTDOWN_COUNTDOWN_SECS: u64 = 10;
+/// Seconds between heartbeat log lines; 0 turns the heartbeat off
+pub const DEFAULT_HEARTBEAT_INTERVAL_SECS: u64 = 60;
+/// Seconds without input before a player is marked idle; 0 never marks anyone idle
+pub const DEFAULT_IDLE_TIMEOUT_SECS: u64 = 300;
+/// How often each player's handler checks whether they have gone idle
+pub const IDLE_CHECK_INTERVAL_MS: u64 = 1_000;
+/// Layers of the `flat` generator, bottom to top
+pub const DEFAULT_FLAT_LAYERS: &str = "bedrock, 2 dirt, grass_block";
+/// RSA key size for the online-mode encryption handshake; vanilla uses 1024
+pub const DEFAULT_RSA_KEY_BITS: u32 = 1024;
+pub const RSA_KEY_BITS_ALLOWED: [u32; 3] = [1024, 2048, 4096];
+/// Seconds a successful session server lookup is reused for the same username and hash
+pub const DEFAULT_SESSION_CACHE_TTL_SECS: u64 = 30;
+pub const SESSION_CACHE_CAPACITY: usize = 256;
+
+/// Default `NoiseSettings` for the noise generator's height map, in blocks unless noted
+/// Width of continents, hills and surface detail
+pub const NOISE_CONTINENT_SCALE: f64 = 512.0;
+pub const NOISE_HILL_SCALE: f64 = 128.0;
+pub const NOISE_DETAIL_SCALE: f64 = 32.0;
+/// Share of each scale in the final height; they add up to 1
+pub const NOISE_CONTINENT_WEIGHT: f64 = 0.6;
+pub const NOISE_HILL_WEIGHT: f64 = 0.3;
+pub const NOISE_DETAIL_WEIGHT: f64 = 0.1;
+/// Amplitude and frequency multipliers between `fbm` octaves
+pub const NOISE_PERSISTENCE: f64 = 0.5;
+pub const NOISE_LACUNARITY: f64 = 2.0;
+/// Spacing of the plate boundaries mountain ranges rise along, and how much they are raised
+pub const NOISE_PLATE_SCALE: f64 = 256.0;
+pub const NOISE_MOUNTAIN_STRENGTH: f64 = 0.15;
+/// Height difference thermal erosion flattens, and how many passes it makes
+pub const NOISE_EROSION_AMOUNT: f64 = 0.1;
+pub const NOISE_EROSION_ITERATIONS: u32 = 2;
+/// Spacing of the river noise channel; larger means fewer, longer rivers
+pub const NOISE_RIVER_SCALE: f64 = 400.0;
+/// How far from the river noise's zero line still counts as river; 0 turns rivers off
+pub const NOISE_RIVER_WIDTH: f64 = 0.02;
+/// Each pass copies the whole height map, so keep startup bounded
+pub const NOISE_MAX_EROSION_ITERATIONS: u32 = 32;
+/// Blocks between height samples within a chunk, interpolated in between; 1 samples every column
+pub const NOISE_SAMPLE_STEP: u32 = 1;
+/// Steps that divide a chunk evenly
+pub const NOISE_SAMPLE_STEPS_ALLOWED: [u32; 5] = [1, 2, 4, 8, 16];
+
+/// Port for the `/metrics` endpoint (only served with the `metrics` feature)
+pub const DEFAULT_METRICS_PORT: u16 = 9225;
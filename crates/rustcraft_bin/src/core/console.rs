@@ -0,0 +1,492 @@
+use std::sync::Arc;
+
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::sync::RwLock;
+use tracing::{info, warn};
+
+use crate::access_control::{AccessControl, DEFAULT_BAN_REASON, DEFAULT_OP_LEVEL, MAX_OP_LEVEL, ban_message};
+use crate::core::game_loop::GameLoop;
+use crate::core::shutdown::{DEFAULT_SHUTDOWN_REASON, Shutdown};
+use crate::network::LoginHandler;
+use crate::player::{PlayerRegistry, seed_message};
+use crate::world::World;
+
+const DEFAULT_KICK_REASON: &str = "Kicked by an operator";
+
+/// Operator commands read from the server console
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    /// List online players
+    List,
+    /// Report the measured ticks per second
+    Tps,
+    /// Report the world seed
+    Seed,
+    /// Disconnect a player, with an optional reason
+    Kick {
+        name:   String,
+        reason: Option<String>,
+    },
+    /// Flush every cached chunk to disk and report what was written
+    /// Saves are always written through before the summary is printed, so `flush` is only accepted
+    /// for vanilla compatibility
+    SaveAll {
+        flush: bool,
+    },
+    /// Warn players for `countdown` seconds (the configured default if missing), then save and
+    /// shut the server down
+    Stop {
+        countdown: Option<u64>,
+        reason:    Option<String>,
+    },
+    /// Cancel a shutdown that is still counting down
+    CancelStop,
+    /// Ban a player by name, kicking them if online
+    Ban {
+        name:   String,
+        reason: Option<String>,
+    },
+    /// Lift a ban
+    Pardon {
+        name: String,
+    },
+    WhitelistAdd {
+        name: String,
+    },
+    WhitelistRemove {
+        name: String,
+    },
+    /// Grant op level `level` (`DEFAULT_OP_LEVEL` if missing)
+    Op {
+        name:  String,
+        level: Option<u8>,
+    },
+    Deop {
+        name: String,
+    },
+    /// Stop chunk generation workers from starting new chunks; queued requests wait for a resume
+    PauseChunkGen,
+    ResumeChunkGen,
+}
+
+/// Parse one console line; `None` for empty, unknown or malformed input
+/// A leading `/` is accepted so in-game habits work at the console too
+pub fn parse_command(input: &str) -> Option<Command> {
+    let input = input.trim();
+    let input = input.strip_prefix('/').unwrap_or(input);
+    let mut parts = input.split_whitespace();
+    let name = parts.next()?.to_ascii_lowercase();
+
+    let command = match name.as_str() {
+        "list" => Command::List,
+        "tps" => Command::Tps,
+        "seed" => Command::Seed,
+        "save-all" => {
+            match parts.next() {
+                Some("flush") => Command::SaveAll { flush: true },
+                Some(_) => return None,
+                None => Command::SaveAll { flush: false },
+            }
+        }
+        "stop" => {
+            let rest: Vec<&str> = parts.collect();
+            return match rest[..] {
+                ["cancel"] => Some(Command::CancelStop),
+                [] => {
+                    Some(Command::Stop {
+                        countdown: None,
+                        reason:    None,
+                    })
+                }
+                [countdown, ref reason @ ..] => {
+                    let reason = reason.join(" ");
+                    Some(Command::Stop {
+                        countdown: Some(countdown.parse().ok()?),
+                        reason:    (!reason.is_empty()).then_some(reason),
+                    })
+                }
+            };
+        }
+        "kick" => {
+            let name = parts.next()?.to_string();
+            let reason = parts.collect::<Vec<_>>().join(" ");
+            return Some(Command::Kick {
+                name,
+                reason: (!reason.is_empty()).then_some(reason),
+            });
+        }
+        "ban" => {
+            let name = parts.next()?.to_string();
+            let reason = parts.collect::<Vec<_>>().join(" ");
+            return Some(Command::Ban {
+                name,
+                reason: (!reason.is_empty()).then_some(reason),
+            });
+        }
+        "op" => {
+            let name = parts.next()?.to_string();
+            let level = match parts.next() {
+                Some(level) => Some(level.parse::<u8>().ok().filter(|&l| l <= MAX_OP_LEVEL)?),
+                None => None,
+            };
+            Command::Op { name, level }
+        }
+        "deop" => {
+            Command::Deop {
+                name: parts.next()?.to_string(),
+            }
+        }
+        "pardon" => {
+            Command::Pardon {
+                name: parts.next()?.to_string(),
+            }
+        }
+        "whitelist" => {
+            match parts.next()? {
+                "add" => {
+                    Command::WhitelistAdd {
+                        name: parts.next()?.to_string(),
+                    }
+                }
+                "remove" => {
+                    Command::WhitelistRemove {
+                        name: parts.next()?.to_string(),
+                    }
+                }
+                _ => return None,
+            }
+        }
+        "chunkgen" => {
+            match parts.next()? {
+                "pause" => Command::PauseChunkGen,
+                "resume" => Command::ResumeChunkGen,
+                _ => return None,
+            }
+        }
+        _ => return None,
+    };
+
+    // Commands without free-text arguments reject trailing input rather than silently ignoring it
+    match parts.next() {
+        Some(_) => None,
+        None => Some(command),
+    }
+}
+
+/// Reads commands from stdin and dispatches them against live server state
+/// The console has `MAX_OP_LEVEL`, so its commands are never permission checked
+pub struct Console {
+    players:             Arc<PlayerRegistry>,
+    world:               Arc<World>,
+    access:              Arc<AccessControl>,
+    game_loop:           Arc<RwLock<GameLoop>>,
+    shutdown:            Arc<Shutdown>,
+    /// Countdown for a `stop` that doesn't give one
+    stop_countdown_secs: u64,
+}
+
+impl Console {
+    pub fn new(
+        players: Arc<PlayerRegistry>,
+        world: Arc<World>,
+        access: Arc<AccessControl>,
+        game_loop: Arc<RwLock<GameLoop>>,
+        shutdown: Arc<Shutdown>,
+        stop_countdown_secs: u64,
+    ) -> Self {
+        Self {
+            players,
+            world,
+            access,
+            game_loop,
+            shutdown,
+            stop_countdown_secs,
+        }
+    }
+
+    /// Run until stdin closes; keeps reading during a `stop` countdown so it can be cancelled
+    pub async fn run(self) {
+        let mut lines = BufReader::new(tokio::io::stdin()).lines();
+
+        loop {
+            let line = match lines.next_line().await {
+                Ok(Some(line)) => line,
+                Ok(None) => {
+                    info!("[CONSOLE] stdin closed, console input disabled");
+                    return;
+                }
+                Err(e) => {
+                    warn!("[CONSOLE] Failed to read stdin: {}", e);
+                    return;
+                }
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            match parse_command(&line) {
+                Some(command) => self.dispatch(command).await,
+                None => warn!("[CONSOLE] Unknown command: {}", line.trim()),
+            }
+        }
+    }
+
+    /// UUID of an online player, or the offline-mode UUID their name would log in with
+    fn uuid_of(&self, name: &str) -> uuid::Uuid {
+        match self.players.get_by_name(name) {
+            Some(player) => player.uuid,
+            None => LoginHandler::generate_offline_uuid(name),
+        }
+    }
+
+    async fn dispatch(&self, command: Command) {
+        match command {
+            Command::List => {
+                let names = self.players.usernames();
+                info!("[CONSOLE] {} player(s) online: {}", names.len(), names.join(", "));
+            }
+            Command::Tps => {
+                let game_loop = self.game_loop.read().await;
+                info!("[CONSOLE] TPS: {:.1} (tick {})", game_loop.tps(), game_loop.tick_count());
+            }
+            Command::Seed => info!("[CONSOLE] {}", seed_message(self.world.seed())),
+            Command::Kick { name, reason } => {
+                let reason = reason.as_deref().unwrap_or(DEFAULT_KICK_REASON);
+                if self.players.kick_by_name(&name, reason) {
+                    info!("[CONSOLE] Kicked {}: {}", name, reason);
+                } else {
+                    warn!("[CONSOLE] No player named {}", name);
+                }
+            }
+            Command::SaveAll { .. } => {
+                let world = Arc::clone(&self.world);
+                match tokio::task::spawn_blocking(move || world.save()).await {
+                    Ok(Ok(summary)) => info!("[CONSOLE] Saved the world: {}", summary),
+                    Ok(Err(e)) => warn!("[CONSOLE] Save failed: {}", e),
+                    Err(e) => warn!("[CONSOLE] Save task failed: {}", e),
+                }
+            }
+            Command::Stop { countdown, reason } => {
+                info!("[CONSOLE] Stopping the server");
+                self.shutdown.shutdown(
+                    reason.as_deref().unwrap_or(DEFAULT_SHUTDOWN_REASON),
+                    countdown.unwrap_or(self.stop_countdown_secs),
+                );
+            }
+            Command::CancelStop => {
+                if !self.shutdown.cancel() {
+                    warn!("[CONSOLE] No shutdown is pending");
+                }
+            }
+            Command::Ban { name, reason } => {
+                let reason = reason.as_deref().unwrap_or(DEFAULT_BAN_REASON);
+                match self.access.ban(&name, self.uuid_of(&name), reason) {
+                    Ok(true) => info!("[CONSOLE] Banned {}: {}", name, reason),
+                    Ok(false) => info!("[CONSOLE] {} was already banned; reason updated", name),
+                    Err(e) => warn!("[CONSOLE] Failed to save the ban list: {}", e),
+                }
+                self.players.kick_by_name(&name, &ban_message(reason));
+            }
+            Command::Pardon { name } => {
+                match self.access.pardon(&name) {
+                    Ok(true) => info!("[CONSOLE] Unbanned {}", name),
+                    Ok(false) => warn!("[CONSOLE] {} is not banned", name),
+                    Err(e) => warn!("[CONSOLE] Failed to save the ban list: {}", e),
+                }
+            }
+            Command::WhitelistAdd { name } => {
+                match self.access.whitelist_add(&name, self.uuid_of(&name)) {
+                    Ok(true) => info!("[CONSOLE] Added {} to the whitelist", name),
+                    Ok(false) => warn!("[CONSOLE] {} is already whitelisted", name),
+                    Err(e) => warn!("[CONSOLE] Failed to save the whitelist: {}", e),
+                }
+            }
+            Command::Op { name, level } => {
+                let level = level.unwrap_or(DEFAULT_OP_LEVEL);
+                match self.access.op(&name, self.uuid_of(&name), level) {
+                    Ok(()) => info!("[CONSOLE] Made {} an op at level {}", name, level),
+                    Err(e) => warn!("[CONSOLE] Failed to save the op list: {}", e),
+                }
+            }
+            Command::Deop { name } => {
+                match self.access.deop(self.uuid_of(&name)) {
+                    Ok(true) => info!("[CONSOLE] {} is no longer an op", name),
+                    Ok(false) => warn!("[CONSOLE] {} is not an op", name),
+                    Err(e) => warn!("[CONSOLE] Failed to save the op list: {}", e),
+                }
+            }
+            Command::WhitelistRemove { name } => {
+                match self.access.whitelist_remove(&name) {
+                    Ok(true) => info!("[CONSOLE] Removed {} from the whitelist", name),
+                    Ok(false) => warn!("[CONSOLE] {} is not whitelisted", name),
+                    Err(e) => warn!("[CONSOLE] Failed to save the whitelist: {}", e),
+                }
+            }
+            Command::PauseChunkGen => {
+                let pool = self.world.chunks().generation_pool();
+                if pool.is_paused() {
+                    warn!("[CONSOLE] Chunk generation is already paused");
+                } else {
+                    pool.pause();
+                }
+            }
+            Command::ResumeChunkGen => {
+                let pool = self.world.chunks().generation_pool();
+                if pool.is_paused() {
+                    pool.resume();
+                } else {
+                    warn!("[CONSOLE] Chunk generation is not paused");
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_simple_commands() {
+        assert_eq!(parse_command("list"), Some(Command::List));
+        assert_eq!(parse_command("  TPS \n"), Some(Command::Tps));
+        assert_eq!(parse_command("seed"), Some(Command::Seed));
+        assert_eq!(parse_command("save-all"), Some(Command::SaveAll { flush: false }));
+        assert_eq!(parse_command("save-all flush"), Some(Command::SaveAll { flush: true }));
+        assert_eq!(
+            parse_command("/stop"),
+            Some(Command::Stop {
+                countdown: None,
+                reason:    None,
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_stop() {
+        assert_eq!(
+            parse_command("stop 30"),
+            Some(Command::Stop {
+                countdown: Some(30),
+                reason:    None,
+            })
+        );
+        assert_eq!(
+            parse_command("stop 0 Back in  five minutes"),
+            Some(Command::Stop {
+                countdown: Some(0),
+                reason:    Some("Back in five minutes".to_string()),
+            })
+        );
+        assert_eq!(parse_command("stop cancel"), Some(Command::CancelStop));
+        assert_eq!(parse_command("stop soon"), None);
+        assert_eq!(parse_command("stop -5"), None);
+    }
+
+    #[test]
+    fn test_parse_kick() {
+        assert_eq!(
+            parse_command("kick Steve"),
+            Some(Command::Kick {
+                name:   "Steve".to_string(),
+                reason: None,
+            })
+        );
+        assert_eq!(
+            parse_command("kick Steve  too many   creepers"),
+            Some(Command::Kick {
+                name:   "Steve".to_string(),
+                reason: Some("too many creepers".to_string()),
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_bad_input() {
+        assert_eq!(parse_command(""), None);
+        assert_eq!(parse_command("   "), None);
+        assert_eq!(parse_command("/"), None);
+        assert_eq!(parse_command("explode"), None);
+        assert_eq!(parse_command("kick"), None);
+        assert_eq!(parse_command("list everyone"), None);
+        assert_eq!(parse_command("save-all now"), None);
+        assert_eq!(parse_command("stop now"), None);
+        assert_eq!(parse_command("ban"), None);
+        assert_eq!(parse_command("pardon"), None);
+        assert_eq!(parse_command("pardon Steve Alex"), None);
+        assert_eq!(parse_command("whitelist"), None);
+        assert_eq!(parse_command("whitelist add"), None);
+        assert_eq!(parse_command("whitelist toggle Steve"), None);
+        assert_eq!(parse_command("chunkgen"), None);
+        assert_eq!(parse_command("chunkgen stop"), None);
+        assert_eq!(parse_command("chunkgen pause now"), None);
+    }
+
+    #[test]
+    fn test_parse_chunkgen() {
+        assert_eq!(parse_command("chunkgen pause"), Some(Command::PauseChunkGen));
+        assert_eq!(parse_command("/chunkgen resume"), Some(Command::ResumeChunkGen));
+    }
+
+    #[test]
+    fn test_parse_op() {
+        assert_eq!(
+            parse_command("op Steve"),
+            Some(Command::Op {
+                name:  "Steve".to_string(),
+                level: None,
+            })
+        );
+        assert_eq!(
+            parse_command("op Steve 2"),
+            Some(Command::Op {
+                name:  "Steve".to_string(),
+                level: Some(2),
+            })
+        );
+        assert_eq!(parse_command("op Steve 5"), None);
+        assert_eq!(parse_command("op Steve two"), None);
+        assert_eq!(
+            parse_command("deop Steve"),
+            Some(Command::Deop {
+                name: "Steve".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_access_commands() {
+        assert_eq!(
+            parse_command("ban Griefer lava   everywhere"),
+            Some(Command::Ban {
+                name:   "Griefer".to_string(),
+                reason: Some("lava everywhere".to_string()),
+            })
+        );
+        assert_eq!(
+            parse_command("/ban Griefer"),
+            Some(Command::Ban {
+                name:   "Griefer".to_string(),
+                reason: None,
+            })
+        );
+        assert_eq!(
+            parse_command("pardon Griefer"),
+            Some(Command::Pardon {
+                name: "Griefer".to_string(),
+            })
+        );
+        assert_eq!(
+            parse_command("whitelist add Steve"),
+            Some(Command::WhitelistAdd {
+                name: "Steve".to_string(),
+            })
+        );
+        assert_eq!(
+            parse_command("whitelist remove Steve"),
+            Some(Command::WhitelistRemove {
+                name: "Steve".to_string(),
+            })
+        );
+    }
+}
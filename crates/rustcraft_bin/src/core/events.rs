@@ -0,0 +1,197 @@
+use std::collections::HashMap;
+
+use parking_lot::RwLock;
+use uuid::Uuid;
+
+use crate::terrain::BlockState;
+
+/// Something that happened in the world which listeners may want to react to
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    PlayerJoin {
+        uuid:     Uuid,
+        username: String,
+    },
+    PlayerLeave {
+        uuid:     Uuid,
+        username: String,
+    },
+    BlockBreak {
+        player: Uuid,
+        x:      i32,
+        y:      i32,
+        z:      i32,
+        block:  BlockState,
+    },
+    BlockPlace {
+        player: Uuid,
+        x:      i32,
+        y:      i32,
+        z:      i32,
+        block:  BlockState,
+    },
+    Chat {
+        uuid:    Uuid,
+        message: String,
+    },
+}
+
+/// Which kind of `Event` a listener subscribes to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EventKind {
+    PlayerJoin,
+    PlayerLeave,
+    BlockBreak,
+    BlockPlace,
+    Chat,
+}
+
+impl Event {
+    pub fn kind(&self) -> EventKind {
+        match self {
+            Event::PlayerJoin { .. } => EventKind::PlayerJoin,
+            Event::PlayerLeave { .. } => EventKind::PlayerLeave,
+            Event::BlockBreak { .. } => EventKind::BlockBreak,
+            Event::BlockPlace { .. } => EventKind::BlockPlace,
+            Event::Chat { .. } => EventKind::Chat,
+        }
+    }
+
+    /// Whether a listener can veto the default action; joins and leaves have already happened
+    pub fn is_cancellable(&self) -> bool {
+        !matches!(self.kind(), EventKind::PlayerJoin | EventKind::PlayerLeave)
+    }
+}
+
+/// What a listener wants done with the event
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EventResult {
+    #[default]
+    Continue,
+    /// Veto the default action, e.g. keep the block a player tried to break
+    Cancel,
+}
+
+type Listener = Box<dyn Fn(&Event) -> EventResult + Send + Sync>;
+
+/// Synchronous observer hooks: `emit` runs every listener for the event on the calling thread,
+/// in the order they subscribed
+///
+/// Listeners must not subscribe from inside a listener; the bus is locked while they run
+#[derive(Default)]
+pub struct EventBus {
+    listeners: RwLock<HashMap<EventKind, Vec<Listener>>>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Call `listener` for every emitted event of `kind`
+    #[allow(dead_code)]
+    pub fn subscribe<F>(&self, kind: EventKind, listener: F)
+    where
+        F: Fn(&Event) -> EventResult + Send + Sync + 'static,
+    {
+        self.listeners
+            .write()
+            .entry(kind)
+            .or_default()
+            .push(Box::new(listener));
+    }
+
+    /// Hand the event to its listeners; `Cancel` if any of them vetoed a cancellable event
+    ///
+    /// Every listener sees the event even after one has cancelled it
+    pub fn emit(&self, event: &Event) -> EventResult {
+        let listeners = self.listeners.read();
+        let Some(listeners) = listeners.get(&event.kind()) else {
+            return EventResult::Continue;
+        };
+
+        let mut result = EventResult::Continue;
+        for listener in listeners {
+            if listener(event) == EventResult::Cancel {
+                result = EventResult::Cancel;
+            }
+        }
+
+        if result == EventResult::Cancel && !event.is_cancellable() {
+            tracing::debug!("[EVENTS] Ignoring veto of {:?}, which cannot be cancelled", event.kind());
+            return EventResult::Continue;
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use parking_lot::Mutex;
+
+    use super::*;
+    use crate::terrain::BlockType;
+
+    fn block_break() -> Event {
+        Event::BlockBreak {
+            player: Uuid::new_v4(),
+            x:      1,
+            y:      64,
+            z:      -3,
+            block:  BlockType::Stone.into(),
+        }
+    }
+
+    #[test]
+    fn test_listener_receives_emitted_events() {
+        let bus = EventBus::new();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+
+        let log = Arc::clone(&seen);
+        bus.subscribe(EventKind::PlayerJoin, move |event| {
+            log.lock().push(event.clone());
+            EventResult::Continue
+        });
+
+        let join = Event::PlayerJoin {
+            uuid:     Uuid::new_v4(),
+            username: "Steve".to_string(),
+        };
+        assert_eq!(bus.emit(&join), EventResult::Continue);
+        // Other kinds don't reach the join listener
+        assert_eq!(bus.emit(&block_break()), EventResult::Continue);
+
+        assert_eq!(*seen.lock(), vec![join]);
+    }
+
+    #[test]
+    fn test_any_veto_cancels_and_every_listener_runs() {
+        let bus = EventBus::new();
+        let calls = Arc::new(Mutex::new(0));
+
+        for result in [EventResult::Cancel, EventResult::Continue] {
+            let calls = Arc::clone(&calls);
+            bus.subscribe(EventKind::BlockBreak, move |_| {
+                *calls.lock() += 1;
+                result
+            });
+        }
+
+        assert_eq!(bus.emit(&block_break()), EventResult::Cancel);
+        assert_eq!(*calls.lock(), 2);
+    }
+
+    #[test]
+    fn test_leaving_cannot_be_vetoed() {
+        let bus = EventBus::new();
+        bus.subscribe(EventKind::PlayerLeave, |_| EventResult::Cancel);
+
+        let leave = Event::PlayerLeave {
+            uuid:     Uuid::new_v4(),
+            username: "Alex".to_string(),
+        };
+        assert_eq!(bus.emit(&leave), EventResult::Continue);
+    }
+}
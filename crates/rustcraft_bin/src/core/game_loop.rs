@@ -1,25 +1,59 @@
 #![allow(dead_code)]
 
-use std::sync::atomic::AtomicBool;
-use std::time::Instant;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use anyhow::Result;
+use tokio::sync::mpsc::UnboundedReceiver;
 
-use crate::consts::GAMELOOP_TICK_RATE_DURATION; // replaces 'TICK_RATE'
-// use crate::GAMELOOP_TICK_RATE_DURATION; // replaces 'TICK_DURATION'
+use crate::core::EventBus;
+use crate::player::{BlockRequest, PlayerRegistry};
+use crate::world::{TIME_UPDATE_INTERVAL, World};
+
+/// How often the measured TPS is refreshed
+const TPS_WINDOW: Duration = Duration::from_secs(1);
 
 pub struct GameLoop {
-    tick_count: u64,
-    last_tick:  Instant,
+    world:          Arc<World>,
+    players:        Arc<PlayerRegistry>,
+    events:         Arc<EventBus>,
+    /// Digs and placements from players' tasks, applied at the start of each tick
+    block_requests: UnboundedReceiver<BlockRequest>,
+    tick_count:     u64,
+    /// Time between ticks, from the configured tick rate
+    tick_interval:  Duration,
+    last_tick:      Instant,
     // atomic:     AtomicBool,
+    window_start:   Instant,
+    window_ticks:   u64,
+    /// Time spent inside tick updates during the current window
+    window_busy:    Duration,
+    tps:            f64,
+    mean_tick_ms:   f64,
 }
 
 impl GameLoop {
-    pub fn new() -> Self {
+    pub fn new(
+        tick_interval: Duration,
+        world: Arc<World>,
+        players: Arc<PlayerRegistry>,
+        events: Arc<EventBus>,
+        block_requests: UnboundedReceiver<BlockRequest>,
+    ) -> Self {
+        let now = Instant::now();
         Self {
+            world,
+            players,
+            events,
+            block_requests,
             tick_count: 0,
-            last_tick:  Instant::now(),
+            tick_interval,
+            last_tick: now,
             // atomic:     AtomicBool::new(false),
+            window_start: now,
+            window_ticks: 0,
+            window_busy: Duration::ZERO,
+            tps: 1.0 / tick_interval.as_secs_f64(),
+            mean_tick_ms: 0.0,
         }
     }
 
@@ -27,10 +61,24 @@ impl GameLoop {
         let now = Instant::now();
         let elapsed = now.duration_since(self.last_tick);
 
-        if elapsed >= GAMELOOP_TICK_RATE_DURATION {
+        if elapsed >= self.tick_interval {
             self.tick_count += 1;
             self.last_tick = now;
 
+            while let Ok(request) = self.block_requests.try_recv() {
+                request.apply(&self.world, &self.events, &self.players);
+            }
+
+            if let Some(weather) = self.world.tick() {
+                tracing::info!("[WORLD] Weather changed to {}", weather);
+                for frame in weather.frames() {
+                    self.players.broadcast(&frame);
+                }
+            }
+            if self.tick_count.is_multiple_of(TIME_UPDATE_INTERVAL) {
+                self.players.broadcast(&self.world.time().update_frame());
+            }
+
             // TODO: @update_fns : Implement the actual update functions
             // Perform tick updates
             // self.update_players();
@@ -38,6 +86,17 @@ impl GameLoop {
             // self.update_physics();
 
             tracing::trace!("Tick {}", self.tick_count);
+
+            self.window_ticks += 1;
+            self.window_busy += now.elapsed();
+            let window = now.duration_since(self.window_start);
+            if window >= TPS_WINDOW {
+                self.tps = self.window_ticks as f64 / window.as_secs_f64();
+                self.mean_tick_ms = self.window_busy.as_secs_f64() * 1000.0 / self.window_ticks as f64;
+                self.window_start = now;
+                self.window_ticks = 0;
+                self.window_busy = Duration::ZERO;
+            }
         }
 
         // Ok(())
@@ -58,7 +117,21 @@ impl GameLoop {
         todo!("Need to implement physics updates");
     }
 
+    pub fn tick_interval(&self) -> Duration {
+        self.tick_interval
+    }
+
     pub fn tick_count(&self) -> u64 {
         self.tick_count
     }
+
+    /// Ticks per second over the last full measurement window
+    pub fn tps(&self) -> f64 {
+        self.tps
+    }
+
+    /// Mean time spent per tick over the last full measurement window
+    pub fn mean_tick_ms(&self) -> f64 {
+        self.mean_tick_ms
+    }
 }
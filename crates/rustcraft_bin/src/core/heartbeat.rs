@@ -0,0 +1,149 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::RwLock;
+use tracing::info;
+
+use crate::chunk::ChunkStorage;
+use crate::core::game_loop::GameLoop;
+use crate::error_tracker::ErrorTracker;
+use crate::player::PlayerRegistry;
+
+/// Server health values logged by the heartbeat
+#[derive(Debug, Clone, PartialEq)]
+pub struct Vitals {
+    pub players:       usize,
+    pub tps:           f64,
+    pub cached_chunks: usize,
+    pub evictions:     usize,
+    /// Errors recorded across every key still tracked
+    pub errors:        usize,
+}
+
+impl Vitals {
+    /// One log line, e.g. `players=3 tps=19.9 cached_chunks=441 evictions=12 errors=0`
+    pub fn format_line(&self) -> String {
+        format!(
+            "players={} tps={:.1} cached_chunks={} evictions={} errors={}",
+            self.players, self.tps, self.cached_chunks, self.evictions, self.errors
+        )
+    }
+
+    /// Whether these vitals are worth logging again after `previous`
+    /// TPS only counts once it moves by a tenth, so the jitter of a healthy loop stays quiet
+    pub fn changed_since(&self, previous: &Vitals) -> bool {
+        self.players != previous.players
+            || self.cached_chunks != previous.cached_chunks
+            || self.evictions != previous.evictions
+            || self.errors != previous.errors
+            || (self.tps * 10.0).round() != (previous.tps * 10.0).round()
+    }
+}
+
+/// Periodically logs `Vitals`, staying silent while nothing changes
+pub struct Heartbeat {
+    players:       Arc<PlayerRegistry>,
+    chunk_storage: Arc<ChunkStorage>,
+    game_loop:     Arc<RwLock<GameLoop>>,
+    error_tracker: Arc<ErrorTracker>,
+}
+
+impl Heartbeat {
+    pub fn new(
+        players: Arc<PlayerRegistry>,
+        chunk_storage: Arc<ChunkStorage>,
+        game_loop: Arc<RwLock<GameLoop>>,
+        error_tracker: Arc<ErrorTracker>,
+    ) -> Self {
+        Self {
+            players,
+            chunk_storage,
+            game_loop,
+            error_tracker,
+        }
+    }
+
+    /// Log vitals every `interval` for as long as the server runs
+    pub async fn run(self, interval: Duration) {
+        let mut ticker = tokio::time::interval(interval);
+        // The first tick fires immediately, before anything is worth reporting
+        ticker.tick().await;
+
+        let mut last: Option<Vitals> = None;
+        loop {
+            ticker.tick().await;
+            let vitals = self.vitals().await;
+            if last.as_ref().is_none_or(|last| vitals.changed_since(last)) {
+                info!("[HEARTBEAT] {}", vitals.format_line());
+                last = Some(vitals);
+            }
+        }
+    }
+
+    async fn vitals(&self) -> Vitals {
+        let tps = self.game_loop.read().await.tps();
+        let cache = self.chunk_storage.cache_metrics();
+
+        Vitals {
+            players: self.players.len(),
+            tps,
+            cached_chunks: cache.len,
+            evictions: cache.evictions,
+            errors: self.error_tracker.snapshot().iter().map(|(_, count)| count).sum(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Vitals {
+        Vitals {
+            players:       3,
+            tps:           19.94,
+            cached_chunks: 441,
+            evictions:     12,
+            errors:        2,
+        }
+    }
+
+    #[test]
+    fn test_format_line() {
+        assert_eq!(sample().format_line(), "players=3 tps=19.9 cached_chunks=441 evictions=12 errors=2");
+    }
+
+    #[test]
+    fn test_changed_since() {
+        let previous = sample();
+        assert!(!sample().changed_since(&previous));
+        assert!(
+            !Vitals {
+                tps: 19.91,
+                ..sample()
+            }
+            .changed_since(&previous)
+        );
+        assert!(
+            Vitals {
+                tps: 18.0,
+                ..sample()
+            }
+            .changed_since(&previous)
+        );
+        assert!(
+            Vitals {
+                players: 4,
+                ..sample()
+            }
+            .changed_since(&previous)
+        );
+        assert!(
+            Vitals {
+                errors: 3,
+                ..sample()
+            }
+            .changed_since(&previous)
+        );
+    }
+}
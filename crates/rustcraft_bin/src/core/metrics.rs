@@ -0,0 +1,253 @@
+use std::fmt::Write;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::RwLock;
+use tracing::{info, warn};
+
+use crate::chunk::{CacheMetrics, ChunkStorage};
+use crate::consts::ACCEPT_BACKOFF_MAX_MS;
+use crate::core::game_loop::GameLoop;
+use crate::error_tracker::{ErrorKey, ErrorTracker};
+use crate::player::PlayerRegistry;
+
+/// Prometheus text exposition format
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+/// Only the request line matters, anything longer is not a scrape
+const MAX_REQUEST_BYTES: usize = 1024;
+
+/// Point-in-time values rendered by `/metrics`
+#[derive(Debug, Clone)]
+pub struct MetricsSnapshot {
+    pub players:      usize,
+    pub tps:          f64,
+    pub mean_tick_ms: f64,
+    pub cache:        CacheMetrics,
+    pub errors:       Vec<(ErrorKey, usize)>,
+}
+
+/// Render a snapshot in the Prometheus text format
+pub fn render(snapshot: &MetricsSnapshot) -> String {
+    let mut out = String::new();
+    let cache = &snapshot.cache;
+
+    write_metric(
+        &mut out,
+        "rustcraft_players_online",
+        "gauge",
+        "Players currently connected",
+        snapshot.players,
+    );
+    write_metric(&mut out, "rustcraft_tps", "gauge", "Measured ticks per second", snapshot.tps);
+    write_metric(
+        &mut out,
+        "rustcraft_tick_mean_ms",
+        "gauge",
+        "Mean time spent per tick",
+        snapshot.mean_tick_ms,
+    );
+    write_metric(
+        &mut out,
+        "rustcraft_chunk_cache_hits_total",
+        "counter",
+        "Chunk lookups served from cache",
+        cache.hits,
+    );
+    write_metric(
+        &mut out,
+        "rustcraft_chunk_cache_misses_total",
+        "counter",
+        "Chunk lookups that missed the cache",
+        cache.misses,
+    );
+    write_metric(
+        &mut out,
+        "rustcraft_chunk_cache_hit_rate",
+        "gauge",
+        "Fraction of chunk lookups served from cache",
+        cache.hit_rate(),
+    );
+    write_metric(
+        &mut out,
+        "rustcraft_chunk_cache_evictions_total",
+        "counter",
+        "Chunks evicted from the cache",
+        cache.evictions,
+    );
+
+    let _ = writeln!(out, "# HELP rustcraft_errors Errors recorded in the current tracking window");
+    let _ = writeln!(out, "# TYPE rustcraft_errors gauge");
+    for (key, count) in &snapshot.errors {
+        let _ = writeln!(
+            out,
+            "rustcraft_errors{{category=\"{}\",semantics=\"{}\"}} {}",
+            escape_label(key.category()),
+            escape_label(key.semantics()),
+            count
+        );
+    }
+
+    out
+}
+
+fn write_metric(out: &mut String, name: &str, kind: &str, help: &str, value: impl std::fmt::Display) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+    let _ = writeln!(out, "{} {}", name, value);
+}
+
+/// Label values escape backslashes, quotes and newlines
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Serves `/metrics` on its own port, separate from the game listener
+pub struct MetricsServer {
+    players:       Arc<PlayerRegistry>,
+    chunk_storage: Arc<ChunkStorage>,
+    game_loop:     Arc<RwLock<GameLoop>>,
+    error_tracker: Arc<ErrorTracker>,
+}
+
+impl MetricsServer {
+    pub fn new(
+        players: Arc<PlayerRegistry>,
+        chunk_storage: Arc<ChunkStorage>,
+        game_loop: Arc<RwLock<GameLoop>>,
+        error_tracker: Arc<ErrorTracker>,
+    ) -> Self {
+        Self {
+            players,
+            chunk_storage,
+            game_loop,
+            error_tracker,
+        }
+    }
+
+    /// Accept scrapes for as long as the server runs; only failing to bind returns
+    pub async fn run(self, port: u16) -> Result<()> {
+        let listener = TcpListener::bind(("0.0.0.0", port)).await?;
+        info!("[METRICS] Serving /metrics on port {}", port);
+
+        loop {
+            let socket = match listener.accept().await {
+                Ok((socket, _)) => socket,
+                Err(e) => {
+                    // Usually out of file descriptors; a scrape can wait for some to free up
+                    warn!("[METRICS] Accept error: {}", e);
+                    tokio::time::sleep(Duration::from_millis(ACCEPT_BACKOFF_MAX_MS)).await;
+                    continue;
+                }
+            };
+            let snapshot = self.snapshot().await;
+            tokio::spawn(async move {
+                if let Err(e) = respond(socket, &snapshot).await {
+                    warn!("[METRICS] Failed to answer scrape: {}", e);
+                }
+            });
+        }
+    }
+
+    async fn snapshot(&self) -> MetricsSnapshot {
+        let (tps, mean_tick_ms) = {
+            let game_loop = self.game_loop.read().await;
+            (game_loop.tps(), game_loop.mean_tick_ms())
+        };
+
+        MetricsSnapshot {
+            players: self.players.len(),
+            tps,
+            mean_tick_ms,
+            cache: self.chunk_storage.cache_metrics(),
+            errors: self.error_tracker.snapshot(),
+        }
+    }
+}
+
+async fn respond(mut socket: TcpStream, snapshot: &MetricsSnapshot) -> Result<()> {
+    let mut buf = vec![0u8; MAX_REQUEST_BYTES];
+    let n = socket.read(&mut buf).await?;
+    let request = String::from_utf8_lossy(&buf[..n]);
+    let request_line = request.lines().next().unwrap_or_default();
+
+    let (status, body) = match request_line.split_whitespace().collect::<Vec<_>>()[..] {
+        ["GET", "/metrics", ..] => ("200 OK", render(snapshot)),
+        _ => ("404 Not Found", String::from("Not Found\n")),
+    };
+
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        CONTENT_TYPE,
+        body.len(),
+        body
+    );
+    socket.write_all(response.as_bytes()).await?;
+    socket.shutdown().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> MetricsSnapshot {
+        MetricsSnapshot {
+            players:      3,
+            tps:          19.5,
+            mean_tick_ms: 0.25,
+            cache:        CacheMetrics {
+                hits:        30,
+                misses:      10,
+                disk_loads:  4,
+                generations: 6,
+                evictions:   2,
+                len:         100,
+                capacity:    1130,
+            },
+            errors:       vec![(ErrorKey::new("NETWORK", "accept \"failed\""), 5)],
+        }
+    }
+
+    #[test]
+    fn test_render_sample_snapshot() {
+        let text = render(&sample());
+
+        assert!(text.contains("# TYPE rustcraft_players_online gauge\nrustcraft_players_online 3\n"));
+        assert!(text.contains("# TYPE rustcraft_tps gauge\nrustcraft_tps 19.5\n"));
+        assert!(text.contains("rustcraft_tick_mean_ms 0.25\n"));
+        assert!(text.contains(
+            "# TYPE rustcraft_chunk_cache_hits_total counter\nrustcraft_chunk_cache_hits_total 30\n"
+        ));
+        assert!(text.contains("rustcraft_chunk_cache_hit_rate 0.75\n"));
+        assert!(text.contains("rustcraft_chunk_cache_evictions_total 2\n"));
+        assert!(
+            text.contains("rustcraft_errors{category=\"NETWORK\",semantics=\"accept \\\"failed\\\"\"} 5\n")
+        );
+    }
+
+    #[test]
+    fn test_render_line_format() {
+        for line in render(&sample()).lines() {
+            if let Some(comment) = line.strip_prefix("# ") {
+                let mut parts = comment.splitn(3, ' ');
+                let kind = parts.next().unwrap();
+                assert!(kind == "HELP" || kind == "TYPE", "bad comment: {}", line);
+                assert!(parts.next().unwrap().starts_with("rustcraft_"));
+                if kind == "TYPE" {
+                    assert!(matches!(parts.next(), Some("gauge") | Some("counter")));
+                }
+            } else {
+                let (name, value) = line.rsplit_once(' ').unwrap();
+                assert!(name.starts_with("rustcraft_"), "bad metric: {}", line);
+                assert!(value.parse::<f64>().is_ok(), "bad value: {}", line);
+            }
+        }
+    }
+}
@@ -1,6 +1,16 @@
+mod console;
+mod events;
 mod game_loop;
+mod heartbeat;
+// Only started with the `metrics` feature, but always built so its tests run
+#[cfg_attr(not(feature = "metrics"), allow(dead_code))]
+mod metrics;
 mod server;
+mod shutdown;
 mod thread_pool;
 
+#[cfg(test)]
+pub use events::EventKind;
+pub use events::{Event, EventBus, EventResult};
 pub use server::{HandlerData, MinecraftServer};
-pub use thread_pool::ChunkGenThreadPool;
+pub use thread_pool::{ChunkGenThreadPool, TaskPriority};
@@ -1,23 +1,57 @@
-use std::error::Error;
 use std::fmt::{Debug, Display};
 use std::io::Error as StdIoError;
 use std::net::SocketAddr;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::result::Result as StdResult;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
 
-use anyhow::Result;
+use anyhow::{Result, anyhow};
 use tokio::net::{TcpListener, TcpStream, ToSocketAddrs};
-use tokio::sync::RwLock;
-use tracing::{error, info};
+use tokio::sync::mpsc::unbounded_channel;
+use tokio::sync::{OwnedSemaphorePermit, RwLock, Semaphore};
+use tracing::{Instrument, Span, debug, error, info, warn};
 
+use crate::access_control::AccessControl;
 use crate::chunk::ChunkStorage;
-use crate::consts::{CHUNK_SEED, GAMELOOP_SLEEP_TICK, WORLD_PATH};
+use crate::config::{ErrorPolicy, ForwardingMode, GeneratorKind, ServerConfig};
+use crate::consts::{
+    ACCEPT_BACKOFF_BASE_MS,
+    ACCEPT_BACKOFF_MAX_MS,
+    MAX_PENDING_REJECTS,
+    REJECT_TIMEOUT_MS,
+    SERVER_DIR,
+    WORLD_PATH,
+};
+use crate::core::console::Console;
+use crate::core::events::EventBus;
 use crate::core::game_loop::GameLoop;
+use crate::core::heartbeat::Heartbeat;
+use crate::core::shutdown::{DEFAULT_SHUTDOWN_REASON, Shutdown};
 use crate::core::thread_pool::ChunkGenThreadPool;
 use crate::error_tracker::{ErrorKey, ErrorTracker};
-use crate::player::PlayerData;
-use crate::terrain::ChunkGenerator;
+use crate::network::{
+    AuthProvider,
+    ConnectionRateLimiter,
+    LoginHandler,
+    LoginPluginHook,
+    OfflineAuth,
+    PluginChannels,
+    SERVER_FULL_REASON,
+    VelocityForwarding,
+};
+use crate::player::{
+    BlockRequestSender,
+    DisconnectGuard,
+    EntityIdAllocator,
+    PlayerData,
+    PlayerRegistry,
+    PlayerStore,
+    watch_stage_timeouts,
+};
+use crate::terrain::{ChunkGenerator, ChunkPos, FlatWorldGenerator, VoidWorldGenerator, WorldGenerator};
+use crate::world::{LevelData, World};
 
 // TODO: @dx : for various reasons, we might consider having a chunk_manager: ChunkManager as a single field
 // and it's constructed of ChunkStorage + ChunKGenerator + ChunkGenThreadPool etc.
@@ -30,31 +64,112 @@ pub struct MinecraftServer {
     listener:  TcpListener,
     game_loop: Arc<RwLock<GameLoop>>,
     hdata:     HandlerData,
+    /// Started by the console `stop` command or Ctrl-C
+    shutdown:  Arc<Shutdown>,
+    /// Read operator commands from stdin
+    console:   bool,
 }
 
 #[derive(Clone)]
 pub struct HandlerData {
-    pub chunk_storage:  Arc<ChunkStorage>,
-    pub error_tracker:  Arc<ErrorTracker>,
-    pub chunk_gen_pool: Arc<ChunkGenThreadPool>,
+    pub world:            Arc<World>,
+    pub error_tracker:    Arc<ErrorTracker>,
+    pub chunk_gen_pool:   Arc<ChunkGenThreadPool>,
+    pub players:          Arc<PlayerRegistry>,
+    pub entity_ids:       Arc<EntityIdAllocator>,
+    pub config:           Arc<ServerConfig>,
+    /// One permit per open connection, sized by `ServerConfig::max_connections`
+    pub connection_slots: Arc<Semaphore>,
+    /// One permit per "Server full" disconnect in flight, sized by `MAX_PENDING_REJECTS`
+    pub reject_slots:     Arc<Semaphore>,
+    pub rate_limiter:     Arc<ConnectionRateLimiter>,
+    pub plugin_channels:  Arc<PluginChannels>,
+    pub access:           Arc<AccessControl>,
+    /// Identifies players at login, chosen by `ServerConfig::online_mode`
+    pub auth:             Arc<dyn AuthProvider>,
+    /// Login plugin exchange every login goes through, if any
+    pub login_plugin:     Option<Arc<dyn LoginPluginHook>>,
+    /// Block and player event listeners, run synchronously by whoever emits the event
+    pub events:           Arc<EventBus>,
+    /// Digs and placements for the game loop to apply
+    pub block_requests:   BlockRequestSender,
 }
 
 impl HandlerData {
+    #[allow(clippy::too_many_arguments)]
     fn new(
-        chunk_storage: Arc<ChunkStorage>,
+        world: Arc<World>,
         error_tracker: Arc<ErrorTracker>,
         chunk_gen_pool: Arc<ChunkGenThreadPool>,
-    ) -> Self {
-        Self {
-            chunk_storage,
+        players: Arc<PlayerRegistry>,
+        entity_ids: Arc<EntityIdAllocator>,
+        access: Arc<AccessControl>,
+        config: Arc<ServerConfig>,
+        block_requests: BlockRequestSender,
+    ) -> Result<Self> {
+        let connection_slots = Arc::new(Semaphore::new(config.max_connections as usize));
+        let rate_limiter = Arc::new(ConnectionRateLimiter::from_config(&config.rate_limit));
+        let auth = Self::auth_for(&config)?;
+        let login_plugin = Self::login_plugin_for(&config);
+        Ok(Self {
+            world,
             error_tracker,
             chunk_gen_pool,
+            players,
+            entity_ids,
+            config,
+            connection_slots,
+            reject_slots: Arc::new(Semaphore::new(MAX_PENDING_REJECTS)),
+            rate_limiter,
+            plugin_channels: Arc::new(PluginChannels::new()),
+            access,
+            auth,
+            login_plugin,
+            events: Arc::new(EventBus::new()),
+            block_requests,
+        })
+    }
+
+    /// Offline-mode UUIDs; online mode is refused until the login flow has the encryption
+    /// handshake that gives `MojangAuth` its server hash
+    fn auth_for(config: &ServerConfig) -> Result<Arc<dyn AuthProvider>> {
+        if config.online_mode {
+            return Err(anyhow!(
+                "online_mode needs the encryption handshake, which isn't supported yet; run behind \
+                 Velocity with forwarding to get verified players"
+            ));
+        }
+        Ok(Arc::new(OfflineAuth))
+    }
+
+    fn login_plugin_for(config: &ServerConfig) -> Option<Arc<dyn LoginPluginHook>> {
+        match (config.forwarding, &config.forwarding_secret) {
+            (ForwardingMode::Velocity, Some(secret)) => {
+                info!("[STARTUP] Accepting players forwarded by Velocity");
+                Some(Arc::new(VelocityForwarding::new(secret)))
+            }
+            _ => None,
         }
     }
 }
 
 impl MinecraftServer {
-    pub async fn new<A>(addr: A, error_tracker: Arc<ErrorTracker>) -> Result<Self>
+    pub async fn new<A>(addr: A, error_tracker: Arc<ErrorTracker>, config: Arc<ServerConfig>) -> Result<Self>
+    where
+        A: ToSocketAddrs + Display + Debug,
+    {
+        Self::with_dirs(addr, error_tracker, config, WORLD_PATH, SERVER_DIR).await
+    }
+
+    /// Like `new`, but keeps the world in `world_dir` and the ban/whitelist/op lists in
+    /// `server_dir` instead of the default locations
+    pub async fn with_dirs<A>(
+        addr: A,
+        error_tracker: Arc<ErrorTracker>,
+        config: Arc<ServerConfig>,
+        world_dir: impl Into<PathBuf>,
+        server_dir: impl AsRef<Path>,
+    ) -> Result<Self>
     where
         A: ToSocketAddrs + Display + Debug,
     {
@@ -64,31 +179,78 @@ impl MinecraftServer {
         // Initialize thread pools
         let chunk_gen_pool = Arc::new(ChunkGenThreadPool::new());
 
+        // The seed has to be settled before the generator exists, so read the world's metadata first
+        let world_dir = world_dir.into();
+        let level = LevelData::load(&world_dir)?;
+        let seed = level.map_or(config.seed, |level| level.seed_or(config.seed));
+
         // Create chunk generator and storage with the pool
-        let chunk_gen = Arc::new(ChunkGenerator::new::<u64>(CHUNK_SEED));
-        let chunk_storage = Arc::new(ChunkStorage::new(chunk_gen, Arc::clone(&chunk_gen_pool))?);
+        let chunk_gen = world_generator(&config, seed)?;
+        let chunk_storage = Arc::new(ChunkStorage::new(world_dir, chunk_gen, Arc::clone(&chunk_gen_pool))?);
+
+        // A saved spawn is restored by `apply_level_data` below
+        let mut spawn = config.spawn;
+        if config.spawn_on_surface && level.is_none_or(|level| level.spawn.is_none()) {
+            let (x, z) = (spawn.x.floor() as i32, spawn.z.floor() as i32);
+            spawn.y = chunk_storage.surface_height(x, z)? as f64;
+            info!("[STARTUP] Spawn resolved to the surface at {}", spawn);
+        }
+        let world = Arc::new(World::new(World::default_name(), chunk_storage, seed, spawn));
+        if let Some(level) = &level {
+            world.apply_level_data(level);
+        }
+        world.set_daylight_cycle(config.do_daylight_cycle);
 
+        let store = PlayerStore::new(world.chunks().world_dir());
+        let players = Arc::new(PlayerRegistry::new().with_store(store));
+        let (block_requests, block_request_rx) = unbounded_channel();
         let handler_data = HandlerData::new(
-            Arc::clone(&chunk_storage),
+            Arc::clone(&world),
             Arc::clone(&error_tracker),
             Arc::clone(&chunk_gen_pool),
-        );
+            Arc::clone(&players),
+            Arc::new(EntityIdAllocator::new()),
+            Arc::new(AccessControl::load(server_dir, config.whitelist)?),
+            config,
+            block_requests,
+        )?;
 
         Ok(Self {
             listener,
-            game_loop: Arc::new(RwLock::new(GameLoop::new())),
+            game_loop: Arc::new(RwLock::new(GameLoop::new(
+                handler_data.config.tick_interval(),
+                world,
+                players,
+                Arc::clone(&handler_data.events),
+                block_request_rx,
+            ))),
+            shutdown: Arc::new(Shutdown::new(Arc::clone(&handler_data.players))),
             hdata: handler_data,
+            console: true,
         })
     }
 
-    pub async fn run(self) -> Result<()> {
+    /// Don't read operator commands from stdin, e.g. when something else owns it
+    #[cfg(test)]
+    pub fn without_console(mut self) -> Self {
+        self.console = false;
+        self
+    }
+
+    /// Address the listener is bound to, which resolves a port of 0 to the one actually chosen
+    #[cfg(test)]
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.listener.local_addr()?)
+    }
+
+    pub async fn run(mut self) -> Result<()> {
         // Start hit count reset task (runs every 5 minutes)
         // self.chunk_storage.start_hit_reset_task(); // now done inside ChunkStorage::new()
         // Realistically; this should never happen due to generation
         // running on the constructor of `MinecraftServer::new()`, which itself constructs
         // a `ChunkStorage` that initializes the world folder.
-        // The path (until a later refactor) is defined in `consts::WORLD_PATH`.
-        if !Path::new(&WORLD_PATH).exists() {
+        // The path defaults to `consts::WORLD_PATH`.
+        if !self.hdata.world.chunks().world_dir().exists() {
             error!("[STARTUP] World directory does not exist after initialization!");
             error!(
                 "[STARTUP] This should never happen unless you've deleted the world folder while the server is setting up."
@@ -100,18 +262,84 @@ impl MinecraftServer {
         info!("[STARTUP] Chunk generation thread pool initialization complete.");
 
         // Spawn game loop task (main thread for game loop and logging)
+        let game_loop = Arc::clone(&self.game_loop);
+        let tick_interval = self.hdata.config.tick_interval();
         tokio::spawn(async move {
-            let game_loop = Arc::clone(&self.game_loop);
             loop {
                 let mut gl = game_loop.write().await;
                 gl.tick(); // function is infallible. Semantically, prefer an Option though
                 drop(gl);
-                tokio::time::sleep(tokio::time::Duration::from_millis(GAMELOOP_SLEEP_TICK)).await;
+                tokio::time::sleep(tick_interval).await;
             }
         });
 
         let hdata = self.hdata;
 
+        // Players can join while this runs; their chunks jump the queue
+        let pregen_cancel = Arc::new(AtomicBool::new(false));
+        if hdata.config.pregenerate {
+            let storage = Arc::clone(hdata.world.chunks());
+            let spawn = hdata.world.spawn();
+            let center = ChunkPos::from_world(spawn.x, spawn.z);
+            let radius = hdata.config.pregen_radius;
+            let cancel = Arc::clone(&pregen_cancel);
+            tokio::task::spawn_blocking(move || {
+                if let Err(e) = storage.pregenerate_spawn_area(center, radius, cancel) {
+                    error!("[STARTUP] Pregeneration failed: {}", e);
+                }
+            });
+        }
+
+        // Operator commands from stdin
+        if self.console {
+            let console = Console::new(
+                Arc::clone(&hdata.players),
+                Arc::clone(&hdata.world),
+                Arc::clone(&hdata.access),
+                Arc::clone(&self.game_loop),
+                Arc::clone(&self.shutdown),
+                hdata.config.shutdown_countdown_secs,
+            );
+            tokio::spawn(console.run());
+        }
+
+        hdata
+            .world
+            .chunks()
+            .start_unload_task(Arc::clone(&hdata.players), hdata.config.simulation_distance as i32);
+
+        if hdata.config.heartbeat_interval_secs > 0 {
+            let heartbeat = Heartbeat::new(
+                Arc::clone(&hdata.players),
+                Arc::clone(hdata.world.chunks()),
+                Arc::clone(&self.game_loop),
+                Arc::clone(&hdata.error_tracker),
+            );
+            let interval = Duration::from_secs(hdata.config.heartbeat_interval_secs);
+            tokio::spawn(heartbeat.run(interval));
+        }
+
+        #[cfg(feature = "metrics")]
+        {
+            let metrics = crate::core::metrics::MetricsServer::new(
+                Arc::clone(&hdata.players),
+                Arc::clone(hdata.world.chunks()),
+                Arc::clone(&self.game_loop),
+                Arc::clone(&hdata.error_tracker),
+            );
+            let port = hdata.config.metrics_port;
+            tokio::spawn(async move {
+                if let Err(e) = metrics.run(port).await {
+                    error!("[METRICS] Metrics endpoint stopped: {}", e);
+                }
+            });
+        }
+
+        let mut backoff = AcceptBackoff::default();
+        let mut interrupted = false;
+        // Made once and re-armed after each Ctrl-C, so a signal between loop turns isn't missed
+        let ctrl_c = tokio::signal::ctrl_c();
+        tokio::pin!(ctrl_c);
         loop {
             tokio::select! {
                 biased; // biased here causes futures to be polled in the order they appear/defined
@@ -120,7 +348,32 @@ impl MinecraftServer {
                 res = self.listener.accept() => {
                     // let hd = Arc::clone(&handler_data);
                     let hdata = hdata.clone();
-                    handle_accept(hdata, res).await?;
+                    match handle_accept(hdata, res, &mut backoff).await? {
+                        AcceptAction::Continue => {}
+                        AcceptAction::Shutdown => self.shutdown.shutdown(CRITICAL_ERROR_SHUTDOWN_REASON, 0),
+                        AcceptAction::RestartListener => {
+                            if let Err(e) = rebind(&mut self.listener).await {
+                                error!("[NETWORK] Failed to restart the listener: {}", e);
+                                self.shutdown.shutdown(CRITICAL_ERROR_SHUTDOWN_REASON, 0);
+                            }
+                        }
+                    }
+                }
+
+                _ = &mut ctrl_c => {
+                    ctrl_c.set(tokio::signal::ctrl_c());
+                    // A second Ctrl-C skips whatever is left of the countdown
+                    let countdown = if interrupted { 0 } else { hdata.config.shutdown_countdown_secs };
+                    interrupted = true;
+                    self.shutdown.shutdown(DEFAULT_SHUTDOWN_REASON, countdown);
+                }
+
+                _ = self.shutdown.wait() => {
+                    pregen_cancel.store(true, Ordering::Relaxed);
+                    info!("[SHUTDOWN] Saving the world before exit");
+                    let world = Arc::clone(&hdata.world);
+                    tokio::task::spawn_blocking(move || world.save()).await??;
+                    return Ok(());
                 }
 
                 // Easily add other handlers as needed (sep heartbeat, logging, etc.)
@@ -132,30 +385,515 @@ impl MinecraftServer {
 async fn handle_accept(
     hdata: HandlerData,
     res: StdResult<(TcpStream, SocketAddr), StdIoError>,
-) -> Result<()> {
-    if let Err(e) = &res {
-        error!("[NETWORK] Accept error: {}", e);
-        let key = ErrorKey::new("NETWORK", "accept_failed");
-        if hdata.error_tracker.record_error(key) {
-            error!("[SHUTDOWN] Initiating safe shutdown due to critical errors");
-            return Ok(());
+    backoff: &mut AcceptBackoff,
+) -> Result<AcceptAction> {
+    let (socket, addr) = match res {
+        Ok(accepted) => {
+            backoff.reset();
+            accepted
+        }
+        Err(e) => {
+            error!("[NETWORK] Accept error: {}", e);
+            return Ok(accept_failed(&hdata.error_tracker, hdata.config.error_policy, backoff).await);
         }
+    };
+    info!("[CONNECTION] New connection from {}", addr);
+
+    // Dropping the socket closes it; flooding clients don't get a disconnect message
+    if !hdata.rate_limiter.check(addr.ip()) {
+        warn!("[CONNECTION] Dropping {}: connection rate limit exceeded", addr);
+        hdata
+            .error_tracker
+            .record_error(ErrorKey::new("NETWORK", "rate_limited"));
+        return Ok(AcceptAction::Continue);
     }
 
-    let (socket, addr) = res.unwrap(); // if it's not an err above, we can unwrap safely
-    info!("[CONNECTION] New connection from {}", addr);
+    let Some((socket, permit)) = admit(&hdata.connection_slots, &hdata.reject_slots, socket) else {
+        warn!("[CONNECTION] Refusing {}: connection limit reached", addr);
+        return Ok(AcceptAction::Continue);
+    };
+
+    tokio::spawn(
+        async move {
+            if let Err(e) = handle_client(socket, hdata).await {
+                error!("[CLIENT] Connection error: {}", e);
+            }
+            // The slot frees up once the connection is fully cleaned up
+            drop(permit);
+        }
+        .instrument(connection_span(addr)),
+    );
 
-    tokio::spawn(async move {
-        if let Err(e) = handle_client(socket, hdata).await {
-            error!("[CLIENT] Connection error: {}", e);
+    Ok(AcceptAction::Continue)
+}
+
+/// Count a failed `accept()` and decide what the accept loop does about it under `policy`
+async fn accept_failed(
+    error_tracker: &ErrorTracker,
+    policy: ErrorPolicy,
+    backoff: &mut AcceptBackoff,
+) -> AcceptAction {
+    let key = ErrorKey::new("NETWORK", "accept_failed");
+    if error_tracker.record_error(key.clone()) {
+        match policy {
+            ErrorPolicy::ShutdownOnCritical => {
+                error!("[SHUTDOWN] Initiating safe shutdown due to critical errors");
+                return AcceptAction::Shutdown;
+            }
+            ErrorPolicy::RestartSubsystem => {
+                warn!("[NETWORK] Restarting the listener after repeated accept errors");
+                error_tracker.reset(&key);
+                backoff.reset();
+                return AcceptAction::RestartListener;
+            }
+            ErrorPolicy::LogOnly => {
+                warn!("[NETWORK] Accept errors past the threshold; error_policy is log_only, carrying on");
+            }
         }
-    });
+    }
 
+    // Errors like EMFILE persist until something else closes, so don't spin on them
+    let delay = backoff.next_delay();
+    debug!("[NETWORK] Backing off accept for {:?}", delay);
+    tokio::time::sleep(delay).await;
+    AcceptAction::Continue
+}
+
+/// Close `listener` and listen on the same address again
+async fn rebind(listener: &mut TcpListener) -> Result<()> {
+    let addr = listener.local_addr()?;
+    // The old socket has to be closed before its port can be bound again
+    let placeholder = TcpListener::bind((addr.ip(), 0)).await?;
+    drop(std::mem::replace(listener, placeholder));
+    *listener = TcpListener::bind(addr).await?;
+    info!("[NETWORK] Listening again on {}", addr);
     Ok(())
 }
 
+/// Span around everything a connection's task logs, so concurrent players' lines can be told apart
+/// `username` and `uuid` are recorded once the player has logged in
+fn connection_span(addr: SocketAddr) -> Span {
+    tracing::info_span!(
+        "connection",
+        peer = %addr,
+        username = tracing::field::Empty,
+        uuid = tracing::field::Empty
+    )
+}
+
+/// The generator the config asks for
+fn world_generator(config: &ServerConfig, seed: u64) -> Result<Arc<dyn WorldGenerator>> {
+    let generator: Arc<dyn WorldGenerator> = match config.generator {
+        GeneratorKind::Noise => Arc::new(ChunkGenerator::new::<u64>(seed, config.noise)),
+        GeneratorKind::Flat => Arc::new(config.flat_layers.parse::<FlatWorldGenerator>()?),
+        GeneratorKind::Void => Arc::new(VoidWorldGenerator),
+    };
+    info!("[STARTUP] Using the {:?} world generator", config.generator);
+    Ok(generator)
+}
+
+/// Kick reason when an error crossing its threshold stops the server
+const CRITICAL_ERROR_SHUTDOWN_REASON: &str = "Server closed after repeated internal errors";
+
+/// What the accept loop does after handling one `accept()` result
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AcceptAction {
+    Continue,
+    /// Save and stop, under `ErrorPolicy::ShutdownOnCritical`
+    Shutdown,
+    /// Rebind the listener, under `ErrorPolicy::RestartSubsystem`
+    RestartListener,
+}
+
+/// Growing pause between `accept()` calls while they keep failing
+#[derive(Debug, Default)]
+struct AcceptBackoff {
+    consecutive_errors: u32,
+}
+
+impl AcceptBackoff {
+    /// Record a failed accept and return how long to wait before the next one
+    fn next_delay(&mut self) -> Duration {
+        self.consecutive_errors = self.consecutive_errors.saturating_add(1);
+        accept_backoff_delay(self.consecutive_errors)
+    }
+
+    fn reset(&mut self) {
+        self.consecutive_errors = 0;
+    }
+}
+
+/// `ACCEPT_BACKOFF_BASE_MS` doubled for each failure after the first, capped at
+/// `ACCEPT_BACKOFF_MAX_MS`
+fn accept_backoff_delay(consecutive_errors: u32) -> Duration {
+    if consecutive_errors == 0 {
+        return Duration::ZERO;
+    }
+
+    let factor = 1u64.checked_shl(consecutive_errors - 1).unwrap_or(u64::MAX);
+    Duration::from_millis(
+        ACCEPT_BACKOFF_BASE_MS
+            .saturating_mul(factor)
+            .min(ACCEPT_BACKOFF_MAX_MS),
+    )
+}
+
+/// Take a connection slot for `socket`, or tell the client the server is full and close it
+/// At most `rejects` refusals are sent at once; past that the socket is closed without one
+fn admit(
+    slots: &Arc<Semaphore>,
+    rejects: &Arc<Semaphore>,
+    socket: TcpStream,
+) -> Option<(TcpStream, OwnedSemaphorePermit)> {
+    match Arc::clone(slots).try_acquire_owned() {
+        Ok(permit) => Some((socket, permit)),
+        Err(_) => {
+            let Ok(reject_permit) = Arc::clone(rejects).try_acquire_owned() else {
+                debug!("[CONNECTION] Closing without a server full disconnect: too many pending");
+                return None;
+            };
+            // Off the accept loop, so a slow client can't hold up new connections
+            tokio::spawn(async move {
+                let _reject_permit = reject_permit;
+                let reject = LoginHandler::from(socket).reject(SERVER_FULL_REASON);
+                match tokio::time::timeout(Duration::from_millis(REJECT_TIMEOUT_MS), reject).await {
+                    Ok(Ok(())) => {}
+                    Ok(Err(e)) => debug!("[CONNECTION] Failed to send server full disconnect: {}", e),
+                    Err(_) => debug!("[CONNECTION] Timed out sending server full disconnect"),
+                }
+            });
+            None
+        }
+    }
+}
+
 async fn handle_client(socket: TcpStream, hd: HandlerData) -> Result<()> {
     let player = PlayerData::new(socket).await?;
-    player.handle(hd).await?;
+    let connection = Arc::clone(&player.connection);
+    let timeouts = hd.config.stage_timeouts;
+    let error_tracker = Arc::clone(&hd.error_tracker);
+
+    // Runs the disconnect cleanup however this function exits, including on panic
+    let _cleanup = DisconnectGuard::new(
+        Arc::clone(&connection),
+        Arc::clone(&hd.players),
+        Arc::clone(&hd.entity_ids),
+        Arc::clone(&hd.events),
+    );
+
+    // Dropping the handler future closes the socket, which disconnects a client stuck mid-login
+    tokio::select! {
+        res = player.handle(hd) => res?,
+        stage = watch_stage_timeouts(&connection, timeouts) => {
+            let info = connection.state_info();
+            error!("[CONNECTION] Timed out in stage {} {}", stage, info);
+            error_tracker.record_error(ErrorKey::new("CONNECTION", format!("stage_timeout: {}", stage)));
+            return Err(anyhow!("Connection timed out in stage {}", stage));
+        }
+    }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::config::{LogFormat, LogLevel};
+    use crate::consts::ERROR_THRESHOLD;
+    use crate::logging::{self, CapturedLogs};
+    use crate::network::{
+        ByteWritable,
+        ClientboundConfig,
+        ClientboundLogin,
+        ClientboundPlay,
+        PacketReader,
+        PacketWriter,
+        ProtocolVersion,
+        ServerboundConfig,
+        ServerboundHandshake,
+        ServerboundLogin,
+        read_packet_frame,
+        write_varint,
+    };
+
+    fn frame(packet_id: i32, payload: &[u8]) -> Vec<u8> {
+        let id = write_varint(packet_id);
+        let mut frame = write_varint((id.len() + payload.len()) as i32);
+        frame.extend_from_slice(&id);
+        frame.extend_from_slice(payload);
+        frame
+    }
+
+    /// Read frames until one with `packet_id` arrives, returning its payload and the ids skipped
+    async fn read_until(stream: &mut TcpStream, packet_id: i32) -> (Vec<u8>, Vec<i32>) {
+        let mut skipped = Vec::new();
+        loop {
+            let (id, payload) = read_packet_frame(stream).await.unwrap();
+            if id == packet_id {
+                return (payload, skipped);
+            }
+            skipped.push(id);
+        }
+    }
+
+    /// Drive a 1.21.7 client through login, configuration and into Play against a real server
+    async fn join(addr: SocketAddr) {
+        let mut client = TcpStream::connect(addr).await.unwrap();
+
+        let mut handshake = PacketWriter::new();
+        handshake.write_varint(ProtocolVersion::V1_21_7.protocol());
+        handshake.write_string("localhost");
+        handshake.write_short(addr.port() as i16);
+        handshake.write_varint(2);
+        let mut login_start = PacketWriter::new();
+        login_start.write_string("Joiner");
+        login_start.write_uuid(Uuid::nil());
+        let mut bytes = frame(ServerboundHandshake::Handshake.id(), &handshake.finish());
+        bytes.extend_from_slice(&frame(ServerboundLogin::LoginStart.id(), &login_start.finish()));
+        client.write_all(&bytes).await.unwrap();
+
+        let (success, _) = read_until(&mut client, ClientboundLogin::LoginSuccess.id()).await;
+        let mut reader = PacketReader::new(&success);
+        reader.read_uuid().unwrap();
+        assert_eq!(reader.read_string().unwrap(), "Joiner");
+        client
+            .write_all(&frame(ServerboundLogin::LoginAcknowledged.id(), &[]))
+            .await
+            .unwrap();
+
+        // Answer Known Packs with the same list and tell the server our view distance
+        let (packs, _) = read_until(&mut client, ClientboundConfig::KnownPacks.id()).await;
+        let mut information = PacketWriter::new();
+        information.write_string("en_us");
+        information.write_byte(2u8); // view distance
+        information.write_varint(0); // chat mode
+        information.write_bool(true); // chat colors
+        information.write_byte(0x7Fu8); // skin parts
+        information.write_varint(1); // main hand
+        information.write_bool(false); // text filtering
+        information.write_bool(true); // server listings
+        information.write_varint(0); // particles
+        let mut bytes = frame(ServerboundConfig::KnownPacks.id(), &packs);
+        bytes.extend_from_slice(&frame(ServerboundConfig::ClientInformation.id(), &information.finish()));
+        client.write_all(&bytes).await.unwrap();
+
+        let (_, before_finish) = read_until(&mut client, ClientboundConfig::FinishConfiguration.id()).await;
+        let registries = before_finish
+            .iter()
+            .filter(|&&id| id == ClientboundConfig::RegistryData.id())
+            .count();
+        assert!(registries > 0, "no Registry Data before Finish Configuration: {:?}", before_finish);
+        client
+            .write_all(&frame(ServerboundConfig::AcknowledgeFinishConfiguration.id(), &[]))
+            .await
+            .unwrap();
+
+        let (_, before_login) = read_until(&mut client, ClientboundPlay::Login.id()).await;
+        assert!(before_login.is_empty(), "Join Game must open Play, got {:?} first", before_login);
+        read_until(&mut client, ClientboundPlay::ChunkDataAndUpdateLight.id()).await;
+    }
+
+    #[tokio::test]
+    async fn test_client_joins_and_receives_chunks() {
+        let world_dir = std::env::temp_dir().join(format!("rustcraft_join_{}", Uuid::new_v4()));
+        let config = ServerConfig {
+            generator: GeneratorKind::Flat,
+            pregenerate: false,
+            view_distance: 2,
+            ..ServerConfig::default()
+        };
+        let server = MinecraftServer::with_dirs(
+            "127.0.0.1:0",
+            Arc::new(ErrorTracker::new()),
+            Arc::new(config),
+            &world_dir,
+            &world_dir,
+        )
+        .await
+        .unwrap()
+        .without_console();
+        let addr = server.local_addr().unwrap();
+        let server = tokio::spawn(server.run());
+
+        tokio::time::timeout(Duration::from_secs(30), join(addr))
+            .await
+            .expect("join timed out");
+
+        server.abort();
+        std::fs::remove_dir_all(&world_dir).ok();
+    }
+
+    #[tokio::test]
+    async fn test_connection_logs_carry_the_connection_span() {
+        let logs = CapturedLogs::default();
+        let writer = logs.clone();
+        let _subscriber = tracing::subscriber::set_default(logging::build_subscriber(
+            LogFormat::Json,
+            LogLevel::Debug,
+            move || writer.clone(),
+        ));
+
+        let world_dir = std::env::temp_dir().join(format!("rustcraft_span_{}", Uuid::new_v4()));
+        let config = ServerConfig {
+            generator: GeneratorKind::Void,
+            pregenerate: false,
+            view_distance: 2,
+            ..ServerConfig::default()
+        };
+        let server = MinecraftServer::with_dirs(
+            "127.0.0.1:0",
+            Arc::new(ErrorTracker::new()),
+            Arc::new(config),
+            &world_dir,
+            &world_dir,
+        )
+        .await
+        .unwrap()
+        .without_console();
+        let addr = server.local_addr().unwrap();
+        let server = tokio::spawn(server.run());
+
+        tokio::time::timeout(Duration::from_secs(30), join(addr))
+            .await
+            .expect("join timed out");
+        server.abort();
+        std::fs::remove_dir_all(&world_dir).ok();
+
+        let events: Vec<serde_json::Value> = logs
+            .lines()
+            .iter()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        let span_of = |message: &str| {
+            events
+                .iter()
+                .find(|event| {
+                    event["fields"]["message"]
+                        .as_str()
+                        .is_some_and(|m| m.contains(message))
+                })
+                .unwrap_or_else(|| panic!("no event logged containing {:?}", message))["span"]
+                .clone()
+        };
+
+        // Before login only the peer is known
+        let span = span_of("[PLAYER] Starting login flow");
+        assert_eq!(span["name"], "connection");
+        assert!(span["peer"].as_str().unwrap().starts_with("127.0.0.1:"));
+        assert!(span.get("username").is_none());
+
+        let span = span_of("[PLAYER] Starting configuration phase");
+        assert_eq!(span["username"], "Joiner");
+        assert!(span["uuid"].as_str().unwrap().parse::<Uuid>().is_ok());
+    }
+
+    #[test]
+    fn test_accept_backoff_grows_and_resets() {
+        let mut backoff = AcceptBackoff::default();
+        let delays: Vec<u64> = (0..10).map(|_| backoff.next_delay().as_millis() as u64).collect();
+        assert_eq!(delays, vec![5, 10, 20, 40, 80, 160, 320, 640, 1_000, 1_000]);
+
+        // Stays capped however long the errors go on
+        assert_eq!(accept_backoff_delay(64), Duration::from_millis(ACCEPT_BACKOFF_MAX_MS));
+        assert_eq!(accept_backoff_delay(u32::MAX), Duration::from_millis(ACCEPT_BACKOFF_MAX_MS));
+
+        // One successful accept starts over from the base delay
+        backoff.reset();
+        assert_eq!(accept_backoff_delay(0), Duration::ZERO);
+        assert_eq!(backoff.next_delay(), Duration::from_millis(ACCEPT_BACKOFF_BASE_MS));
+    }
+
+    #[tokio::test]
+    async fn test_error_policy_decides_the_accept_loop_action() {
+        for (policy, expected) in [
+            (ErrorPolicy::ShutdownOnCritical, AcceptAction::Shutdown),
+            (ErrorPolicy::LogOnly, AcceptAction::Continue),
+            (ErrorPolicy::RestartSubsystem, AcceptAction::RestartListener),
+        ] {
+            let tracker = ErrorTracker::new();
+            let mut backoff = AcceptBackoff::default();
+
+            // Below the threshold every policy just backs off and keeps accepting
+            for _ in 1..ERROR_THRESHOLD {
+                assert_eq!(accept_failed(&tracker, policy, &mut backoff).await, AcceptAction::Continue);
+            }
+            assert_eq!(accept_failed(&tracker, policy, &mut backoff).await, expected, "{:?}", policy);
+        }
+
+        // A restart starts the count over, so the next failure doesn't restart again
+        let tracker = ErrorTracker::new();
+        let mut backoff = AcceptBackoff::default();
+        for _ in 0..ERROR_THRESHOLD {
+            accept_failed(&tracker, ErrorPolicy::RestartSubsystem, &mut backoff).await;
+        }
+        assert!(tracker.snapshot().is_empty());
+        assert_eq!(
+            accept_failed(&tracker, ErrorPolicy::RestartSubsystem, &mut backoff).await,
+            AcceptAction::Continue
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rebind_keeps_the_address() {
+        let mut listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        rebind(&mut listener).await.unwrap();
+        assert_eq!(listener.local_addr().unwrap(), addr);
+
+        let _client = TcpStream::connect(addr).await.unwrap();
+        listener.accept().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_connections_past_the_limit_are_rejected() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let slots = Arc::new(Semaphore::new(2));
+        let rejects = Arc::new(Semaphore::new(MAX_PENDING_REJECTS));
+
+        let mut clients = Vec::new();
+        let mut held = Vec::new();
+        for _ in 0..2 {
+            clients.push(TcpStream::connect(addr).await.unwrap());
+            let (socket, _) = listener.accept().await.unwrap();
+            held.push(admit(&slots, &rejects, socket).expect("slot available"));
+        }
+
+        let mut third = TcpStream::connect(addr).await.unwrap();
+        let (socket, _) = listener.accept().await.unwrap();
+        assert!(admit(&slots, &rejects, socket).is_none());
+
+        // Login Disconnect with the reason as a JSON text component
+        let (packet_id, payload) = read_packet_frame(&mut third).await.unwrap();
+        assert_eq!(packet_id, 0x00);
+        let reason = PacketReader::new(&payload).read_string().unwrap();
+        assert_eq!(reason, r#"{"text":"Server full"}"#);
+
+        // Closing a held connection frees its slot
+        held.pop();
+        let _fourth = TcpStream::connect(addr).await.unwrap();
+        let (socket, _) = listener.accept().await.unwrap();
+        assert!(admit(&slots, &rejects, socket).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_rejects_past_the_pending_limit_are_just_closed() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let slots = Arc::new(Semaphore::new(0));
+        let rejects = Arc::new(Semaphore::new(1));
+        let _pending = Arc::clone(&rejects).try_acquire_owned().unwrap();
+
+        let mut client = TcpStream::connect(addr).await.unwrap();
+        let (socket, _) = listener.accept().await.unwrap();
+        assert!(admit(&slots, &rejects, socket).is_none());
+
+        // No disconnect packet, the socket is simply closed
+        let mut buf = [0u8; 1];
+        assert_eq!(client.read(&mut buf).await.unwrap(), 0);
+    }
+}
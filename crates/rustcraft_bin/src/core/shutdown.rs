@@ -0,0 +1,182 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use parking_lot::Mutex;
+use tokio::sync::Notify;
+use tokio::task::AbortHandle;
+use tracing::info;
+
+use crate::player::{PlayerRegistry, system_chat_frame};
+
+/// Kick reason when the operator gives none
+pub const DEFAULT_SHUTDOWN_REASON: &str = "Server closed";
+
+/// Seconds left at which a pending shutdown is announced, besides the moment it is scheduled
+const ANNOUNCE_AT_SECS: [u64; 11] = [300, 120, 60, 30, 15, 10, 5, 4, 3, 2, 1];
+
+/// Seconds left at each announcement of a `countdown_secs` countdown, counting down
+pub fn countdown_announcements(countdown_secs: u64) -> Vec<u64> {
+    let mut announcements = vec![countdown_secs];
+    announcements.extend(ANNOUNCE_AT_SECS.into_iter().filter(|&secs| secs < countdown_secs));
+    announcements.retain(|&secs| secs > 0);
+    announcements
+}
+
+/// `secs` countdown seconds of length `second`; counts past `u32::MAX` saturate rather than wrap
+fn seconds(second: Duration, secs: u64) -> Duration {
+    second.saturating_mul(u32::try_from(secs).unwrap_or(u32::MAX))
+}
+
+pub fn countdown_message(secs_left: u64, reason: &str) -> String {
+    let unit = if secs_left == 1 { "second" } else { "seconds" };
+    format!("Server closing in {} {}: {}", secs_left, unit, reason)
+}
+
+/// Warns players, disconnects them and then wakes the server to save and exit
+pub struct Shutdown {
+    players:   Arc<PlayerRegistry>,
+    /// The server saves the world and returns from `run` once this is notified
+    exit:      Arc<Notify>,
+    /// Length of one countdown second, shortened in tests
+    second:    Duration,
+    countdown: Mutex<Option<AbortHandle>>,
+}
+
+impl Shutdown {
+    pub fn new(players: Arc<PlayerRegistry>) -> Self {
+        Self::with_second(players, Duration::from_secs(1))
+    }
+
+    fn with_second(players: Arc<PlayerRegistry>, second: Duration) -> Self {
+        Self {
+            players,
+            exit: Arc::new(Notify::new()),
+            second,
+            countdown: Mutex::new(None),
+        }
+    }
+
+    /// Count down in chat for `countdown_secs`, then kick everyone with `reason` and stop the server
+    /// Replaces a countdown already running, so a second `stop 0` skips the wait
+    pub fn shutdown<S: Into<String>>(&self, reason: S, countdown_secs: u64) {
+        let reason = reason.into();
+        info!("[SHUTDOWN] Stopping in {}s: {}", countdown_secs, reason);
+
+        let players = Arc::clone(&self.players);
+        let exit = Arc::clone(&self.exit);
+        let second = self.second;
+        let task = tokio::spawn(async move {
+            let mut secs_left = countdown_secs;
+            for announce_at in countdown_announcements(countdown_secs) {
+                tokio::time::sleep(seconds(second, secs_left - announce_at)).await;
+                secs_left = announce_at;
+                players.broadcast(&system_chat_frame(&countdown_message(secs_left, &reason)));
+            }
+            tokio::time::sleep(seconds(second, secs_left)).await;
+
+            players.kick_all(&reason);
+            exit.notify_one();
+        });
+
+        if let Some(previous) = self.countdown.lock().replace(task.abort_handle()) {
+            previous.abort();
+        }
+    }
+
+    /// Stop a pending countdown; returns false if there was none
+    pub fn cancel(&self) -> bool {
+        let Some(countdown) = self.countdown.lock().take() else {
+            return false;
+        };
+        if countdown.is_finished() {
+            return false;
+        }
+
+        countdown.abort();
+        info!("[SHUTDOWN] Shutdown cancelled");
+        self.players
+            .broadcast(&system_chat_frame("Server shutdown cancelled"));
+        true
+    }
+
+    /// Resolves once the countdown has run out and every player was kicked
+    pub async fn wait(&self) {
+        self.exit.notified().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::mpsc::UnboundedReceiver;
+
+    use super::*;
+    use crate::player::{Outbound, RegisteredPlayer};
+
+    fn registry_with_player() -> (Arc<PlayerRegistry>, UnboundedReceiver<Outbound>) {
+        let registry = Arc::new(PlayerRegistry::new());
+        let (player, rx) = RegisteredPlayer::test("Alex", 1);
+        registry.register(RegisteredPlayer {
+            in_world: true,
+            ..player
+        });
+        (registry, rx)
+    }
+
+    #[test]
+    fn test_countdown_announcements() {
+        assert_eq!(countdown_announcements(0), Vec::<u64>::new());
+        assert_eq!(countdown_announcements(3), vec![3, 2, 1]);
+        assert_eq!(countdown_announcements(12), vec![12, 10, 5, 4, 3, 2, 1]);
+        assert_eq!(countdown_announcements(60), vec![60, 30, 15, 10, 5, 4, 3, 2, 1]);
+        assert_eq!(countdown_message(1, "Restarting"), "Server closing in 1 second: Restarting");
+    }
+
+    #[test]
+    fn test_long_countdowns_saturate() {
+        let second = Duration::from_secs(1);
+        assert_eq!(seconds(second, 90), Duration::from_secs(90));
+        // 2^32 + 5 would wrap to 5 seconds with a plain cast
+        assert_eq!(seconds(second, (1 << 32) + 5), Duration::from_secs(u32::MAX as u64));
+    }
+
+    #[tokio::test]
+    async fn test_countdown_broadcasts_then_kicks() {
+        let (registry, mut rx) = registry_with_player();
+        let shutdown = Shutdown::with_second(Arc::clone(&registry), Duration::from_millis(1));
+
+        shutdown.shutdown("Restarting", 5);
+        shutdown.wait().await;
+
+        let received: Vec<Outbound> = std::iter::from_fn(|| rx.try_recv().ok()).collect();
+        let mut expected: Vec<Outbound> = [5, 4, 3, 2, 1]
+            .into_iter()
+            .map(|secs| Outbound::Packet(system_chat_frame(&countdown_message(secs, "Restarting"))))
+            .collect();
+        expected.push(Outbound::Kick("Restarting".to_string()));
+        assert_eq!(received, expected);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_stops_the_countdown() {
+        let (registry, mut rx) = registry_with_player();
+        let shutdown = Shutdown::with_second(Arc::clone(&registry), Duration::from_millis(20));
+
+        assert!(!shutdown.cancel());
+        shutdown.shutdown("Restarting", 10);
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        assert!(shutdown.cancel());
+        tokio::time::sleep(Duration::from_millis(300)).await;
+
+        let received: Vec<Outbound> = std::iter::from_fn(|| rx.try_recv().ok()).collect();
+        assert_eq!(
+            received.first(),
+            Some(&Outbound::Packet(system_chat_frame(&countdown_message(10, "Restarting"))))
+        );
+        assert_eq!(received.last(), Some(&Outbound::Packet(system_chat_frame("Server shutdown cancelled"))));
+        assert!(
+            !received
+                .iter()
+                .any(|message| matches!(message, Outbound::Kick(_)))
+        );
+    }
+}
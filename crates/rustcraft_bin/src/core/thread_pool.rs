@@ -1,5 +1,7 @@
 #![allow(dead_code)]
 
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
 use std::marker::PhantomData;
 use std::sync::atomic::AtomicBool;
 use std::sync::mpsc::{Sender, channel};
@@ -90,10 +92,178 @@ where
     }
 }
 
+/// How urgently a queued task should run; higher variants are picked first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TaskPriority {
+    /// Background work such as pregenerating the spawn area
+    Pregeneration,
+    /// A chunk a player is waiting on
+    PlayerRequest,
+}
+
+struct PrioritizedTask {
+    priority: TaskPriority,
+    /// Submission order, so tasks of equal priority still run first-in first-out
+    sequence: u64,
+    job:      Box<dyn FnOnce() + Send>,
+}
+
+impl PartialEq for PrioritizedTask {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for PrioritizedTask {}
+
+impl PartialOrd for PrioritizedTask {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for PrioritizedTask {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // BinaryHeap pops the greatest, so older tasks must compare greater
+        self.priority
+            .cmp(&other.priority)
+            .then_with(|| other.sequence.cmp(&self.sequence))
+    }
+}
+
+#[derive(Default)]
+struct PriorityQueue {
+    tasks:         BinaryHeap<PrioritizedTask>,
+    next_sequence: u64,
+    /// Workers leave tasks queued until this is cleared; checked before popping, so a paused
+    /// worker never sits on a task a higher priority one should overtake
+    paused:        bool,
+    shutdown:      bool,
+}
+
+impl PriorityQueue {
+    /// Whether a worker has nothing to do yet; shutting down overrides a pause so the queue drains
+    fn idle(&self) -> bool {
+        !self.shutdown && (self.tasks.is_empty() || self.paused)
+    }
+}
+
+/// A thread pool whose workers take the highest priority task queued instead of the oldest
+pub struct PriorityThreadPool<T: Send + 'static> {
+    workers: Vec<Worker<T>>,
+    queue:   Arc<(Mutex<PriorityQueue>, Condvar)>,
+}
+
+impl<T: Send + 'static> PriorityThreadPool<T> {
+    pub fn new<S: AsRef<str>>(num_threads: usize, name: S) -> Self {
+        assert!(num_threads > 0, "Pool must have at least 1 thread");
+
+        let queue = Arc::new((Mutex::new(PriorityQueue::default()), Condvar::new()));
+        let mut workers = Vec::with_capacity(num_threads);
+
+        for id in 0..num_threads {
+            let queue = Arc::clone(&queue);
+            let thread_name = format!("{}-{}", name.as_ref(), id);
+
+            let thread = thread::Builder::new()
+                .name(thread_name)
+                .spawn(move || {
+                    loop {
+                        let task = {
+                            let (lock, condvar) = &*queue;
+                            let mut queue = condvar
+                                .wait_while(lock.lock().unwrap(), |queue| queue.idle())
+                                .unwrap();
+                            queue.tasks.pop()
+                        };
+
+                        match task {
+                            Some(task) => (task.job)(),
+                            None => break, // Shut down with nothing left queued
+                        }
+                    }
+                })
+                .unwrap();
+
+            workers.push(Worker {
+                _id:      id,
+                _thread:  Some(thread),
+                _phantom: PhantomData,
+            });
+        }
+
+        PriorityThreadPool { workers, queue }
+    }
+
+    pub fn execute<F>(&self, priority: TaskPriority, f: F) -> Result<()>
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        let (lock, condvar) = &*self.queue;
+        let mut queue = lock.lock().unwrap();
+        if queue.shutdown {
+            anyhow::bail!("Failed to send task to thread pool: pool is shutting down");
+        }
+
+        let sequence = queue.next_sequence;
+        queue.next_sequence += 1;
+        queue.tasks.push(PrioritizedTask {
+            priority,
+            sequence,
+            job: Box::new(f),
+        });
+        condvar.notify_one();
+        Ok(())
+    }
+
+    /// Tasks waiting for a worker
+    pub fn queued(&self) -> usize {
+        self.queue.0.lock().unwrap().tasks.len()
+    }
+
+    /// Stop workers from taking tasks; tasks already running finish normally
+    pub fn pause(&self) {
+        self.queue.0.lock().unwrap().paused = true;
+    }
+
+    pub fn resume(&self) {
+        let (lock, condvar) = &*self.queue;
+        lock.lock().unwrap().paused = false;
+        condvar.notify_all();
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.queue.0.lock().unwrap().paused
+    }
+}
+
+impl<T> Drop for PriorityThreadPool<T>
+where
+    T: Send + 'static,
+{
+    fn drop(&mut self) {
+        // Workers finish whatever is still queued, then exit, even if the pool was paused
+        let (lock, condvar) = &*self.queue;
+        {
+            let mut queue = lock.lock().unwrap();
+            queue.shutdown = true;
+            queue.paused = false;
+        }
+        condvar.notify_all();
+
+        for worker in &mut self.workers {
+            if let Some(thread) = worker._thread.take() {
+                thread.join().unwrap();
+            }
+        }
+    }
+}
+
 /// Thread pool specifically for chunk generation (4 threads)
+/// Chunks players are waiting on jump ahead of pregeneration
 #[derive(Clone)]
 pub struct ChunkGenThreadPool {
-    pool:       Arc<ThreadPool<ChunkGenTask>>,
+    pool:       Arc<PriorityThreadPool<ChunkGenTask>>,
     // PERF: @atomic : Possible to do with an atomic bool instead of Mutex<bool>?
     init_state: Arc<(AtomicBool, Condvar)>,
     // Arc<(Mutex<bool>, Condvar)>,
@@ -111,18 +281,42 @@ pub struct PluginTask;
 
 impl ChunkGenThreadPool {
     pub fn new() -> Self {
-        let pool = Arc::new(ThreadPool::new(4, "ChunkGen"));
+        let pool = Arc::new(PriorityThreadPool::new(4, "ChunkGen"));
         info!("[STARTUP] Chunk generation thread pool created with 4 workers");
         // let init_state = Arc::new((Mutex::new(false), Condvar::new()));
         let init_state = Arc::new((AtomicBool::new(false), Condvar::new()));
         Self { pool, init_state }
     }
 
+    /// Queue a background task; see `execute_with_priority`
     pub fn execute<F>(&self, f: F) -> Result<()>
     where
         F: FnOnce() + Send + 'static,
     {
-        self.pool.execute(f)
+        self.execute_with_priority(TaskPriority::Pregeneration, f)
+    }
+
+    /// Queue a task; while paused it stays queued until `resume`
+    pub fn execute_with_priority<F>(&self, priority: TaskPriority, f: F) -> Result<()>
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        self.pool.execute(priority, f)
+    }
+
+    /// Stop workers from starting new tasks; tasks already running finish normally
+    pub fn pause(&self) {
+        self.pool.pause();
+        info!("[CHUNK_GEN_POOL] Chunk generation paused");
+    }
+
+    pub fn resume(&self) {
+        self.pool.resume();
+        info!("[CHUNK_GEN_POOL] Chunk generation resumed");
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.pool.is_paused()
     }
 
     pub fn signal_init_complete(&self) {
@@ -192,4 +386,102 @@ mod tests {
         thread::sleep(std::time::Duration::from_millis(100));
         assert_eq!(counter.load(Ordering::SeqCst), 10);
     }
+
+    #[test]
+    fn test_priority_pool_runs_high_priority_first() {
+        let pool = PriorityThreadPool::<ChunkGenTask>::new(1, "PriorityTest");
+        let order = Arc::new(Mutex::new(Vec::new()));
+
+        // Hold the only worker until everything is queued
+        let (release, gate) = std::sync::mpsc::channel::<()>();
+        pool.execute(TaskPriority::Pregeneration, move || gate.recv().unwrap())
+            .unwrap();
+        while pool.queued() > 0 {
+            thread::yield_now();
+        }
+
+        for i in 0..20 {
+            let order = Arc::clone(&order);
+            pool.execute(TaskPriority::Pregeneration, move || order.lock().unwrap().push(i))
+                .unwrap();
+        }
+        let high = Arc::clone(&order);
+        pool.execute(TaskPriority::PlayerRequest, move || high.lock().unwrap().push(100))
+            .unwrap();
+
+        release.send(()).unwrap();
+        drop(pool); // Drains the queue before returning
+
+        let order = order.lock().unwrap();
+        assert_eq!(order[0], 100);
+        // The rest keep their submission order
+        assert_eq!(order[1..], (0..20).collect::<Vec<_>>()[..]);
+    }
+
+    #[test]
+    fn test_chunk_gen_pool_pause_resume() {
+        let pool = ChunkGenThreadPool::new();
+        let counter = Arc::new(AtomicUsize::new(0));
+
+        pool.pause();
+        assert!(pool.is_paused());
+        for _ in 0..10 {
+            let c = Arc::clone(&counter);
+            pool.execute(move || {
+                c.fetch_add(1, Ordering::SeqCst);
+            })
+            .unwrap();
+        }
+
+        thread::sleep(std::time::Duration::from_millis(100));
+        assert_eq!(counter.load(Ordering::SeqCst), 0);
+        // Nothing was taken off the queue while paused
+        assert_eq!(pool.pool.queued(), 10);
+
+        pool.resume();
+        assert!(!pool.is_paused());
+        thread::sleep(std::time::Duration::from_millis(100));
+        assert_eq!(counter.load(Ordering::SeqCst), 10);
+    }
+
+    #[test]
+    fn test_paused_pool_drains_on_drop() {
+        let pool = PriorityThreadPool::<ChunkGenTask>::new(2, "PausedDropTest");
+        let counter = Arc::new(AtomicUsize::new(0));
+
+        pool.pause();
+        for _ in 0..5 {
+            let c = Arc::clone(&counter);
+            pool.execute(TaskPriority::Pregeneration, move || {
+                c.fetch_add(1, Ordering::SeqCst);
+            })
+            .unwrap();
+        }
+        thread::sleep(std::time::Duration::from_millis(50));
+        assert_eq!(counter.load(Ordering::SeqCst), 0);
+
+        // Joins the workers rather than hanging on the pause
+        drop(pool);
+        assert_eq!(counter.load(Ordering::SeqCst), 5);
+    }
+
+    #[test]
+    fn test_resume_keeps_priority_order() {
+        let pool = PriorityThreadPool::<ChunkGenTask>::new(1, "PausedOrderTest");
+        let order = Arc::new(Mutex::new(Vec::new()));
+
+        pool.pause();
+        for i in 0..5 {
+            let order = Arc::clone(&order);
+            pool.execute(TaskPriority::Pregeneration, move || order.lock().unwrap().push(i))
+                .unwrap();
+        }
+        let high = Arc::clone(&order);
+        pool.execute(TaskPriority::PlayerRequest, move || high.lock().unwrap().push(100))
+            .unwrap();
+
+        pool.resume();
+        drop(pool);
+        assert_eq!(*order.lock().unwrap(), [100, 0, 1, 2, 3, 4]);
+    }
 }
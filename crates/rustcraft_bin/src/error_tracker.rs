@@ -20,6 +20,14 @@ impl ErrorKey {
             semantics: semantics.into(),
         }
     }
+
+    pub fn category(&self) -> &str {
+        &self.category
+    }
+
+    pub fn semantics(&self) -> &str {
+        &self.semantics
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -71,6 +79,11 @@ impl ErrorTracker {
         false
     }
 
+    /// Forget the count for `key`, e.g. after restarting whatever kept failing
+    pub fn reset(&self, key: &ErrorKey) {
+        self.errors.write().remove(key);
+    }
+
     pub fn clear(&self) {
         self.errors.write().clear();
     }
@@ -87,6 +100,18 @@ impl ErrorTracker {
             })
             .collect()
     }
+
+    /// Current count per error key, sorted by key so repeated snapshots line up
+    pub fn snapshot(&self) -> Vec<(ErrorKey, usize)> {
+        let mut counts: Vec<_> = self
+            .errors
+            .read()
+            .iter()
+            .map(|(key, entry)| (key.clone(), entry.count))
+            .collect();
+        counts.sort_by(|(a, _), (b, _)| (&a.category, &a.semantics).cmp(&(&b.category, &b.semantics)));
+        counts
+    }
 }
 
 impl Clone for ErrorTracker {
@@ -0,0 +1,130 @@
+use anyhow::{Result, anyhow};
+use tracing::Subscriber;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::util::SubscriberInitExt;
+
+use crate::config::{LogFormat, LogLevel};
+
+/// Build a subscriber writing events at `level` and above to `writer` in `format`
+pub fn build_subscriber<W>(format: LogFormat, level: LogLevel, writer: W) -> Box<dyn Subscriber + Send + Sync>
+where
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    let builder = tracing_subscriber::fmt()
+        .with_target(false)
+        .with_thread_ids(false)
+        .with_thread_names(false)
+        .with_line_number(true)
+        .with_max_level(level.as_level())
+        .with_writer(writer);
+
+    match format {
+        LogFormat::Compact => Box::new(builder.compact().finish()),
+        LogFormat::Json => Box::new(builder.json().finish()),
+    }
+}
+
+/// Install the global subscriber, logging to stdout
+pub fn init(format: LogFormat, level: LogLevel) -> Result<()> {
+    build_subscriber(format, level, std::io::stdout).try_init()?;
+    Ok(())
+}
+
+/// Format given as `--log-format <format>` or `--log-format=<format>`, if any
+pub fn log_format_arg<I>(args: I) -> Result<Option<LogFormat>>
+where
+    I: IntoIterator<Item = String>,
+{
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if let Some(value) = arg.strip_prefix("--log-format=") {
+            return value.parse().map(Some);
+        }
+        if arg == "--log-format" {
+            let value = args.next().ok_or_else(|| anyhow!("--log-format needs a value"))?;
+            return value.parse().map(Some);
+        }
+    }
+    Ok(None)
+}
+
+/// Collects everything a subscriber writes, for asserting on log output
+#[cfg(test)]
+#[derive(Clone, Default)]
+pub struct CapturedLogs(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+#[cfg(test)]
+impl CapturedLogs {
+    pub fn lines(&self) -> Vec<String> {
+        String::from_utf8(self.0.lock().unwrap().clone())
+            .unwrap()
+            .lines()
+            .map(str::to_string)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+impl std::io::Write for CapturedLogs {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log_with(format: LogFormat, level: LogLevel) -> Vec<String> {
+        let captured = CapturedLogs::default();
+        let writer = captured.clone();
+        tracing::subscriber::with_default(build_subscriber(format, level, move || writer.clone()), || {
+            tracing::debug!("[PLAYER] Steve moved");
+            tracing::info!(player = "Steve", "[PLAYER] Steve joined");
+        });
+        captured.lines()
+    }
+
+    #[test]
+    fn test_json_format_writes_one_object_per_event() {
+        let lines = log_with(LogFormat::Json, LogLevel::Debug);
+        assert_eq!(lines.len(), 2);
+
+        let event: serde_json::Value = serde_json::from_str(&lines[1]).unwrap();
+        assert_eq!(event["level"], "INFO");
+        assert_eq!(event["fields"]["message"], "[PLAYER] Steve joined");
+        assert_eq!(event["fields"]["player"], "Steve");
+    }
+
+    #[test]
+    fn test_compact_format_is_not_json() {
+        let lines = log_with(LogFormat::Compact, LogLevel::Debug);
+        assert_eq!(lines.len(), 2);
+        assert!(lines[1].contains("[PLAYER] Steve joined"));
+        assert!(serde_json::from_str::<serde_json::Value>(&lines[1]).is_err());
+    }
+
+    #[test]
+    fn test_level_filters_events() {
+        let lines = log_with(LogFormat::Json, LogLevel::Info);
+        assert_eq!(lines.len(), 1);
+        assert!(lines[0].contains("Steve joined"));
+
+        assert!(log_with(LogFormat::Compact, LogLevel::Warn).is_empty());
+    }
+
+    #[test]
+    fn test_log_format_arg() {
+        let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(log_format_arg(args(&[])).unwrap(), None);
+        assert_eq!(log_format_arg(args(&["--log-format", "json"])).unwrap(), Some(LogFormat::Json));
+        assert_eq!(log_format_arg(args(&["--log-format=compact"])).unwrap(), Some(LogFormat::Compact));
+        assert!(log_format_arg(args(&["--log-format", "xml"])).is_err());
+        assert!(log_format_arg(args(&["--log-format"])).is_err());
+    }
+}
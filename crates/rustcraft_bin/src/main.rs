@@ -1,8 +1,11 @@
 // Core modules
+mod access_control;
 mod chunk;
+mod config;
 mod consts;
 mod core;
 mod error_tracker;
+mod logging;
 mod network;
 mod player;
 mod terrain;
@@ -18,7 +21,8 @@ mod sdk;
 use anyhow::Result;
 pub use error_tracker::{ErrorKey, ErrorTracker};
 
-use crate::consts::SERVER_ADDR;
+use crate::config::{LogLevel, ServerConfig};
+use crate::consts::{SERVER_ADDR, SERVER_CONFIG_PATH};
 use crate::core::MinecraftServer;
 #[cfg(feature = "dev-sdk")]
 use crate::sdk::PacketLogger;
@@ -29,20 +33,21 @@ pub static LOGGER: std::sync::LazyLock<PacketLogger> =
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    // Initialize logging with a custom format
-    tracing_subscriber::fmt()
-        .with_target(false)
-        .with_thread_ids(false)
-        .with_thread_names(false)
-        .with_line_number(true)
-        .with_max_level(tracing::Level::DEBUG)
-        .compact()
-        .init();
+    // The log level (and format, unless given on the command line) comes from the config, so loading
+    // it logs with the defaults
+    let format_arg = logging::log_format_arg(std::env::args().skip(1))?;
+    let config = tracing::subscriber::with_default(
+        logging::build_subscriber(format_arg.unwrap_or_default(), LogLevel::default(), std::io::stdout),
+        || ServerConfig::load(SERVER_CONFIG_PATH),
+    )?;
+    let log_format = format_arg.unwrap_or(config.log_format);
+    logging::init(log_format, config.log_level)?;
 
     let error_tracker = std::sync::Arc::new(ErrorTracker::new());
+    let config = std::sync::Arc::new(config);
 
     // Start the Minecraft server
-    let server = MinecraftServer::new(SERVER_ADDR, error_tracker.clone()).await?;
+    let server = MinecraftServer::new(SERVER_ADDR, error_tracker.clone(), config).await?;
     server.run().await?;
 
     Ok(())
@@ -0,0 +1,227 @@
+#![allow(dead_code)]
+
+// Deciding who a connecting player is, kept apart from the login packet flow
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{Result, anyhow};
+use parking_lot::Mutex;
+use uuid::Uuid;
+
+use crate::chunk::LruCache;
+use crate::config::ServerConfig;
+use crate::consts::SESSION_CACHE_CAPACITY;
+use crate::network::encryption::ServerKeyPair;
+
+/// The identity a player logs in with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlayerProfile {
+    pub uuid:     Uuid,
+    pub username: String,
+}
+
+pub trait AuthProvider: Send + Sync {
+    /// Identify the player who sent Login Start as `username`
+    /// `server_hash` is the digest from the encryption handshake, when there was one
+    fn authenticate(&self, username: &str, server_hash: Option<&str>) -> Result<PlayerProfile>;
+}
+
+/// Offline mode: anyone may use any name, and the UUID is derived from it
+pub struct OfflineAuth;
+
+impl OfflineAuth {
+    /// Vanilla's offline UUID: version 3 from `OfflinePlayer:<name>`
+    pub fn uuid_for(username: &str) -> Uuid {
+        let namespace = Uuid::NAMESPACE_DNS;
+        let offline_name = format!("OfflinePlayer:{}", username);
+        Uuid::new_v3(&namespace, offline_name.as_bytes())
+    }
+}
+
+impl AuthProvider for OfflineAuth {
+    fn authenticate(&self, username: &str, _server_hash: Option<&str>) -> Result<PlayerProfile> {
+        Ok(PlayerProfile {
+            uuid:     Self::uuid_for(username),
+            username: username.to_string(),
+        })
+    }
+}
+
+/// The `hasJoined` lookup, behind a trait so logins can be tested without the network
+pub trait SessionServer: Send + Sync {
+    /// The profile that joined as `username` with `server_hash`, or `None` if it didn't
+    fn has_joined(&self, username: &str, server_hash: &str) -> Result<Option<PlayerProfile>>;
+}
+
+/// Online mode: the session server must confirm the player joined with our server hash
+/// Confirmed sessions are reused for `session_cache_ttl_secs`, so a client reconnecting straight
+/// away, or a flaky session server, doesn't cost another round trip
+pub struct MojangAuth {
+    sessions: Arc<dyn SessionServer>,
+    /// Confirmed profiles by username and server hash; `None` when the TTL is 0
+    cache:    Option<Mutex<LruCache<(String, String), PlayerProfile>>>,
+    key:      ServerKeyPair,
+}
+
+impl MojangAuth {
+    /// Generates the keypair for this run at `rsa_key_bits`
+    pub fn new(sessions: Arc<dyn SessionServer>, config: &ServerConfig) -> Result<Self> {
+        let ttl = Duration::from_secs(config.session_cache_ttl_secs);
+        Ok(Self {
+            sessions,
+            // Expire from when the session server answered, however often it is looked up
+            cache: (!ttl.is_zero())
+                .then(|| Mutex::new(LruCache::with_ttl(SESSION_CACHE_CAPACITY, ttl).refresh_on_get(false))),
+            key: ServerKeyPair::generate(config.rsa_key_bits)?,
+        })
+    }
+
+    /// The keypair whose public half goes in Encryption Request
+    pub fn key(&self) -> &ServerKeyPair {
+        &self.key
+    }
+
+    fn authenticate_at(
+        &self,
+        username: &str,
+        server_hash: Option<&str>,
+        now: Instant,
+    ) -> Result<PlayerProfile> {
+        let server_hash = server_hash
+            .ok_or_else(|| anyhow!("Online mode needs the encryption handshake for {}", username))?;
+        let key = (username.to_string(), server_hash.to_string());
+        if let Some(profile) = self
+            .cache
+            .as_ref()
+            .and_then(|c| c.lock().get_at(&key, now).cloned())
+        {
+            tracing::debug!("[AUTH] Session cache hit for {}", username);
+            return Ok(profile);
+        }
+
+        // Refusals and errors aren't cached, so the next attempt asks again
+        let profile = self
+            .sessions
+            .has_joined(username, server_hash)?
+            .ok_or_else(|| anyhow!("{} has not joined through the session server", username))?;
+        if let Some(cache) = &self.cache {
+            cache.lock().insert_at(key, profile.clone(), now);
+        }
+        Ok(profile)
+    }
+}
+
+impl AuthProvider for MojangAuth {
+    fn authenticate(&self, username: &str, server_hash: Option<&str>) -> Result<PlayerProfile> {
+        self.authenticate_at(username, server_hash, Instant::now())
+    }
+}
+
+/// Hands out a fixed profile, whatever name the client sent
+#[cfg(test)]
+pub struct MockAuth(pub PlayerProfile);
+
+#[cfg(test)]
+impl AuthProvider for MockAuth {
+    fn authenticate(&self, _username: &str, _server_hash: Option<&str>) -> Result<PlayerProfile> {
+        Ok(self.0.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    /// Anyone who joined with hash "abc"; counts every lookup
+    #[derive(Default)]
+    struct OneSession {
+        lookups: AtomicUsize,
+    }
+
+    impl OneSession {
+        fn lookups(&self) -> usize {
+            self.lookups.load(Ordering::SeqCst)
+        }
+    }
+
+    impl SessionServer for OneSession {
+        fn has_joined(&self, username: &str, server_hash: &str) -> Result<Option<PlayerProfile>> {
+            self.lookups.fetch_add(1, Ordering::SeqCst);
+            Ok((server_hash == "abc").then(|| {
+                PlayerProfile {
+                    uuid:     Uuid::from_u128(1),
+                    username: username.to_string(),
+                }
+            }))
+        }
+    }
+
+    #[test]
+    fn test_offline_auth_derives_uuid_from_name() {
+        let profile = OfflineAuth.authenticate("Steve", None).unwrap();
+        assert_eq!(profile.username, "Steve");
+        assert_eq!(profile.uuid, OfflineAuth::uuid_for("Steve"));
+        assert_eq!(profile.uuid.get_version_num(), 3);
+        assert_ne!(profile.uuid, OfflineAuth::uuid_for("steve"));
+    }
+
+    #[test]
+    fn test_mojang_auth_requires_a_confirmed_session() {
+        let auth = MojangAuth::new(Arc::new(OneSession::default()), &ServerConfig::default()).unwrap();
+        assert_eq!(auth.key().bits(), ServerConfig::default().rsa_key_bits as usize);
+        assert_eq!(auth.authenticate("Alex", Some("abc")).unwrap().uuid, Uuid::from_u128(1));
+        assert!(auth.authenticate("Alex", Some("def")).is_err());
+        assert!(auth.authenticate("Alex", None).is_err());
+    }
+
+    #[test]
+    fn test_mojang_auth_session_cache_hit_and_expiry() {
+        let sessions = Arc::new(OneSession::default());
+        let auth = MojangAuth::new(sessions.clone(), &ServerConfig::default()).unwrap();
+        let ttl = Duration::from_secs(ServerConfig::default().session_cache_ttl_secs);
+        let start = Instant::now();
+
+        let profile = auth.authenticate_at("Alex", Some("abc"), start).unwrap();
+        assert_eq!(sessions.lookups(), 1);
+
+        // Served from the cache until the TTL runs out
+        let cached = auth.authenticate_at("Alex", Some("abc"), start + ttl - Duration::from_secs(1));
+        assert_eq!(cached.unwrap(), profile);
+        assert_eq!(sessions.lookups(), 1);
+
+        auth.authenticate_at("Alex", Some("abc"), start + ttl + Duration::from_secs(1))
+            .unwrap();
+        assert_eq!(sessions.lookups(), 2);
+    }
+
+    #[test]
+    fn test_mojang_auth_session_cache_misses_go_to_the_session_server() {
+        let sessions = Arc::new(OneSession::default());
+        let auth = MojangAuth::new(sessions.clone(), &ServerConfig::default()).unwrap();
+        let now = Instant::now();
+
+        // A different shared secret is a different session
+        auth.authenticate_at("Alex", Some("abc"), now).unwrap();
+        assert!(auth.authenticate_at("Alex", Some("def"), now).is_err());
+        assert_eq!(sessions.lookups(), 2);
+
+        // Refusals aren't cached
+        assert!(auth.authenticate_at("Steve", Some("def"), now).is_err());
+        assert!(auth.authenticate_at("Steve", Some("def"), now).is_err());
+        assert_eq!(sessions.lookups(), 4);
+
+        // Nor is anything with the cache turned off
+        let sessions = Arc::new(OneSession::default());
+        let config = ServerConfig {
+            session_cache_ttl_secs: 0,
+            ..ServerConfig::default()
+        };
+        let auth = MojangAuth::new(sessions.clone(), &config).unwrap();
+        auth.authenticate_at("Alex", Some("abc"), now).unwrap();
+        auth.authenticate_at("Alex", Some("abc"), now).unwrap();
+        assert_eq!(sessions.lookups(), 2);
+    }
+}
@@ -0,0 +1,64 @@
+#![allow(dead_code)]
+
+// The server's half of the online-mode encryption handshake
+
+use anyhow::{Result, anyhow};
+use rsa::pkcs8::EncodePublicKey;
+use rsa::rand_core::OsRng;
+use rsa::traits::PublicKeyParts;
+use rsa::{Pkcs1v15Encrypt, RsaPrivateKey, RsaPublicKey};
+
+/// RSA keypair generated once per start; clients encrypt the shared secret and verify token with
+/// its public half
+pub struct ServerKeyPair {
+    private:    RsaPrivateKey,
+    /// X.509 SubjectPublicKeyInfo DER, as sent in Encryption Request and hashed into the server hash
+    public_der: Vec<u8>,
+}
+
+impl ServerKeyPair {
+    pub fn generate(bits: u32) -> Result<Self> {
+        let private = RsaPrivateKey::new(&mut OsRng, bits as usize)
+            .map_err(|e| anyhow!("Failed to generate a {}-bit RSA key: {}", bits, e))?;
+        let public_der = RsaPublicKey::from(&private)
+            .to_public_key_der()
+            .map_err(|e| anyhow!("Failed to encode the RSA public key: {}", e))?
+            .into_vec();
+        Ok(Self { private, public_der })
+    }
+
+    pub fn bits(&self) -> usize {
+        self.private.size() * 8
+    }
+
+    pub fn public_key_der(&self) -> &[u8] {
+        &self.public_der
+    }
+
+    /// Decrypt a PKCS#1 v1.5 block the client encrypted with our public key
+    pub fn decrypt(&self, data: &[u8]) -> Result<Vec<u8>> {
+        self.private
+            .decrypt(Pkcs1v15Encrypt, data)
+            .map_err(|e| anyhow!("Failed to decrypt with the server key: {}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rsa::pkcs8::DecodePublicKey;
+
+    use super::*;
+
+    #[test]
+    fn test_key_pair_round_trip() {
+        let key = ServerKeyPair::generate(1024).unwrap();
+        assert_eq!(key.bits(), 1024);
+
+        // What the client does with the key from Encryption Request
+        let public = RsaPublicKey::from_public_key_der(key.public_key_der()).unwrap();
+        let secret = [7u8; 16];
+        let encrypted = public.encrypt(&mut OsRng, Pkcs1v15Encrypt, &secret).unwrap();
+        assert_eq!(key.decrypt(&encrypted).unwrap(), secret);
+        assert!(key.decrypt(&[0u8; 16]).is_err());
+    }
+}
@@ -0,0 +1,210 @@
+use std::io::Cursor;
+
+use anyhow::{Result, anyhow};
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+use crate::consts::MAX_PACKET_SIZE;
+use crate::network::protocol::read_varint;
+
+/// A VarInt is at most 5 bytes on the wire
+const VARINT_MAX_BYTES: usize = 5;
+
+/// Frame reader failures that callers handle differently from a plain read error
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum FrameError {
+    #[error("Client disconnected")]
+    ClientDisconnected,
+    #[error("Packet too large: {0} bytes, limit is {MAX_PACKET_SIZE}")]
+    PacketTooLarge(usize),
+}
+
+/// Whether the error is the frame reader reporting a clean EOF from the client
+pub fn is_client_disconnect(e: &anyhow::Error) -> bool {
+    matches!(e.downcast_ref(), Some(FrameError::ClientDisconnected))
+}
+
+/// Whether the error is a frame rejected by `check_packet_length` for exceeding `MAX_PACKET_SIZE`
+pub fn is_packet_too_large(e: &anyhow::Error) -> bool {
+    matches!(e.downcast_ref(), Some(FrameError::PacketTooLarge(_)))
+}
+
+/// Validate a client-declared frame length before a buffer is allocated for it
+pub fn check_packet_length(packet_length: i32) -> Result<usize> {
+    if packet_length <= 0 {
+        return Err(anyhow!("Invalid packet length: {}", packet_length));
+    }
+    let packet_length = packet_length as usize;
+    if packet_length > MAX_PACKET_SIZE {
+        return Err(FrameError::PacketTooLarge(packet_length).into());
+    }
+    Ok(packet_length)
+}
+
+/// Read a VarInt from the stream one byte at a time
+/// Never consumes bytes past the end of the VarInt, so a length prefix split across
+/// TCP segments (or merged with the packet body in a single segment) is handled correctly
+pub async fn read_varint_from_stream<R>(stream: &mut R) -> Result<i32>
+where
+    R: AsyncRead + Unpin,
+{
+    let mut result: i32 = 0;
+
+    for bytes_read in 0..VARINT_MAX_BYTES {
+        let byte = match stream.read_u8().await {
+            Ok(b) => b,
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                return Err(FrameError::ClientDisconnected.into());
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        result |= ((byte & 0x7F) as i32) << (7 * bytes_read);
+        if byte & 0x80 == 0 {
+            return Ok(result);
+        }
+    }
+
+    Err(anyhow!("Packet length too long"))
+}
+
+/// Read a single `[length][id][data]` frame from the stream
+/// Returns the packet ID and the payload following it
+pub async fn read_packet_frame<R>(stream: &mut R) -> Result<(i32, Vec<u8>)>
+where
+    R: AsyncRead + Unpin,
+{
+    let packet_length = read_varint_from_stream(stream).await?;
+    let mut packet_data = vec![0u8; check_packet_length(packet_length)?];
+    match stream.read_exact(&mut packet_data).await {
+        Ok(_) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+            tracing::debug!("[PACKET] Client disconnected (unexpected EOF)");
+            return Err(FrameError::ClientDisconnected.into());
+        }
+        Err(e) => return Err(e.into()),
+    }
+
+    #[cfg(feature = "dev-sdk")]
+    {
+        let mut full_packet = crate::network::write_varint(packet_length);
+        full_packet.extend_from_slice(&packet_data);
+        let _ = &crate::LOGGER.log_client_packet(&full_packet);
+    }
+
+    let mut cursor = Cursor::new(&packet_data[..]);
+    let packet_id = read_varint(&mut cursor)?;
+    let payload = packet_data[cursor.position() as usize..].to_vec();
+
+    tracing::trace!("[PACKET] Packet ID: 0x{:02x}, payload: {} bytes", packet_id, payload.len());
+
+    Ok((packet_id, payload))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+
+    use tokio::io::ReadBuf;
+
+    use super::*;
+    use crate::network::write_varint;
+
+    /// Mock reader that hands out at most one byte per read, like a worst-case TCP stream
+    struct OneByteReader {
+        data: Vec<u8>,
+        pos:  usize,
+    }
+
+    impl AsyncRead for OneByteReader {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            buf: &mut ReadBuf<'_>,
+        ) -> Poll<std::io::Result<()>> {
+            if self.pos < self.data.len() && buf.remaining() > 0 {
+                let byte = self.data[self.pos];
+                buf.put_slice(&[byte]);
+                self.pos += 1;
+            }
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    fn frame(packet_id: i32, payload: &[u8]) -> Vec<u8> {
+        let id = write_varint(packet_id);
+        let mut frame = write_varint((id.len() + payload.len()) as i32);
+        frame.extend_from_slice(&id);
+        frame.extend_from_slice(payload);
+        frame
+    }
+
+    #[tokio::test]
+    async fn test_read_frame_one_byte_at_a_time() {
+        // 200 byte payload so the length prefix is a two-byte VarInt
+        let payload = vec![0xAB; 200];
+        let mut reader = OneByteReader {
+            data: frame(0x1D, &payload),
+            pos:  0,
+        };
+
+        let (packet_id, data) = read_packet_frame(&mut reader).await.unwrap();
+        assert_eq!(packet_id, 0x1D);
+        assert_eq!(data, payload);
+    }
+
+    #[tokio::test]
+    async fn test_read_frame_all_at_once() {
+        // Two frames merged into a single read must not bleed into each other
+        let mut bytes = frame(0x1D, &[1, 2, 3]);
+        bytes.extend_from_slice(&frame(0x1E, &[4, 5]));
+        let mut reader: &[u8] = &bytes;
+
+        let (first_id, first) = read_packet_frame(&mut reader).await.unwrap();
+        assert_eq!(first_id, 0x1D);
+        assert_eq!(first, vec![1, 2, 3]);
+
+        let (second_id, second) = read_packet_frame(&mut reader).await.unwrap();
+        assert_eq!(second_id, 0x1E);
+        assert_eq!(second, vec![4, 5]);
+        assert!(reader.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_read_frame_disconnect() {
+        let mut reader: &[u8] = &[];
+        assert!(read_packet_frame(&mut reader).await.is_err());
+
+        // Truncated body
+        let bytes = frame(0x1D, &[1, 2, 3]);
+        let mut reader: &[u8] = &bytes[..bytes.len() - 1];
+        let err = read_packet_frame(&mut reader).await.unwrap_err();
+        assert!(is_client_disconnect(&err), "{}", err);
+        // Wrapped in context it is still recognised
+        assert!(is_client_disconnect(&err.context("Reading a play packet")));
+    }
+
+    #[tokio::test]
+    async fn test_read_frame_too_large() {
+        // Only the length prefix is sent; the reader must give up before waiting for a body
+        let bytes = write_varint(MAX_PACKET_SIZE as i32 + 1);
+        let mut reader: &[u8] = &bytes;
+        let err = read_packet_frame(&mut reader).await.unwrap_err();
+        assert!(is_packet_too_large(&err), "{}", err);
+        assert!(!is_client_disconnect(&err));
+
+        let bytes = write_varint(i32::MAX);
+        let mut reader: &[u8] = &bytes;
+        assert!(is_packet_too_large(&read_packet_frame(&mut reader).await.unwrap_err()));
+
+        assert_eq!(check_packet_length(MAX_PACKET_SIZE as i32).unwrap(), MAX_PACKET_SIZE);
+        assert!(check_packet_length(0).is_err());
+        assert!(check_packet_length(-5).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_read_varint_too_long() {
+        let mut reader: &[u8] = &[0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x01];
+        assert!(read_varint_from_stream(&mut reader).await.is_err());
+    }
+}
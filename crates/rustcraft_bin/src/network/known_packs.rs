@@ -0,0 +1,100 @@
+// Known Packs negotiation: the server lists the data packs it has, the client answers with the
+// ones it also has, and registry entries from a shared pack could then be sent without their NBT
+
+use anyhow::Result;
+use bytes::BytesMut;
+
+use crate::network::protocol::{PacketReader, PacketWriter};
+use crate::network::{ByteWritable, ProtocolVersion};
+
+/// A data pack as identified in the Known Packs packets
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KnownPack {
+    pub namespace: String,
+    pub id:        String,
+    pub version:   String,
+}
+
+impl KnownPack {
+    /// The vanilla `minecraft:core` pack, versioned by the game version
+    pub fn core(version: ProtocolVersion) -> Self {
+        Self {
+            namespace: "minecraft".to_string(),
+            id:        "core".to_string(),
+            version:   version.name().to_string(),
+        }
+    }
+}
+
+/// `[count: VarInt]` then `[namespace: String][id: String][version: String]` per pack
+pub fn encode_known_packs(packs: &[KnownPack]) -> BytesMut {
+    let mut writer = PacketWriter::new();
+    writer.write_prefixed_array(packs, |writer, pack| {
+        writer.write_string(&pack.namespace);
+        writer.write_string(&pack.id);
+        writer.write_string(&pack.version);
+    });
+    writer.finish()
+}
+
+pub fn decode_known_packs(payload: &[u8]) -> Result<Vec<KnownPack>> {
+    let mut reader = PacketReader::new(payload);
+    // Each pack is at least three empty strings, one length byte each
+    let count = reader.read_length(3)?;
+    let mut packs = Vec::with_capacity(count);
+    for _ in 0..count {
+        packs.push(KnownPack {
+            namespace: reader.read_string()?,
+            id:        reader.read_string()?,
+            version:   reader.read_string()?,
+        });
+    }
+    Ok(packs)
+}
+
+/// Our packs the client reported having too, at the same version
+pub fn shared_packs(ours: &[KnownPack], theirs: &[KnownPack]) -> Vec<KnownPack> {
+    ours.iter()
+        .filter(|pack| theirs.contains(pack))
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known_packs_round_trip() {
+        let packs = vec![
+            KnownPack::core(ProtocolVersion::V1_21_7),
+            KnownPack {
+                namespace: "example".to_string(),
+                id:        "extra".to_string(),
+                version:   "2".to_string(),
+            },
+        ];
+        let payload = encode_known_packs(&packs);
+        assert_eq!(payload[0], 2);
+        assert_eq!(decode_known_packs(&payload).unwrap(), packs);
+
+        assert!(decode_known_packs(&encode_known_packs(&[])).unwrap().is_empty());
+        // Count larger than the packs that follow
+        assert!(decode_known_packs(&payload[..payload.len() - 1]).is_err());
+        assert!(decode_known_packs(&[0x7F]).is_err());
+    }
+
+    #[test]
+    fn test_shared_packs() {
+        let core = KnownPack::core(ProtocolVersion::V1_21_7);
+        let older_core = KnownPack {
+            version: "1.21.6".to_string(),
+            ..core.clone()
+        };
+
+        let ours = vec![core.clone()];
+        assert_eq!(shared_packs(&ours, &[older_core.clone(), core]), ours);
+        assert!(shared_packs(&ours, &[older_core]).is_empty());
+        assert!(shared_packs(&ours, &[]).is_empty());
+    }
+}
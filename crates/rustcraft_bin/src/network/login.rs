@@ -1,35 +1,134 @@
+use std::sync::Arc;
+
 use anyhow::{Result, anyhow};
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::TcpStream;
 use tracing::{info, warn};
 use uuid::Uuid;
 
-use crate::network::ByteWritable;
+use crate::access_control::AccessControl;
+use crate::network::auth::PlayerProfile;
+use crate::network::login_plugin::{LoginPluginRequest, LoginPluginResponse};
 use crate::network::protocol::{PacketReader, PacketWriter, read_varint, write_varint};
-
-#[derive(Debug, Clone)]
+use crate::network::{
+    AuthProvider,
+    ByteWritable,
+    ClientboundLogin,
+    ClientboundStatus,
+    LoginPluginHook,
+    OfflineAuth,
+    PacketIdTable,
+    ProtocolVersion,
+    ServerboundHandshake,
+    ServerboundLogin,
+    ServerboundStatus,
+    StatusResponse,
+    check_packet_length,
+    is_client_disconnect,
+    read_packet_frame,
+    unsupported_version_reason,
+};
+use crate::player::{ConnectionStage, ConnectionStateTracker, PlayerRegistry, PlayerSlot};
+
+#[derive(Debug)]
 pub struct PlayerLogin {
     pub username: String,
     pub uuid:     Uuid,
+    /// Version negotiated from the handshake
+    pub protocol: ProtocolVersion,
+    /// Place held under `max_players` until the player is registered; `None` without a limit
+    pub slot:     Option<PlayerSlot>,
+}
+
+/// Where the handshake asked to go next
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NextState {
+    Status,
+    Login,
+}
+
+/// Result of driving a fresh connection through the handshake
+#[derive(Debug)]
+pub enum LoginOutcome {
+    /// Client asked for the server list entry; call `handle_status` next
+    Status,
+    /// Pre-1.7 client or scanner sent a `0xFE` ping; call `handle_legacy_ping` next
+    LegacyPing,
+    /// Client logged in and is now in the Configuration state
+    Login(PlayerLogin),
 }
 
 pub struct LoginHandler {
     stream:           TcpStream,
     protocol_version: i32,
+    /// Set once the handshake's protocol version is found to be supported
+    version:          Option<ProtocolVersion>,
+    next_state:       NextState,
+    /// Online players and the most allowed in the world, checked before Login Success
+    player_limit:     Option<(Arc<PlayerRegistry>, u32)>,
+    /// Ban list and whitelist, checked once the username is known
+    access:           Option<Arc<AccessControl>>,
+    /// Turns the Login Start name into the player's identity
+    auth:             Arc<dyn AuthProvider>,
+    /// Login plugin exchange run before authenticating, e.g. proxy forwarding
+    login_plugin:     Option<Arc<dyn LoginPluginHook>>,
+    next_message_id:  i32,
 }
 
-use crate::consts::NETWORK_VALID_PROTOCOL_VERSION;
+const LEGACY_PING_PACKET_ID: u8 = 0xFE;
+const STATUS_REQUEST: i32 = ServerboundStatus::StatusRequest.id();
+const PING_REQUEST: i32 = ServerboundStatus::PingRequest.id();
+
+pub const SERVER_FULL_REASON: &str = "Server full";
 
 impl From<TcpStream> for LoginHandler {
     fn from(stream: TcpStream) -> Self {
         Self {
             stream,
             protocol_version: 0,
+            version: None,
+            next_state: NextState::Login,
+            player_limit: None,
+            access: None,
+            auth: Arc::new(OfflineAuth),
+            login_plugin: None,
+            next_message_id: 0,
         }
     }
 }
 
 impl LoginHandler {
+    /// Refuse logins with "Server full" once `players` holds `max_players`
+    pub fn with_player_limit(mut self, players: Arc<PlayerRegistry>, max_players: u32) -> Self {
+        self.player_limit = Some((players, max_players));
+        self
+    }
+
+    /// Refuse banned players, and everyone not whitelisted while the whitelist is on
+    pub fn with_access_control(mut self, access: Arc<AccessControl>) -> Self {
+        self.access = Some(access);
+        self
+    }
+
+    /// Identify players with `auth` instead of offline-mode UUIDs
+    pub fn with_auth(mut self, auth: Arc<dyn AuthProvider>) -> Self {
+        self.auth = auth;
+        self
+    }
+
+    /// Query the client with `hook` after Login Start; a profile it returns skips the `AuthProvider`
+    pub fn with_login_plugin(mut self, hook: Arc<dyn LoginPluginHook>) -> Self {
+        self.login_plugin = Some(hook);
+        self
+    }
+
+    /// Tell a client it can't connect right now and close the connection
+    pub async fn reject(mut self, reason: &str) -> Result<()> {
+        self.send_disconnect(reason).await?;
+        self.stream.shutdown().await?;
+        Ok(())
+    }
+
     // pub fn new(stream: TcpStream) -> Self {
     //     Self {
     //         stream,
@@ -37,9 +136,18 @@ impl LoginHandler {
     //     }
     // }
 
-    pub async fn handle_login(&mut self) -> Result<PlayerLogin> {
+    /// Drive the Handshake and Login states, recording each stage on `tracker`
+    /// Returns early with `LoginOutcome::Status` for server list pings, otherwise the connection
+    /// has entered the Configuration state
+    pub async fn handle_login(&mut self, tracker: &ConnectionStateTracker) -> Result<LoginOutcome> {
         tracing::debug!("[LOGIN] Starting login flow");
 
+        // Legacy pings start with 0xFE instead of a VarInt length
+        if self.is_legacy_ping().await? {
+            tracing::debug!("[LOGIN] Legacy server list ping received");
+            return Ok(LoginOutcome::LegacyPing);
+        }
+
         // Read Handshake packet
         tracing::debug!("[LOGIN] Waiting for Handshake packet...");
         if let Err(e) = self.read_handshake().await {
@@ -48,29 +156,29 @@ impl LoginHandler {
             return Err(e);
         }
         tracing::debug!("[LOGIN] Handshake received, protocol version: {}", self.protocol_version);
+        tracker.transition(ConnectionStage::Handshaking);
 
-        // Validate protocol version
-        if self.protocol_version != NETWORK_VALID_PROTOCOL_VERSION {
-            warn!(
-                "[LOGIN] Invalid protocol version: {} (expected {})",
-                self.protocol_version, NETWORK_VALID_PROTOCOL_VERSION
-            );
-            self.send_disconnect("Outdated server! Please use 1.21.7")
-                .await
-                .ok();
-            return Err(anyhow!(
-                "Protocol version mismatch: {} vs {}",
-                self.protocol_version,
-                NETWORK_VALID_PROTOCOL_VERSION
-            ));
+        // Server list pings may come from any client version, so skip validation
+        if self.next_state == NextState::Status {
+            return Ok(LoginOutcome::Status);
         }
-        tracing::debug!("[LOGIN] Protocol version validated");
+
+        // Negotiate the protocol version
+        let Some(version) = ProtocolVersion::from_protocol(self.protocol_version) else {
+            let reason = unsupported_version_reason(self.protocol_version);
+            warn!("[LOGIN] Unsupported protocol version: {}", self.protocol_version);
+            self.send_disconnect(&reason).await.ok();
+            return Err(anyhow!("Unsupported protocol version {}: {}", self.protocol_version, reason));
+        };
+        self.version = Some(version);
+        tracing::debug!("[LOGIN] Negotiated protocol version {}", version.name());
 
         // Read Login Start packet
         tracing::debug!("[LOGIN] Waiting for Login Start packet...");
         let username = match self.read_login_start().await {
             Ok(name) => {
                 tracing::debug!("[LOGIN] Login Start received, username: {}", name);
+                tracker.transition(ConnectionStage::Authenticating);
                 name
             }
             Err(e) => {
@@ -88,9 +196,64 @@ impl LoginHandler {
         }
         tracing::debug!("[LOGIN] Username validated: {}", username);
 
-        // Generate UUID for offline mode
-        let uuid = Self::generate_offline_uuid(&username);
-        tracing::debug!("[LOGIN] Generated UUID: {}", uuid);
+        let forwarded = match self.login_plugin.clone() {
+            Some(hook) => {
+                match self.run_login_plugin(hook.as_ref(), &username).await {
+                    Ok(profile) => profile,
+                    Err(e) => {
+                        warn!("[LOGIN] Login plugin on {} refused '{}': {}", hook.channel(), username, e);
+                        self.send_disconnect(&e.to_string()).await.ok();
+                        return Err(e);
+                    }
+                }
+            }
+            None => None,
+        };
+
+        let authenticated = match forwarded {
+            Some(profile) => Ok(profile),
+            None => {
+                // Providers may wait on the session server, so run them off the runtime's workers
+                let auth = Arc::clone(&self.auth);
+                let name = username.clone();
+                tokio::task::spawn_blocking(move || auth.authenticate(&name, None))
+                    .await
+                    .unwrap_or_else(|e| Err(e.into()))
+            }
+        };
+        let (username, uuid) = match authenticated {
+            Ok(profile) => (profile.username, profile.uuid),
+            Err(e) => {
+                warn!("[LOGIN] Failed to authenticate '{}': {}", username, e);
+                self.send_disconnect("Failed to verify username!").await.ok();
+                return Err(e);
+            }
+        };
+        tracing::debug!("[LOGIN] Authenticated as {} ({})", username, uuid);
+
+        if let Some(access) = &self.access {
+            let decision = access.check(&username, uuid);
+            if let Some(reason) = decision.reason() {
+                info!("[LOGIN] Refusing '{}': {:?}", username, decision);
+                self.send_disconnect(&reason).await.ok();
+                return Err(anyhow!("Access denied for {}: {:?}", username, decision));
+            }
+        }
+
+        // Only joins into the world count against max_players; status pings never get here
+        let slot = match &self.player_limit {
+            Some((players, max_players)) => {
+                match players.reserve(*max_players) {
+                    Some(slot) => Some(slot),
+                    None => {
+                        info!("[LOGIN] Refusing '{}': server full ({} players)", username, max_players);
+                        self.send_disconnect(SERVER_FULL_REASON).await.ok();
+                        return Err(anyhow!("Server full"));
+                    }
+                }
+            }
+            None => None,
+        };
 
         // Send Login Success packet
         tracing::debug!("[LOGIN] Sending Login Success packet...");
@@ -109,8 +272,139 @@ impl LoginHandler {
             return Err(e);
         }
         tracing::info!("[LOGIN] Login Acknowledged received");
+        tracker.transition(ConnectionStage::Configuring);
+
+        Ok(LoginOutcome::Login(PlayerLogin {
+            username,
+            uuid,
+            protocol: version,
+            slot,
+        }))
+    }
+
+    /// Send a Login Plugin Request on `channel`, returning its message id
+    pub async fn send_login_plugin_request(&mut self, channel: &str, data: &[u8]) -> Result<i32> {
+        let message_id = self.next_message_id;
+        self.next_message_id += 1;
+
+        let request = LoginPluginRequest {
+            message_id,
+            channel: channel.to_string(),
+            data: data.to_vec(),
+        };
+        let packet_id = self
+            .packet_ids()
+            .clientbound_login(ClientboundLogin::LoginPluginRequest);
+        self.send_packet(packet_id, &request.encode()).await?;
+        Ok(message_id)
+    }
+
+    /// Wait for the Login Plugin Response to request `message_id`; `None` if the client didn't
+    /// understand it. Responses to other ids are logged and skipped
+    pub async fn read_login_plugin_response(&mut self, message_id: i32) -> Result<Option<Vec<u8>>> {
+        loop {
+            let (packet_id, payload) = read_packet_frame(&mut self.stream).await?;
+            if packet_id != ServerboundLogin::LoginPluginResponse.id() {
+                return Err(anyhow!("Expected Login Plugin Response packet, got {:#x}", packet_id));
+            }
+
+            let response = LoginPluginResponse::parse(&payload)?;
+            if response.message_id == message_id {
+                return Ok(response.data);
+            }
+            warn!("[LOGIN] Ignoring Login Plugin Response to unknown message {}", response.message_id);
+        }
+    }
+
+    async fn run_login_plugin(
+        &mut self,
+        hook: &dyn LoginPluginHook,
+        username: &str,
+    ) -> Result<Option<PlayerProfile>> {
+        let message_id = self
+            .send_login_plugin_request(hook.channel(), &hook.request_data())
+            .await?;
+        let data = self.read_login_plugin_response(message_id).await?;
+        tracing::debug!(
+            "[LOGIN] Login plugin {} answered {}",
+            hook.channel(),
+            if data.is_some() {
+                "with data"
+            } else {
+                "not understood"
+            }
+        );
+        hook.handle_response(username, data.as_deref())
+    }
 
-        Ok(PlayerLogin { username, uuid })
+    /// Serve the Status state: answer Status Request with `response` and echo the Ping
+    /// The client closes the connection after the Pong, so this consumes the rest of the connection
+    pub async fn handle_status(&mut self, response: &StatusResponse) -> Result<()> {
+        loop {
+            let (packet_id, payload) = match read_packet_frame(&mut self.stream).await {
+                Ok(frame) => frame,
+                // Clients may close without pinging once they have the response
+                Err(e) if is_client_disconnect(&e) => return Ok(()),
+                Err(e) => return Err(e),
+            };
+
+            match packet_id {
+                STATUS_REQUEST => {
+                    tracing::debug!("[STATUS] Status Request received");
+                    let mut writer = PacketWriter::new();
+                    writer.write_string(response.to_json());
+                    self.send_packet(ClientboundStatus::StatusResponse.id(), &writer.finish())
+                        .await?;
+                }
+                PING_REQUEST => {
+                    tracing::debug!("[STATUS] Ping received");
+                    // Pong echoes the client's 8-byte timestamp back untouched
+                    self.send_packet(ClientboundStatus::PongResponse.id(), &payload)
+                        .await?;
+                    return Ok(());
+                }
+                other => return Err(anyhow!("Unexpected packet in Status state: {:#x}", other)),
+            }
+        }
+    }
+
+    /// Reply to a legacy `0xFE` ping with the kick-style status string
+    /// The rest of the legacy request is ignored, the client only waits for the `0xFF` reply
+    pub async fn handle_legacy_ping(&mut self, response: &StatusResponse) -> Result<()> {
+        self.stream.write_all(&response.to_legacy_bytes()).await?;
+        self.stream.flush().await?;
+
+        // Drain the unread request until the client hangs up; closing with unread bytes would
+        // reset the connection and could discard the reply before the client reads it
+        self.stream.shutdown().await?;
+        let mut discard = Vec::new();
+        let _ = self.stream.read_to_end(&mut discard).await;
+        Ok(())
+    }
+
+    /// Peek at the first byte without consuming it
+    async fn is_legacy_ping(&mut self) -> Result<bool> {
+        let mut first = [0u8; 1];
+        let n = self.stream.peek(&mut first).await?;
+        if n == 0 {
+            return Err(anyhow!("Connection closed during handshake"));
+        }
+        Ok(first[0] == LEGACY_PING_PACKET_ID)
+    }
+
+    async fn send_packet(&mut self, packet_id: i32, packet_data: &[u8]) -> Result<()> {
+        let packet_id = write_varint(packet_id);
+
+        // Write packet: [length][id][data]
+        let mut frame = Vec::new();
+        frame.extend_from_slice(&write_varint((packet_id.len() + packet_data.len()) as i32));
+        frame.extend_from_slice(&packet_id);
+        frame.extend_from_slice(packet_data);
+
+        self.stream.write_all(&frame).await?;
+        self.stream.flush().await?;
+
+        Ok(())
     }
 
     async fn read_handshake(&mut self) -> Result<()> {
@@ -163,7 +457,8 @@ impl LoginHandler {
         //     }
         // }
 
-        let packet_length = read_varint(&mut std::io::Cursor::new(&length_buf[..bytes_read]))? as usize;
+        let packet_length =
+            check_packet_length(read_varint(&mut std::io::Cursor::new(&length_buf[..bytes_read]))?)?;
 
         // Read packet data
         let mut packet_data = vec![0u8; packet_length];
@@ -172,8 +467,8 @@ impl LoginHandler {
         let mut reader = PacketReader::new(&packet_data);
         let packet_id: i32 = reader.read_varint()?;
 
-        if packet_id != 0x00 {
-            return Err(anyhow!("Expected Handshake packet (0x00), got {:#x}", packet_id));
+        if packet_id != ServerboundHandshake::Handshake.id() {
+            return Err(anyhow!("Expected Handshake packet, got {:#x}", packet_id));
         }
 
         self.protocol_version = reader.read_varint()?;
@@ -183,9 +478,11 @@ impl LoginHandler {
 
         // Accept both Status (1) and Login (2) states
         // Client may ping first, then connect for login
-        if next_state != 1 && next_state != 2 {
-            return Err(anyhow!("Expected Status (1) or Login (2) state, got {}", next_state));
-        }
+        self.next_state = match next_state {
+            1 => NextState::Status,
+            2 => NextState::Login,
+            _ => return Err(anyhow!("Expected Status (1) or Login (2) state, got {}", next_state)),
+        };
 
         Ok(())
     }
@@ -239,7 +536,7 @@ impl LoginHandler {
         tracing::debug!("[LOGIN] Reading Login Acknowledged packet, length bytes read: {}", bytes_read);
 
         let packet_length: usize =
-            read_varint(&mut std::io::Cursor::new(&length_buf[..bytes_read]))? as usize;
+            check_packet_length(read_varint(&mut std::io::Cursor::new(&length_buf[..bytes_read]))?)?;
 
         // Read packet data
         let mut packet_data: Vec<u8> = vec![0u8; packet_length];
@@ -248,8 +545,8 @@ impl LoginHandler {
         let mut reader = PacketReader::new(&packet_data);
         let packet_id: i32 = reader.read_varint()?;
 
-        if packet_id != 0x03 {
-            return Err(anyhow!("Expected Login Acknowledged packet (0x03), got {:#x}", packet_id));
+        if packet_id != ServerboundLogin::LoginAcknowledged.id() {
+            return Err(anyhow!("Expected Login Acknowledged packet, got {:#x}", packet_id));
         }
 
         // Login Acknowledged has no payload
@@ -303,7 +600,8 @@ impl LoginHandler {
         //     }
         // }
 
-        let packet_length = read_varint(&mut std::io::Cursor::new(&length_buf[..bytes_read]))? as usize;
+        let packet_length =
+            check_packet_length(read_varint(&mut std::io::Cursor::new(&length_buf[..bytes_read]))?)?;
 
         // Read packet data
         let mut packet_data = vec![0u8; packet_length];
@@ -312,8 +610,8 @@ impl LoginHandler {
         let mut reader = PacketReader::new(&packet_data);
         let packet_id = reader.read_varint()?;
 
-        if packet_id != 0x00 {
-            return Err(anyhow!("Expected Login Start packet (0x00), got {:#x}", packet_id));
+        if packet_id != ServerboundLogin::LoginStart.id() {
+            return Err(anyhow!("Expected Login Start packet, got {:#x}", packet_id));
         }
 
         let username = reader.read_string()?;
@@ -343,7 +641,10 @@ impl LoginHandler {
         writer.write_varint(0);
 
         let packet_data = writer.finish();
-        let packet_id = write_varint(0x02);
+        let packet_id = write_varint(
+            self.packet_ids()
+                .clientbound_login(ClientboundLogin::LoginSuccess),
+        );
 
         // Write packet: [length][id][data]
         let mut frame = Vec::new();
@@ -357,15 +658,12 @@ impl LoginHandler {
         Ok(())
     }
 
-    fn generate_offline_uuid(username: &str) -> Uuid {
-        // Create UUID v3 from username (offline mode)
-        // UUID v3 uses MD5 hash of namespace + name
-        let namespace = Uuid::NAMESPACE_DNS;
-        let offline_name = format!("OfflinePlayer:{}", username);
-        Uuid::new_v3(&namespace, offline_name.as_bytes())
+    pub fn generate_offline_uuid(username: &str) -> Uuid {
+        OfflineAuth::uuid_for(username)
     }
 
-    fn is_valid_username(username: &str) -> bool {
+    /// Whether a name could belong to a Minecraft account; forwarded names are held to it too
+    pub(crate) fn is_valid_username(username: &str) -> bool {
         // Minecraft username must be 3-16 characters, alphanumeric + underscore
         if username.is_empty() || username.len() > 16 {
             return false;
@@ -391,7 +689,7 @@ impl LoginHandler {
         writer.write_string(&json_message);
 
         let packet_data = writer.finish();
-        let packet_id = write_varint(0x00); // Disconnect packet ID in Login state
+        let packet_id = write_varint(self.packet_ids().clientbound_login(ClientboundLogin::Disconnect));
 
         // Write packet: [length][id][data]
         let mut frame = Vec::new();
@@ -406,7 +704,354 @@ impl LoginHandler {
         Ok(())
     }
 
+    /// Ids for the negotiated version, or the newest one before negotiation
+    fn packet_ids(&self) -> &'static dyn PacketIdTable {
+        self.version.unwrap_or_else(ProtocolVersion::newest).packet_ids()
+    }
+
     pub fn get_stream(self) -> TcpStream {
         self.stream
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use tokio::net::TcpListener;
+
+    use super::*;
+    use crate::network::auth::{MockAuth, PlayerProfile};
+
+    fn frame(packet_id: i32, payload: &[u8]) -> Vec<u8> {
+        let id = write_varint(packet_id);
+        let mut frame = write_varint((id.len() + payload.len()) as i32);
+        frame.extend_from_slice(&id);
+        frame.extend_from_slice(payload);
+        frame
+    }
+
+    fn handshake(next_state: i32) -> Vec<u8> {
+        versioned_handshake(ProtocolVersion::V1_21_7.protocol(), next_state)
+    }
+
+    fn versioned_handshake(protocol: i32, next_state: i32) -> Vec<u8> {
+        let mut handshake = PacketWriter::new();
+        handshake.write_varint(protocol);
+        handshake.write_string("localhost");
+        handshake.write_short(25565i16);
+        handshake.write_varint(next_state);
+        frame(0x00, &handshake.finish())
+    }
+
+    /// Bytes a 1.21.7 client sends from connect up to Login Acknowledged
+    fn client_login_sequence(username: &str) -> Vec<u8> {
+        let mut login_start = PacketWriter::new();
+        login_start.write_string(username);
+        login_start.write_uuid(Uuid::nil());
+
+        let mut bytes = handshake(2);
+        bytes.extend_from_slice(&frame(0x00, &login_start.finish()));
+        bytes.extend_from_slice(&frame(0x03, &[]));
+        bytes
+    }
+
+    #[tokio::test]
+    async fn test_login_sequence_advances_tracker() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let client = tokio::spawn(async move {
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            stream.write_all(&client_login_sequence("Steve")).await.unwrap();
+            // Keep the socket open until the server has read everything
+            let mut buf = Vec::new();
+            let _ = stream.read_to_end(&mut buf).await;
+        });
+
+        let (socket, _) = listener.accept().await.unwrap();
+        let tracker = ConnectionStateTracker::new();
+        let mut handler = LoginHandler::from(socket);
+
+        let LoginOutcome::Login(login) = handler.handle_login(&tracker).await.unwrap() else {
+            panic!("expected a login");
+        };
+        assert_eq!(login.username, "Steve");
+        assert_eq!(login.protocol, ProtocolVersion::V1_21_7);
+        assert_eq!(tracker.current_stage(), ConnectionStage::Configuring);
+        assert_eq!(
+            tracker.history(),
+            vec![
+                ConnectionStage::Connected,
+                ConnectionStage::Handshaking,
+                ConnectionStage::Authenticating,
+                ConnectionStage::Configuring,
+            ]
+        );
+
+        drop(handler);
+        client.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_login_uses_auth_provider_profile() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let client = tokio::spawn(async move {
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            stream.write_all(&client_login_sequence("Steve")).await.unwrap();
+            read_packet_frame(&mut stream).await.unwrap()
+        });
+
+        let profile = PlayerProfile {
+            uuid:     Uuid::from_u128(0x1234),
+            username: "Notch".to_string(),
+        };
+        let (socket, _) = listener.accept().await.unwrap();
+        let tracker = ConnectionStateTracker::new();
+        let mut handler = LoginHandler::from(socket).with_auth(Arc::new(MockAuth(profile.clone())));
+
+        let LoginOutcome::Login(login) = handler.handle_login(&tracker).await.unwrap() else {
+            panic!("expected a login");
+        };
+        assert_eq!(login.uuid, profile.uuid);
+        assert_eq!(login.username, "Notch");
+
+        // Login Success tells the client the same identity
+        let (packet_id, payload) = client.await.unwrap();
+        assert_eq!(packet_id, ClientboundLogin::LoginSuccess.id());
+        let mut reader = PacketReader::new(&payload);
+        assert_eq!(reader.read_uuid().unwrap(), profile.uuid);
+        assert_eq!(reader.read_string().unwrap(), "Notch");
+    }
+
+    /// Answers with the profile the client put in its response
+    struct EchoProfile;
+
+    impl LoginPluginHook for EchoProfile {
+        fn channel(&self) -> &str {
+            "test:profile"
+        }
+
+        fn handle_response(&self, _username: &str, data: Option<&[u8]>) -> Result<Option<PlayerProfile>> {
+            let data = data.ok_or_else(|| anyhow!("Proxy required"))?;
+            let mut reader = PacketReader::new(data);
+            Ok(Some(PlayerProfile {
+                uuid:     reader.read_uuid()?,
+                username: reader.read_string()?,
+            }))
+        }
+    }
+
+    fn login_plugin_response(message_id: i32, data: Option<&[u8]>) -> Vec<u8> {
+        let mut response = PacketWriter::new();
+        response.write_varint(message_id);
+        response.write_bool(data.is_some());
+        if let Some(data) = data {
+            response.write_bytes(data);
+        }
+        frame(ServerboundLogin::LoginPluginResponse.id(), &response.finish())
+    }
+
+    #[tokio::test]
+    async fn test_login_plugin_round_trip() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let forwarded = Uuid::from_u128(0xF00D);
+
+        let client = tokio::spawn(async move {
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            let mut login_start = PacketWriter::new();
+            login_start.write_string("Steve");
+            login_start.write_uuid(Uuid::nil());
+            let mut bytes = handshake(2);
+            bytes.extend_from_slice(&frame(0x00, &login_start.finish()));
+            stream.write_all(&bytes).await.unwrap();
+
+            let (packet_id, payload) = read_packet_frame(&mut stream).await.unwrap();
+            assert_eq!(packet_id, ClientboundLogin::LoginPluginRequest.id());
+            let mut reader = PacketReader::new(&payload);
+            let message_id = reader.read_varint().unwrap();
+            assert_eq!(reader.read_string().unwrap(), "test:profile");
+
+            let mut profile = PacketWriter::new();
+            profile.write_uuid(forwarded);
+            profile.write_string("Proxied");
+            let profile = profile.finish();
+
+            // A stray response to a request never made is skipped
+            let mut bytes = login_plugin_response(message_id + 1, Some(b"junk"));
+            bytes.extend_from_slice(&login_plugin_response(message_id, Some(&profile)));
+            bytes.extend_from_slice(&frame(0x03, &[]));
+            stream.write_all(&bytes).await.unwrap();
+
+            let (packet_id, _) = read_packet_frame(&mut stream).await.unwrap();
+            assert_eq!(packet_id, ClientboundLogin::LoginSuccess.id());
+        });
+
+        let (socket, _) = listener.accept().await.unwrap();
+        let tracker = ConnectionStateTracker::new();
+        let mut handler = LoginHandler::from(socket).with_login_plugin(Arc::new(EchoProfile));
+
+        let LoginOutcome::Login(login) = handler.handle_login(&tracker).await.unwrap() else {
+            panic!("expected a login");
+        };
+        assert_eq!(login.uuid, forwarded);
+        assert_eq!(login.username, "Proxied");
+        client.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_login_plugin_not_understood_disconnects() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let client = tokio::spawn(async move {
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            let mut login_start = PacketWriter::new();
+            login_start.write_string("Steve");
+            login_start.write_uuid(Uuid::nil());
+            let mut bytes = handshake(2);
+            bytes.extend_from_slice(&frame(0x00, &login_start.finish()));
+            stream.write_all(&bytes).await.unwrap();
+
+            read_packet_frame(&mut stream).await.unwrap();
+            stream.write_all(&login_plugin_response(0, None)).await.unwrap();
+            read_packet_frame(&mut stream).await.unwrap()
+        });
+
+        let (socket, _) = listener.accept().await.unwrap();
+        let tracker = ConnectionStateTracker::new();
+        let mut handler = LoginHandler::from(socket).with_login_plugin(Arc::new(EchoProfile));
+        assert!(handler.handle_login(&tracker).await.is_err());
+
+        let (packet_id, payload) = client.await.unwrap();
+        assert_eq!(packet_id, ClientboundLogin::Disconnect.id());
+        let reason = PacketReader::new(&payload).read_string().unwrap();
+        assert_eq!(reason, r#"{"text":"Proxy required"}"#);
+    }
+
+    #[tokio::test]
+    async fn test_unsupported_version_is_disconnected() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let client = tokio::spawn(async move {
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            stream.write_all(&versioned_handshake(771, 2)).await.unwrap();
+            read_packet_frame(&mut stream).await.unwrap()
+        });
+
+        let (socket, _) = listener.accept().await.unwrap();
+        let tracker = ConnectionStateTracker::new();
+        let mut handler = LoginHandler::from(socket);
+
+        let err = handler.handle_login(&tracker).await.unwrap_err();
+        assert!(err.to_string().contains("771"));
+        assert_eq!(handler.version, None);
+        assert_eq!(tracker.current_stage(), ConnectionStage::Handshaking);
+
+        let (packet_id, payload) = client.await.unwrap();
+        assert_eq!(packet_id, ClientboundLogin::Disconnect.id());
+        let reason = PacketReader::new(&payload).read_string().unwrap();
+        assert_eq!(reason, r#"{"text":"Outdated client! Please use 1.21.7"}"#);
+    }
+
+    #[tokio::test]
+    async fn test_banned_player_is_disconnected() {
+        let dir = std::env::temp_dir().join(format!("rustcraft_login_access_{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let access = Arc::new(AccessControl::load(&dir, false).unwrap());
+        access
+            .ban("Griefer", LoginHandler::generate_offline_uuid("Griefer"), "Lava casts")
+            .unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let client = tokio::spawn(async move {
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            stream.write_all(&client_login_sequence("Griefer")).await.unwrap();
+            read_packet_frame(&mut stream).await.unwrap()
+        });
+
+        let (socket, _) = listener.accept().await.unwrap();
+        let tracker = ConnectionStateTracker::new();
+        let mut handler = LoginHandler::from(socket).with_access_control(access);
+
+        assert!(handler.handle_login(&tracker).await.is_err());
+        assert_eq!(tracker.current_stage(), ConnectionStage::Authenticating);
+
+        let (packet_id, payload) = client.await.unwrap();
+        assert_eq!(packet_id, ClientboundLogin::Disconnect.id());
+        let reason = PacketReader::new(&payload).read_string().unwrap();
+        assert!(reason.contains("banned") && reason.contains("Lava casts"), "{}", reason);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_status_ping() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let client = tokio::spawn(async move {
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            let mut bytes = handshake(1);
+            bytes.extend_from_slice(&frame(0x00, &[]));
+            bytes.extend_from_slice(&frame(0x01, &42i64.to_be_bytes()));
+            stream.write_all(&bytes).await.unwrap();
+
+            let (status_id, status) = read_packet_frame(&mut stream).await.unwrap();
+            let (pong_id, pong) = read_packet_frame(&mut stream).await.unwrap();
+            (status_id, status, pong_id, pong)
+        });
+
+        let (socket, _) = listener.accept().await.unwrap();
+        let tracker = ConnectionStateTracker::new();
+        let mut handler = LoginHandler::from(socket);
+
+        let outcome = handler.handle_login(&tracker).await.unwrap();
+        assert!(matches!(outcome, LoginOutcome::Status));
+        handler
+            .handle_status(&StatusResponse::new("motd", 20))
+            .await
+            .unwrap();
+
+        let (status_id, status, pong_id, pong) = client.await.unwrap();
+        assert_eq!(status_id, 0x00);
+        let json = PacketReader::new(&status).read_string().unwrap();
+        assert!(json.contains(r#""text":"motd""#));
+        assert_eq!(pong_id, 0x01);
+        assert_eq!(pong, 42i64.to_be_bytes());
+    }
+
+    #[tokio::test]
+    async fn test_legacy_ping() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let client = tokio::spawn(async move {
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            // 1.6 style: FE 01 FA + plugin message, which the server ignores
+            stream.write_all(&[0xFE, 0x01, 0xFA, 0x00, 0x0B]).await.unwrap();
+            let mut response = Vec::new();
+            stream.read_to_end(&mut response).await.unwrap();
+            response
+        });
+
+        let (socket, _) = listener.accept().await.unwrap();
+        let tracker = ConnectionStateTracker::new();
+        let mut handler = LoginHandler::from(socket);
+
+        let outcome = handler.handle_login(&tracker).await.unwrap();
+        assert!(matches!(outcome, LoginOutcome::LegacyPing));
+
+        let status = StatusResponse::new("motd", 20);
+        handler.handle_legacy_ping(&status).await.unwrap();
+        drop(handler);
+
+        let response = client.await.unwrap();
+        assert_eq!(response, status.to_legacy_bytes());
+        assert_eq!(tracker.current_stage(), ConnectionStage::Connected);
+    }
+}
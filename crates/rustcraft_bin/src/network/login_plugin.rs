@@ -0,0 +1,103 @@
+// Login Plugin Request/Response: custom queries the server may make between Login Start and Login
+// Success, used by proxies to forward player info. Every request gets exactly one response with
+// the same message id; a client that doesn't know the channel answers without data.
+
+use anyhow::Result;
+use bytes::BytesMut;
+
+use crate::network::auth::PlayerProfile;
+use crate::network::{ByteWritable, PacketReader, PacketWriter};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoginPluginRequest {
+    pub message_id: i32,
+    pub channel:    String,
+    pub data:       Vec<u8>,
+}
+
+impl LoginPluginRequest {
+    /// `[message_id: VarInt][channel: String][data: rest of packet]`
+    pub fn encode(&self) -> BytesMut {
+        let mut writer = PacketWriter::new();
+        writer.write_varint(self.message_id);
+        writer.write_string(&self.channel);
+        writer.write_bytes(&self.data);
+        writer.finish()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoginPluginResponse {
+    pub message_id: i32,
+    /// `None` when the client didn't understand the request
+    pub data:       Option<Vec<u8>>,
+}
+
+impl LoginPluginResponse {
+    /// `[message_id: VarInt][successful: Boolean][data: rest of packet, only when successful]`
+    pub fn parse(payload: &[u8]) -> Result<Self> {
+        let mut reader = PacketReader::new(payload);
+        let message_id = reader.read_varint()?;
+        let data = if reader.read_bool()? {
+            Some(reader.read_bytes(reader.remaining())?)
+        } else {
+            None
+        };
+        Ok(Self { message_id, data })
+    }
+}
+
+/// A login plugin exchange run after Login Start, e.g. proxy player info forwarding
+pub trait LoginPluginHook: Send + Sync {
+    fn channel(&self) -> &str;
+
+    /// Data sent with the request
+    fn request_data(&self) -> Vec<u8> {
+        Vec::new()
+    }
+
+    /// Act on the client's answer to our request (`None` if it didn't understand it)
+    /// A profile replaces the `AuthProvider`'s; an error disconnects the player
+    fn handle_response(&self, username: &str, data: Option<&[u8]>) -> Result<Option<PlayerProfile>>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_login_plugin_request_framing() {
+        let request = LoginPluginRequest {
+            message_id: 300,
+            channel:    "velocity:player_info".to_string(),
+            data:       vec![4],
+        };
+        let bytes = request.encode();
+
+        let mut reader = PacketReader::new(&bytes);
+        assert_eq!(reader.read_varint().unwrap(), 300);
+        assert_eq!(reader.read_string().unwrap(), "velocity:player_info");
+        assert_eq!(reader.read_bytes(reader.remaining()).unwrap(), vec![4]);
+    }
+
+    #[test]
+    fn test_login_plugin_response_parsing() {
+        let mut writer = PacketWriter::new();
+        writer.write_varint(7);
+        writer.write_bool(true);
+        writer.write_bytes([1, 2, 3]);
+        assert_eq!(
+            LoginPluginResponse::parse(&writer.finish()).unwrap(),
+            LoginPluginResponse {
+                message_id: 7,
+                data:       Some(vec![1, 2, 3]),
+            }
+        );
+
+        // Not understood: no data follows
+        assert_eq!(LoginPluginResponse::parse(&[7, 0]).unwrap().data, None);
+        // Understood, with an empty answer
+        assert_eq!(LoginPluginResponse::parse(&[7, 1]).unwrap().data, Some(vec![]));
+        assert!(LoginPluginResponse::parse(&[7]).is_err());
+    }
+}
@@ -1,4 +1,15 @@
+mod auth;
+mod encryption;
+mod frame;
+mod known_packs;
 mod login;
+mod login_plugin;
+mod packet_ids;
+mod plugin_message;
+mod rate_limit;
+mod status;
+mod velocity;
+mod version;
 
 mod protocol;
 
@@ -7,16 +18,52 @@ use bytes::BytesMut;
 // use protocol::*;
 use uuid::Uuid;
 
-pub use crate::network::login::LoginHandler;
+pub use crate::network::auth::{AuthProvider, OfflineAuth};
+pub use crate::network::frame::{
+    check_packet_length,
+    is_client_disconnect,
+    is_packet_too_large,
+    read_packet_frame,
+};
+pub use crate::network::known_packs::{KnownPack, decode_known_packs, encode_known_packs, shared_packs};
+pub use crate::network::login::{LoginHandler, LoginOutcome, SERVER_FULL_REASON};
+pub use crate::network::login_plugin::LoginPluginHook;
+pub use crate::network::packet_ids::{
+    ClientboundConfig,
+    ClientboundLogin,
+    ClientboundPlay,
+    ClientboundStatus,
+    ServerboundConfig,
+    ServerboundHandshake,
+    ServerboundLogin,
+    ServerboundPlay,
+    ServerboundStatus,
+};
+pub use crate::network::plugin_message::{PluginChannels, PluginMessage};
+#[cfg(test)]
+pub(crate) use crate::network::protocol::Nbt;
 pub use crate::network::protocol::{
+    BiomeCompound,
     DamageTypeCompound,
     DimensionCompound,
     NBTBuilder,
     PacketReader,
     PacketWriter,
+    pack_block_position,
     read_varint,
+    text_component_nbt,
+    unpack_block_position,
     write_varint,
 };
+pub use crate::network::rate_limit::ConnectionRateLimiter;
+pub use crate::network::status::{StatusPlayer, StatusResponse};
+pub use crate::network::velocity::VelocityForwarding;
+pub use crate::network::version::{
+    PacketIdTable,
+    ProtocolVersion,
+    supported_version_names,
+    unsupported_version_reason,
+};
 
 pub trait ByteWritable {
     fn write_varint<N: Into<i32>>(&mut self, value: N);
@@ -43,5 +90,27 @@ pub trait ByteWritable {
 
     fn write_bytes<A: AsRef<[u8]>>(&mut self, bytes: A);
 
+    /// A varint count, then each item as `write_elem` writes it
+    fn write_prefixed_array<T>(&mut self, items: &[T], write_elem: impl Fn(&mut Self, &T))
+    where
+        Self: Sized,
+    {
+        self.write_varint(items.len() as i32);
+        for item in items {
+            write_elem(self, item);
+        }
+    }
+
+    /// A boolean presence flag, then the value as `write_value` writes it when present
+    fn write_prefixed_optional<T: ?Sized>(&mut self, value: Option<&T>, write_value: impl Fn(&mut Self, &T))
+    where
+        Self: Sized,
+    {
+        self.write_bool(value.is_some());
+        if let Some(value) = value {
+            write_value(self, value);
+        }
+    }
+
     fn finish(self) -> BytesMut;
 }
@@ -0,0 +1,252 @@
+#![allow(dead_code)]
+// Packet ids for protocol 772 (1.21.7), one enum per connection state and direction
+// Only packets the server reads or writes are listed; add variants here rather than writing ids
+// inline, so a protocol bump only touches this file and its test
+
+use anyhow::{Result, anyhow};
+
+macro_rules! packet_ids {
+    ($($name:ident),* $(,)?) => {
+        $(
+            impl $name {
+                pub const fn id(self) -> i32 {
+                    self as i32
+                }
+            }
+        )*
+    };
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(i32)]
+pub enum ServerboundHandshake {
+    Handshake = 0x00,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(i32)]
+pub enum ClientboundStatus {
+    StatusResponse = 0x00,
+    PongResponse = 0x01,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(i32)]
+pub enum ServerboundStatus {
+    StatusRequest = 0x00,
+    PingRequest = 0x01,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(i32)]
+pub enum ClientboundLogin {
+    Disconnect = 0x00,
+    LoginSuccess = 0x02,
+    LoginPluginRequest = 0x04,
+}
+
+// Vanilla's names, which all happen to start with "Login"
+#[allow(clippy::enum_variant_names)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(i32)]
+pub enum ServerboundLogin {
+    LoginStart = 0x00,
+    LoginPluginResponse = 0x02,
+    LoginAcknowledged = 0x03,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(i32)]
+pub enum ClientboundConfig {
+    PluginMessage = 0x01,
+    FinishConfiguration = 0x03,
+    RegistryData = 0x07,
+    KnownPacks = 0x0E,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(i32)]
+pub enum ServerboundConfig {
+    ClientInformation = 0x00,
+    PluginMessage = 0x02,
+    AcknowledgeFinishConfiguration = 0x03,
+    KnownPacks = 0x07,
+}
+
+impl TryFrom<i32> for ServerboundConfig {
+    type Error = anyhow::Error;
+
+    fn try_from(id: i32) -> Result<Self> {
+        match id {
+            0x00 => Ok(Self::ClientInformation),
+            0x02 => Ok(Self::PluginMessage),
+            0x03 => Ok(Self::AcknowledgeFinishConfiguration),
+            0x07 => Ok(Self::KnownPacks),
+            other => Err(anyhow!("Unexpected packet in Configuration state: {:#04x}", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(i32)]
+pub enum ClientboundPlay {
+    SpawnEntity = 0x01,
+    AcknowledgeBlockChange = 0x04,
+    BlockUpdate = 0x08,
+    SetContainerContent = 0x12,
+    PluginMessage = 0x18,
+    Disconnect = 0x1C,
+    EntityEvent = 0x1E,
+    /// Absolute position of an entity, used for moves too long for a delta
+    EntityPositionSync = 0x1F,
+    UnloadChunk = 0x21,
+    GameEvent = 0x22,
+    ChunkDataAndUpdateLight = 0x27,
+    Login = 0x2B,
+    UpdateEntityPosition = 0x2E,
+    UpdateEntityPositionAndRotation = 0x2F,
+    UpdateEntityRotation = 0x31,
+    PlayerAbilities = 0x39,
+    PlayerInfoRemove = 0x3E,
+    PlayerInfoUpdate = 0x3F,
+    SynchronizePlayerPosition = 0x41,
+    RemoveEntities = 0x46,
+    SetHeadRotation = 0x4C,
+    SetCenterChunk = 0x57,
+    SetDefaultSpawnPosition = 0x5A,
+    SetHealth = 0x61,
+    SetHeldItem = 0x62,
+    SetSimulationDistance = 0x68,
+    UpdateTime = 0x6A,
+    SystemChatMessage = 0x72,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(i32)]
+pub enum ServerboundPlay {
+    ConfirmTeleportation = 0x00,
+    ChatCommand = 0x06,
+    SignedChatCommand = 0x07,
+    ChatMessage = 0x08,
+    ClientCommand = 0x0B,
+    ClientTickEnd = 0x0C,
+    PluginMessage = 0x15,
+    KeepAlive = 0x1B,
+    SetPlayerPosition = 0x1D,
+    SetPlayerPositionAndRotation = 0x1E,
+    SetPlayerRotation = 0x1F,
+    SetPlayerMovementFlags = 0x20,
+    PlayerAbilities = 0x27,
+    /// Digging, which also covers dropping items and swapping hands
+    PlayerAction = 0x28,
+    SetHeldItem = 0x34,
+    SetCreativeModeSlot = 0x37,
+    UseItemOn = 0x3F,
+}
+
+packet_ids!(
+    ServerboundHandshake,
+    ClientboundStatus,
+    ServerboundStatus,
+    ClientboundLogin,
+    ServerboundLogin,
+    ClientboundConfig,
+    ServerboundConfig,
+    ClientboundPlay,
+    ServerboundPlay,
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Changing any of these is a protocol version change; update them together
+    #[test]
+    fn test_packet_ids_match_protocol_772() {
+        let pinned = [
+            (ServerboundHandshake::Handshake.id(), 0x00),
+            (ClientboundStatus::StatusResponse.id(), 0x00),
+            (ClientboundStatus::PongResponse.id(), 0x01),
+            (ServerboundStatus::StatusRequest.id(), 0x00),
+            (ServerboundStatus::PingRequest.id(), 0x01),
+            (ClientboundLogin::Disconnect.id(), 0x00),
+            (ClientboundLogin::LoginSuccess.id(), 0x02),
+            (ClientboundLogin::LoginPluginRequest.id(), 0x04),
+            (ServerboundLogin::LoginStart.id(), 0x00),
+            (ServerboundLogin::LoginPluginResponse.id(), 0x02),
+            (ServerboundLogin::LoginAcknowledged.id(), 0x03),
+            (ClientboundConfig::PluginMessage.id(), 0x01),
+            (ClientboundConfig::FinishConfiguration.id(), 0x03),
+            (ClientboundConfig::RegistryData.id(), 0x07),
+            (ClientboundConfig::KnownPacks.id(), 0x0E),
+            (ServerboundConfig::ClientInformation.id(), 0x00),
+            (ServerboundConfig::PluginMessage.id(), 0x02),
+            (ServerboundConfig::AcknowledgeFinishConfiguration.id(), 0x03),
+            (ServerboundConfig::KnownPacks.id(), 0x07),
+            (ClientboundPlay::SpawnEntity.id(), 0x01),
+            (ClientboundPlay::AcknowledgeBlockChange.id(), 0x04),
+            (ClientboundPlay::BlockUpdate.id(), 0x08),
+            (ClientboundPlay::SetContainerContent.id(), 0x12),
+            (ClientboundPlay::PluginMessage.id(), 0x18),
+            (ClientboundPlay::Disconnect.id(), 0x1C),
+            (ClientboundPlay::EntityEvent.id(), 0x1E),
+            (ClientboundPlay::EntityPositionSync.id(), 0x1F),
+            (ClientboundPlay::UnloadChunk.id(), 0x21),
+            (ClientboundPlay::GameEvent.id(), 0x22),
+            (ClientboundPlay::ChunkDataAndUpdateLight.id(), 0x27),
+            (ClientboundPlay::Login.id(), 0x2B),
+            (ClientboundPlay::UpdateEntityPosition.id(), 0x2E),
+            (ClientboundPlay::UpdateEntityPositionAndRotation.id(), 0x2F),
+            (ClientboundPlay::UpdateEntityRotation.id(), 0x31),
+            (ClientboundPlay::PlayerAbilities.id(), 0x39),
+            (ClientboundPlay::PlayerInfoRemove.id(), 0x3E),
+            (ClientboundPlay::PlayerInfoUpdate.id(), 0x3F),
+            (ClientboundPlay::SynchronizePlayerPosition.id(), 0x41),
+            (ClientboundPlay::RemoveEntities.id(), 0x46),
+            (ClientboundPlay::SetHeadRotation.id(), 0x4C),
+            (ClientboundPlay::SetCenterChunk.id(), 0x57),
+            (ClientboundPlay::SetDefaultSpawnPosition.id(), 0x5A),
+            (ClientboundPlay::SetHealth.id(), 0x61),
+            (ClientboundPlay::SetHeldItem.id(), 0x62),
+            (ClientboundPlay::SetSimulationDistance.id(), 0x68),
+            (ClientboundPlay::UpdateTime.id(), 0x6A),
+            (ClientboundPlay::SystemChatMessage.id(), 0x72),
+            (ServerboundPlay::ConfirmTeleportation.id(), 0x00),
+            (ServerboundPlay::ChatCommand.id(), 0x06),
+            (ServerboundPlay::SignedChatCommand.id(), 0x07),
+            (ServerboundPlay::ChatMessage.id(), 0x08),
+            (ServerboundPlay::ClientCommand.id(), 0x0B),
+            (ServerboundPlay::ClientTickEnd.id(), 0x0C),
+            (ServerboundPlay::PluginMessage.id(), 0x15),
+            (ServerboundPlay::KeepAlive.id(), 0x1B),
+            (ServerboundPlay::SetPlayerPosition.id(), 0x1D),
+            (ServerboundPlay::SetPlayerPositionAndRotation.id(), 0x1E),
+            (ServerboundPlay::SetPlayerRotation.id(), 0x1F),
+            (ServerboundPlay::SetPlayerMovementFlags.id(), 0x20),
+            (ServerboundPlay::PlayerAbilities.id(), 0x27),
+            (ServerboundPlay::PlayerAction.id(), 0x28),
+            (ServerboundPlay::SetHeldItem.id(), 0x34),
+            (ServerboundPlay::SetCreativeModeSlot.id(), 0x37),
+            (ServerboundPlay::UseItemOn.id(), 0x3F),
+        ];
+
+        for (i, (id, expected)) in pinned.into_iter().enumerate() {
+            assert_eq!(id, expected, "entry {}", i);
+        }
+    }
+
+    #[test]
+    fn test_serverbound_config_from_id() {
+        for packet in [
+            ServerboundConfig::ClientInformation,
+            ServerboundConfig::PluginMessage,
+            ServerboundConfig::AcknowledgeFinishConfiguration,
+            ServerboundConfig::KnownPacks,
+        ] {
+            assert_eq!(ServerboundConfig::try_from(packet.id()).unwrap(), packet);
+        }
+
+        // Keep Alive is valid in Configuration but not handled yet
+        assert!(ServerboundConfig::try_from(0x04).is_err());
+    }
+}
@@ -0,0 +1,167 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::Result;
+use parking_lot::RwLock;
+use tracing::debug;
+
+use crate::network::{ByteWritable, PacketReader, PacketWriter, write_varint};
+
+/// Channel clients and servers announce their software name on
+pub const BRAND_CHANNEL: &str = "minecraft:brand";
+/// Brand we answer with, shown in the client's F3 screen
+pub const SERVER_BRAND: &str = "RustCraft";
+
+/// Custom payload on a namespaced channel; the data runs to the end of the packet
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PluginMessage {
+    pub channel: String,
+    pub data:    Vec<u8>,
+}
+
+impl PluginMessage {
+    pub fn new<S: Into<String>>(channel: S, data: Vec<u8>) -> Self {
+        Self {
+            channel: channel.into(),
+            data,
+        }
+    }
+
+    /// Parse a plugin message payload (everything after the packet id)
+    pub fn parse(payload: &[u8]) -> Result<Self> {
+        let mut reader = PacketReader::new(payload);
+        let channel = reader.read_string()?;
+        let data = reader.read_bytes(reader.remaining())?;
+        Ok(Self { channel, data })
+    }
+
+    /// Frame the message as `[length][id][channel][data]`; the id differs between Configuration
+    /// (`ClientboundConfig::PluginMessage`) and Play (`ClientboundPlay::PluginMessage`)
+    pub fn frame(&self, packet_id: i32) -> Vec<u8> {
+        let mut writer = PacketWriter::new();
+        writer.write_string(&self.channel);
+        writer.write_bytes(&self.data);
+
+        let packet_data = writer.finish();
+        let packet_id = write_varint(packet_id);
+        let packet_length = (packet_id.len() + packet_data.len()) as i32;
+
+        let mut frame = Vec::new();
+        frame.extend_from_slice(&write_varint(packet_length));
+        frame.extend_from_slice(&packet_id);
+        frame.extend_from_slice(&packet_data);
+        frame
+    }
+}
+
+/// Handles the data sent on one channel, returning data to send back on the same channel
+pub type PluginChannelHandler = Arc<dyn Fn(&[u8]) -> Option<Vec<u8>> + Send + Sync>;
+
+/// Handlers for the plugin channels the server understands, `minecraft:brand` included
+pub struct PluginChannels {
+    handlers: RwLock<HashMap<String, PluginChannelHandler>>,
+}
+
+impl PluginChannels {
+    pub fn new() -> Self {
+        let channels = Self {
+            handlers: RwLock::new(HashMap::new()),
+        };
+
+        channels.register_plugin_channel(BRAND_CHANNEL, |data| {
+            let brand = PacketReader::new(data).read_string().unwrap_or_default();
+            debug!("[PLUGIN] Client brand: {}", brand);
+            Some(brand_payload(SERVER_BRAND))
+        });
+
+        channels
+    }
+
+    /// Handle messages on `name`, replacing any handler already registered for it
+    pub fn register_plugin_channel<S, F>(&self, name: S, handler: F)
+    where
+        S: Into<String>,
+        F: Fn(&[u8]) -> Option<Vec<u8>> + Send + Sync + 'static,
+    {
+        self.handlers.write().insert(name.into(), Arc::new(handler));
+    }
+
+    /// Run the handler for the message's channel, returning the reply if it has one
+    /// Messages on unregistered channels are ignored, as vanilla does
+    pub fn dispatch(&self, message: &PluginMessage) -> Option<PluginMessage> {
+        let Some(handler) = self.handlers.read().get(&message.channel).cloned() else {
+            debug!("[PLUGIN] Ignoring message on unregistered channel {}", message.channel);
+            return None;
+        };
+
+        handler(&message.data).map(|data| PluginMessage::new(message.channel.clone(), data))
+    }
+}
+
+impl Default for PluginChannels {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Brand payload: a single length-prefixed string
+fn brand_payload(brand: &str) -> Vec<u8> {
+    let mut writer = PacketWriter::new();
+    writer.write_string(brand);
+    writer.finish().to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::ClientboundConfig;
+
+    /// `minecraft:brand` carrying "vanilla", as sent by the notchian client
+    fn vanilla_brand() -> Vec<u8> {
+        let mut payload = vec![15];
+        payload.extend_from_slice(b"minecraft:brand");
+        payload.push(7);
+        payload.extend_from_slice(b"vanilla");
+        payload
+    }
+
+    #[test]
+    fn test_parse_brand_message() {
+        let message = PluginMessage::parse(&vanilla_brand()).unwrap();
+        assert_eq!(message.channel, BRAND_CHANNEL);
+        assert_eq!(message.data, b"\x07vanilla");
+
+        // Channel name cut short
+        assert!(PluginMessage::parse(b"\x0fminecraft").is_err());
+    }
+
+    #[test]
+    fn test_brand_response_frame() {
+        let channels = PluginChannels::new();
+        let reply = channels
+            .dispatch(&PluginMessage::parse(&vanilla_brand()).unwrap())
+            .expect("brand is answered");
+
+        assert_eq!(reply.channel, BRAND_CHANNEL);
+        assert_eq!(reply.data, b"\x09RustCraft");
+
+        let frame = reply.frame(ClientboundConfig::PluginMessage.id());
+        let mut expected = vec![27, 0x01, 15];
+        expected.extend_from_slice(b"minecraft:brand");
+        expected.push(9);
+        expected.extend_from_slice(b"RustCraft");
+        assert_eq!(frame, expected);
+    }
+
+    #[test]
+    fn test_custom_channels() {
+        let channels = PluginChannels::new();
+        channels.register_plugin_channel("rustcraft:echo", |data| Some(data.to_vec()));
+        channels.register_plugin_channel("rustcraft:sink", |_| None);
+
+        let echo = PluginMessage::new("rustcraft:echo", vec![1, 2, 3]);
+        assert_eq!(channels.dispatch(&echo), Some(echo.clone()));
+        assert_eq!(channels.dispatch(&PluginMessage::new("rustcraft:sink", vec![1])), None);
+        assert_eq!(channels.dispatch(&PluginMessage::new("other:channel", vec![])), None);
+    }
+}
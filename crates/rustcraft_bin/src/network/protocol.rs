@@ -5,8 +5,16 @@ use std::ops::{AddAssign, BitOrAssign};
 
 use anyhow::{Result, anyhow};
 use bytes::{BufMut, Bytes, BytesMut};
+use serde::Deserialize;
 use uuid::Uuid;
 
+use crate::consts::{
+    DIMENSION_MAX_Y,
+    DIMENSION_MIN_Y,
+    TERRAIN_CHUNK_HEIGHT,
+    TERRAIN_SECTION_HEIGHT,
+    WORLD_MIN_Y,
+};
 use crate::network::ByteWritable;
 
 /// Validate a Minecraft identifier (resource location)
@@ -131,7 +139,7 @@ impl ByteWritable for PacketWriter {
     }
 
     fn write_long<N: Into<i64>>(&mut self, value: N) {
-        self.data.put_i64_ne(value.into());
+        self.data.extend_from_slice(&value.into().to_be_bytes());
     }
 
     fn write_float<N: Into<f32>>(&mut self, value: N) {
@@ -139,7 +147,7 @@ impl ByteWritable for PacketWriter {
     }
 
     fn write_double<N: Into<f64>>(&mut self, value: N) {
-        self.data.put_f64_ne(value.into());
+        self.data.extend_from_slice(&value.into().to_be_bytes());
     }
 
     fn write_bool<B: Into<bool>>(&mut self, value: B) {
@@ -183,12 +191,23 @@ impl<'a> PacketReader<'a> {
     }
 
     pub fn read_string(&mut self) -> std::io::Result<String> {
-        let len = self.read_varint()? as usize;
+        let len = self.read_length(1)?;
         let mut buf = vec![0u8; len];
         self.cursor.read_exact(&mut buf)?;
         Ok(String::from_utf8_lossy(&buf).to_string())
     }
 
+    /// Read a varint length or array count, rejecting it before anything is allocated if `len`
+    /// elements of at least `min_element_len` bytes each cannot fit in what is left of the packet
+    pub fn read_length(&mut self, min_element_len: usize) -> std::io::Result<usize> {
+        let declared = self.read_varint()?;
+        let len = usize::try_from(declared).map_err(|_| {
+            std::io::Error::new(std::io::ErrorKind::InvalidData, format!("Negative length {}", declared))
+        })?;
+        self.check_available(len.saturating_mul(min_element_len))?;
+        Ok(len)
+    }
+
     pub fn read_byte(&mut self) -> std::io::Result<u8> {
         let mut buf = [0u8; 1];
         self.cursor.read_exact(&mut buf)?;
@@ -198,31 +217,31 @@ impl<'a> PacketReader<'a> {
     pub fn read_short(&mut self) -> std::io::Result<i16> {
         let mut buf = [0u8; 2];
         self.cursor.read_exact(&mut buf)?;
-        Ok(i16::from_ne_bytes(buf))
+        Ok(i16::from_be_bytes(buf))
     }
 
     pub fn read_int(&mut self) -> std::io::Result<i32> {
         let mut buf = [0u8; 4];
         self.cursor.read_exact(&mut buf)?;
-        Ok(i32::from_ne_bytes(buf))
+        Ok(i32::from_be_bytes(buf))
     }
 
     pub fn read_long(&mut self) -> std::io::Result<i64> {
         let mut buf = [0u8; 8];
         self.cursor.read_exact(&mut buf)?;
-        Ok(i64::from_ne_bytes(buf))
+        Ok(i64::from_be_bytes(buf))
     }
 
     pub fn read_float(&mut self) -> std::io::Result<f32> {
         let mut buf = [0u8; 4];
         self.cursor.read_exact(&mut buf)?;
-        Ok(f32::from_ne_bytes(buf))
+        Ok(f32::from_be_bytes(buf))
     }
 
     pub fn read_double(&mut self) -> std::io::Result<f64> {
         let mut buf = [0u8; 8];
         self.cursor.read_exact(&mut buf)?;
-        Ok(f64::from_ne_bytes(buf))
+        Ok(f64::from_be_bytes(buf))
     }
 
     pub fn read_bool(&mut self) -> std::io::Result<bool> {
@@ -236,6 +255,7 @@ impl<'a> PacketReader<'a> {
     }
 
     pub fn read_bytes(&mut self, len: usize) -> std::io::Result<Vec<u8>> {
+        self.check_available(len)?;
         let mut buf = vec![0u8; len];
         self.cursor.read_exact(&mut buf)?;
         Ok(buf)
@@ -243,40 +263,71 @@ impl<'a> PacketReader<'a> {
 
     pub fn remaining(&self) -> usize {
         let pos = self.cursor.position() as usize;
-        self.cursor.get_ref().len() - pos
+        self.cursor.get_ref().len().saturating_sub(pos)
     }
-}
 
-// Helper functions for Prefixed Optional encoding
-pub fn write_optional_bytes<A: AsRef<[u8]>>(writer: &mut PacketWriter, data: Option<A>) {
-    match data {
-        Some(bytes) => {
-            let bytes_ref = bytes.as_ref();
-            writer.write_varint(bytes_ref.len() as i32);
-            writer.write_bytes(bytes_ref);
-        }
-        None => {
-            writer.write_varint(-1); // -1 indicates no data
+    fn check_available(&self, len: usize) -> std::io::Result<()> {
+        if len > self.remaining() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("Length {} exceeds the {} bytes left in the packet", len, self.remaining()),
+            ));
         }
+        Ok(())
     }
 }
 
-#[derive(Debug)]
+/// One `minecraft:dimension_type` entry; custom ones are read from the `dimensions` config key
+#[derive(Debug, Clone, PartialEq, Deserialize)]
 pub struct DimensionCompound {
-    name:             &'static str,
-    height:           i32,
-    min_y:            i32,
-    has_skylight:     bool,
-    has_ceiling:      bool,
-    ultrawarm:        bool,
-    natural:          bool,
-    coordinate_scale: f32,
+    /// Entry id, `minecraft:` unless namespaced
+    name:                      String,
+    height:                    i32,
+    min_y:                     i32,
+    #[serde(default = "default_true")]
+    has_skylight:              bool,
+    #[serde(default)]
+    has_ceiling:               bool,
+    #[serde(default)]
+    ultrawarm:                 bool,
+    #[serde(default = "default_true")]
+    natural:                   bool,
+    #[serde(default = "default_coordinate_scale")]
+    coordinate_scale:          f32,
+    /// How bright unlit blocks are, from 0.0 to 1.0
+    #[serde(default)]
+    ambient_light:             f32,
+    /// Sky and fog rendering, e.g. `minecraft:the_nether`
+    #[serde(default = "default_effects")]
+    effects:                   String,
+    /// Block tag that burns forever, e.g. `#minecraft:infiniburn_overworld`
+    #[serde(default = "default_infiniburn")]
+    infiniburn:                String,
+    /// Highest light level monsters spawn at
+    #[serde(default)]
+    monster_spawn_light_level: i32,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_coordinate_scale() -> f32 {
+    1.0
+}
+
+fn default_effects() -> String {
+    "minecraft:overworld".to_string()
+}
+
+fn default_infiniburn() -> String {
+    "#minecraft:infiniburn_overworld".to_string()
 }
 
 impl DimensionCompound {
     #[allow(clippy::too_many_arguments)]
     pub fn new(
-        name: &'static str,
+        name: impl Into<String>,
         height: i32,
         min_y: i32,
         has_skylight: bool,
@@ -284,9 +335,10 @@ impl DimensionCompound {
         ultrawarm: bool,
         natural: bool,
         coordinate_scale: f32,
+        ambient_light: f32,
     ) -> Self {
         Self {
-            name,
+            name: name.into(),
             height,
             min_y,
             has_skylight,
@@ -294,14 +346,118 @@ impl DimensionCompound {
             ultrawarm,
             natural,
             coordinate_scale,
+            ambient_light,
+            effects: default_effects(),
+            infiniburn: default_infiniburn(),
+            monster_spawn_light_level: 0,
         }
     }
+
+    /// Use the sky effects and infiniburn tag of another dimension, e.g. `the_nether`
+    pub fn with_effects(mut self, effects: &str, infiniburn: &str) -> Self {
+        self.effects = format!("minecraft:{}", effects);
+        self.infiniburn = format!("#minecraft:{}", infiniburn);
+        self
+    }
+
+    pub fn with_monster_spawn_light_level(mut self, level: i32) -> Self {
+        self.monster_spawn_light_level = level;
+        self
+    }
+
+    /// Overworld, the nether and the end, which every dimension type registry starts with
+    #[rustfmt::skip]
+    pub fn defaults() -> Vec<Self> {
+        vec![
+            Self::new("overworld", TERRAIN_CHUNK_HEIGHT as i32, WORLD_MIN_Y, true, false, false, true, 1.0, 0.0),
+            Self::new("the_nether", 256, 0, false, true, true, false, 8.0, 0.1)
+                .with_effects("the_nether", "infiniburn_nether")
+                .with_monster_spawn_light_level(7),
+            Self::new("the_end", 256, 0, false, false, false, false, 1.0, 0.0)
+                .with_effects("the_end", "infiniburn_end"),
+        ]
+    }
+
+    /// Registry entry id, e.g. `minecraft:overworld`
+    pub fn key(&self) -> String {
+        if self.name.contains(':') {
+            self.name.clone()
+        } else {
+            format!("minecraft:{}", self.name)
+        }
+    }
+
+    /// Reject dimension types the client would refuse
+    pub fn validate(&self) -> Result<()> {
+        if self.name.is_empty() {
+            return Err(anyhow!("Dimension name is empty"));
+        }
+        validate_identifier(&self.name)?;
+
+        let section = TERRAIN_SECTION_HEIGHT as i32;
+        if self.height <= 0 || self.height % section != 0 || self.min_y % section != 0 {
+            return Err(anyhow!(
+                "Dimension {}: height and min_y must be multiples of {}, got {} and {}",
+                self.name,
+                section,
+                self.height,
+                self.min_y
+            ));
+        }
+        if self.min_y < DIMENSION_MIN_Y || self.min_y + self.height > DIMENSION_MAX_Y {
+            return Err(anyhow!(
+                "Dimension {}: blocks {}..{} fall outside {}..{}",
+                self.name,
+                self.min_y,
+                self.min_y + self.height,
+                DIMENSION_MIN_Y,
+                DIMENSION_MAX_Y
+            ));
+        }
+        if !(0.0..=1.0).contains(&self.ambient_light) {
+            return Err(anyhow!(
+                "Dimension {}: ambient_light must be between 0 and 1, got {}",
+                self.name,
+                self.ambient_light
+            ));
+        }
+        validate_identifier(&self.effects)?;
+        match self.infiniburn.strip_prefix('#') {
+            Some(tag) => validate_identifier(tag)?,
+            None => {
+                return Err(anyhow!(
+                    "Dimension {}: infiniburn must be a block tag starting with '#', got {}",
+                    self.name,
+                    self.infiniburn
+                ));
+            }
+        }
+        if !(0..=15).contains(&self.monster_spawn_light_level) {
+            return Err(anyhow!(
+                "Dimension {}: monster_spawn_light_level must be between 0 and 15, got {}",
+                self.name,
+                self.monster_spawn_light_level
+            ));
+        }
+        if self.coordinate_scale <= 0.0 {
+            return Err(anyhow!(
+                "Dimension {}: coordinate_scale must be positive, got {}",
+                self.name,
+                self.coordinate_scale
+            ));
+        }
+        Ok(())
+    }
 }
 
 pub struct DamageTypeCompound {
-    message_id: &'static str,
-    scaling:    &'static str,
-    exhaustion: f32,
+    message_id:         &'static str,
+    scaling:            &'static str,
+    exhaustion:         f32,
+    /// Hurt sound: `hurt` when left out
+    effects:            Option<&'static str>,
+    /// Death message wording: `default` when left out
+    death_message_type: Option<&'static str>,
 }
 
 impl DamageTypeCompound {
@@ -313,10 +469,79 @@ impl DamageTypeCompound {
             message_id: message_id.as_ref(),
             scaling: scaling.as_ref(),
             exhaustion,
+            effects: None,
+            death_message_type: None,
+        }
+    }
+
+    /// `hurt`, `thorns`, `drowning`, `burning`, `poking` or `freezing`
+    pub fn effects(mut self, effects: &'static str) -> Self {
+        self.effects = Some(effects);
+        self
+    }
+
+    /// `default`, `fall_variants` or `intentional_game_design`
+    pub fn death_message_type(mut self, death_message_type: &'static str) -> Self {
+        self.death_message_type = Some(death_message_type);
+        self
+    }
+}
+
+/// Vanilla's default fog, water and underwater fog colors, shared by every biome we send
+const BIOME_FOG_COLOR: i32 = 12_638_463;
+const BIOME_WATER_COLOR: i32 = 4_159_204;
+const BIOME_WATER_FOG_COLOR: i32 = 329_011;
+
+pub struct BiomeCompound {
+    has_precipitation: bool,
+    temperature:       f32,
+    downfall:          f32,
+    sky_color:         i32,
+}
+
+impl BiomeCompound {
+    pub fn new(has_precipitation: bool, temperature: f32, downfall: f32, sky_color: i32) -> Self {
+        Self {
+            has_precipitation,
+            temperature,
+            downfall,
+            sky_color,
         }
     }
 }
 
+/// Block position packed into a long: 26 bits x, 26 bits z, 12 bits y
+pub fn pack_block_position(x: i32, y: i32, z: i32) -> i64 {
+    ((x as i64 & 0x3FF_FFFF) << 38) | ((z as i64 & 0x3FF_FFFF) << 12) | (y as i64 & 0xFFF)
+}
+
+/// Block coordinates `(x, y, z)` from a packed position; the inverse of `pack_block_position`
+pub fn unpack_block_position(packed: i64) -> (i32, i32, i32) {
+    // Shifting each field up to the top of the i64 and back sign-extends it
+    let x = packed >> 38;
+    let y = packed << 52 >> 52;
+    let z = packed << 26 >> 38;
+    (x as i32, y as i32, z as i32)
+}
+
+/// Plain text component as network NBT (a nameless TAG_String root), as used by chat and disconnect
+pub fn text_component_nbt(text: &str) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(3 + text.len());
+    bytes.put_u8(0x08); // TAG_String
+    put_nbt_string(&mut bytes, text);
+    bytes
+}
+
+/// TAG_String payload: a u16 length, then the text, cut at the last whole character that fits
+fn put_nbt_string(buf: &mut impl BufMut, text: &str) {
+    let mut len = text.len().min(u16::MAX as usize);
+    while !text.is_char_boundary(len) {
+        len -= 1;
+    }
+    buf.put_u16(len as u16);
+    buf.put_slice(&text.as_bytes()[..len]);
+}
+
 // Simple NBT encoder for registry data
 #[derive(Debug)]
 pub struct NBTBuilder {
@@ -336,117 +561,429 @@ impl NBTBuilder {
         }
     }
 
+    /// Start a nameless root compound; add tags with the chained writers and close it with `finish`
+    /// Network NBT, as every packet since 1.20.2 uses: the root tag has no name at all
+    pub fn root_compound() -> Self {
+        let mut builder = Self::new();
+        builder.data.put_u8(0x0A); // TAG_Compound
+        builder
+    }
+
+    fn tag_header(&mut self, tag: u8, name: &str) {
+        self.data.put_u8(tag);
+        self.data.extend_from_slice(&(name.len() as i16).to_be_bytes());
+        self.data.extend_from_slice(name.as_bytes());
+    }
+
+    pub fn byte(mut self, name: &str, value: u8) -> Self {
+        self.tag_header(0x01, name); // TAG_Byte
+        self.data.put_u8(value);
+        self
+    }
+
+    pub fn int(mut self, name: &str, value: i32) -> Self {
+        self.tag_header(0x03, name); // TAG_Int
+        self.data.extend_from_slice(&value.to_be_bytes());
+        self
+    }
+
+    pub fn float(mut self, name: &str, value: f32) -> Self {
+        self.tag_header(0x05, name); // TAG_Float
+        self.data.extend_from_slice(&value.to_be_bytes());
+        self
+    }
+
+    pub fn string(mut self, name: &str, value: &str) -> Self {
+        self.tag_header(0x08, name); // TAG_String
+        put_nbt_string(&mut self.data, value);
+        self
+    }
+
+    /// Open a nested compound; every `begin_compound` needs a matching `end_compound`
+    pub fn begin_compound(mut self, name: &str) -> Self {
+        self.tag_header(0x0A, name); // TAG_Compound
+        self
+    }
+
+    pub fn end_compound(mut self) -> Self {
+        self.data.put_u8(0x00); // TAG_End
+        self
+    }
+
+    /// Close the root compound
+    pub fn finish(self) -> Vec<u8> {
+        self.end_compound().data.to_vec()
+    }
+
     /// Create an empty compound (root compound with no tags)
     pub fn empty_compound() -> Vec<u8> {
-        vec![0x0A, 0x00, 0x00, 0x00] // TAG_Compound, empty name, TAG_End
+        vec![0x0A, 0x00] // TAG_Compound, TAG_End
     }
 
     /// Create a dimension type compound with minimal properties
-    pub fn dimension_compound(dim_comp: DimensionCompound) -> Vec<u8> {
-        let mut bytes = BytesMut::new();
-
-        // TAG_Compound
-        bytes.put_u8(0x0A);
-
-        // Root compound name (empty)
-        bytes.extend_from_slice(&(0i16).to_be_bytes());
-
-        // Helper macro to write NBT tags
-        macro_rules! write_nbt_byte {
-            ($name:expr, $value:expr) => {
-                bytes.put_u8(0x01); // TAG_Byte
-                let name_bytes = $name.as_bytes();
-                bytes.extend_from_slice(&(name_bytes.len() as i16).to_be_bytes());
-                bytes.extend_from_slice(name_bytes);
-                bytes.put_u8($value);
-            };
+    pub fn dimension_compound(dim_comp: &DimensionCompound) -> Vec<u8> {
+        let nether = dim_comp.name.contains("nether");
+        let end = dim_comp.name.contains("end");
+        Self::root_compound()
+            .byte("bed_works", u8::from(!nether && !end))
+            .byte("has_ceiling", u8::from(dim_comp.has_ceiling))
+            .byte("has_skylight", u8::from(dim_comp.has_skylight))
+            .byte("has_raids", u8::from(!end))
+            .int("height", dim_comp.height)
+            .int("logical_height", dim_comp.height)
+            .int("min_y", dim_comp.min_y)
+            .byte("ultrawarm", u8::from(dim_comp.ultrawarm))
+            .byte("natural", u8::from(dim_comp.natural))
+            .float("coordinate_scale", dim_comp.coordinate_scale)
+            .float("ambient_light", dim_comp.ambient_light)
+            .string("effects", &dim_comp.effects)
+            .string("infiniburn", &dim_comp.infiniburn)
+            .int("monster_spawn_light_level", dim_comp.monster_spawn_light_level)
+            .int("monster_spawn_block_light_limit", if nether { 15 } else { 0 })
+            .byte("piglin_safe", 0)
+            .byte("respawn_anchor_works", u8::from(nether))
+            .finish()
+    }
+
+    /// Create a damage type compound
+    pub fn damage_type_compound(dmg_comp: DamageTypeCompound) -> Vec<u8> {
+        let mut builder = Self::root_compound()
+            .float("exhaustion", dmg_comp.exhaustion)
+            .string("message_id", dmg_comp.message_id)
+            .string("scaling", dmg_comp.scaling);
+        if let Some(effects) = dmg_comp.effects {
+            builder = builder.string("effects", effects);
         }
+        if let Some(death_message_type) = dmg_comp.death_message_type {
+            builder = builder.string("death_message_type", death_message_type);
+        }
+        builder.finish()
+    }
+
+    /// Create a worldgen/biome compound with the climate and the `effects` colors the client needs
+    pub fn biome_compound(biome_comp: BiomeCompound) -> Vec<u8> {
+        Self::root_compound()
+            .byte("has_precipitation", u8::from(biome_comp.has_precipitation))
+            .float("temperature", biome_comp.temperature)
+            .float("downfall", biome_comp.downfall)
+            .begin_compound("effects")
+            .int("sky_color", biome_comp.sky_color)
+            .int("fog_color", BIOME_FOG_COLOR)
+            .int("water_color", BIOME_WATER_COLOR)
+            .int("water_fog_color", BIOME_WATER_FOG_COLOR)
+            .end_compound()
+            .finish()
+    }
+}
 
-        macro_rules! write_nbt_int {
-            ($name:expr, $value:expr) => {
-                bytes.put_u8(0x03); // TAG_Int
-                let name_bytes = $name.as_bytes();
-                bytes.extend_from_slice(&(name_bytes.len() as i16).to_be_bytes());
-                bytes.extend_from_slice(name_bytes);
-                bytes.extend_from_slice(&($value as i32).to_be_bytes());
-            };
+/// Decoded network NBT, for tests to check what the builders wrote
+#[cfg(test)]
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Nbt {
+    Byte(u8),
+    Int(i32),
+    Float(f32),
+    String(String),
+    Compound(Vec<(String, Nbt)>),
+}
+
+#[cfg(test)]
+impl Nbt {
+    /// Decode `bytes` as exactly one nameless root compound
+    pub(crate) fn decode(bytes: &[u8]) -> std::io::Result<Self> {
+        let mut reader = PacketReader::new(bytes);
+        let nbt = Self::read(&mut reader)?;
+        match reader.remaining() {
+            0 => Ok(nbt),
+            left => {
+                Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("{} bytes after the root compound", left),
+                ))
+            }
         }
+    }
 
-        macro_rules! write_nbt_float {
-            ($name:expr, $value:expr) => {
-                bytes.put_u8(0x05); // TAG_Float
-                let name_bytes = $name.as_bytes();
-                bytes.extend_from_slice(&(name_bytes.len() as i16).to_be_bytes());
-                bytes.extend_from_slice(name_bytes);
-                bytes.extend_from_slice(&($value as f32).to_be_bytes());
-            };
+    /// Read a nameless root compound from the reader's position, leaving whatever follows it
+    pub(crate) fn read(reader: &mut PacketReader) -> std::io::Result<Self> {
+        match reader.read_byte()? {
+            0x0A => Self::read_payload(reader, 0x0A),
+            tag => {
+                Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("root tag {} is not a compound", tag),
+                ))
+            }
         }
+    }
 
-        // Write all fields
-        write_nbt_byte!(
-            "bed_works",
-            if dim_comp.name.contains("nether") || dim_comp.name.contains("end") {
-                0
-            } else {
-                1
+    fn read_string(reader: &mut PacketReader) -> std::io::Result<String> {
+        let len = reader.read_short()? as u16 as usize;
+        String::from_utf8(reader.read_bytes(len)?)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+    }
+
+    fn read_payload(reader: &mut PacketReader, tag: u8) -> std::io::Result<Self> {
+        Ok(match tag {
+            0x01 => Self::Byte(reader.read_byte()?),
+            0x03 => Self::Int(reader.read_int()?),
+            0x05 => Self::Float(reader.read_float()?),
+            0x08 => Self::String(Self::read_string(reader)?),
+            0x0A => {
+                let mut tags = Vec::new();
+                loop {
+                    let tag = reader.read_byte()?;
+                    if tag == 0x00 {
+                        break Self::Compound(tags);
+                    }
+                    let name = Self::read_string(reader)?;
+                    tags.push((name, Self::read_payload(reader, tag)?));
+                }
             }
-        );
-        write_nbt_byte!("has_ceiling", if dim_comp.has_ceiling { 1 } else { 0 });
-        write_nbt_byte!("has_skylight", if dim_comp.has_skylight { 1 } else { 0 });
-        write_nbt_byte!("has_raids", if dim_comp.name.contains("end") { 0 } else { 1 });
-        write_nbt_int!("height", dim_comp.height);
-        write_nbt_int!("logical_height", dim_comp.height);
-        write_nbt_int!("min_y", dim_comp.min_y);
-        write_nbt_byte!("ultrawarm", if dim_comp.ultrawarm { 1 } else { 0 });
-        write_nbt_byte!("natural", if dim_comp.natural { 1 } else { 0 });
-        write_nbt_float!("coordinate_scale", dim_comp.coordinate_scale);
-        write_nbt_byte!("piglin_safe", 0);
-        write_nbt_byte!("respawn_anchor_works", if dim_comp.name.contains("nether") { 1 } else { 0 });
+            _ => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("unexpected tag {}", tag),
+                ));
+            }
+        })
+    }
 
-        // TAG_End
-        bytes.put_u8(0x00);
+    /// Tag names of a compound, in the order they were written
+    pub(crate) fn keys(&self) -> Vec<&str> {
+        match self {
+            Self::Compound(tags) => tags.iter().map(|(name, _)| name.as_str()).collect(),
+            _ => Vec::new(),
+        }
+    }
 
-        bytes.to_vec()
+    pub(crate) fn get(&self, name: &str) -> Option<&Nbt> {
+        match self {
+            Self::Compound(tags) => tags.iter().find(|(tag, _)| tag == name).map(|(_, value)| value),
+            _ => None,
+        }
     }
 
-    /// Create a damage type compound
-    pub fn damage_type_compound(
-        // message_id: &str, scaling: &str, exhaustion: f32
-        dmg_comp: DamageTypeCompound,
-    ) -> Vec<u8> {
-        let mut bytes = BytesMut::new();
-
-        bytes.put_u8(0x0A); // TAG_Compound
-        bytes.extend_from_slice(&(0i16).to_be_bytes()); // empty root name
-
-        // exhaustion: TAG_Float
-        bytes.put_u8(0x05);
-        let name_bytes = b"exhaustion";
-        bytes.extend_from_slice(&(name_bytes.len() as i16).to_be_bytes());
-        bytes.extend_from_slice(name_bytes);
-        bytes.extend_from_slice(&dmg_comp.exhaustion.to_be_bytes());
-
-        // message_id: TAG_String
-        bytes.put_u8(0x08);
-        let name_bytes = b"message_id";
-        bytes.extend_from_slice(&(name_bytes.len() as i16).to_be_bytes());
-        bytes.extend_from_slice(name_bytes);
-        let value_bytes = dmg_comp.message_id.as_bytes();
-        bytes.extend_from_slice(&(value_bytes.len() as i16).to_be_bytes());
-        bytes.extend_from_slice(value_bytes);
-
-        // scaling: TAG_String
-        bytes.put_u8(0x08);
-        let name_bytes = b"scaling";
-        bytes.extend_from_slice(&(name_bytes.len() as i16).to_be_bytes());
-        bytes.extend_from_slice(name_bytes);
-        let value_bytes = dmg_comp.scaling.as_bytes();
-        bytes.extend_from_slice(&(value_bytes.len() as i16).to_be_bytes());
-        bytes.extend_from_slice(value_bytes);
-
-        // TAG_End
-        bytes.put_u8(0x00);
-
-        bytes.to_vec()
+    pub(crate) fn string(&self, name: &str) -> Option<&str> {
+        match self.get(name)? {
+            Self::String(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pack_block_position() {
+        assert_eq!(pack_block_position(0, 0, 0), 0);
+        assert_eq!(pack_block_position(1, 64, 2), (1 << 38) | (2 << 12) | 64);
+        // Negative coordinates keep only their low bits
+        assert_eq!(pack_block_position(-1, -1, -1), -1);
+        assert_eq!(pack_block_position(0, -64, 0), 0xFC0);
+    }
+
+    #[test]
+    fn test_unpack_block_position() {
+        for (x, y, z) in [
+            (0, 0, 0),
+            (1, 64, 2),
+            (-1, -1, -1),
+            (0, -64, 0),
+            (-33_554_432, 2047, 33_554_431),
+        ] {
+            assert_eq!(unpack_block_position(pack_block_position(x, y, z)), (x, y, z));
+        }
+    }
+
+    #[test]
+    fn test_text_component_nbt() {
+        assert_eq!(text_component_nbt("hi"), vec![0x08, 0x00, 0x02, b'h', b'i']);
+
+        // Too long for a u16 length: cut before the 3-byte character that would straddle the limit
+        let text = format!("{}€", "a".repeat(u16::MAX as usize - 2));
+        let nbt = text_component_nbt(&text);
+        assert_eq!(&nbt[1..3], &(u16::MAX - 2).to_be_bytes());
+        assert_eq!(nbt.len(), 3 + u16::MAX as usize - 2);
+    }
+
+    #[test]
+    fn test_nbt_builder() {
+        let nbt = NBTBuilder::root_compound()
+            .string("id", "a")
+            .begin_compound("inner")
+            .int("n", 2)
+            .end_compound()
+            .finish();
+
+        #[rustfmt::skip]
+        let expected = vec![
+            0x0A,
+            0x08, 0x00, 0x02, b'i', b'd', 0x00, 0x01, b'a',
+            0x0A, 0x00, 0x05, b'i', b'n', b'n', b'e', b'r',
+            0x03, 0x00, 0x01, b'n', 0x00, 0x00, 0x00, 0x02,
+            0x00,
+            0x00,
+        ];
+        assert_eq!(nbt, expected);
+        assert_eq!(NBTBuilder::root_compound().finish(), NBTBuilder::empty_compound());
+    }
+
+    #[test]
+    fn test_dimension_compound_has_required_keys() {
+        let required = [
+            "ambient_light",
+            "effects",
+            "infiniburn",
+            "monster_spawn_light_level",
+            "monster_spawn_block_light_limit",
+            "height",
+            "min_y",
+            "logical_height",
+            "coordinate_scale",
+        ];
+        for dimension in DimensionCompound::defaults() {
+            let nbt = Nbt::decode(&NBTBuilder::dimension_compound(&dimension)).unwrap();
+            for key in required {
+                assert!(nbt.get(key).is_some(), "{} missing {}", dimension.key(), key);
+            }
+        }
+
+        let overworld =
+            Nbt::decode(&NBTBuilder::dimension_compound(&DimensionCompound::defaults()[0])).unwrap();
+        assert_eq!(overworld.string("effects"), Some("minecraft:overworld"));
+        assert_eq!(overworld.string("infiniburn"), Some("#minecraft:infiniburn_overworld"));
+        let nether = Nbt::decode(&NBTBuilder::dimension_compound(&DimensionCompound::defaults()[1])).unwrap();
+        assert_eq!(nether.string("effects"), Some("minecraft:the_nether"));
+        assert_eq!(nether.get("respawn_anchor_works"), Some(&Nbt::Byte(1)));
+    }
+
+    #[test]
+    fn test_damage_type_compound_keys() {
+        let plain =
+            Nbt::decode(&NBTBuilder::damage_type_compound(DamageTypeCompound::new("generic", "always", 0.0)))
+                .unwrap();
+        assert_eq!(plain.keys(), ["exhaustion", "message_id", "scaling"]);
+        assert_eq!(plain.string("message_id"), Some("generic"));
+        assert_eq!(plain.string("scaling"), Some("always"));
+
+        let fall = DamageTypeCompound::new("fall", "when_caused_by_living_non_player", 0.0)
+            .effects("hurt")
+            .death_message_type("fall_variants");
+        let nbt = Nbt::decode(&NBTBuilder::damage_type_compound(fall)).unwrap();
+        assert_eq!(nbt.keys().len(), 5);
+        assert_eq!(nbt.string("effects"), Some("hurt"));
+        assert_eq!(nbt.string("death_message_type"), Some("fall_variants"));
+    }
+
+    #[test]
+    fn test_damage_type_compound_is_network_nbt() {
+        let nbt = NBTBuilder::damage_type_compound(DamageTypeCompound::new("magic", "never", 0.5));
+
+        #[rustfmt::skip]
+        let expected = [
+            &[0x0A][..],
+            &[0x05, 0x00, 0x0A], b"exhaustion", &0.5f32.to_be_bytes(),
+            &[0x08, 0x00, 0x0A], b"message_id", &[0x00, 0x05], b"magic",
+            &[0x08, 0x00, 0x07], b"scaling", &[0x00, 0x05], b"never",
+            &[0x00],
+        ]
+        .concat();
+        assert_eq!(nbt, expected);
+    }
+
+    #[test]
+    fn test_biome_compound_effects() {
+        let nbt = Nbt::decode(&NBTBuilder::biome_compound(BiomeCompound::new(false, 2.0, 0.0, 7))).unwrap();
+        assert_eq!(nbt.keys(), ["has_precipitation", "temperature", "downfall", "effects"]);
+        assert_eq!(nbt.get("temperature"), Some(&Nbt::Float(2.0)));
+        let effects = nbt.get("effects").unwrap();
+        assert_eq!(effects.keys(), ["sky_color", "fog_color", "water_color", "water_fog_color"]);
+        assert_eq!(effects.get("sky_color"), Some(&Nbt::Int(7)));
+    }
+
+    #[test]
+    fn test_numbers_are_big_endian() {
+        let mut writer = PacketWriter::new();
+        writer.write_long(1i64);
+        writer.write_double(1.5f64);
+        let bytes = writer.finish();
+
+        assert_eq!(&bytes[..8], &1i64.to_be_bytes());
+        assert_eq!(&bytes[8..], &1.5f64.to_be_bytes());
+
+        let mut reader = PacketReader::new(&bytes);
+        assert_eq!(reader.read_long().unwrap(), 1);
+        assert_eq!(reader.read_double().unwrap(), 1.5);
+    }
+
+    #[test]
+    fn test_oversized_lengths_are_rejected() {
+        // A string claiming to be 2 GiB long with three bytes behind it
+        let mut writer = PacketWriter::new();
+        writer.write_varint(i32::MAX);
+        writer.write_bytes(b"abc");
+        let bytes = writer.finish();
+        let err = PacketReader::new(&bytes).read_string().unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+        // Negative lengths
+        let bytes = write_varint(-1);
+        let err = PacketReader::new(&bytes).read_string().unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+        let mut reader = PacketReader::new(b"abc");
+        assert_eq!(reader.read_bytes(usize::MAX).unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+        assert_eq!(reader.read_bytes(3).unwrap(), b"abc");
+
+        // Array counts account for the size of each element
+        let mut writer = PacketWriter::new();
+        writer.write_varint(3);
+        writer.write_bytes([0u8; 20]);
+        let bytes = writer.finish();
+        assert_eq!(
+            PacketReader::new(&bytes).read_length(16).unwrap_err().kind(),
+            std::io::ErrorKind::InvalidData
+        );
+        assert_eq!(PacketReader::new(&bytes).read_length(4).unwrap(), 3);
+    }
+
+    #[test]
+    fn test_strings_round_trip() {
+        let mut writer = PacketWriter::new();
+        writer.write_string("minecraft:overworld");
+        writer.write_string("");
+        let bytes = writer.finish();
+
+        let mut reader = PacketReader::new(&bytes);
+        assert_eq!(reader.read_string().unwrap(), "minecraft:overworld");
+        assert_eq!(reader.read_string().unwrap(), "");
+        assert_eq!(reader.remaining(), 0);
+    }
+
+    #[test]
+    fn test_prefixed_array() {
+        let mut writer = PacketWriter::new();
+        writer.write_prefixed_array(&[1i16, -1, 256], |writer, &n| writer.write_short(n));
+        assert_eq!(&writer.finish()[..], &[0x03, 0x00, 0x01, 0xFF, 0xFF, 0x01, 0x00]);
+
+        // Counts past 127 take a second varint byte
+        let ids: Vec<i32> = (0..200).collect();
+        let mut writer = PacketWriter::new();
+        writer.write_prefixed_array(&ids, |writer, &id| writer.write_varint(id));
+        let bytes = writer.finish();
+        let mut reader = PacketReader::new(&bytes);
+        assert_eq!(&bytes[..2], &[0xC8, 0x01]);
+        assert_eq!(reader.read_varint().unwrap(), 200);
+        for id in ids {
+            assert_eq!(reader.read_varint().unwrap(), id);
+        }
+        assert_eq!(reader.remaining(), 0);
+
+        let mut writer = PacketWriter::new();
+        writer.write_prefixed_array(&[] as &[Uuid], |writer, uuid| writer.write_uuid(uuid));
+        assert_eq!(&writer.finish()[..], &[0x00]);
     }
 }
@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+
+use parking_lot::Mutex;
+
+use crate::config::RateLimitConfig;
+
+/// Idle buckets are only swept once this many addresses are tracked, and at most once a window
+const PRUNE_THRESHOLD: usize = 1024;
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens:      f64,
+    last_refill: Instant,
+}
+
+struct Buckets {
+    by_ip:      HashMap<IpAddr, Bucket>,
+    last_prune: Instant,
+}
+
+/// Token bucket per source IP: each connection takes a token, and tokens refill steadily so an
+/// address may open `connections` per `window`, bursting up to `connections` at once
+pub struct ConnectionRateLimiter {
+    buckets:        Mutex<Buckets>,
+    capacity:       f64,
+    refill_per_sec: f64,
+    window:         Duration,
+}
+
+impl ConnectionRateLimiter {
+    pub fn new(connections: u32, window: Duration) -> Self {
+        let capacity = connections as f64;
+        Self {
+            buckets: Mutex::new(Buckets {
+                by_ip:      HashMap::new(),
+                last_prune: Instant::now(),
+            }),
+            capacity,
+            refill_per_sec: capacity / window.as_secs_f64().max(f64::EPSILON),
+            window,
+        }
+    }
+
+    pub fn from_config(config: &RateLimitConfig) -> Self {
+        Self::new(config.connections, Duration::from_millis(config.window_ms))
+    }
+
+    /// Take a token for a new connection from `ip`, returning false if it is over the limit
+    pub fn check(&self, ip: IpAddr) -> bool {
+        self.check_at(ip, Instant::now())
+    }
+
+    fn check_at(&self, ip: IpAddr, now: Instant) -> bool {
+        let mut buckets = self.buckets.lock();
+        // A bucket takes a window to refill, so sweeping more often would find next to nothing
+        if buckets.by_ip.len() >= PRUNE_THRESHOLD
+            && now.saturating_duration_since(buckets.last_prune) >= self.window
+        {
+            self.prune(&mut buckets.by_ip, now);
+            buckets.last_prune = now;
+        }
+
+        let bucket = buckets.by_ip.entry(ip).or_insert(Bucket {
+            tokens:      self.capacity,
+            last_refill: now,
+        });
+        self.refill(bucket, now);
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn refill(&self, bucket: &mut Bucket, now: Instant) {
+        let elapsed = now.saturating_duration_since(bucket.last_refill).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.refill_per_sec).min(self.capacity);
+        bucket.last_refill = now;
+    }
+
+    /// Forget addresses whose bucket has refilled completely; they'd start full anyway
+    fn prune(&self, buckets: &mut HashMap<IpAddr, Bucket>, now: Instant) {
+        buckets.retain(|_, bucket| {
+            self.refill(bucket, now);
+            bucket.tokens < self.capacity
+        });
+    }
+
+    #[cfg(test)]
+    fn tracked(&self) -> usize {
+        self.buckets.lock().by_ip.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use super::*;
+
+    fn ip(last: u8) -> IpAddr {
+        IpAddr::V4(Ipv4Addr::new(10, 0, 0, last))
+    }
+
+    #[test]
+    fn test_burst_then_reject() {
+        let limiter = ConnectionRateLimiter::new(3, Duration::from_secs(3));
+        let now = Instant::now();
+
+        assert!(limiter.check_at(ip(1), now));
+        assert!(limiter.check_at(ip(1), now));
+        assert!(limiter.check_at(ip(1), now));
+        assert!(!limiter.check_at(ip(1), now));
+
+        // Other addresses have their own bucket
+        assert!(limiter.check_at(ip(2), now));
+    }
+
+    #[test]
+    fn test_tokens_refill_over_time() {
+        let limiter = ConnectionRateLimiter::new(2, Duration::from_secs(2));
+        let start = Instant::now();
+
+        assert!(limiter.check_at(ip(1), start));
+        assert!(limiter.check_at(ip(1), start));
+        assert!(!limiter.check_at(ip(1), start));
+
+        // One token per second
+        assert!(!limiter.check_at(ip(1), start + Duration::from_millis(900)));
+        assert!(limiter.check_at(ip(1), start + Duration::from_millis(1900)));
+        assert!(!limiter.check_at(ip(1), start + Duration::from_millis(1950)));
+
+        // Refill is capped at the burst size
+        let later = start + Duration::from_secs(60);
+        assert!(limiter.check_at(ip(1), later));
+        assert!(limiter.check_at(ip(1), later));
+        assert!(!limiter.check_at(ip(1), later));
+    }
+
+    #[test]
+    fn test_full_buckets_are_pruned() {
+        let limiter = ConnectionRateLimiter::new(1, Duration::from_secs(1));
+        let start = Instant::now();
+
+        for i in 0..PRUNE_THRESHOLD {
+            let addr = IpAddr::V4(Ipv4Addr::from(i as u32));
+            assert!(limiter.check_at(addr, start));
+        }
+        assert_eq!(limiter.tracked(), PRUNE_THRESHOLD);
+
+        // Everyone has refilled by now, so only the new address is kept
+        assert!(limiter.check_at(ip(1), start + Duration::from_secs(5)));
+        assert_eq!(limiter.tracked(), 1);
+    }
+
+    #[test]
+    fn test_prune_runs_at_most_once_a_window() {
+        let limiter = ConnectionRateLimiter::new(1, Duration::from_secs(1));
+        let start = Instant::now();
+
+        for i in 0..PRUNE_THRESHOLD {
+            let addr = IpAddr::V4(Ipv4Addr::from(i as u32));
+            assert!(limiter.check_at(addr, start));
+        }
+        assert!(limiter.check_at(ip(1), start + Duration::from_secs(2)));
+        assert_eq!(limiter.tracked(), 1);
+
+        // Over the threshold again, but the last sweep was less than a window ago
+        for i in 0..PRUNE_THRESHOLD {
+            let addr = IpAddr::V4(Ipv4Addr::from(i as u32));
+            assert!(limiter.check_at(addr, start + Duration::from_secs(2)));
+        }
+        assert!(limiter.check_at(ip(2), start + Duration::from_millis(2500)));
+        assert_eq!(limiter.tracked(), PRUNE_THRESHOLD + 2);
+
+        assert!(limiter.check_at(ip(3), start + Duration::from_secs(4)));
+        assert_eq!(limiter.tracked(), 1);
+    }
+}
@@ -0,0 +1,293 @@
+use std::path::Path;
+
+use anyhow::Result;
+use serde_json::{Value, json};
+use uuid::Uuid;
+
+use crate::config::ServerConfig;
+use crate::consts::STATUS_SAMPLE_MAX_PLAYERS;
+use crate::network::{ProtocolVersion, supported_version_names};
+
+const LEGACY_KICK_PACKET_ID: u8 = 0xFF;
+const FAVICON_PREFIX: &str = "data:image/png;base64,";
+const BASE64_ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+/// Colors `0-9a-f`, formatting `k-o` and reset `r`
+const FORMATTING_CODES: &str = "0123456789abcdefklmnor";
+/// The server list only has room for two lines of MOTD
+const MOTD_MAX_LINES: usize = 2;
+
+/// Turn a configured MOTD into what the client renders
+/// `&` followed by a color or formatting code becomes `§` (any other `&` is kept as is), and
+/// anything past the second line is dropped
+pub fn format_motd(raw: &str) -> String {
+    let mut formatted = String::with_capacity(raw.len());
+    let mut chars = raw.chars().peekable();
+    while let Some(c) = chars.next() {
+        match chars.peek() {
+            Some(&code) if c == '&' && FORMATTING_CODES.contains(code.to_ascii_lowercase()) => {
+                formatted.push('§');
+                formatted.push(code.to_ascii_lowercase());
+                chars.next();
+            }
+            _ => formatted.push(c),
+        }
+    }
+
+    formatted
+        .lines()
+        .take(MOTD_MAX_LINES)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// A player shown when hovering the player count in the server list
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatusPlayer {
+    pub name: String,
+    pub id:   Uuid,
+}
+
+/// Server list entry sent in reply to a Status Request
+#[derive(Debug, Clone)]
+pub struct StatusResponse {
+    pub version_name:   String,
+    pub protocol:       i32,
+    /// MOTD, may contain `§` color codes
+    pub description:    String,
+    pub max_players:    u32,
+    pub online_players: u32,
+    pub sample:         Vec<StatusPlayer>,
+    /// Full `data:image/png;base64,...` URI
+    pub favicon:        Option<String>,
+}
+
+impl StatusResponse {
+    pub fn new<S: Into<String>>(description: S, max_players: u32) -> Self {
+        Self {
+            version_name: supported_version_names(),
+            protocol: ProtocolVersion::newest().protocol(),
+            description: description.into(),
+            max_players,
+            online_players: 0,
+            sample: Vec::new(),
+            favicon: None,
+        }
+    }
+
+    /// Build the response from config, loading the favicon if one is configured
+    /// A missing or unreadable favicon is logged and left out rather than failing the ping
+    pub fn from_config(config: &ServerConfig) -> Self {
+        let response = Self::new(format_motd(&config.motd), config.max_players);
+        match &config.favicon_path {
+            Some(path) => {
+                match response.clone().with_favicon_file(path) {
+                    Ok(with_favicon) => with_favicon,
+                    Err(e) => {
+                        tracing::warn!("[STATUS] Failed to load favicon from {}: {}", path, e);
+                        response
+                    }
+                }
+            }
+            None => response,
+        }
+    }
+
+    /// Set the online count and the hover sample (capped at `STATUS_SAMPLE_MAX_PLAYERS`)
+    pub fn with_players<I>(mut self, online: u32, players: I) -> Self
+    where
+        I: IntoIterator<Item = StatusPlayer>,
+    {
+        self.online_players = online;
+        self.sample = players.into_iter().take(STATUS_SAMPLE_MAX_PLAYERS).collect();
+        self
+    }
+
+    /// Use raw PNG bytes as the favicon
+    pub fn with_favicon_png<A: AsRef<[u8]>>(mut self, png: A) -> Self {
+        self.favicon = Some(format!("{}{}", FAVICON_PREFIX, encode_base64(png.as_ref())));
+        self
+    }
+
+    pub fn with_favicon_file<P: AsRef<Path>>(self, path: P) -> Result<Self> {
+        let png = std::fs::read(path)?;
+        Ok(self.with_favicon_png(png))
+    }
+
+    /// JSON in the shape the client expects for the server list
+    pub fn to_json(&self) -> String {
+        let mut players = json!({
+            "max": self.max_players,
+            "online": self.online_players,
+        });
+        if !self.sample.is_empty() {
+            players["sample"] = self
+                .sample
+                .iter()
+                .map(|p| json!({ "name": p.name, "id": p.id.to_string() }))
+                .collect::<Value>();
+        }
+
+        let mut status = json!({
+            "version": {
+                "name": self.version_name,
+                "protocol": self.protocol,
+            },
+            "players": players,
+            "description": {
+                "text": self.description,
+            },
+        });
+        if let Some(favicon) = &self.favicon {
+            status["favicon"] = Value::String(favicon.clone());
+        }
+
+        status.to_string()
+    }
+
+    /// Legacy (pre-1.7) server list reply: `0xFF`, a big-endian UTF-16 length, then
+    /// `§1\0protocol\0version\0motd\0online\0max` as UTF-16BE
+    pub fn to_legacy_bytes(&self) -> Vec<u8> {
+        let payload = format!(
+            "§1\0{}\0{}\0{}\0{}\0{}",
+            self.protocol, self.version_name, self.description, self.online_players, self.max_players
+        );
+        let units: Vec<u16> = payload.encode_utf16().collect();
+
+        let mut bytes = Vec::with_capacity(3 + units.len() * 2);
+        bytes.push(LEGACY_KICK_PACKET_ID);
+        bytes.extend_from_slice(&(units.len() as u16).to_be_bytes());
+        for unit in units {
+            bytes.extend_from_slice(&unit.to_be_bytes());
+        }
+        bytes
+    }
+}
+
+/// Standard (padded) base64
+fn encode_base64(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+
+    for chunk in bytes.chunks(3) {
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = ((b[0] as u32) << 16) | ((b[1] as u32) << 8) | b[2] as u32;
+
+        out.push(BASE64_ALPHABET[(n >> 18) as usize & 0x3F] as char);
+        out.push(BASE64_ALPHABET[(n >> 12) as usize & 0x3F] as char);
+        out.push(if chunk.len() > 1 {
+            BASE64_ALPHABET[(n >> 6) as usize & 0x3F] as char
+        } else {
+            '='
+        });
+        out.push(if chunk.len() > 2 {
+            BASE64_ALPHABET[n as usize & 0x3F] as char
+        } else {
+            '='
+        });
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_json_shape() {
+        let steve = StatusPlayer {
+            name: "Steve".to_string(),
+            id:   Uuid::nil(),
+        };
+        let status = StatusResponse::new("§aHello", 20)
+            .with_players(1, vec![steve])
+            .with_favicon_png(b"png");
+
+        let json: Value = serde_json::from_str(&status.to_json()).unwrap();
+        assert_eq!(json["version"]["name"], "1.21.7");
+        assert_eq!(json["version"]["protocol"], 772);
+        assert_eq!(json["players"]["max"], 20);
+        assert_eq!(json["players"]["online"], 1);
+        assert_eq!(json["players"]["sample"][0]["name"], "Steve");
+        assert_eq!(json["players"]["sample"][0]["id"], "00000000-0000-0000-0000-000000000000");
+        assert_eq!(json["description"]["text"], "§aHello");
+        assert_eq!(json["favicon"], "data:image/png;base64,cG5n");
+    }
+
+    #[test]
+    fn test_format_motd_translates_codes() {
+        assert_eq!(format_motd("&aHello &lWorld"), "§aHello §lWorld");
+        assert_eq!(format_motd("&AUpper &R"), "§aUpper §r");
+        // Already-translated codes pass through
+        assert_eq!(format_motd("§6Gold"), "§6Gold");
+        // Not a code
+        assert_eq!(format_motd("Fish & Chips &z"), "Fish & Chips &z");
+        assert_eq!(format_motd("trailing &"), "trailing &");
+        assert_eq!(format_motd("&&a"), "&§a");
+    }
+
+    #[test]
+    fn test_format_motd_lines() {
+        assert_eq!(format_motd("&aLine one\n&bLine two"), "§aLine one\n§bLine two");
+        assert_eq!(format_motd("one\r\ntwo"), "one\ntwo");
+        assert_eq!(format_motd("one\ntwo\nthree"), "one\ntwo");
+        assert_eq!(format_motd(""), "");
+
+        let config = ServerConfig {
+            motd: "&cRed\nPlain".to_string(),
+            ..ServerConfig::default()
+        };
+        assert_eq!(StatusResponse::from_config(&config).description, "§cRed\nPlain");
+    }
+
+    #[test]
+    fn test_status_json_omits_absent_favicon_and_sample() {
+        let json: Value = serde_json::from_str(&StatusResponse::new("motd", 20).to_json()).unwrap();
+        let status = json.as_object().unwrap();
+
+        assert!(!status.contains_key("favicon"));
+        assert!(!json["players"].as_object().unwrap().contains_key("sample"));
+    }
+
+    #[test]
+    fn test_sample_is_capped() {
+        let players = (0..50).map(|i| {
+            StatusPlayer {
+                name: format!("player{}", i),
+                id:   Uuid::nil(),
+            }
+        });
+        let status = StatusResponse::new("motd", 100).with_players(50, players);
+
+        assert_eq!(status.online_players, 50);
+        assert_eq!(status.sample.len(), STATUS_SAMPLE_MAX_PLAYERS);
+    }
+
+    #[test]
+    fn test_legacy_bytes() {
+        let bytes = StatusResponse::new("motd", 20)
+            .with_players(3, vec![])
+            .to_legacy_bytes();
+        assert_eq!(bytes[0], 0xFF);
+
+        let len = u16::from_be_bytes([bytes[1], bytes[2]]) as usize;
+        let units: Vec<u16> = bytes[3..]
+            .chunks(2)
+            .map(|c| u16::from_be_bytes([c[0], c[1]]))
+            .collect();
+        assert_eq!(units.len(), len);
+
+        let text = String::from_utf16(&units).unwrap();
+        let fields: Vec<&str> = text.split('\0').collect();
+        assert_eq!(fields, vec!["§1", "772", "1.21.7", "motd", "3", "20"]);
+    }
+
+    #[test]
+    fn test_encode_base64() {
+        assert_eq!(encode_base64(b""), "");
+        assert_eq!(encode_base64(b"f"), "Zg==");
+        assert_eq!(encode_base64(b"fo"), "Zm8=");
+        assert_eq!(encode_base64(b"foo"), "Zm9v");
+        assert_eq!(encode_base64(b"foobar"), "Zm9vYmFy");
+        assert_eq!(encode_base64(&[0xFF, 0xEE, 0x00]), "/+4A");
+    }
+}
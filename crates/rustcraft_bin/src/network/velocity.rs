@@ -0,0 +1,206 @@
+// Velocity "modern" forwarding: the proxy answers a `velocity:player_info` login plugin request with
+// the player's real address and profile, signed with HMAC-SHA256 using the secret shared with the
+// proxy. Without it every player behind the proxy would get an offline UUID and the proxy's IP.
+
+use std::net::IpAddr;
+
+use anyhow::{Result, anyhow};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use uuid::Uuid;
+
+use crate::network::auth::PlayerProfile;
+use crate::network::{LoginHandler, LoginPluginHook, PacketReader};
+
+pub const VELOCITY_CHANNEL: &str = "velocity:player_info";
+/// Forwarding version 1 (`MODERN_DEFAULT`): address, UUID, name and properties, no chat keys
+const MODERN_FORWARDING_VERSION: u8 = 1;
+/// HMAC-SHA256 output, in front of the signed data
+const SIGNATURE_LEN: usize = 32;
+
+const NOT_PROXIED_REASON: &str = "This server requires you to connect with Velocity.";
+const BAD_SIGNATURE_REASON: &str = "Unable to verify player details.";
+
+/// What the proxy tells us about a player
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForwardedPlayer {
+    pub address: IpAddr,
+    pub profile: PlayerProfile,
+}
+
+pub struct VelocityForwarding {
+    secret: Vec<u8>,
+}
+
+impl VelocityForwarding {
+    pub fn new(secret: &str) -> Self {
+        Self {
+            secret: secret.as_bytes().to_vec(),
+        }
+    }
+
+    /// Check the signature on a `velocity:player_info` response and read the player out of it
+    /// Layout: `[signature: 32 bytes]` then, signed, `[version: VarInt][address: String]
+    /// [uuid: UUID][username: String][properties: VarInt count of (name, value, signature?)]`
+    pub fn verify(&self, response: &[u8]) -> Result<ForwardedPlayer> {
+        if response.len() < SIGNATURE_LEN {
+            return Err(anyhow!(BAD_SIGNATURE_REASON));
+        }
+        let (signature, signed) = response.split_at(SIGNATURE_LEN);
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.secret).expect("HMAC takes keys of any length");
+        mac.update(signed);
+        mac.verify_slice(signature)
+            .map_err(|_| anyhow!(BAD_SIGNATURE_REASON))?;
+
+        let mut reader = PacketReader::new(signed);
+        let version = reader.read_varint()?;
+        if version != MODERN_FORWARDING_VERSION as i32 {
+            return Err(anyhow!("Unsupported Velocity forwarding version {}", version));
+        }
+
+        let address = reader
+            .read_string()?
+            .parse()
+            .map_err(|e| anyhow!("Bad forwarded address: {}", e))?;
+        let uuid: Uuid = reader.read_uuid()?;
+        let username = reader.read_string()?;
+        // Signed by the proxy, but still ends up in logs, commands and the tab list
+        if !LoginHandler::is_valid_username(&username) {
+            return Err(anyhow!("Bad forwarded username '{}'", username.escape_debug()));
+        }
+
+        // Skins and capes; not used yet, but read so a malformed payload is rejected
+        let properties = reader.read_length(3)?;
+        for _ in 0..properties {
+            let _name = reader.read_string()?;
+            let _value = reader.read_string()?;
+            if reader.read_bool()? {
+                let _signature = reader.read_string()?;
+            }
+        }
+
+        Ok(ForwardedPlayer {
+            address,
+            profile: PlayerProfile { uuid, username },
+        })
+    }
+}
+
+impl LoginPluginHook for VelocityForwarding {
+    fn channel(&self) -> &str {
+        VELOCITY_CHANNEL
+    }
+
+    fn request_data(&self) -> Vec<u8> {
+        vec![MODERN_FORWARDING_VERSION]
+    }
+
+    fn handle_response(&self, username: &str, data: Option<&[u8]>) -> Result<Option<PlayerProfile>> {
+        // A client connecting directly doesn't know the channel
+        let data = data.ok_or_else(|| anyhow!(NOT_PROXIED_REASON))?;
+        let forwarded = self.verify(data)?;
+        tracing::info!(
+            "[LOGIN] Velocity forwarded '{}' as {} ({}) from {}",
+            username,
+            forwarded.profile.username,
+            forwarded.profile.uuid,
+            forwarded.address
+        );
+        Ok(Some(forwarded.profile))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::{ByteWritable, PacketWriter};
+
+    fn signed_payload(secret: &[u8]) -> Vec<u8> {
+        signed_payload_for(secret, "Alex")
+    }
+
+    fn signed_payload_for(secret: &[u8], username: &str) -> Vec<u8> {
+        let mut writer = PacketWriter::new();
+        writer.write_varint(1);
+        writer.write_string("203.0.113.9");
+        writer.write_uuid(Uuid::from_u128(0x0123_4567_89ab_cdef));
+        writer.write_string(username);
+        writer.write_varint(1);
+        writer.write_string("textures");
+        writer.write_string("e30=");
+        writer.write_bool(true);
+        writer.write_string("c2ln");
+        let signed = writer.finish();
+
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret).unwrap();
+        mac.update(&signed);
+        let mut payload = mac.finalize().into_bytes().to_vec();
+        payload.extend_from_slice(&signed);
+        payload
+    }
+
+    #[test]
+    fn test_known_signature_vector() {
+        // HMAC-SHA256 of "what do ya want for nothing?" under "Jefe" (RFC 4231 test case 2)
+        let mut response = vec![
+            0x5b, 0xdc, 0xc1, 0x46, 0xbf, 0x60, 0x75, 0x4e, 0x6a, 0x04, 0x24, 0x26, 0x08, 0x95, 0x75, 0xc7,
+            0x5a, 0x00, 0x3f, 0x08, 0x9d, 0x27, 0x39, 0x83, 0x9d, 0xec, 0x58, 0xb9, 0x64, 0xec, 0x38, 0x43,
+        ];
+        response.extend_from_slice(b"what do ya want for nothing?");
+
+        // The signature checks out, so it gets as far as the (nonsense) version
+        let err = VelocityForwarding::new("Jefe").verify(&response).unwrap_err();
+        assert!(err.to_string().contains("version"), "{}", err);
+        let err = VelocityForwarding::new("jefe").verify(&response).unwrap_err();
+        assert_eq!(err.to_string(), BAD_SIGNATURE_REASON);
+    }
+
+    #[test]
+    fn test_verify_forwarded_player() {
+        let forwarding = VelocityForwarding::new("s3cret");
+        let payload = signed_payload(b"s3cret");
+
+        let player = forwarding.verify(&payload).unwrap();
+        assert_eq!(player.address, "203.0.113.9".parse::<IpAddr>().unwrap());
+        assert_eq!(player.profile.uuid, Uuid::from_u128(0x0123_4567_89ab_cdef));
+        assert_eq!(player.profile.username, "Alex");
+
+        // Wrong secret, tampered payload, truncated signature
+        assert!(VelocityForwarding::new("other").verify(&payload).is_err());
+        let mut tampered = payload.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(forwarding.verify(&tampered).is_err());
+        assert!(forwarding.verify(&payload[..16]).is_err());
+    }
+
+    #[test]
+    fn test_forwarded_username_is_validated() {
+        let forwarding = VelocityForwarding::new("s3cret");
+        for username in [
+            "",
+            "Seventeen_Letters",
+            "§cAdmin",
+            "Alex Smith",
+            "Alex\n[SERVER] hi",
+        ] {
+            let err = forwarding
+                .verify(&signed_payload_for(b"s3cret", username))
+                .unwrap_err();
+            assert!(err.to_string().starts_with("Bad forwarded username"), "{}", err);
+        }
+        assert!(forwarding.verify(&signed_payload_for(b"s3cret", "A_1")).is_ok());
+    }
+
+    #[test]
+    fn test_direct_connection_is_refused() {
+        let forwarding = VelocityForwarding::new("s3cret");
+        assert_eq!(forwarding.request_data(), vec![1]);
+        let err = forwarding.handle_response("Alex", None).unwrap_err();
+        assert_eq!(err.to_string(), NOT_PROXIED_REASON);
+
+        let profile = forwarding
+            .handle_response("Alex", Some(&signed_payload(b"s3cret")))
+            .unwrap();
+        assert_eq!(profile.unwrap().username, "Alex");
+    }
+}
@@ -0,0 +1,135 @@
+#![allow(dead_code)]
+// Protocol versions the server can negotiate with a client
+// Supporting another version means adding a `ProtocolVersion` variant and a `PacketIdTable` that
+// overrides the ids which moved relative to the 1.21.7 ids in `packet_ids.rs`
+
+use crate::network::{
+    ClientboundConfig,
+    ClientboundLogin,
+    ClientboundPlay,
+    ServerboundConfig,
+    ServerboundPlay,
+};
+
+/// A protocol version a client may log in with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[repr(i32)]
+pub enum ProtocolVersion {
+    V1_21_7 = 772,
+}
+
+const SUPPORTED_VERSIONS: &[ProtocolVersion] = &[ProtocolVersion::V1_21_7];
+
+/// Every version the login handler accepts, oldest first
+pub fn supported_versions() -> &'static [ProtocolVersion] {
+    SUPPORTED_VERSIONS
+}
+
+impl ProtocolVersion {
+    /// The version for a handshake's protocol number, if it is supported
+    pub fn from_protocol(protocol: i32) -> Option<Self> {
+        supported_versions()
+            .iter()
+            .copied()
+            .find(|version| version.protocol() == protocol)
+    }
+
+    pub const fn protocol(self) -> i32 {
+        self as i32
+    }
+
+    /// Game version shown to players, e.g. "1.21.7"
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::V1_21_7 => "1.21.7",
+        }
+    }
+
+    /// Packet ids to use when talking to a client on this version
+    pub fn packet_ids(self) -> &'static dyn PacketIdTable {
+        match self {
+            Self::V1_21_7 => &Protocol772,
+        }
+    }
+
+    pub fn oldest() -> Self {
+        supported_versions()[0]
+    }
+
+    pub fn newest() -> Self {
+        supported_versions()[supported_versions().len() - 1]
+    }
+}
+
+/// "1.21.7", or "1.21.6-1.21.7" once more than one version is supported
+pub fn supported_version_names() -> String {
+    let (oldest, newest) = (ProtocolVersion::oldest(), ProtocolVersion::newest());
+    if oldest == newest {
+        oldest.name().to_string()
+    } else {
+        format!("{}-{}", oldest.name(), newest.name())
+    }
+}
+
+/// Disconnect reason for a client on an unsupported protocol, worded as vanilla does
+pub fn unsupported_version_reason(protocol: i32) -> String {
+    if protocol < ProtocolVersion::oldest().protocol() {
+        format!("Outdated client! Please use {}", supported_version_names())
+    } else {
+        format!("Outdated server! I'm still on {}", supported_version_names())
+    }
+}
+
+/// Packet ids for one protocol version
+/// Defaults return the 1.21.7 ids; a table for another version overrides only what changed
+pub trait PacketIdTable: Send + Sync {
+    fn clientbound_login(&self, packet: ClientboundLogin) -> i32 {
+        packet.id()
+    }
+
+    fn clientbound_config(&self, packet: ClientboundConfig) -> i32 {
+        packet.id()
+    }
+
+    fn serverbound_config(&self, packet: ServerboundConfig) -> i32 {
+        packet.id()
+    }
+
+    fn clientbound_play(&self, packet: ClientboundPlay) -> i32 {
+        packet.id()
+    }
+
+    fn serverbound_play(&self, packet: ServerboundPlay) -> i32 {
+        packet.id()
+    }
+}
+
+/// 1.21.7, which the id enums are written against
+struct Protocol772;
+
+impl PacketIdTable for Protocol772 {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_supported_versions() {
+        for &version in supported_versions() {
+            assert_eq!(ProtocolVersion::from_protocol(version.protocol()), Some(version));
+        }
+        assert_eq!(ProtocolVersion::from_protocol(772), Some(ProtocolVersion::V1_21_7));
+        assert_eq!(ProtocolVersion::from_protocol(771), None);
+        assert_eq!(ProtocolVersion::from_protocol(0), None);
+
+        let ids = ProtocolVersion::V1_21_7.packet_ids();
+        assert_eq!(ids.clientbound_play(ClientboundPlay::Login), 0x2B);
+        assert_eq!(ids.serverbound_config(ServerboundConfig::KnownPacks), 0x07);
+    }
+
+    #[test]
+    fn test_unsupported_version_reason() {
+        assert_eq!(unsupported_version_reason(771), "Outdated client! Please use 1.21.7");
+        assert_eq!(unsupported_version_reason(773), "Outdated server! I'm still on 1.21.7");
+    }
+}
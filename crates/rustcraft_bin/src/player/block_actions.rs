@@ -0,0 +1,515 @@
+use anyhow::{Result, anyhow};
+use tokio::sync::mpsc::UnboundedSender;
+use uuid::Uuid;
+
+use crate::core::EventBus;
+use crate::network::{
+    ByteWritable,
+    ClientboundPlay,
+    PacketReader,
+    PacketWriter,
+    pack_block_position,
+    unpack_block_position,
+};
+use crate::player::inventory::{OFFHAND_SLOT, block_for_item, hotbar_slot};
+use crate::player::spawn_packets::frame;
+use crate::player::{GameMode, Inventory, PlayerRegistry, RegisteredPlayer, Vec3};
+use crate::terrain::{BlockState, BlockType, ChunkPos};
+use crate::world::World;
+
+/// Player Action statuses that can break a block; the rest (cancelling, dropping items, ...) don't
+const STARTED_DIGGING: i32 = 0;
+const FINISHED_DIGGING: i32 = 2;
+
+/// Furthest a player's eyes can be from the center of a block it digs or builds against, a little
+/// past vanilla's creative reach to allow for movement the server hasn't seen yet
+const MAX_REACH: f64 = 6.0;
+const EYE_HEIGHT: f64 = 1.62;
+
+/// Block next to the one clicked on each face, in protocol order: -Y, +Y, -Z, +Z, -X, +X
+const FACE_OFFSETS: [(i32, i32, i32); 6] = [
+    (0, -1, 0),
+    (0, 1, 0),
+    (0, 0, -1),
+    (0, 0, 1),
+    (-1, 0, 0),
+    (1, 0, 0),
+];
+
+/// Queue from the players' tasks to the game loop, which applies block changes
+pub type BlockRequestSender = UnboundedSender<BlockRequest>;
+
+/// What a player is trying to do to a block
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BlockAction {
+    Break,
+    /// Place the item held in `slot`, using one up when `consume` is set (outside creative)
+    Place {
+        block:   BlockState,
+        item_id: i32,
+        slot:    usize,
+        consume: bool,
+    },
+    /// Something the server won't do, such as building out of reach; the client is told what is
+    /// really there, undoing whatever it predicted
+    Refuse,
+}
+
+/// A dig or placement from one player, applied on the game loop so `BlockBreak` and `BlockPlace`
+/// listeners all run on the same thread
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BlockRequest {
+    pub player:   Uuid,
+    pub position: Vec3<i32>,
+    pub action:   BlockAction,
+    /// Echoed back in Acknowledge Block Change so the client can settle its prediction
+    pub sequence: i32,
+}
+
+impl BlockRequest {
+    /// Read a Player Action: status, position, face, sequence
+    ///
+    /// Creative players break a block as soon as they start digging, everyone else when they
+    /// finish; `None` for statuses that don't break anything
+    pub fn from_player_action(player: Uuid, game_mode: GameMode, payload: &[u8]) -> Result<Option<Self>> {
+        let mut reader = PacketReader::new(payload);
+        let status = reader.read_varint()?;
+        let position = reader.read_long()?;
+        let _face = reader.read_byte()?;
+        let sequence = reader.read_varint()?;
+
+        let breaks = match game_mode {
+            GameMode::Creative => status == STARTED_DIGGING,
+            GameMode::Survival => status == FINISHED_DIGGING,
+            GameMode::Adventure | GameMode::Spectator => false,
+        };
+        if !breaks {
+            return Ok(None);
+        }
+        Ok(Some(Self {
+            player,
+            position: Self::unpack(position),
+            action: BlockAction::Break,
+            sequence,
+        }))
+    }
+
+    /// Read a Use Item On: hand, position, face, cursor x/y/z, inside block, world border hit,
+    /// sequence; `held_index` is the hotbar slot the player has selected
+    ///
+    /// The block goes against the clicked face. Items that aren't blocks are refused, which also
+    /// covers using them on blocks, as nothing reacts to that yet
+    pub fn from_use_item_on(
+        player: Uuid,
+        game_mode: GameMode,
+        held_index: u8,
+        inventory: &Inventory,
+        payload: &[u8],
+    ) -> Result<Self> {
+        let mut reader = PacketReader::new(payload);
+        let hand = reader.read_varint()?;
+        let clicked = Self::unpack(reader.read_long()?);
+        let face = reader.read_varint()?;
+        let _cursor = (reader.read_float()?, reader.read_float()?, reader.read_float()?);
+        let _inside_block = reader.read_bool()?;
+        let _world_border_hit = reader.read_bool()?;
+        let sequence = reader.read_varint()?;
+
+        let (dx, dy, dz) = usize::try_from(face)
+            .ok()
+            .and_then(|face| FACE_OFFSETS.get(face))
+            .ok_or_else(|| anyhow!("Unknown block face {}", face))?;
+        let position = Vec3::new(clicked.x + dx, clicked.y + dy, clicked.z + dz);
+
+        let slot = match hand {
+            0 => hotbar_slot(held_index),
+            1 => OFFHAND_SLOT,
+            _ => return Err(anyhow!("Unknown hand {}", hand)),
+        };
+        let can_build = matches!(game_mode, GameMode::Survival | GameMode::Creative);
+        let action = inventory
+            .get(slot)
+            .filter(|_| can_build)
+            .and_then(|stack| {
+                block_for_item(stack.item_id).map(|block| {
+                    BlockAction::Place {
+                        block: block.into(),
+                        item_id: stack.item_id,
+                        slot,
+                        consume: game_mode != GameMode::Creative,
+                    }
+                })
+            })
+            .unwrap_or(BlockAction::Refuse);
+
+        Ok(Self {
+            player,
+            position,
+            action,
+            sequence,
+        })
+    }
+
+    fn unpack(packed: i64) -> Vec3<i32> {
+        let (x, y, z) = unpack_block_position(packed);
+        Vec3::new(x, y, z)
+    }
+
+    /// Whether a player standing at `feet` can reach the block
+    pub fn in_reach(&self, feet: Vec3<f64>) -> bool {
+        let dx = self.position.x as f64 + 0.5 - feet.x;
+        let dy = self.position.y as f64 + 0.5 - (feet.y + EYE_HEIGHT);
+        let dz = self.position.z as f64 + 0.5 - feet.z;
+        dx * dx + dy * dy + dz * dz <= MAX_REACH * MAX_REACH
+    }
+
+    /// Put the block into air or a fluid, if the player still holds the item it is made from
+    fn place(&self, world: &World, events: &EventBus, requester: &RegisteredPlayer) -> Result<bool> {
+        let BlockAction::Place {
+            block,
+            item_id,
+            slot,
+            consume,
+        } = self.action
+        else {
+            return Ok(false);
+        };
+        let Vec3 { x, y, z } = self.position;
+
+        if !matches!(world.get_block(x, y, z)?, BlockType::Air | BlockType::Water | BlockType::Lava) {
+            return Ok(false);
+        }
+        let holding = requester.inventory.read().get(slot).map(|stack| stack.item_id) == Some(item_id);
+        if consume && !holding {
+            return Ok(false);
+        }
+
+        let placed = world.place_block(events, self.player, x, y, z, block)?;
+        if placed && consume {
+            requester.inventory.write().take_one(slot, item_id);
+        }
+        Ok(placed)
+    }
+
+    /// Make the change unless a listener vetoes it, then tell the players watching the chunk about
+    /// it, or the requesting player what is still there, and acknowledge the sequence
+    pub fn apply(self, world: &World, events: &EventBus, players: &PlayerRegistry) {
+        let Some(requester) = players.get(&self.player) else {
+            // Gone since asking; nobody is left to answer
+            return;
+        };
+        let Vec3 { x, y, z } = self.position;
+
+        let changed = match self.action {
+            BlockAction::Break => world.break_block(events, self.player, x, y, z),
+            BlockAction::Place { .. } => self.place(world, events, &requester),
+            BlockAction::Refuse => Ok(false),
+        };
+
+        let changed = changed.unwrap_or_else(|e| {
+            tracing::warn!("[WORLD] Block change at ({}, {}, {}) failed: {}", x, y, z, e);
+            false
+        });
+        if changed {
+            let state = world.get_block_state(x, y, z).unwrap_or(BlockState::AIR);
+            players.send_to_chunk_watchers(
+                ChunkPos::from_block_pos(x, z),
+                &block_update_frame(self.position, state),
+            );
+        } else if let Ok(state) = world.get_block_state(x, y, z) {
+            requester.send(block_update_frame(self.position, state));
+        }
+        requester.send(acknowledge_block_change_frame(self.sequence));
+    }
+}
+
+/// Block Update: the block at `position` is now `state`
+pub fn block_update_frame(position: Vec3<i32>, state: BlockState) -> Vec<u8> {
+    let mut writer = PacketWriter::new();
+
+    writer.write_long(pack_block_position(position.x, position.y, position.z));
+    writer.write_varint(state.state_id());
+
+    frame(ClientboundPlay::BlockUpdate.id(), &writer.finish())
+}
+
+/// Acknowledge Block Change: every change the client predicted up to `sequence` has been handled
+pub fn acknowledge_block_change_frame(sequence: i32) -> Vec<u8> {
+    let mut writer = PacketWriter::new();
+
+    writer.write_varint(sequence);
+
+    frame(ClientboundPlay::AcknowledgeBlockChange.id(), &writer.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use tokio::sync::mpsc::UnboundedReceiver;
+
+    use super::*;
+    use crate::chunk::ChunkStorage;
+    use crate::core::{ChunkGenThreadPool, EventKind, EventResult};
+    use crate::player::Outbound;
+    use crate::player::inventory::ItemStack;
+    use crate::terrain::VoidWorldGenerator;
+
+    const DIRT: i32 = 28;
+
+    fn player_action(status: i32, (x, y, z): (i32, i32, i32), sequence: i32) -> Vec<u8> {
+        let mut writer = PacketWriter::new();
+        writer.write_varint(status);
+        writer.write_long(pack_block_position(x, y, z));
+        writer.write_byte(1);
+        writer.write_varint(sequence);
+        writer.finish().to_vec()
+    }
+
+    fn use_item_on(hand: i32, (x, y, z): (i32, i32, i32), face: i32, sequence: i32) -> Vec<u8> {
+        let mut writer = PacketWriter::new();
+        writer.write_varint(hand);
+        writer.write_long(pack_block_position(x, y, z));
+        writer.write_varint(face);
+        for cursor in [0.5f32, 1.0, 0.5] {
+            writer.write_float(cursor);
+        }
+        writer.write_bool(false);
+        writer.write_bool(false);
+        writer.write_varint(sequence);
+        writer.finish().to_vec()
+    }
+
+    /// A world with one player standing next to the origin, who has the chunk there loaded
+    fn setup() -> (World, PlayerRegistry, Uuid, UnboundedReceiver<Outbound>, std::path::PathBuf) {
+        let world_dir = std::env::temp_dir().join(format!("rustcraft_blocks_{}", Uuid::new_v4()));
+        let chunks = ChunkStorage::with_world_dir(
+            world_dir.clone(),
+            Arc::new(VoidWorldGenerator),
+            Arc::new(ChunkGenThreadPool::new()),
+        )
+        .unwrap();
+        let world = World::new("test", Arc::new(chunks), 7, Vec3::new(0.5, 65.0, 0.5));
+
+        let players = PlayerRegistry::new();
+        let (player, rx) = RegisteredPlayer::test("Steve", 1);
+        player.loaded_chunks.write().insert(ChunkPos::new(0, 0));
+        let uuid = player.uuid;
+        players.register(RegisteredPlayer {
+            in_world: true,
+            ..player
+        });
+        (world, players, uuid, rx, world_dir)
+    }
+
+    fn drain(rx: &mut UnboundedReceiver<Outbound>) -> Vec<Vec<u8>> {
+        std::iter::from_fn(|| rx.try_recv().ok())
+            .filter_map(|message| {
+                match message {
+                    Outbound::Packet(frame) => Some(frame),
+                    Outbound::Kick(_) => None,
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_player_action_breaks_by_game_mode() {
+        let player = Uuid::new_v4();
+        let started = player_action(STARTED_DIGGING, (-3, -60, 17), 4);
+        let finished = player_action(FINISHED_DIGGING, (-3, -60, 17), 5);
+
+        let creative = BlockRequest::from_player_action(player, GameMode::Creative, &started)
+            .unwrap()
+            .unwrap();
+        assert_eq!(creative.position, Vec3::new(-3, -60, 17));
+        assert_eq!(creative.action, BlockAction::Break);
+        assert_eq!(creative.sequence, 4);
+        assert_eq!(BlockRequest::from_player_action(player, GameMode::Creative, &finished).unwrap(), None);
+
+        assert_eq!(BlockRequest::from_player_action(player, GameMode::Survival, &started).unwrap(), None);
+        let survival = BlockRequest::from_player_action(player, GameMode::Survival, &finished)
+            .unwrap()
+            .unwrap();
+        assert_eq!(survival.sequence, 5);
+
+        for mode in [GameMode::Adventure, GameMode::Spectator] {
+            assert_eq!(BlockRequest::from_player_action(player, mode, &started).unwrap(), None);
+            assert_eq!(BlockRequest::from_player_action(player, mode, &finished).unwrap(), None);
+        }
+        assert!(BlockRequest::from_player_action(player, GameMode::Creative, &started[..3]).is_err());
+    }
+
+    #[test]
+    fn test_use_item_on_places_against_the_clicked_face() {
+        let player = Uuid::new_v4();
+        let mut inventory = Inventory::default();
+        inventory
+            .set(hotbar_slot(2), Some(ItemStack::new(DIRT, 3)))
+            .unwrap();
+        inventory.set(OFFHAND_SLOT, Some(ItemStack::new(1, 1))).unwrap();
+
+        let top = use_item_on(0, (3, 64, -5), 1, 9);
+        let request =
+            BlockRequest::from_use_item_on(player, GameMode::Survival, 2, &inventory, &top).unwrap();
+        assert_eq!(request.position, Vec3::new(3, 65, -5));
+        assert_eq!(request.sequence, 9);
+        assert_eq!(
+            request.action,
+            BlockAction::Place {
+                block:   BlockType::Dirt.into(),
+                item_id: DIRT,
+                slot:    hotbar_slot(2),
+                consume: true,
+            }
+        );
+
+        let west = use_item_on(1, (3, 64, -5), 4, 10);
+        let request =
+            BlockRequest::from_use_item_on(player, GameMode::Creative, 2, &inventory, &west).unwrap();
+        assert_eq!(request.position, Vec3::new(2, 64, -5));
+        assert!(matches!(
+            request.action,
+            BlockAction::Place {
+                slot: OFFHAND_SLOT,
+                consume: false,
+                ..
+            }
+        ));
+
+        // An empty hand, or a mode that can't build
+        let request =
+            BlockRequest::from_use_item_on(player, GameMode::Survival, 0, &inventory, &top).unwrap();
+        assert_eq!(request.action, BlockAction::Refuse);
+        let request =
+            BlockRequest::from_use_item_on(player, GameMode::Adventure, 2, &inventory, &top).unwrap();
+        assert_eq!(request.action, BlockAction::Refuse);
+
+        let bad_face = use_item_on(0, (3, 64, -5), 6, 11);
+        assert!(
+            BlockRequest::from_use_item_on(player, GameMode::Survival, 2, &inventory, &bad_face).is_err()
+        );
+        let bad_hand = use_item_on(2, (3, 64, -5), 1, 11);
+        assert!(
+            BlockRequest::from_use_item_on(player, GameMode::Survival, 2, &inventory, &bad_hand).is_err()
+        );
+    }
+
+    #[test]
+    fn test_reach() {
+        let request = BlockRequest {
+            player:   Uuid::new_v4(),
+            position: Vec3::new(0, 64, 0),
+            action:   BlockAction::Break,
+            sequence: 0,
+        };
+        assert!(request.in_reach(Vec3::new(0.5, 64.0, 3.5)));
+        assert!(!request.in_reach(Vec3::new(0.5, 64.0, 8.0)));
+        assert!(!request.in_reach(Vec3::new(0.5, 80.0, 0.5)));
+    }
+
+    #[test]
+    fn test_break_emits_the_event_and_respects_a_veto() {
+        let (world, players, uuid, mut rx, world_dir) = setup();
+        let events = EventBus::new();
+        let position = Vec3::new(1, 64, 2);
+        world.set_block(1, 64, 2, BlockType::Stone).unwrap();
+        let request = BlockRequest {
+            player: uuid,
+            position,
+            action: BlockAction::Break,
+            sequence: 3,
+        };
+
+        let vetoed = Arc::new(std::sync::atomic::AtomicBool::new(true));
+        let veto = Arc::clone(&vetoed);
+        events.subscribe(EventKind::BlockBreak, move |_| {
+            if veto.load(std::sync::atomic::Ordering::SeqCst) {
+                EventResult::Cancel
+            } else {
+                EventResult::Continue
+            }
+        });
+
+        // The client is told the block is still there
+        request.apply(&world, &events, &players);
+        assert_eq!(world.get_block(1, 64, 2).unwrap(), BlockType::Stone);
+        assert_eq!(
+            drain(&mut rx),
+            [
+                block_update_frame(position, BlockType::Stone.into()),
+                acknowledge_block_change_frame(3)
+            ]
+        );
+
+        vetoed.store(false, std::sync::atomic::Ordering::SeqCst);
+        request.apply(&world, &events, &players);
+        assert_eq!(world.get_block(1, 64, 2).unwrap(), BlockType::Air);
+        assert_eq!(
+            drain(&mut rx),
+            [
+                block_update_frame(position, BlockState::AIR),
+                acknowledge_block_change_frame(3)
+            ]
+        );
+
+        let _ = std::fs::remove_dir_all(&world_dir);
+    }
+
+    #[test]
+    fn test_place_uses_up_the_held_item() {
+        let (world, players, uuid, mut rx, world_dir) = setup();
+        let events = EventBus::new();
+        let slot = hotbar_slot(0);
+        players
+            .get(&uuid)
+            .unwrap()
+            .inventory
+            .write()
+            .set(slot, Some(ItemStack::new(DIRT, 1)))
+            .unwrap();
+        let place = |position, sequence| {
+            BlockRequest {
+                player: uuid,
+                position,
+                action: BlockAction::Place {
+                    block: BlockType::Dirt.into(),
+                    item_id: DIRT,
+                    slot,
+                    consume: true,
+                },
+                sequence,
+            }
+        };
+
+        place(Vec3::new(0, 64, 0), 1).apply(&world, &events, &players);
+        assert_eq!(world.get_block(0, 64, 0).unwrap(), BlockType::Dirt);
+        assert_eq!(players.get(&uuid).unwrap().inventory.read().get(slot), None);
+        assert_eq!(
+            drain(&mut rx),
+            [
+                block_update_frame(Vec3::new(0, 64, 0), BlockType::Dirt.into()),
+                acknowledge_block_change_frame(1)
+            ]
+        );
+
+        // Out of dirt, and the spot next to it stays empty
+        place(Vec3::new(1, 64, 0), 2).apply(&world, &events, &players);
+        assert_eq!(world.get_block(1, 64, 0).unwrap(), BlockType::Air);
+
+        // Not into a solid block either
+        players
+            .get(&uuid)
+            .unwrap()
+            .inventory
+            .write()
+            .set(slot, Some(ItemStack::new(DIRT, 1)))
+            .unwrap();
+        world.set_block(2, 64, 0, BlockType::Stone).unwrap();
+        place(Vec3::new(2, 64, 0), 3).apply(&world, &events, &players);
+        assert_eq!(world.get_block(2, 64, 0).unwrap(), BlockType::Stone);
+        assert_eq!(players.get(&uuid).unwrap().inventory.read().get(slot), Some(ItemStack::new(DIRT, 1)));
+
+        let _ = std::fs::remove_dir_all(&world_dir);
+    }
+}
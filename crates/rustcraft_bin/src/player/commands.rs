@@ -0,0 +1,335 @@
+use anyhow::{Result, anyhow};
+
+use crate::network::{
+    ByteWritable,
+    ClientboundPlay,
+    PacketReader,
+    PacketWriter,
+    ServerboundPlay,
+    text_component_nbt,
+};
+use crate::player::Vec3;
+use crate::player::spawn_packets::frame;
+use crate::terrain::ChunkPos;
+use crate::world::Weather;
+
+/// Carries the command without its leading `/`
+const CHAT_COMMAND_PACKET_ID: i32 = ServerboundPlay::ChatCommand.id();
+/// Same leading command string as Chat Command
+const SIGNED_CHAT_COMMAND_PACKET_ID: i32 = ServerboundPlay::SignedChatCommand.id();
+const CHAT_MESSAGE_PACKET_ID: i32 = ServerboundPlay::ChatMessage.id();
+
+/// Vanilla refuses to teleport past these
+const TELEPORT_MAX_HORIZONTAL: f64 = 30_000_000.0;
+const TELEPORT_MAX_VERTICAL: f64 = 20_000_000.0;
+
+/// Commands players can run from chat
+#[derive(Debug, Clone, PartialEq)]
+pub enum PlayerCommand {
+    /// Absolute target, with any `~` offsets already resolved
+    Teleport(Vec3<f64>),
+    Give {
+        item:  String,
+        count: u32,
+    },
+    Weather(Weather),
+    /// Show the world seed
+    Seed,
+    /// Save the world and report what was written; `flush` as for the console's `save-all`
+    SaveAll {
+        flush: bool,
+    },
+    /// Replace a chunk with a freshly generated one, for trying out world generation changes
+    Regen(ChunkPos),
+}
+
+/// Command text (without the `/`) from a chat command packet, or a chat message starting with `/`
+pub fn command_from_packet(packet_id: i32, payload: &[u8]) -> Option<String> {
+    let text = match packet_id {
+        CHAT_COMMAND_PACKET_ID | SIGNED_CHAT_COMMAND_PACKET_ID | CHAT_MESSAGE_PACKET_ID => {
+            PacketReader::new(payload).read_string().ok()?
+        }
+        _ => return None,
+    };
+
+    match packet_id {
+        CHAT_MESSAGE_PACKET_ID => text.strip_prefix('/').map(str::to_string),
+        _ => Some(text),
+    }
+}
+
+/// Text of a plain chat message, `None` for commands and other packets
+pub fn chat_from_packet(packet_id: i32, payload: &[u8]) -> Option<String> {
+    if packet_id != CHAT_MESSAGE_PACKET_ID {
+        return None;
+    }
+    let text = PacketReader::new(payload).read_string().ok()?;
+    (!text.starts_with('/')).then_some(text)
+}
+
+/// Lowercased name of a command, e.g. `tp` for `/TP ~ ~1 ~`; empty for blank input
+pub fn command_name(input: &str) -> String {
+    let input = input.trim();
+    let input = input.strip_prefix('/').unwrap_or(input);
+    input
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .to_ascii_lowercase()
+}
+
+/// Parse a command typed by a player standing at `position`
+/// Errors are worded for the player, since they are sent back as chat
+pub fn parse_player_command(input: &str, position: Vec3<f64>) -> Result<PlayerCommand> {
+    let input = input.trim();
+    let input = input.strip_prefix('/').unwrap_or(input);
+    let mut args = input.split_whitespace();
+
+    match args.next() {
+        Some("tp") | Some("teleport") => {
+            let args: Vec<&str> = args.collect();
+            let [x, y, z] = args[..] else {
+                return Err(anyhow!("Usage: /tp <x> <y> <z>"));
+            };
+
+            Ok(PlayerCommand::Teleport(Vec3::new(
+                parse_coordinate(x, position.x, TELEPORT_MAX_HORIZONTAL)?,
+                parse_coordinate(y, position.y, TELEPORT_MAX_VERTICAL)?,
+                parse_coordinate(z, position.z, TELEPORT_MAX_HORIZONTAL)?,
+            )))
+        }
+        Some("give") => {
+            let item = args
+                .next()
+                .ok_or_else(|| anyhow!("Usage: /give <item> [count]"))?;
+            let count = match args.next() {
+                Some(count) => {
+                    count
+                        .parse::<u32>()
+                        .ok()
+                        .filter(|&c| c > 0)
+                        .ok_or_else(|| anyhow!("Invalid count: {}", count))?
+                }
+                None => 1,
+            };
+            if args.next().is_some() {
+                return Err(anyhow!("Usage: /give <item> [count]"));
+            }
+
+            Ok(PlayerCommand::Give {
+                item: item.to_string(),
+                count,
+            })
+        }
+        Some("weather") => {
+            let args: Vec<&str> = args.collect();
+            let [weather] = args[..] else {
+                return Err(anyhow!("Usage: /weather clear|rain|thunder"));
+            };
+            Ok(PlayerCommand::Weather(weather.parse()?))
+        }
+        Some("seed") => {
+            match args.next() {
+                None => Ok(PlayerCommand::Seed),
+                Some(_) => Err(anyhow!("Usage: /seed")),
+            }
+        }
+        Some("save-all") => {
+            let args: Vec<&str> = args.collect();
+            match args[..] {
+                [] => Ok(PlayerCommand::SaveAll { flush: false }),
+                ["flush"] => Ok(PlayerCommand::SaveAll { flush: true }),
+                _ => Err(anyhow!("Usage: /save-all [flush]")),
+            }
+        }
+        Some("regen") => {
+            let args: Vec<&str> = args.collect();
+            args.join(" ")
+                .parse()
+                .map(PlayerCommand::Regen)
+                .map_err(|_| anyhow!("Usage: /regen <chunk x> <chunk z>"))
+        }
+        Some(other) => Err(anyhow!("Unknown command: /{}", other)),
+        None => Err(anyhow!("Empty command")),
+    }
+}
+
+/// A coordinate, either absolute or `~`/`~offset` relative to `current`
+fn parse_coordinate(arg: &str, current: f64, max: f64) -> Result<f64> {
+    let (base, number) = match arg.strip_prefix('~') {
+        Some("") => (current, "0"),
+        Some(offset) => (current, offset),
+        None => (0.0, arg),
+    };
+    let number = number
+        .parse::<f64>()
+        .map_err(|_| anyhow!("Invalid coordinate: {}", arg))?;
+
+    let value = base + number;
+    if !value.is_finite() || value.abs() > max {
+        return Err(anyhow!("Coordinate out of range: {}", arg));
+    }
+    Ok(value)
+}
+
+/// `/seed` reply, worded like vanilla's
+pub fn seed_message(seed: u64) -> String {
+    format!("Seed: [{}]", seed)
+}
+
+/// System Chat Message shown in the chat box
+pub fn system_chat_frame(text: &str) -> Vec<u8> {
+    let mut writer = PacketWriter::new();
+    writer.write_bytes(text_component_nbt(text));
+    // Overlay: false puts it in chat rather than the action bar
+    writer.write_bool(false);
+
+    frame(ClientboundPlay::SystemChatMessage.id(), &writer.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HERE: Vec3<f64> = Vec3 {
+        x: 10.0,
+        y: 64.0,
+        z: -5.0,
+    };
+
+    #[test]
+    fn test_parse_tp_absolute_and_relative() {
+        assert_eq!(
+            parse_player_command("tp 1 2.5 -3", HERE).unwrap(),
+            PlayerCommand::Teleport(Vec3::new(1.0, 2.5, -3.0))
+        );
+        assert_eq!(
+            parse_player_command("/tp ~ ~10 ~-5", HERE).unwrap(),
+            PlayerCommand::Teleport(Vec3::new(10.0, 74.0, -10.0))
+        );
+    }
+
+    #[test]
+    fn test_parse_tp_bad_syntax() {
+        assert!(parse_player_command("tp", HERE).is_err());
+        assert!(parse_player_command("tp 1 2", HERE).is_err());
+        assert!(parse_player_command("tp 1 2 3 4", HERE).is_err());
+        assert!(parse_player_command("tp one 2 3", HERE).is_err());
+        assert!(parse_player_command("tp ~x 2 3", HERE).is_err());
+        assert!(parse_player_command("tp NaN 2 3", HERE).is_err());
+    }
+
+    #[test]
+    fn test_parse_tp_out_of_range() {
+        assert!(parse_player_command("tp 30000000 64 0", HERE).is_ok());
+        assert!(parse_player_command("tp 30000001 64 0", HERE).is_err());
+        assert!(parse_player_command("tp 0 64 -30000001", HERE).is_err());
+        assert!(parse_player_command("tp 0 20000001 0", HERE).is_err());
+        assert!(parse_player_command("tp ~29999995 64 0", HERE).is_err());
+        assert!(parse_player_command("tp inf 64 0", HERE).is_err());
+    }
+
+    #[test]
+    fn test_parse_give() {
+        assert_eq!(
+            parse_player_command("give minecraft:stone", HERE).unwrap(),
+            PlayerCommand::Give {
+                item:  "minecraft:stone".to_string(),
+                count: 1,
+            }
+        );
+        assert_eq!(
+            parse_player_command("give dirt 64", HERE).unwrap(),
+            PlayerCommand::Give {
+                item:  "dirt".to_string(),
+                count: 64,
+            }
+        );
+        assert!(parse_player_command("give", HERE).is_err());
+        assert!(parse_player_command("give dirt 0", HERE).is_err());
+        assert!(parse_player_command("give dirt -1", HERE).is_err());
+        assert!(parse_player_command("fly", HERE).is_err());
+    }
+
+    #[test]
+    fn test_parse_weather() {
+        assert_eq!(
+            parse_player_command("weather thunder", HERE).unwrap(),
+            PlayerCommand::Weather(Weather::Thunder)
+        );
+        assert_eq!(
+            parse_player_command("/weather clear", HERE).unwrap(),
+            PlayerCommand::Weather(Weather::Clear)
+        );
+        assert!(parse_player_command("weather", HERE).is_err());
+        assert!(parse_player_command("weather snow", HERE).is_err());
+        assert!(parse_player_command("weather rain 600", HERE).is_err());
+    }
+
+    #[test]
+    fn test_seed() {
+        assert_eq!(parse_player_command("seed", HERE).unwrap(), PlayerCommand::Seed);
+        assert_eq!(parse_player_command("/seed", HERE).unwrap(), PlayerCommand::Seed);
+        assert!(parse_player_command("seed 42", HERE).is_err());
+
+        assert_eq!(seed_message(12_345), "Seed: [12345]");
+        assert_eq!(seed_message(u64::MAX), "Seed: [18446744073709551615]");
+    }
+
+    #[test]
+    fn test_parse_save_all() {
+        assert_eq!(parse_player_command("save-all", HERE).unwrap(), PlayerCommand::SaveAll { flush: false });
+        assert_eq!(
+            parse_player_command("/save-all flush", HERE).unwrap(),
+            PlayerCommand::SaveAll { flush: true }
+        );
+        assert!(parse_player_command("save-all now", HERE).is_err());
+        assert!(parse_player_command("save-all flush flush", HERE).is_err());
+    }
+
+    #[test]
+    fn test_parse_regen() {
+        assert_eq!(
+            parse_player_command("regen 3 -5", HERE).unwrap(),
+            PlayerCommand::Regen(ChunkPos::new(3, -5))
+        );
+        assert_eq!(
+            parse_player_command("/regen -1,0", HERE).unwrap(),
+            PlayerCommand::Regen(ChunkPos::new(-1, 0))
+        );
+        for bad in ["regen", "regen 3", "regen 3 4 5", "regen ~ ~"] {
+            assert!(parse_player_command(bad, HERE).is_err(), "{:?}", bad);
+        }
+    }
+
+    #[test]
+    fn test_command_name() {
+        assert_eq!(command_name("tp 0 64 0"), "tp");
+        assert_eq!(command_name("/Weather rain"), "weather");
+        assert_eq!(command_name("   "), "");
+    }
+
+    #[test]
+    fn test_command_from_packet() {
+        let mut writer = PacketWriter::new();
+        writer.write_string("tp 0 64 0");
+        let payload = writer.finish();
+        assert_eq!(command_from_packet(CHAT_COMMAND_PACKET_ID, &payload), Some("tp 0 64 0".to_string()));
+
+        let mut writer = PacketWriter::new();
+        writer.write_string("/tp 0 64 0");
+        let payload = writer.finish();
+        assert_eq!(command_from_packet(CHAT_MESSAGE_PACKET_ID, &payload), Some("tp 0 64 0".to_string()));
+
+        let mut writer = PacketWriter::new();
+        writer.write_string("hello");
+        let payload = writer.finish();
+        assert_eq!(command_from_packet(CHAT_MESSAGE_PACKET_ID, &payload), None);
+        assert_eq!(chat_from_packet(CHAT_MESSAGE_PACKET_ID, &payload), Some("hello".to_string()));
+        assert_eq!(chat_from_packet(CHAT_COMMAND_PACKET_ID, &payload), None);
+
+        let mut writer = PacketWriter::new();
+        writer.write_string("/seed");
+        assert_eq!(chat_from_packet(CHAT_MESSAGE_PACKET_ID, &writer.finish()), None);
+    }
+}
@@ -1,47 +1,77 @@
 use std::sync::Arc;
 
 use anyhow::{Result, anyhow};
+use bytes::BytesMut;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::TcpStream;
 use tokio::sync::Mutex;
 use tracing::debug;
 
 use crate::network::{
+    BiomeCompound,
     ByteWritable,
+    ClientboundConfig,
     DamageTypeCompound,
     DimensionCompound,
+    KnownPack,
     NBTBuilder,
     PacketReader,
     PacketWriter,
+    PluginChannels,
+    PluginMessage,
+    ProtocolVersion,
+    ServerboundConfig,
+    check_packet_length,
+    decode_known_packs,
+    encode_known_packs,
     read_varint,
+    shared_packs,
     write_varint,
 };
+use crate::terrain::Biome;
 
-pub enum ConfigurationAckPacket {
-    ClientInformation = 0x00,
-    ServerboundPluginMessage = 0x01,
-    ServerboundKnownPacks = 0x02,
-    AcknowledgeFinishConfiguration = 0x03,
+/// `(entry id, NBT data)` pairs of one registry; empty data is sent as "from a known pack"
+type RegistryEntries = Vec<(Vec<u8>, Vec<u8>)>;
+
+/// The parts of the client's Client Information the server uses
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientInformation {
+    pub locale:        String,
+    /// Chunks the client wants to render in each direction
+    pub view_distance: u8,
 }
 
-impl From<i32> for ConfigurationAckPacket {
-    fn from(value: i32) -> Self {
-        match value {
-            0x00 => ConfigurationAckPacket::ClientInformation,
-            0x01 => ConfigurationAckPacket::ServerboundPluginMessage,
-            0x02 => ConfigurationAckPacket::ServerboundKnownPacks,
-            0x03 => ConfigurationAckPacket::AcknowledgeFinishConfiguration,
-            _ => panic!("Invalid ConfigurationAckPacket value: {}", value),
-        }
+impl ClientInformation {
+    /// `[locale: String][view distance: Byte]...`; the chat and skin settings after that are ignored
+    pub fn parse(payload: &[u8]) -> Result<Self> {
+        let mut reader = PacketReader::new(payload);
+        Ok(Self {
+            locale:        reader.read_string()?,
+            view_distance: reader.read_byte()?,
+        })
     }
 }
 
+/// What the configuration phase learned about the client
+#[derive(Debug, Default)]
+pub struct ConfigurationOutcome {
+    pub client_information: Option<ClientInformation>,
+    /// Our data packs the client also has; empty if it never answered Known Packs
+    pub shared_packs:       Vec<KnownPack>,
+}
+
 pub struct ConfigurationHandler;
 
 impl ConfigurationHandler {
     /// Handle the Configuration phase after login
-    /// Sends required registry data and finish configuration packet
-    pub async fn handle_configuration(stream: &mut TcpStream) -> Result<()> {
+    /// Offers our known packs, sends required registry data and finish configuration packet, and
+    /// returns the client's settings and shared packs if it sent them
+    pub async fn handle_configuration(
+        stream: &mut TcpStream,
+        plugin_channels: &PluginChannels,
+        protocol: ProtocolVersion,
+        dimensions: &[DimensionCompound],
+    ) -> Result<ConfigurationOutcome> {
         debug!("[CONFIG] Starting configuration phase");
 
         let stream_c = Arc::new(Mutex::new(stream));
@@ -54,11 +84,34 @@ impl ConfigurationHandler {
         //     Self::read_acknowledge_finish_configuration(Arc::clone(&stream_c)),
         // )?;
 
-        Self::send_registry_data(Arc::clone(&stream_c)).await?;
+        let known_packs = vec![KnownPack::core(protocol)];
+        Self::send_known_packs(Arc::clone(&stream_c), &known_packs).await?;
+        Self::send_registry_data(Arc::clone(&stream_c), dimensions).await?;
         Self::send_finish_configuration(Arc::clone(&stream_c)).await?;
-        Self::read_acknowledge_finish_configuration(Arc::clone(&stream_c)).await?;
+        let outcome =
+            Self::read_acknowledge_finish_configuration(Arc::clone(&stream_c), plugin_channels, &known_packs)
+                .await?;
 
         debug!("[CONFIG] Configuration phase complete");
+        Ok(outcome)
+    }
+
+    /// Send Clientbound Known Packs, listing the data packs whose registry entries we use
+    /// Registry entries are still sent in full, so the client's answer is only recorded
+    async fn send_known_packs(stream: Arc<Mutex<&mut TcpStream>>, packs: &[KnownPack]) -> Result<()> {
+        let packet_data = encode_known_packs(packs);
+        let packet_id = write_varint(ClientboundConfig::KnownPacks.id());
+
+        let mut frame = Vec::new();
+        frame.extend_from_slice(&write_varint((packet_id.len() + packet_data.len()) as i32));
+        frame.extend_from_slice(&packet_id);
+        frame.extend_from_slice(&packet_data);
+
+        let stream = &mut *stream.lock().await;
+        stream.write_all(&frame).await?;
+        stream.flush().await?;
+        debug!("[CONFIG] Sent Known Packs ({} packs)", packs.len());
+
         Ok(())
     }
 
@@ -68,15 +121,11 @@ impl ConfigurationHandler {
     /// - Entries (Prefixed Array):
     ///   - Entry ID (Identifier): The entry name (e.g., "minecraft:overworld")
     ///   - Data (Prefixed Optional NBT): Entry data in NBT format (or null if from known packs)
-    async fn send_registry_data(stream: Arc<Mutex<&mut TcpStream>>) -> Result<()> {
-        // Send minimal required registries for basic functionality
-        // For a full server, you'd need to send ALL synchronized registries
-        let registries = vec![
-            ("minecraft:dimension_type", Self::get_dimension_type_registry()),
-            ("minecraft:damage_type", Self::get_damage_type_registry()),
-        ];
-
-        for (registry_id, entries) in registries {
+    async fn send_registry_data(
+        stream: Arc<Mutex<&mut TcpStream>>,
+        dimensions: &[DimensionCompound],
+    ) -> Result<()> {
+        for (registry_id, entries) in Self::registries(dimensions) {
             Self::send_single_registry(Arc::clone(&stream), registry_id, &entries).await?;
         }
 
@@ -84,19 +133,102 @@ impl ConfigurationHandler {
         Ok(())
     }
 
+    /// Every synchronized registry the client refuses to finish configuration without
+    /// The variant registries only need one entry each, which every mob of that kind then uses
+    /// `dimensions` are the configured dimension types, sent after the vanilla three
+    #[rustfmt::skip]
+    fn registries(dimensions: &[DimensionCompound]) -> Vec<(&'static str, RegistryEntries)> {
+        vec![
+            ("minecraft:dimension_type",     Self::get_dimension_type_registry(dimensions)),
+            ("minecraft:damage_type",        Self::get_damage_type_registry()),
+            ("minecraft:worldgen/biome",     Self::get_biome_registry()),
+            ("minecraft:painting_variant",   Self::single_entry("minecraft:kebab", Self::painting_variant())),
+            ("minecraft:wolf_variant",       Self::single_entry("minecraft:pale", Self::wolf_variant())),
+            ("minecraft:wolf_sound_variant", Self::single_entry("minecraft:classic", Self::wolf_sound_variant())),
+            ("minecraft:cat_variant",        Self::single_entry("minecraft:tabby", Self::mob_variant("cat/tabby", None))),
+            ("minecraft:chicken_variant",    Self::single_entry("minecraft:temperate", Self::mob_variant("chicken/temperate_chicken", Some("normal")))),
+            ("minecraft:cow_variant",        Self::single_entry("minecraft:temperate", Self::mob_variant("cow/temperate_cow", Some("normal")))),
+            ("minecraft:frog_variant",       Self::single_entry("minecraft:temperate", Self::mob_variant("frog/temperate_frog", None))),
+            ("minecraft:pig_variant",        Self::single_entry("minecraft:temperate", Self::mob_variant("pig/temperate_pig", Some("normal")))),
+        ]
+    }
+
+    fn single_entry(entry_id: &str, nbt: Vec<u8>) -> RegistryEntries {
+        vec![(entry_id.into(), nbt)]
+    }
+
+    fn painting_variant() -> Vec<u8> {
+        NBTBuilder::root_compound()
+            .string("asset_id", "minecraft:kebab")
+            .int("width", 1)
+            .int("height", 1)
+            .finish()
+    }
+
+    fn wolf_variant() -> Vec<u8> {
+        NBTBuilder::root_compound()
+            .begin_compound("assets")
+            .string("wild", "minecraft:entity/wolf/wolf")
+            .string("tame", "minecraft:entity/wolf/wolf_tame")
+            .string("angry", "minecraft:entity/wolf/wolf_angry")
+            .end_compound()
+            .finish()
+    }
+
+    fn wolf_sound_variant() -> Vec<u8> {
+        NBTBuilder::root_compound()
+            .string("ambient_sound", "minecraft:entity.wolf.ambient")
+            .string("death_sound", "minecraft:entity.wolf.death")
+            .string("growl_sound", "minecraft:entity.wolf.growl")
+            .string("hurt_sound", "minecraft:entity.wolf.hurt")
+            .string("pant_sound", "minecraft:entity.wolf.pant")
+            .string("whine_sound", "minecraft:entity.wolf.whine")
+            .finish()
+    }
+
+    /// Cat/chicken/cow/frog/pig variant: a texture under `minecraft:entity/`, plus a model for the
+    /// mobs that have more than one
+    fn mob_variant(texture: &str, model: Option<&str>) -> Vec<u8> {
+        let builder =
+            NBTBuilder::root_compound().string("asset_id", &format!("minecraft:entity/{}", texture));
+        match model {
+            Some(model) => builder.string("model", model).finish(),
+            None => builder.finish(),
+        }
+    }
+
     /// Send a single Registry Data packet
     /// Packet Structure (1.21.7):
     /// - Registry ID (String): e.g., "minecraft:dimension_type"
     /// - Entries (VarInt count, then array):
     ///   - Entry ID (String): e.g., "minecraft:overworld"
-    ///   - Data (Optional NBT - Prefixed by length):
-    ///     - Length (-1 for null, otherwise byte count)
-    ///     - NBT Data: The serialized NBT data
+    ///   - Data (Prefixed Optional NBT):
+    ///     - Has data (Boolean): false when the entry comes from a known pack
+    ///     - NBT Data: network NBT, a root compound without a name
     async fn send_single_registry(
         stream: Arc<Mutex<&mut TcpStream>>,
         registry_id: &str,
         entries: &[(Vec<u8>, Vec<u8>)],
     ) -> Result<()> {
+        let packet_data = Self::registry_data_payload(registry_id, entries);
+        let packet_id = write_varint(ClientboundConfig::RegistryData.id());
+
+        // Write packet: [length][id][data]
+        let mut frame = Vec::new();
+        frame.extend_from_slice(&write_varint((packet_id.len() + packet_data.len()) as i32));
+        frame.extend_from_slice(&packet_id);
+        frame.extend_from_slice(&packet_data);
+
+        let stream = &mut *stream.lock().await;
+        stream.write_all(&frame).await?;
+        stream.flush().await?;
+        debug!("[CONFIG] Sent registry data for: {} ({} entries)", registry_id, entries.len());
+
+        Ok(())
+    }
+
+    /// Registry Data payload, without the packet id
+    fn registry_data_payload(registry_id: &str, entries: &[(Vec<u8>, Vec<u8>)]) -> BytesMut {
         let mut writer = PacketWriter::new();
 
         tracing::debug!("[CONFIG] Preparing Registry Data for: {}", registry_id);
@@ -105,89 +237,120 @@ impl ConfigurationHandler {
         // Write Registry ID (as a String identifier)
         writer.write_string(registry_id);
 
-        // Write number of entries
-        writer.write_varint(entries.len() as i32);
-
-        // Write each entry
-        for (entry_id, nbt_data) in entries {
+        // Entry count, then each entry
+        writer.write_prefixed_array(entries, |writer, (entry_id, nbt_data)| {
             // Convert entry_id bytes to string if needed
             let id_str = String::from_utf8_lossy(entry_id).to_string();
 
             // Write Entry ID (as a String identifier)
             writer.write_string(&id_str);
 
-            // Write Data (Prefixed Optional NBT)
-            // Length of NBT data followed by the data itself
-            if nbt_data.is_empty() {
-                // If no data, write -1 to indicate null
-                writer.write_varint(-1);
-            } else {
-                writer.write_varint(nbt_data.len() as i32);
-                writer.write_bytes(nbt_data);
-            }
-        }
-
-        let packet_data = writer.finish();
-        let packet_id = write_varint(0x07); // Registry Data packet ID
+            // Write Data (Prefixed Optional NBT); the NBT carries its own end, so it has no length
+            let nbt_data = (!nbt_data.is_empty()).then_some(nbt_data);
+            writer.write_prefixed_optional(nbt_data, |writer, nbt| writer.write_bytes(nbt));
+        });
 
-        // Write packet: [length][id][data]
-        let mut frame = Vec::new();
-        frame.extend_from_slice(&write_varint((packet_id.len() + packet_data.len()) as i32));
-        frame.extend_from_slice(&packet_id);
-        frame.extend_from_slice(&packet_data);
-
-        let stream = &mut *stream.lock().await;
-        stream.write_all(&frame).await?;
-        stream.flush().await?;
-        debug!("[CONFIG] Sent registry data for: {} ({} entries)", registry_id, entries.len());
-
-        Ok(())
+        writer.finish()
     }
 
-    /// Get the dimension_type registry entries with proper NBT data
-    #[rustfmt::skip]
-    fn get_dimension_type_registry() -> Vec<(Vec<u8>, Vec<u8>)> {
-        let overworld_comp =    DimensionCompound::new("overworld", 384, -64, true, false, false, true, 1.0);
-        let the_nether_comp =   DimensionCompound::new("the_nether", 256, 0, false, true, true, false, 8.0);
-        let the_end_comp =      DimensionCompound::new("the_end", 256, 0, false, false, false, false, 1.0);
-
-        vec![
-            ("minecraft:overworld".into(),  NBTBuilder::dimension_compound(overworld_comp)),
-            ("minecraft:the_nether".into(), NBTBuilder::dimension_compound(the_nether_comp)),
-            ("minecraft:the_end".into(),    NBTBuilder::dimension_compound(the_end_comp)),
-        ]
+    /// Get the dimension_type registry entries with proper NBT data: the defaults, then `custom`
+    fn get_dimension_type_registry(custom: &[DimensionCompound]) -> Vec<(Vec<u8>, Vec<u8>)> {
+        DimensionCompound::defaults()
+            .iter()
+            .chain(custom)
+            .map(|dimension| (dimension.key().into(), NBTBuilder::dimension_compound(dimension)))
+            .collect()
     }
 
-    /// Get the damage_type registry entries with proper NBT data
+    /// Get the damage_type registry entries with proper NBT data: every vanilla damage type, since
+    /// the client looks some of them up by key
     #[rustfmt::skip]
     fn get_damage_type_registry() -> Vec<(Vec<u8>, Vec<u8>)> {
-        let generic_comp =          DamageTypeCompound::new("generic", "when_caused_by_living_non_player", 0.0);
-        let player_attack_comp =    DamageTypeCompound::new("player_attack", "when_caused_by_living_non_player", 0.1);
-        let player_knockback_comp = DamageTypeCompound::new("player_knockback", "when_caused_by_living_non_player", 0.1);
-        let world_border_comp =     DamageTypeCompound::new("world_border", "always", 0.0);
-        let falling_comp =          DamageTypeCompound::new("falling", "when_caused_by_living_non_player", 0.1);
-        let suffocation_comp =      DamageTypeCompound::new("suffocation", "always", 0.0);
-        let drowning_comp =         DamageTypeCompound::new("drowning", "always", 0.0);
-        let starving_comp =         DamageTypeCompound::new("starving", "always", 0.0);
-        let falling_anvil_comp =    DamageTypeCompound::new("falling_anvil", "when_caused_by_living_non_player", 0.1);
+        const LIVING: &str = "when_caused_by_living_non_player";
+        let damage_types = [
+            ("arrow",                 DamageTypeCompound::new("arrow", LIVING, 0.1)),
+            ("bad_respawn_point",     DamageTypeCompound::new("badRespawnPoint", "always", 0.1)
+                .death_message_type("intentional_game_design")),
+            ("cactus",                DamageTypeCompound::new("cactus", LIVING, 0.1)),
+            ("campfire",              DamageTypeCompound::new("inFire", LIVING, 0.1).effects("burning")),
+            ("cramming",              DamageTypeCompound::new("cramming", LIVING, 0.0)),
+            ("dragon_breath",         DamageTypeCompound::new("dragonBreath", LIVING, 0.0)),
+            ("drown",                 DamageTypeCompound::new("drown", LIVING, 0.0).effects("drowning")),
+            ("dry_out",               DamageTypeCompound::new("dryout", LIVING, 0.1)),
+            ("ender_pearl",           DamageTypeCompound::new("fall", LIVING, 0.0).death_message_type("fall_variants")),
+            ("explosion",             DamageTypeCompound::new("explosion", "always", 0.1)),
+            ("fall",                  DamageTypeCompound::new("fall", LIVING, 0.0).death_message_type("fall_variants")),
+            ("falling_anvil",         DamageTypeCompound::new("anvil", LIVING, 0.1)),
+            ("falling_block",         DamageTypeCompound::new("fallingBlock", LIVING, 0.1)),
+            ("falling_stalactite",    DamageTypeCompound::new("fallingStalactite", LIVING, 0.1)),
+            ("fireball",              DamageTypeCompound::new("fireball", LIVING, 0.1).effects("burning")),
+            ("fireworks",             DamageTypeCompound::new("fireworks", LIVING, 0.1)),
+            ("fly_into_wall",         DamageTypeCompound::new("flyIntoWall", LIVING, 0.0)),
+            ("freeze",                DamageTypeCompound::new("freeze", LIVING, 0.0).effects("freezing")),
+            ("generic",               DamageTypeCompound::new("generic", LIVING, 0.0)),
+            ("generic_kill",          DamageTypeCompound::new("genericKill", LIVING, 0.0)),
+            ("hot_floor",             DamageTypeCompound::new("hotFloor", LIVING, 0.1).effects("burning")),
+            ("in_fire",               DamageTypeCompound::new("inFire", LIVING, 0.1).effects("burning")),
+            ("in_wall",               DamageTypeCompound::new("inWall", LIVING, 0.0)),
+            ("indirect_magic",        DamageTypeCompound::new("indirectMagic", LIVING, 0.0)),
+            ("lava",                  DamageTypeCompound::new("lava", LIVING, 0.1).effects("burning")),
+            ("lightning_bolt",        DamageTypeCompound::new("lightningBolt", LIVING, 0.1)),
+            ("mace_smash",            DamageTypeCompound::new("mace_smash", LIVING, 0.1)),
+            ("magic",                 DamageTypeCompound::new("magic", LIVING, 0.0)),
+            ("mob_attack",            DamageTypeCompound::new("mob", LIVING, 0.1)),
+            ("mob_attack_no_aggro",   DamageTypeCompound::new("mob", LIVING, 0.1)),
+            ("mob_projectile",        DamageTypeCompound::new("mob", LIVING, 0.1)),
+            ("on_fire",               DamageTypeCompound::new("onFire", LIVING, 0.0).effects("burning")),
+            ("out_of_world",          DamageTypeCompound::new("outOfWorld", LIVING, 0.0)),
+            ("outside_border",        DamageTypeCompound::new("outsideBorder", LIVING, 0.0)),
+            ("player_attack",         DamageTypeCompound::new("player", LIVING, 0.1)),
+            ("player_explosion",      DamageTypeCompound::new("explosion.player", "always", 0.1)),
+            ("sonic_boom",            DamageTypeCompound::new("sonic_boom", "always", 0.0)),
+            ("spit",                  DamageTypeCompound::new("mob", LIVING, 0.1)),
+            ("stalagmite",            DamageTypeCompound::new("stalagmite", LIVING, 0.0)),
+            ("starve",                DamageTypeCompound::new("starve", LIVING, 0.0)),
+            ("sting",                 DamageTypeCompound::new("sting", LIVING, 0.1)),
+            ("sweet_berry_bush",      DamageTypeCompound::new("sweetBerryBush", LIVING, 0.1).effects("poking")),
+            ("thorns",                DamageTypeCompound::new("thorns", LIVING, 0.1).effects("thorns")),
+            ("thrown",                DamageTypeCompound::new("thrown", LIVING, 0.1)),
+            ("trident",               DamageTypeCompound::new("trident", LIVING, 0.1)),
+            ("unattributed_fireball", DamageTypeCompound::new("onFire", LIVING, 0.1).effects("burning")),
+            ("wind_charge",           DamageTypeCompound::new("mob", LIVING, 0.1)),
+            ("wither",                DamageTypeCompound::new("wither", LIVING, 0.0)),
+            ("wither_skull",          DamageTypeCompound::new("witherSkull", LIVING, 0.1)),
+        ];
 
-        vec![
-            ("minecraft:generic".into(),            NBTBuilder::damage_type_compound(generic_comp)),
-            ("minecraft:player_attack".into(),      NBTBuilder::damage_type_compound(player_attack_comp)),
-            ("minecraft:player_knockback".into(),   NBTBuilder::damage_type_compound(player_knockback_comp)),
-            ("minecraft:world_border".into(),       NBTBuilder::damage_type_compound(world_border_comp)),
-            ("minecraft:falling".into(),            NBTBuilder::damage_type_compound(falling_comp)),
-            ("minecraft:suffocation".into(),        NBTBuilder::damage_type_compound(suffocation_comp)),
-            ("minecraft:drowning".into(),           NBTBuilder::damage_type_compound(drowning_comp)),
-            ("minecraft:starving".into(),           NBTBuilder::damage_type_compound(starving_comp)),
-            ("minecraft:falling_anvil".into(),      NBTBuilder::damage_type_compound(falling_anvil_comp)),
-        ]
+        damage_types
+            .into_iter()
+            .map(|(key, damage_type)| {
+                (format!("minecraft:{}", key).into_bytes(), NBTBuilder::damage_type_compound(damage_type))
+            })
+            .collect()
+    }
+
+    /// Get the worldgen/biome registry entries, one per `Biome`, with vanilla climate and sky colors
+    fn get_biome_registry() -> Vec<(Vec<u8>, Vec<u8>)> {
+        Biome::ALL
+            .iter()
+            .map(|&biome| {
+                let comp = match biome {
+                    Biome::Ocean => BiomeCompound::new(true, 0.5, 0.5, 8_103_167),
+                    Biome::Beach => BiomeCompound::new(true, 0.8, 0.4, 7_907_327),
+                    Biome::Plains => BiomeCompound::new(true, 0.8, 0.4, 7_907_327),
+                    Biome::Forest => BiomeCompound::new(true, 0.7, 0.8, 7_972_607),
+                    Biome::Mountain => BiomeCompound::new(true, 0.2, 0.3, 8_233_727),
+                    Biome::Snow => BiomeCompound::new(true, 0.0, 0.5, 8_364_543),
+                    Biome::SnowMountain => BiomeCompound::new(true, -0.3, 0.9, 8_560_639),
+                    Biome::Desert => BiomeCompound::new(false, 2.0, 0.0, 7_254_527),
+                };
+                (biome.key().into(), NBTBuilder::biome_compound(comp))
+            })
+            .collect()
     }
 
     async fn send_finish_configuration(stream: Arc<Mutex<&mut TcpStream>>) -> Result<()> {
         debug!("[CONFIG] Sending Finish Configuration");
-        // Finish Configuration packet (0x03 in Configuration state)
-        let packet_id = write_varint(0x03);
+        let packet_id = write_varint(ClientboundConfig::FinishConfiguration.id());
 
         // This packet has no payload, just packet ID
         let mut frame = Vec::new();
@@ -202,14 +365,19 @@ impl ConfigurationHandler {
         Ok(())
     }
 
-    async fn read_acknowledge_finish_configuration(stream: Arc<Mutex<&mut TcpStream>>) -> Result<()> {
+    async fn read_acknowledge_finish_configuration(
+        stream: Arc<Mutex<&mut TcpStream>>,
+        plugin_channels: &PluginChannels,
+        known_packs: &[KnownPack],
+    ) -> Result<ConfigurationOutcome> {
         debug!("[CONFIG] Waiting for Acknowledge Finish Configuration");
+        let mut outcome = ConfigurationOutcome::default();
         // Client may send optional packets before Acknowledge Finish Configuration
         // Valid packets in Configuration state (serverbound):
         // 0x00 = Client Information
-        // 0x01 = Serverbound Plugin Message
-        // 0x02 = Serverbound Known Packs
+        // 0x02 = Serverbound Plugin Message
         // 0x03 = Acknowledge Finish Configuration
+        // 0x07 = Serverbound Known Packs
 
         loop {
             let mut length_buf = [0u8; 5];
@@ -245,7 +413,8 @@ impl ConfigurationHandler {
 
             tracing::debug!("[CONFIG] Packet length bytes read: {}", bytes_read);
 
-            let packet_length = read_varint(&mut std::io::Cursor::new(&length_buf[..bytes_read]))? as usize;
+            let packet_length =
+                check_packet_length(read_varint(&mut std::io::Cursor::new(&length_buf[..bytes_read]))?)?;
 
             tracing::debug!("[CONFIG] Packet length: {}", packet_length);
 
@@ -259,27 +428,222 @@ impl ConfigurationHandler {
 
             tracing::debug!("[CONFIG] Received packet ID: 0x{:02X}", packet_id);
 
-            let packet_id_enum: ConfigurationAckPacket = packet_id.into();
-
-            match packet_id_enum {
-                ConfigurationAckPacket::ClientInformation => {
-                    // Client Information - optional, skip it
+            match ServerboundConfig::try_from(packet_id)? {
+                ServerboundConfig::ClientInformation => {
+                    // Client Information - optional; keep it for the view distance
                     debug!("[CONFIG] Received Client Information (0x00)");
+                    match ClientInformation::parse(&reader.read_bytes(reader.remaining())?) {
+                        Ok(information) => outcome.client_information = Some(information),
+                        Err(e) => debug!("[CONFIG] Ignoring malformed Client Information: {}", e),
+                    }
                 }
-                ConfigurationAckPacket::ServerboundPluginMessage => {
-                    // Serverbound Plugin Message - optional, skip it
-                    debug!("[CONFIG] Received Serverbound Plugin Message (0x01)");
+                ServerboundConfig::PluginMessage => {
+                    // Serverbound Plugin Message - answer channels we handle, e.g. the client's brand
+                    let message = PluginMessage::parse(&reader.read_bytes(reader.remaining())?)?;
+                    debug!("[CONFIG] Received Serverbound Plugin Message (0x02) on {}", message.channel);
+                    if let Some(reply) = plugin_channels.dispatch(&message) {
+                        stream
+                            .write_all(&reply.frame(ClientboundConfig::PluginMessage.id()))
+                            .await?;
+                        stream.flush().await?;
+                    }
                 }
-                ConfigurationAckPacket::ServerboundKnownPacks => {
-                    // Serverbound Known Packs - optional, skip it
-                    debug!("[CONFIG] Received Serverbound Known Packs (0x02)");
+                ServerboundConfig::KnownPacks => {
+                    // Serverbound Known Packs - the client's answer to ours
+                    let client_packs = decode_known_packs(&reader.read_bytes(reader.remaining())?)?;
+                    outcome.shared_packs = shared_packs(known_packs, &client_packs);
+                    debug!(
+                        "[CONFIG] Received Serverbound Known Packs (0x07): {} of {} shared",
+                        outcome.shared_packs.len(),
+                        known_packs.len()
+                    );
                 }
-                ConfigurationAckPacket::AcknowledgeFinishConfiguration => {
+                ServerboundConfig::AcknowledgeFinishConfiguration => {
                     // Acknowledge Finish Configuration - this is what we're waiting for
                     debug!("[CONFIG] Acknowledge Finish Configuration received");
-                    return Ok(());
+                    return Ok(outcome);
                 }
             }
         } // end loop
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::Nbt;
+
+    #[test]
+    fn test_mandatory_registries_are_sent() {
+        let mandatory = [
+            "minecraft:dimension_type",
+            "minecraft:damage_type",
+            "minecraft:worldgen/biome",
+            "minecraft:painting_variant",
+            "minecraft:wolf_variant",
+            "minecraft:wolf_sound_variant",
+            "minecraft:cat_variant",
+            "minecraft:chicken_variant",
+            "minecraft:cow_variant",
+            "minecraft:frog_variant",
+            "minecraft:pig_variant",
+        ];
+        let registries = ConfigurationHandler::registries(&[]);
+
+        let mut sent: Vec<_> = registries.iter().map(|(id, _)| *id).collect();
+        sent.sort_unstable();
+        let mut expected = mandatory.to_vec();
+        expected.sort_unstable();
+        assert_eq!(sent, expected);
+
+        for (registry_id, entries) in &registries {
+            assert!(!entries.is_empty(), "{} is empty", registry_id);
+            for (entry_id, nbt) in entries {
+                let decoded = Nbt::decode(nbt);
+                assert!(decoded.is_ok(), "{} {}", registry_id, String::from_utf8_lossy(entry_id));
+            }
+        }
+    }
+
+    #[test]
+    fn test_registry_data_writes_each_entry_once() {
+        let entries = vec![
+            (b"test:first".to_vec(), NBTBuilder::root_compound().int("n", 1).finish()),
+            (b"test:second".to_vec(), Vec::new()),
+            (b"test:third".to_vec(), NBTBuilder::empty_compound()),
+        ];
+        let payload = ConfigurationHandler::registry_data_payload("test:registry", &entries);
+
+        // Exactly [registry id][count][(entry id, nbt)...], with nothing ahead of the registry id
+        let occurrences = |needle: &[u8]| payload.windows(needle.len()).filter(|w| *w == needle).count();
+        assert_eq!(occurrences(b"test:registry"), 1);
+        for (entry_id, _) in &entries {
+            assert_eq!(occurrences(entry_id), 1, "{}", String::from_utf8_lossy(entry_id));
+        }
+
+        let mut reader = PacketReader::new(&payload);
+        assert_eq!(reader.read_string().unwrap(), "test:registry");
+        assert_eq!(reader.read_length(1).unwrap(), entries.len());
+        for (entry_id, nbt) in &entries {
+            assert_eq!(reader.read_string().unwrap().as_bytes(), &entry_id[..]);
+            // A boolean, then nameless network NBT straight after it with no length
+            assert_eq!(reader.read_bool().unwrap(), !nbt.is_empty());
+            if !nbt.is_empty() {
+                assert_eq!(Nbt::read(&mut reader).unwrap(), Nbt::decode(nbt).unwrap());
+            }
+        }
+        assert_eq!(reader.remaining(), 0);
+
+        #[rustfmt::skip]
+        let first = [
+            0x01,
+            0x0A,
+            0x03, 0x00, 0x01, b'n', 0x00, 0x00, 0x00, 0x01,
+            0x00,
+        ];
+        assert!(payload.windows(first.len()).any(|w| w == first));
+        assert!(payload.ends_with(&[0x01, 0x0A, 0x00]));
+    }
+
+    #[test]
+    fn test_custom_dimension_in_registry() {
+        let void = DimensionCompound::new("skyblock_void", 128, -32, true, false, false, false, 1.0, 0.25);
+        let entries = ConfigurationHandler::get_dimension_type_registry(&[void]);
+        let payload = ConfigurationHandler::registry_data_payload("minecraft:dimension_type", &entries);
+
+        let mut reader = PacketReader::new(&payload);
+        assert_eq!(reader.read_string().unwrap(), "minecraft:dimension_type");
+        assert_eq!(reader.read_length(1).unwrap(), 4);
+        let mut ids = Vec::new();
+        let mut nbt = None;
+        for _ in 0..4 {
+            ids.push(reader.read_string().unwrap());
+            assert!(reader.read_bool().unwrap());
+            nbt = Some(Nbt::read(&mut reader).unwrap());
+        }
+        assert_eq!(
+            ids,
+            [
+                "minecraft:overworld",
+                "minecraft:the_nether",
+                "minecraft:the_end",
+                "minecraft:skyblock_void"
+            ]
+        );
+
+        // The custom dimension comes last, with its own fields
+        let nbt = nbt.unwrap();
+        assert_eq!(nbt.get("height"), Some(&Nbt::Int(128)));
+        assert_eq!(nbt.get("min_y"), Some(&Nbt::Int(-32)));
+        assert_eq!(nbt.get("natural"), Some(&Nbt::Byte(0)));
+        assert_eq!(nbt.get("ambient_light"), Some(&Nbt::Float(0.25)));
+    }
+
+    #[test]
+    fn test_damage_type_registry_is_vanilla() {
+        let entries = ConfigurationHandler::get_damage_type_registry();
+        let keys: Vec<_> = entries
+            .iter()
+            .map(|(id, _)| String::from_utf8(id.clone()).unwrap())
+            .collect();
+        assert_eq!(keys.len(), 49);
+        let mut sorted = keys.clone();
+        sorted.sort_unstable();
+        sorted.dedup();
+        assert_eq!(sorted.len(), keys.len());
+        for key in [
+            "minecraft:generic",
+            "minecraft:fall",
+            "minecraft:in_fire",
+            "minecraft:out_of_world",
+        ] {
+            assert!(keys.iter().any(|k| k == key), "{}", key);
+        }
+
+        for (id, nbt) in &entries {
+            let nbt = Nbt::decode(nbt).unwrap();
+            for key in ["message_id", "scaling", "exhaustion"] {
+                assert!(nbt.get(key).is_some(), "{} missing {}", String::from_utf8_lossy(id), key);
+            }
+        }
+    }
+
+    #[test]
+    fn test_biome_registry_has_every_biome() {
+        let entries = ConfigurationHandler::get_biome_registry();
+        let payload = ConfigurationHandler::registry_data_payload("minecraft:worldgen/biome", &entries);
+
+        let mut reader = PacketReader::new(&payload);
+        assert_eq!(reader.read_string().unwrap(), "minecraft:worldgen/biome");
+        assert_eq!(reader.read_length(1).unwrap(), Biome::ALL.len());
+        for (biome, (_, sent)) in Biome::ALL.iter().zip(&entries) {
+            assert_eq!(reader.read_string().unwrap(), biome.key());
+            assert!(reader.read_bool().unwrap());
+
+            // A nameless root compound that is fully consumed by its own TAG_End
+            assert_eq!(Nbt::read(&mut reader).unwrap(), Nbt::decode(sent).unwrap(), "{}", biome.key());
+        }
+        assert_eq!(reader.remaining(), 0);
+        assert!(entries.iter().any(|(id, _)| id == b"minecraft:plains"));
+    }
+
+    #[test]
+    fn test_parse_client_information() {
+        let mut writer = PacketWriter::new();
+        writer.write_string("en_us");
+        writer.write_byte(6u8);
+        // Chat mode, chat colors and the rest are ignored
+        writer.write_varint(0);
+        writer.write_bool(true);
+        let payload = writer.finish();
+
+        assert_eq!(
+            ClientInformation::parse(&payload).unwrap(),
+            ClientInformation {
+                locale:        "en_us".to_string(),
+                view_distance: 6,
+            }
+        );
+        assert!(ClientInformation::parse(&payload[..6]).is_err());
+    }
+}
@@ -5,6 +5,8 @@ use std::time::{SystemTime, UNIX_EPOCH};
 
 use parking_lot::RwLock;
 
+use crate::config::StageTimeouts;
+
 /// Represents the current stage of a player's connection lifecycle
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum ConnectionStage {
@@ -45,6 +47,8 @@ pub struct ConnectionStateTracker {
     connected_at:     u64,
     /// Unix timestamp (ms) when current stage was entered
     stage_started_at: AtomicU64,
+    /// Every stage entered so far, with the Unix timestamp (ms) it was entered at
+    history:          RwLock<Vec<(ConnectionStage, u64)>>,
 }
 
 impl ConnectionStateTracker {
@@ -54,6 +58,7 @@ impl ConnectionStateTracker {
             current_stage:    RwLock::new(ConnectionStage::Connected),
             connected_at:     now,
             stage_started_at: AtomicU64::new(now),
+            history:          RwLock::new(vec![(ConnectionStage::Connected, now)]),
         }
     }
 
@@ -64,10 +69,13 @@ impl ConnectionStateTracker {
 
     /// Transition to a new connection stage
     pub fn transition(&self, new_stage: ConnectionStage) {
-        let old_stage = self.current_stage();
-        *self.current_stage.write() = new_stage;
-        self.stage_started_at
-            .store(current_timestamp_ms(), Ordering::Release);
+        let now = current_timestamp_ms();
+        let old_stage = {
+            let mut stage = self.current_stage.write();
+            std::mem::replace(&mut *stage, new_stage)
+        };
+        self.stage_started_at.store(now, Ordering::Release);
+        self.history.write().push((new_stage, now));
 
         tracing::info!("[CONNECTION] State transition: {} -> {}", old_stage, new_stage);
     }
@@ -95,6 +103,24 @@ impl ConnectionStateTracker {
         )
     }
 
+    /// Check if the connection has been stuck in a pre-game stage for longer than `max_stage_ms`
+    pub fn is_stalled(&self, max_stage_ms: u64) -> bool {
+        let stage = self.current_stage();
+        stage != ConnectionStage::InGame && self.is_connected() && self.stage_duration_ms() > max_stage_ms
+    }
+
+    /// Get the stage that has exceeded its configured limit, if any
+    pub fn timed_out_stage(&self, timeouts: &StageTimeouts) -> Option<ConnectionStage> {
+        let stage = self.current_stage();
+        let limit = timeouts.limit_for(stage)?;
+        (self.stage_duration_ms() > limit).then_some(stage)
+    }
+
+    /// Get the stages entered so far, oldest first
+    pub fn history(&self) -> Vec<ConnectionStage> {
+        self.history.read().iter().map(|(stage, _)| *stage).collect()
+    }
+
     /// Get detailed state info
     pub fn state_info(&self) -> StateInfo {
         let stage = self.current_stage();
@@ -112,6 +138,22 @@ impl Default for ConnectionStateTracker {
     }
 }
 
+/// Poll the tracker until a pre-game stage exceeds its limit, returning the stage that timed out
+/// Meant to be raced against the connection handler so a stuck login/config read can't hang forever
+pub async fn watch_stage_timeouts(
+    tracker: &ConnectionStateTracker,
+    timeouts: StageTimeouts,
+) -> ConnectionStage {
+    let mut interval =
+        tokio::time::interval(std::time::Duration::from_millis(timeouts.watchdog_interval_ms.max(1)));
+    loop {
+        interval.tick().await;
+        if let Some(stage) = tracker.timed_out_stage(&timeouts) {
+            return stage;
+        }
+    }
+}
+
 /// Snapshot of connection state information
 #[derive(Debug, Clone)]
 pub struct StateInfo {
@@ -156,6 +198,62 @@ mod tests {
         assert!(!tracker.is_connected());
     }
 
+    #[test]
+    fn test_stalled_detection() {
+        let tracker = ConnectionStateTracker::new();
+        tracker.transition(ConnectionStage::Authenticating);
+        std::thread::sleep(std::time::Duration::from_millis(10));
+
+        assert!(tracker.is_stalled(5));
+        assert!(!tracker.is_stalled(10_000));
+
+        // In-game and disconnected connections are never considered stalled
+        tracker.transition(ConnectionStage::InGame);
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        assert!(!tracker.is_stalled(5));
+
+        tracker.transition(ConnectionStage::Disconnected);
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        assert!(!tracker.is_stalled(5));
+    }
+
+    fn short_timeouts() -> StageTimeouts {
+        StageTimeouts {
+            configuring_ms: 5,
+            watchdog_interval_ms: 1,
+            ..StageTimeouts::default()
+        }
+    }
+
+    #[test]
+    fn test_stage_timeout_flags_configuring() {
+        let tracker = ConnectionStateTracker::new();
+        tracker.transition(ConnectionStage::Configuring);
+        assert_eq!(tracker.timed_out_stage(&short_timeouts()), None);
+
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        assert_eq!(tracker.timed_out_stage(&short_timeouts()), Some(ConnectionStage::Configuring));
+
+        // Reaching the game clears the timeout
+        tracker.transition(ConnectionStage::InGame);
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        assert_eq!(tracker.timed_out_stage(&short_timeouts()), None);
+    }
+
+    #[tokio::test]
+    async fn test_watchdog_fires_for_stuck_stage() {
+        let tracker = ConnectionStateTracker::new();
+        tracker.transition(ConnectionStage::Configuring);
+
+        let stage = tokio::time::timeout(
+            std::time::Duration::from_secs(1),
+            watch_stage_timeouts(&tracker, short_timeouts()),
+        )
+        .await
+        .expect("watchdog should fire before the test timeout");
+        assert_eq!(stage, ConnectionStage::Configuring);
+    }
+
     #[test]
     fn test_duration_tracking() {
         let tracker = ConnectionStateTracker::new();
@@ -0,0 +1,182 @@
+use std::sync::Arc;
+
+use crate::core::{Event, EventBus};
+use crate::player::{
+    ConnectionStage,
+    ConnectionStateTracker,
+    EntityIdAllocator,
+    PlayerRegistry,
+    RegisteredPlayer,
+};
+
+/// Cleans up after a connection however its handler exits: `Ok`, `Err`, timeout or panic
+/// A panic only reaches the guard when it unwinds; builds with `panic = "abort"` (the dev profile)
+/// or a backend without unwind cleanup (cranelift) exit before it runs
+///
+/// Dropping the guard removes the player from the registry (despawning it for everyone else),
+/// frees its entity id, remembers where it was standing and what it carried for its next login and
+/// emits `PlayerLeave`
+pub struct DisconnectGuard {
+    connection: Arc<ConnectionStateTracker>,
+    players:    Arc<PlayerRegistry>,
+    entity_ids: Arc<EntityIdAllocator>,
+    events:     Arc<EventBus>,
+}
+
+impl DisconnectGuard {
+    pub fn new(
+        connection: Arc<ConnectionStateTracker>,
+        players: Arc<PlayerRegistry>,
+        entity_ids: Arc<EntityIdAllocator>,
+        events: Arc<EventBus>,
+    ) -> Self {
+        Self {
+            connection,
+            players,
+            entity_ids,
+            events,
+        }
+    }
+}
+
+impl Drop for DisconnectGuard {
+    fn drop(&mut self) {
+        // Status pings and failed logins mark themselves disconnected and never register
+        let closed = self.connection.current_stage() == ConnectionStage::Disconnected;
+        if !closed {
+            self.connection.transition(ConnectionStage::Disconnecting);
+        }
+
+        if let Some(player) = self.players.unregister_connection(&self.connection) {
+            player_left(&self.players, &self.entity_ids, &self.events, player);
+        }
+
+        if !closed {
+            self.connection.transition(ConnectionStage::Disconnected);
+        }
+    }
+}
+
+/// Free an unregistered player's entity id, save its data and emit `PlayerLeave`
+pub fn player_left(
+    players: &PlayerRegistry,
+    entity_ids: &EntityIdAllocator,
+    events: &EventBus,
+    player: RegisteredPlayer,
+) {
+    entity_ids.free(player.entity_id);
+    players.save_player(&player);
+    tracing::info!("[PLAYER] '{}' left at {}", player.username, player.position);
+    events.emit(&Event::PlayerLeave {
+        uuid:     player.uuid,
+        username: player.username,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::{Result, anyhow};
+    use parking_lot::RwLock;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::player::inventory::ItemStack;
+    use crate::player::{Inventory, PlayerStore, Vec2, Vec3};
+
+    struct Fixture {
+        connection: Arc<ConnectionStateTracker>,
+        players:    Arc<PlayerRegistry>,
+        entity_ids: Arc<EntityIdAllocator>,
+        uuid:       Uuid,
+        entity_id:  i32,
+        world_dir:  std::path::PathBuf,
+    }
+
+    impl Drop for Fixture {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.world_dir);
+        }
+    }
+
+    fn fixture() -> Fixture {
+        let connection = Arc::new(ConnectionStateTracker::new());
+        let world_dir = std::env::temp_dir().join(format!("rustcraft_disconnect_{}", Uuid::new_v4()));
+        let players = Arc::new(PlayerRegistry::new().with_store(PlayerStore::new(&world_dir)));
+        let entity_ids = Arc::new(EntityIdAllocator::new());
+        let uuid = Uuid::new_v4();
+        let entity_id = entity_ids.allocate();
+
+        let (player, _) = RegisteredPlayer::test("Steve", entity_id);
+        players.register(RegisteredPlayer {
+            uuid,
+            position: Vec3::new(10.0, 70.0, -4.0),
+            rotation: Vec2::new(45.0, 0.0),
+            in_world: true,
+            connection: Arc::clone(&connection),
+            inventory: Arc::new(RwLock::new(carried())),
+            ..player
+        });
+
+        Fixture {
+            connection,
+            players,
+            entity_ids,
+            uuid,
+            entity_id,
+            world_dir,
+        }
+    }
+
+    fn carried() -> Inventory {
+        let mut inventory = Inventory::default();
+        inventory.add_item(ItemStack::new(1, 12));
+        inventory
+    }
+
+    fn guard(f: &Fixture) -> DisconnectGuard {
+        DisconnectGuard::new(
+            Arc::clone(&f.connection),
+            Arc::clone(&f.players),
+            Arc::clone(&f.entity_ids),
+            Arc::new(EventBus::new()),
+        )
+    }
+
+    fn assert_cleaned_up(f: &Fixture) {
+        assert!(f.players.get(&f.uuid).is_none());
+        assert_eq!(f.entity_ids.allocate(), f.entity_id);
+        let saved = f.players.saved_player(&f.uuid).unwrap();
+        assert_eq!(saved.position, Some((Vec3::new(10.0, 70.0, -4.0), Vec2::new(45.0, 0.0))));
+        assert_eq!(saved.inventory, carried());
+        assert_eq!(f.connection.current_stage(), ConnectionStage::Disconnected);
+    }
+
+    #[tokio::test]
+    async fn test_errored_handler_cleans_up() {
+        let f = fixture();
+
+        async fn handler(_guard: DisconnectGuard) -> Result<()> {
+            Err(anyhow!("client went away"))
+        }
+
+        assert!(handler(guard(&f)).await.is_err());
+        assert_cleaned_up(&f);
+    }
+
+    /// Needs unwinding with cleanup, which is why the test profile builds with llvm
+    #[cfg(panic = "unwind")]
+    #[tokio::test]
+    async fn test_panicking_handler_cleans_up() {
+        let f = fixture();
+        let guard = guard(&f);
+
+        let result = tokio::spawn(async move {
+            let _guard = guard;
+            panic!("handler bug");
+        })
+        .await;
+
+        assert!(result.is_err());
+        assert_cleaned_up(&f);
+    }
+}
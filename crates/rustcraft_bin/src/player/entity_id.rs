@@ -0,0 +1,82 @@
+use std::sync::atomic::{AtomicI32, Ordering};
+
+use parking_lot::Mutex;
+
+/// Hands out unique entity ids, reusing ids freed by disconnected players
+#[derive(Debug)]
+pub struct EntityIdAllocator {
+    next: AtomicI32,
+    free: Mutex<Vec<i32>>,
+}
+
+impl EntityIdAllocator {
+    pub fn new() -> Self {
+        Self {
+            next: AtomicI32::new(1),
+            free: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn allocate(&self) -> i32 {
+        if let Some(id) = self.free.lock().pop() {
+            return id;
+        }
+        self.next.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// Return an id so a later `allocate` can hand it out again
+    pub fn free(&self, id: i32) {
+        let mut free = self.free.lock();
+        if !free.contains(&id) {
+            free.push(id);
+        }
+    }
+}
+
+impl Default for EntityIdAllocator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::sync::Arc;
+
+    use super::*;
+
+    #[test]
+    fn test_allocated_ids_are_unique() {
+        let allocator = Arc::new(EntityIdAllocator::new());
+
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let allocator = Arc::clone(&allocator);
+                std::thread::spawn(move || (0..250).map(|_| allocator.allocate()).collect::<Vec<_>>())
+            })
+            .collect();
+
+        let mut seen = HashSet::new();
+        for handle in handles {
+            for id in handle.join().unwrap() {
+                assert!(seen.insert(id), "entity id {} handed out twice", id);
+            }
+        }
+        assert_eq!(seen.len(), 1000);
+    }
+
+    #[test]
+    fn test_freed_ids_are_reused() {
+        let allocator = EntityIdAllocator::new();
+        let a = allocator.allocate();
+        let b = allocator.allocate();
+        assert_ne!(a, b);
+
+        allocator.free(a);
+        // Double free must not hand the same id to two players
+        allocator.free(a);
+        assert_eq!(allocator.allocate(), a);
+        assert_ne!(allocator.allocate(), a);
+    }
+}
@@ -0,0 +1,150 @@
+use crate::network::{ByteWritable, ClientboundPlay, PacketWriter};
+use crate::player::spawn_packets::{frame, to_angle};
+use crate::player::{Vec2, Vec3};
+
+/// Delta moves are in 1/4096ths of a block
+const DELTA_SCALE: f64 = 4096.0;
+
+/// Relative move `(dx, dy, dz)` in 1/4096ths of a block, or `None` if any axis moved too far to fit a
+/// short (8 blocks) and the absolute position has to be sent instead
+pub fn position_delta(old: Vec3<f64>, new: Vec3<f64>) -> Option<[i16; 3]> {
+    let axis = |old: f64, new: f64| {
+        let delta = (new * DELTA_SCALE).round() as i64 - (old * DELTA_SCALE).round() as i64;
+        i16::try_from(delta).ok()
+    };
+
+    Some([axis(old.x, new.x)?, axis(old.y, new.y)?, axis(old.z, new.z)?])
+}
+
+/// Frames telling other clients an entity moved from `old` to `new`, facing `rotation`
+/// Rotation is only sent when `turned` is set (or with an absolute sync); the head follows the body yaw
+pub fn movement_frames(
+    entity_id: i32,
+    old: Vec3<f64>,
+    new: Vec3<f64>,
+    rotation: Vec2<f32>,
+    turned: bool,
+    on_ground: bool,
+) -> Vec<Vec<u8>> {
+    let mut frames = Vec::with_capacity(2);
+    let moved = old != new;
+    let turned_to = turned.then_some(rotation);
+
+    match (moved, position_delta(old, new), turned_to) {
+        (false, _, None) => return frames,
+        (false, _, Some(rotation)) => {
+            let mut writer = PacketWriter::new();
+            writer.write_varint(entity_id);
+            writer.write_byte(to_angle(rotation.yaw));
+            writer.write_byte(to_angle(rotation.pitch));
+            writer.write_bool(on_ground);
+            frames.push(frame(ClientboundPlay::UpdateEntityRotation.id(), &writer.finish()));
+        }
+        (true, Some([dx, dy, dz]), rotation) => {
+            let mut writer = PacketWriter::new();
+            writer.write_varint(entity_id);
+            writer.write_short(dx);
+            writer.write_short(dy);
+            writer.write_short(dz);
+
+            let packet_id = match rotation {
+                Some(rotation) => {
+                    writer.write_byte(to_angle(rotation.yaw));
+                    writer.write_byte(to_angle(rotation.pitch));
+                    ClientboundPlay::UpdateEntityPositionAndRotation.id()
+                }
+                None => ClientboundPlay::UpdateEntityPosition.id(),
+            };
+            writer.write_bool(on_ground);
+            frames.push(frame(packet_id, &writer.finish()));
+        }
+        (true, None, _) => {
+            let mut writer = PacketWriter::new();
+            writer.write_varint(entity_id);
+            writer.write_double(new.x);
+            writer.write_double(new.y);
+            writer.write_double(new.z);
+
+            // Velocity
+            writer.write_double(0.0);
+            writer.write_double(0.0);
+            writer.write_double(0.0);
+
+            // Yaw and pitch as floats; sync packets always carry the full rotation
+            writer.write_float(rotation.yaw);
+            writer.write_float(rotation.pitch);
+            writer.write_bool(on_ground);
+            frames.push(frame(ClientboundPlay::EntityPositionSync.id(), &writer.finish()));
+        }
+    }
+
+    if turned {
+        let mut writer = PacketWriter::new();
+        writer.write_varint(entity_id);
+        writer.write_byte(to_angle(rotation.yaw));
+        frames.push(frame(ClientboundPlay::SetHeadRotation.id(), &writer.finish()));
+    }
+
+    frames
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_position_delta_encoding() {
+        let origin = Vec3::new(0.0, 64.0, 0.0);
+
+        assert_eq!(position_delta(origin, origin), Some([0, 0, 0]));
+        assert_eq!(position_delta(origin, Vec3::new(1.0, 64.5, -0.25)), Some([4096, 2048, -1024]));
+        // Just under 8 blocks still fits a short
+        assert_eq!(position_delta(origin, Vec3::new(7.99, 64.0, 0.0)), Some([32727, 0, 0]));
+    }
+
+    #[test]
+    fn test_large_moves_need_absolute_position() {
+        let origin = Vec3::new(0.0, 64.0, 0.0);
+
+        assert_eq!(position_delta(origin, Vec3::new(8.0, 64.0, 0.0)), None);
+        assert_eq!(position_delta(origin, Vec3::new(0.0, 64.0, -8.5)), None);
+        assert_eq!(position_delta(origin, Vec3::new(0.0, 64.0, -7.5)), Some([0, 0, -30720]));
+    }
+
+    #[test]
+    fn test_movement_frame_selection() {
+        let origin = Vec3::new(0.0, 64.0, 0.0);
+        let look = Vec2::new(90.0, 0.0);
+        let still = Vec2::new(0.0, 0.0);
+        let packet_ids = |frames: Vec<Vec<u8>>| frames.iter().map(|f| f[1]).collect::<Vec<_>>();
+
+        assert!(movement_frames(1, origin, origin, still, false, true).is_empty());
+        assert_eq!(packet_ids(movement_frames(1, origin, origin, look, true, true)), vec![0x31, 0x4C]);
+        assert_eq!(
+            packet_ids(movement_frames(1, origin, Vec3::new(1.0, 64.0, 0.0), still, false, true)),
+            vec![0x2E]
+        );
+        assert_eq!(
+            packet_ids(movement_frames(1, origin, Vec3::new(1.0, 64.0, 0.0), look, true, true)),
+            vec![0x2F, 0x4C]
+        );
+        assert_eq!(
+            packet_ids(movement_frames(1, origin, Vec3::new(100.0, 64.0, 0.0), still, false, true)),
+            vec![0x1F]
+        );
+    }
+
+    #[test]
+    fn test_delta_move_layout() {
+        let frames = movement_frames(
+            5,
+            Vec3::new(0.0, 64.0, 0.0),
+            Vec3::new(1.0, 64.0, -1.0),
+            Vec2::new(0.0, 0.0),
+            false,
+            true,
+        );
+
+        assert_eq!(frames, vec![vec![0x09, 0x2E, 0x05, 0x10, 0x00, 0x00, 0x00, 0xF0, 0x00, 0x01]]);
+    }
+}
@@ -0,0 +1,137 @@
+use crate::network::{PacketReader, ServerboundPlay};
+
+/// Client Command action asking to respawn after death
+const CLIENT_COMMAND_PERFORM_RESPAWN: i32 = 0;
+
+pub const MAX_HEALTH: f32 = 20.0;
+pub const MAX_FOOD: i32 = 20;
+/// Saturation a freshly spawned player starts with
+pub const SPAWN_SATURATION: f32 = 5.0;
+/// Blocks a player can fall without taking damage
+const SAFE_FALL_DISTANCE: f64 = 3.0;
+
+/// Damage (half hearts) for landing after falling `distance` blocks: one per block past the first 3
+pub fn fall_damage(distance: f64) -> f32 {
+    (distance - SAFE_FALL_DISTANCE).ceil().max(0.0) as f32
+}
+
+/// Whether the packet is a Client Command asking to respawn
+pub fn is_respawn_request(packet_id: i32, payload: &[u8]) -> bool {
+    packet_id == ServerboundPlay::ClientCommand.id()
+        && PacketReader::new(payload).read_varint().ok() == Some(CLIENT_COMMAND_PERFORM_RESPAWN)
+}
+
+/// Health, food and saturation as shown by the client's HUD
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Health {
+    pub health:     f32,
+    pub food:       i32,
+    pub saturation: f32,
+}
+
+impl Default for Health {
+    fn default() -> Self {
+        Self {
+            health:     MAX_HEALTH,
+            food:       MAX_FOOD,
+            saturation: SPAWN_SATURATION,
+        }
+    }
+}
+
+impl Health {
+    pub fn is_dead(&self) -> bool {
+        self.health <= 0.0
+    }
+
+    /// Take `amount` damage, never going below 0
+    pub fn damage(&mut self, amount: f32) {
+        self.health = (self.health - amount).max(0.0);
+    }
+}
+
+/// Follows the `on_ground` flag of movement packets to measure how far a player fell
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FallTracker {
+    /// Highest Y since the player last left the ground
+    peak_y: Option<f64>,
+}
+
+impl FallTracker {
+    /// Record a movement, returning the distance fallen if this is the landing
+    pub fn update(&mut self, y: f64, on_ground: bool) -> Option<f64> {
+        if on_ground {
+            return self.peak_y.take().map(|peak| (peak - y).max(0.0));
+        }
+
+        self.peak_y = Some(self.peak_y.map_or(y, |peak| peak.max(y)));
+        None
+    }
+
+    /// Forget the current fall, e.g. after a teleport or respawn
+    pub fn reset(&mut self) {
+        self.peak_y = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fall_damage() {
+        assert_eq!(fall_damage(0.0), 0.0);
+        assert_eq!(fall_damage(3.0), 0.0);
+        assert_eq!(fall_damage(3.5), 1.0);
+        assert_eq!(fall_damage(4.0), 1.0);
+        assert_eq!(fall_damage(10.0), 7.0);
+        assert_eq!(fall_damage(23.0), MAX_HEALTH);
+    }
+
+    #[test]
+    fn test_landing_detection() {
+        let mut fall = FallTracker::default();
+
+        // Walking around never reports a fall
+        assert_eq!(fall.update(64.0, true), None);
+        assert_eq!(fall.update(64.0, true), None);
+
+        // Jump up to 65.25, then drop down a cliff to 52
+        assert_eq!(fall.update(64.5, false), None);
+        assert_eq!(fall.update(65.25, false), None);
+        assert_eq!(fall.update(60.0, false), None);
+        assert_eq!(fall.update(52.0, true), Some(13.25));
+
+        // The landing is only reported once
+        assert_eq!(fall.update(52.0, true), None);
+
+        // Landing higher than the peak (climbing up) is no fall
+        assert_eq!(fall.update(52.0, false), None);
+        assert_eq!(fall.update(53.0, true), Some(0.0));
+
+        // A teleport mid-fall starts over
+        assert_eq!(fall.update(200.0, false), None);
+        fall.reset();
+        assert_eq!(fall.update(70.0, false), None);
+        assert_eq!(fall.update(69.0, true), Some(1.0));
+    }
+
+    #[test]
+    fn test_health_damage_clamps_at_zero() {
+        let mut health = Health::default();
+        health.damage(7.0);
+        assert_eq!(health.health, 13.0);
+        assert!(!health.is_dead());
+
+        health.damage(100.0);
+        assert_eq!(health.health, 0.0);
+        assert!(health.is_dead());
+    }
+
+    #[test]
+    fn test_respawn_request() {
+        assert!(is_respawn_request(0x0B, &[0x00]));
+        assert!(!is_respawn_request(0x0B, &[0x01])); // request stats
+        assert!(!is_respawn_request(0x0A, &[0x00]));
+    }
+}
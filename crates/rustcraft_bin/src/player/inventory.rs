@@ -0,0 +1,462 @@
+#![allow(dead_code)]
+
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+
+use crate::network::{ByteWritable, ClientboundPlay, PacketReader, PacketWriter};
+use crate::player::GameMode;
+use crate::player::spawn_packets::frame;
+use crate::terrain::{BlockType, block_from_name};
+
+/// Slots in the player inventory window: crafting output and grid, armor, main, hotbar, offhand
+pub const PLAYER_INVENTORY_SLOTS: usize = 46;
+/// Window id of the player's own inventory
+pub const PLAYER_INVENTORY_WINDOW: i32 = 0;
+/// Largest stack of any item; items that stack to less aren't told apart yet
+pub const MAX_STACK_SIZE: u8 = 64;
+
+/// Disconnect reason for a player editing its inventory through creative mode without being in it
+pub const NOT_CREATIVE_REASON: &str = "Creative inventory actions need creative mode";
+
+/// Main inventory, filled after the hotbar
+const MAIN_SLOTS: std::ops::Range<usize> = 9..36;
+/// Hotbar, filled first like vanilla pickups
+const HOTBAR_SLOTS: std::ops::Range<usize> = 36..45;
+/// Second hand, right after the hotbar
+pub const OFFHAND_SLOT: usize = 45;
+
+/// Items `/give` knows by name, with their 1.21.7 `minecraft:item` registry ids
+/// Ids come from the vanilla `registries.json` report, like `BLOCK_STATE_TABLE`
+pub const ITEM_TABLE: [(&str, i32); 8] = [
+    ("minecraft:stone", 1),
+    ("minecraft:grass_block", 27),
+    ("minecraft:dirt", 28),
+    ("minecraft:cobblestone", 35),
+    ("minecraft:oak_planks", 36),
+    ("minecraft:bedrock", 58),
+    ("minecraft:sand", 59),
+    ("minecraft:gravel", 62),
+];
+
+/// Item id for a registry name; the `minecraft:` namespace may be left off
+pub fn item_from_name(name: &str) -> Option<i32> {
+    let name = name.strip_prefix("minecraft:").unwrap_or(name);
+    ITEM_TABLE
+        .iter()
+        .find(|(registry_name, _)| registry_name.strip_prefix("minecraft:") == Some(name))
+        .map(|(_, id)| *id)
+}
+
+/// Block placed by using the item, for items that share a registry name with a block
+pub fn block_for_item(item_id: i32) -> Option<BlockType> {
+    ITEM_TABLE
+        .iter()
+        .find(|(_, id)| *id == item_id)
+        .and_then(|(name, _)| block_from_name(name))
+}
+
+/// Inventory slot of a hotbar position, 0 being the left end
+pub fn hotbar_slot(index: u8) -> usize {
+    HOTBAR_SLOTS.start + index as usize
+}
+
+/// Some number of one item; ids are `minecraft:item` registry ids, with no components yet
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ItemStack {
+    pub item_id: i32,
+    pub count:   u8,
+}
+
+impl ItemStack {
+    pub fn new(item_id: i32, count: u8) -> Self {
+        Self { item_id, count }
+    }
+}
+
+/// Outcome of a Set Creative Mode Slot
+#[derive(Debug)]
+pub enum CreativeSlotResult {
+    /// The slot now holds the item, or is empty
+    Set(usize),
+    /// Slot -1: the item was thrown out of the inventory window
+    Dropped(Option<ItemStack>),
+    /// A slot or stack the inventory can't hold; the client needs resyncing
+    Refused(anyhow::Error),
+    /// Only creative players may conjure items
+    NotCreative,
+}
+
+/// Contents of the player inventory window, numbered as the protocol numbers them
+/// Saved as its filled slots only
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(into = "Vec<SavedSlot>", try_from = "Vec<SavedSlot>")]
+pub struct Inventory {
+    slots: [Option<ItemStack>; PLAYER_INVENTORY_SLOTS],
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedSlot {
+    slot:  usize,
+    #[serde(flatten)]
+    stack: ItemStack,
+}
+
+impl From<Inventory> for Vec<SavedSlot> {
+    fn from(inventory: Inventory) -> Self {
+        inventory
+            .slots
+            .iter()
+            .enumerate()
+            .filter_map(|(slot, stack)| stack.map(|stack| SavedSlot { slot, stack }))
+            .collect()
+    }
+}
+
+impl TryFrom<Vec<SavedSlot>> for Inventory {
+    type Error = anyhow::Error;
+
+    fn try_from(saved: Vec<SavedSlot>) -> Result<Self> {
+        let mut inventory = Self::default();
+        for SavedSlot { slot, stack } in saved {
+            if stack.count > MAX_STACK_SIZE {
+                return Err(anyhow!(
+                    "Stack of {} in slot {} is over the limit of {}",
+                    stack.count,
+                    slot,
+                    MAX_STACK_SIZE
+                ));
+            }
+            inventory.set(slot, Some(stack))?;
+        }
+        Ok(inventory)
+    }
+}
+
+impl Default for Inventory {
+    fn default() -> Self {
+        Self {
+            slots: [None; PLAYER_INVENTORY_SLOTS],
+        }
+    }
+}
+
+impl Inventory {
+    pub fn get(&self, slot: usize) -> Option<ItemStack> {
+        self.slots.get(slot).copied().flatten()
+    }
+
+    /// Replace whatever is in `slot`; empty stacks clear it
+    pub fn set(&mut self, slot: usize, stack: Option<ItemStack>) -> Result<()> {
+        let target = self
+            .slots
+            .get_mut(slot)
+            .ok_or_else(|| anyhow!("Inventory slot {} out of range", slot))?;
+        *target = stack.filter(|stack| stack.count > 0);
+        Ok(())
+    }
+
+    /// Put `stack` away, topping up matching stacks before using empty slots, hotbar first
+    /// Returns how many items didn't fit
+    pub fn add_item(&mut self, stack: ItemStack) -> u8 {
+        let mut remaining = stack.count;
+        let order = || HOTBAR_SLOTS.chain(MAIN_SLOTS);
+
+        for slot in order() {
+            if remaining == 0 {
+                return 0;
+            }
+            match &mut self.slots[slot] {
+                Some(existing) if existing.item_id == stack.item_id => {
+                    let moved = remaining.min(MAX_STACK_SIZE.saturating_sub(existing.count));
+                    existing.count += moved;
+                    remaining -= moved;
+                }
+                _ => {}
+            }
+        }
+
+        for slot in order() {
+            if remaining == 0 {
+                return 0;
+            }
+            if self.slots[slot].is_none() {
+                let moved = remaining.min(MAX_STACK_SIZE);
+                self.slots[slot] = Some(ItemStack::new(stack.item_id, moved));
+                remaining -= moved;
+            }
+        }
+        remaining
+    }
+
+    /// Use up one `item_id` from `slot`; false if the slot holds something else
+    pub fn take_one(&mut self, slot: usize, item_id: i32) -> bool {
+        let Some(Some(stack)) = self.slots.get_mut(slot) else {
+            return false;
+        };
+        if stack.item_id != item_id {
+            return false;
+        }
+        stack.count -= 1;
+        if stack.count == 0 {
+            self.slots[slot] = None;
+        }
+        true
+    }
+
+    /// Add `count` of an item, in as many stacks as it takes; returns how many didn't fit
+    pub fn give(&mut self, item_id: i32, count: u32) -> u32 {
+        let mut remaining = count;
+        while remaining > 0 {
+            let stack = remaining.min(MAX_STACK_SIZE as u32) as u8;
+            let left = self.add_item(ItemStack::new(item_id, stack));
+            remaining -= (stack - left) as u32;
+            if left > 0 {
+                break;
+            }
+        }
+        remaining
+    }
+
+    /// Apply a Set Creative Mode Slot from a player in `game_mode`: the slot as a short, then the
+    /// item as slot data whose components are ignored
+    pub fn apply_creative_slot(&mut self, game_mode: GameMode, payload: &[u8]) -> CreativeSlotResult {
+        if game_mode != GameMode::Creative {
+            return CreativeSlotResult::NotCreative;
+        }
+
+        let (slot, stack) = match parse_creative_slot(payload) {
+            Ok(parsed) => parsed,
+            Err(e) => return CreativeSlotResult::Refused(e),
+        };
+        if slot == -1 {
+            return CreativeSlotResult::Dropped(stack);
+        }
+        let Ok(slot) = usize::try_from(slot) else {
+            return CreativeSlotResult::Refused(anyhow!("Inventory slot {} out of range", slot));
+        };
+        match self.set(slot, stack) {
+            Ok(()) => CreativeSlotResult::Set(slot),
+            Err(e) => CreativeSlotResult::Refused(e),
+        }
+    }
+
+    /// Set Container Content for the player inventory window, with nothing on the cursor
+    /// `state_id` is echoed back by the client's clicks so the server can spot stale ones
+    pub fn container_content_frame(&self, state_id: i32) -> Vec<u8> {
+        let mut writer = PacketWriter::new();
+
+        writer.write_varint(PLAYER_INVENTORY_WINDOW);
+        writer.write_varint(state_id);
+        writer.write_varint(self.slots.len() as i32);
+        for stack in &self.slots {
+            write_slot(&mut writer, *stack);
+        }
+        // Carried item
+        write_slot(&mut writer, None);
+
+        frame(ClientboundPlay::SetContainerContent.id(), &writer.finish())
+    }
+}
+
+/// Slot number and item from a Set Creative Mode Slot payload
+fn parse_creative_slot(payload: &[u8]) -> Result<(i16, Option<ItemStack>)> {
+    let mut reader = PacketReader::new(payload);
+    let slot = reader.read_short()?;
+    let count = reader.read_varint()?;
+    if count <= 0 {
+        return Ok((slot, None));
+    }
+    if count > MAX_STACK_SIZE as i32 {
+        return Err(anyhow!("Stack of {} is over the limit of {}", count, MAX_STACK_SIZE));
+    }
+    let item_id = reader.read_varint()?;
+    Ok((slot, Some(ItemStack::new(item_id, count as u8))))
+}
+
+/// Slot data: the count, then for a non-empty slot the item id and empty component patches
+fn write_slot(writer: &mut PacketWriter, stack: Option<ItemStack>) {
+    match stack {
+        Some(stack) => {
+            writer.write_varint(stack.count as i32);
+            writer.write_varint(stack.item_id);
+            // Components to add, components to remove
+            writer.write_varint(0);
+            writer.write_varint(0);
+        }
+        None => writer.write_varint(0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STONE: i32 = 1;
+    const DIRT: i32 = 28;
+
+    #[test]
+    fn test_add_item_tops_up_stacks_before_filling_slots() {
+        let mut inventory = Inventory::default();
+
+        assert_eq!(inventory.add_item(ItemStack::new(STONE, 40)), 0);
+        assert_eq!(inventory.get(36), Some(ItemStack::new(STONE, 40)));
+
+        // Tops up the first stack, then spills into the next free hotbar slot
+        assert_eq!(inventory.add_item(ItemStack::new(STONE, 30)), 0);
+        assert_eq!(inventory.get(36), Some(ItemStack::new(STONE, 64)));
+        assert_eq!(inventory.get(37), Some(ItemStack::new(STONE, 6)));
+
+        // Different items never share a stack
+        assert_eq!(inventory.add_item(ItemStack::new(DIRT, 10)), 0);
+        assert_eq!(inventory.get(38), Some(ItemStack::new(DIRT, 10)));
+
+        // A partial stack earlier in the order is filled before a new slot is used
+        inventory.set(37, None).unwrap();
+        inventory.set(40, Some(ItemStack::new(STONE, 60))).unwrap();
+        assert_eq!(inventory.add_item(ItemStack::new(STONE, 10)), 0);
+        assert_eq!(inventory.get(40), Some(ItemStack::new(STONE, 64)));
+        assert_eq!(inventory.get(37), Some(ItemStack::new(STONE, 6)));
+    }
+
+    #[test]
+    fn test_add_item_returns_what_does_not_fit() {
+        let mut inventory = Inventory::default();
+        for slot in HOTBAR_SLOTS.chain(MAIN_SLOTS) {
+            inventory.set(slot, Some(ItemStack::new(DIRT, 64))).unwrap();
+        }
+        inventory.set(9, Some(ItemStack::new(STONE, 60))).unwrap();
+
+        assert_eq!(inventory.add_item(ItemStack::new(STONE, 10)), 6);
+        assert_eq!(inventory.get(9), Some(ItemStack::new(STONE, 64)));
+        // Crafting and armor slots are never filled by pickups
+        assert_eq!(inventory.get(0), None);
+        assert_eq!(inventory.get(5), None);
+    }
+
+    #[test]
+    fn test_give_splits_into_stacks() {
+        let mut inventory = Inventory::default();
+        assert_eq!(inventory.give(STONE, 150), 0);
+        assert_eq!(inventory.get(36), Some(ItemStack::new(STONE, 64)));
+        assert_eq!(inventory.get(37), Some(ItemStack::new(STONE, 64)));
+        assert_eq!(inventory.get(38), Some(ItemStack::new(STONE, 22)));
+
+        // 36 slots hold 2304 items, 150 of which are already taken
+        assert_eq!(inventory.give(STONE, 3_000), 3_000 - (36 * 64 - 150));
+        assert_eq!(inventory.give(DIRT, 1), 1);
+    }
+
+    #[test]
+    fn test_item_names() {
+        assert_eq!(item_from_name("minecraft:stone"), Some(STONE));
+        assert_eq!(item_from_name("dirt"), Some(DIRT));
+        assert_eq!(item_from_name("minecraft:diamond_sword"), None);
+        assert_eq!(item_from_name("other:dirt"), None);
+
+        assert_eq!(block_for_item(STONE), Some(BlockType::Stone));
+        assert_eq!(block_for_item(27), Some(BlockType::Grass));
+        assert_eq!(block_for_item(-1), None);
+    }
+
+    #[test]
+    fn test_take_one_empties_the_slot() {
+        let mut inventory = Inventory::default();
+        inventory
+            .set(hotbar_slot(2), Some(ItemStack::new(DIRT, 2)))
+            .unwrap();
+
+        assert!(!inventory.take_one(hotbar_slot(2), STONE));
+        assert!(!inventory.take_one(hotbar_slot(3), DIRT));
+        assert!(!inventory.take_one(PLAYER_INVENTORY_SLOTS, DIRT));
+        assert!(inventory.take_one(hotbar_slot(2), DIRT));
+        assert_eq!(inventory.get(38), Some(ItemStack::new(DIRT, 1)));
+        assert!(inventory.take_one(38, DIRT));
+        assert_eq!(inventory.get(38), None);
+        assert!(!inventory.take_one(38, DIRT));
+    }
+
+    #[test]
+    fn test_set_checks_the_slot() {
+        let mut inventory = Inventory::default();
+        assert!(
+            inventory
+                .set(PLAYER_INVENTORY_SLOTS, Some(ItemStack::new(STONE, 1)))
+                .is_err()
+        );
+        assert_eq!(inventory.get(PLAYER_INVENTORY_SLOTS), None);
+
+        inventory.set(45, Some(ItemStack::new(STONE, 0))).unwrap();
+        assert_eq!(inventory.get(45), None);
+    }
+
+    fn creative_slot(slot: i16, count: i32, item_id: i32) -> Vec<u8> {
+        let mut writer = PacketWriter::new();
+        writer.write_short(slot);
+        writer.write_varint(count);
+        if count > 0 {
+            writer.write_varint(item_id);
+            writer.write_varint(0);
+            writer.write_varint(0);
+        }
+        writer.finish().to_vec()
+    }
+
+    #[test]
+    fn test_creative_slot_updates_the_inventory() {
+        let mut inventory = Inventory::default();
+
+        let result = inventory.apply_creative_slot(GameMode::Creative, &creative_slot(36, 64, DIRT));
+        assert!(matches!(result, CreativeSlotResult::Set(36)), "{:?}", result);
+        assert_eq!(inventory.get(36), Some(ItemStack::new(DIRT, 64)));
+
+        // An empty stack clears the slot
+        let result = inventory.apply_creative_slot(GameMode::Creative, &creative_slot(36, 0, 0));
+        assert!(matches!(result, CreativeSlotResult::Set(36)), "{:?}", result);
+        assert_eq!(inventory.get(36), None);
+
+        let result = inventory.apply_creative_slot(GameMode::Creative, &creative_slot(-1, 1, STONE));
+        assert!(matches!(result, CreativeSlotResult::Dropped(Some(_))), "{:?}", result);
+    }
+
+    #[test]
+    fn test_creative_slot_refusals() {
+        let mut inventory = Inventory::default();
+
+        for mode in [GameMode::Survival, GameMode::Adventure, GameMode::Spectator] {
+            let result = inventory.apply_creative_slot(mode, &creative_slot(36, 1, STONE));
+            assert!(matches!(result, CreativeSlotResult::NotCreative), "{:?}", result);
+        }
+
+        for payload in [
+            creative_slot(46, 1, STONE),
+            creative_slot(-2, 1, STONE),
+            creative_slot(36, 65, STONE),
+            vec![0x00],
+        ] {
+            let result = inventory.apply_creative_slot(GameMode::Creative, &payload);
+            assert!(matches!(result, CreativeSlotResult::Refused(_)), "{:?}", result);
+        }
+        assert_eq!(inventory, Inventory::default());
+    }
+
+    #[test]
+    fn test_container_content_encoding() {
+        let mut inventory = Inventory::default();
+        inventory.set(36, Some(ItemStack::new(STONE, 5))).unwrap();
+        inventory.set(45, Some(ItemStack::new(300, 1))).unwrap();
+
+        let frame = inventory.container_content_frame(3);
+
+        let mut expected = vec![0x12, 0x00, 0x03, 46];
+        expected.extend_from_slice(&[0x00; 36]);
+        expected.extend_from_slice(&[0x05, 0x01, 0x00, 0x00]);
+        expected.extend_from_slice(&[0x00; 8]);
+        // Item 300 as a two byte varint
+        expected.extend_from_slice(&[0x01, 0xAC, 0x02, 0x00, 0x00]);
+        // Empty cursor
+        expected.push(0x00);
+
+        assert_eq!(frame[0] as usize, expected.len());
+        assert_eq!(&frame[1..], &expected[..]);
+    }
+}
@@ -1,4 +1,5 @@
 use anyhow::Result;
+use sha2::{Digest, Sha256};
 use tokio::io::AsyncWriteExt;
 use tokio::net::TcpStream;
 use tracing::warn;
@@ -6,58 +7,60 @@ use uuid::Uuid;
 
 // use crate::packet_logger::PacketLogger;
 use crate::{
-    network::{ByteWritable, PacketWriter, write_varint},
-    player::Vec3,
+    config::ServerConfig,
+    network::{
+        ByteWritable,
+        ClientboundConfig,
+        ClientboundPlay,
+        PacketWriter,
+        text_component_nbt,
+        write_varint,
+    },
+    player::GameMode,
+    player::spawn_packets::{frame, player_info_add_frame},
 };
 
-pub struct JoinGameHandler;
-
-#[allow(dead_code)]
-impl JoinGameHandler {
-    /// Send Spawn Position packet (0x4D in Play state)
-    /// Sets the spawn point and angle
-    pub async fn send_spawn_position<N32: Into<i32>>(
-        stream: &mut TcpStream,
-        vec_3: Vec3<N32>,
-        // x: i32,
-        // y: i32,
-        // z: i32,
-        angle: f32,
-    ) -> Result<()> {
-        let mut writer = PacketWriter::new();
-
-        // Position (combined location)
-        // For 1.21.7, position is sent as three separate ints
-        writer.write_int(vec_3.x);
-        writer.write_int(vec_3.y);
-        writer.write_int(vec_3.z);
-
-        // Angle (rotation in degrees, 0-360)
-        writer.write_float(angle);
-
-        let packet_data = writer.finish();
-        let packet_id = write_varint(0x4D); // Spawn Position packet ID in Play state
-        let packet_length = (packet_id.len() + packet_data.len()) as i32;
-
-        // Write packet: [length][id][data]
-        let mut frame = Vec::new();
-        frame.extend_from_slice(&write_varint(packet_length));
-        frame.extend_from_slice(&packet_id);
-        frame.extend_from_slice(&packet_data);
-
-        #[cfg(feature = "dev-sdk")]
-        let _ = crate::LOGGER.log_server_packet(&frame);
+/// The seed as clients see it: the first 8 bytes of its SHA-256, like vanilla's obfuscated seed
+/// Both the seed and the hash are read little-endian; the client only uses it for biome blending
+pub fn hashed_seed(seed: u64) -> i64 {
+    let digest = Sha256::digest(seed.to_le_bytes());
+    i64::from_le_bytes(digest[..8].try_into().expect("SHA-256 is 32 bytes"))
+}
 
-        stream.write_all(&frame).await?;
-        stream.flush().await?;
+/// Login (Play) fields that come from the server config and world rather than the player
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JoinGameSettings {
+    pub hashed_seed:           i64,
+    /// Ignored by the client, which has no use for it since the server list moved to Status
+    pub max_players:           i32,
+    pub reduced_debug_info:    bool,
+    pub enable_respawn_screen: bool,
+    pub is_debug:              bool,
+    pub is_flat:               bool,
+}
 
-        Ok(())
+impl JoinGameSettings {
+    /// `seed` is the world's, which may differ from the configured one for a saved world
+    pub fn new(config: &ServerConfig, seed: u64) -> Self {
+        Self {
+            hashed_seed:           hashed_seed(seed),
+            max_players:           config.max_players.min(i32::MAX as u32) as i32,
+            reduced_debug_info:    config.reduced_debug_info,
+            enable_respawn_screen: config.enable_respawn_screen,
+            is_debug:              config.is_debug,
+            is_flat:               config.is_flat(),
+        }
     }
+}
 
+pub struct JoinGameHandler;
+
+#[allow(dead_code)]
+impl JoinGameHandler {
     pub async fn send_configuration_finish(stream: &mut TcpStream) -> Result<()> {
-        // Configuration Finish packet (0x02) - transitions from Configuration to Play state
+        // Finish Configuration packet - transitions from Configuration to Play state
         // This packet has no data, just the ID
-        let packet_id = write_varint(0x02);
+        let packet_id = write_varint(ClientboundConfig::FinishConfiguration.id());
         let packet_length = packet_id.len() as i32;
 
         let mut frame = Vec::new();
@@ -74,21 +77,7 @@ impl JoinGameHandler {
     }
 
     pub async fn send_disconnect(stream: &mut TcpStream, reason: &str) -> Result<()> {
-        let mut writer = PacketWriter::new();
-
-        // Write JSON chat message
-        let json_message = format!(r#"{{"text":"{}"}}"#, reason.replace('"', "\\\""));
-        writer.write_string(&json_message);
-
-        let packet_data = writer.finish();
-        let packet_id = write_varint(0x19); // Disconnect packet ID in Play state
-        let packet_length = (packet_id.len() + packet_data.len()) as i32;
-
-        // Write packet: [length][id][data]
-        let mut frame = Vec::new();
-        frame.extend_from_slice(&write_varint(packet_length));
-        frame.extend_from_slice(&packet_id);
-        frame.extend_from_slice(&packet_data);
+        let frame = Self::disconnect_frame(reason);
 
         #[cfg(feature = "dev-sdk")]
         let _ = &crate::LOGGER.log_server_packet(&frame);
@@ -106,6 +95,10 @@ impl JoinGameHandler {
         stream: &mut TcpStream,
         entity_id: i32,
         _username: &str,
+        view_distance: i32,
+        simulation_distance: i32,
+        game_mode: GameMode,
+        settings: &JoinGameSettings,
         // packet_logger: &PacketLogger,
     ) -> Result<()> {
         let mut writer = PacketWriter::new();
@@ -116,8 +109,8 @@ impl JoinGameHandler {
         // Hardcore Flag
         writer.write_bool(false);
 
-        // Gamemode (0 = Survival)
-        writer.write_byte(0);
+        // Gamemode
+        writer.write_byte(game_mode);
 
         // Previous Gamemode (0xFF = none)
         writer.write_byte(0xFF);
@@ -138,28 +131,31 @@ impl JoinGameHandler {
         writer.write_string("minecraft:overworld");
 
         // Hashed Seed
-        writer.write_long(12345);
+        writer.write_long(settings.hashed_seed);
 
         // Max Players
-        writer.write_varint(20);
+        writer.write_varint(settings.max_players);
 
         // View Distance
-        writer.write_varint(10);
+        writer.write_varint(view_distance);
+
+        // Simulation Distance
+        writer.write_varint(simulation_distance);
 
         // Reduced Debug Info
-        writer.write_bool(false);
+        writer.write_bool(settings.reduced_debug_info);
 
         // Enable Respawn Screen
-        writer.write_bool(true);
+        writer.write_bool(settings.enable_respawn_screen);
 
         // Is Debug
-        writer.write_bool(false);
+        writer.write_bool(settings.is_debug);
 
         // Is Flat
-        writer.write_bool(false);
+        writer.write_bool(settings.is_flat);
 
         let packet_data = writer.finish();
-        let packet_id = write_varint(0x28);
+        let packet_id = write_varint(ClientboundPlay::Login.id());
         let packet_length = (packet_id.len() + packet_data.len()) as i32;
 
         // Write packet: [length][id][data]
@@ -177,42 +173,14 @@ impl JoinGameHandler {
         Ok(())
     }
 
-    pub async fn send_player_info_add(stream: &mut TcpStream, uuid: Uuid, username: &str) -> Result<()> {
-        let mut writer = PacketWriter::new();
-
-        // Action: 0 = Add Player
-        writer.write_varint(0);
-
-        // Number of entries
-        writer.write_varint(1);
-
-        // Entry UUID
-        writer.write_uuid(uuid);
-
-        // Player name
-        writer.write_string(username);
-
-        // Properties count (0 for now)
-        writer.write_varint(0);
-
-        // Gamemode (0 = Survival)
-        writer.write_varint(0);
-
-        // Ping (milliseconds)
-        writer.write_varint(0);
-
-        // Has display name
-        writer.write_bool(false);
-
-        let packet_data = writer.finish();
-        let packet_id = write_varint(0x53);
-        let packet_length = (packet_id.len() + packet_data.len()) as i32;
+    /// Disconnect (Play state) with a plain text reason
+    pub fn disconnect_frame(reason: &str) -> Vec<u8> {
+        frame(ClientboundPlay::Disconnect.id(), &text_component_nbt(reason))
+    }
 
-        // Write packet: [length][id][data]
-        let mut frame = Vec::new();
-        frame.extend_from_slice(&write_varint(packet_length));
-        frame.extend_from_slice(&packet_id);
-        frame.extend_from_slice(&packet_data);
+    /// Send Player Info Update adding the joining player to its own tab list
+    pub async fn send_player_info_add(stream: &mut TcpStream, uuid: Uuid, username: &str) -> Result<()> {
+        let frame = player_info_add_frame([(uuid, username)]);
 
         #[cfg(feature = "dev-sdk")]
         let _ = &crate::LOGGER.log_server_packet(&frame);
@@ -269,3 +237,39 @@ impl JoinGameHandler {
         ]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hashed_seed() {
+        // Checked against sha256 of the little-endian seed bytes
+        assert_eq!(hashed_seed(0), 8_794_265_229_978_523_055);
+        assert_eq!(hashed_seed(12345), 293_737_985_876_514_017);
+        assert_eq!(hashed_seed(-4_172_144_997_902_289_642i64 as u64), 2_159_143_436_479_834_350);
+        assert_ne!(hashed_seed(1), hashed_seed(2));
+    }
+
+    #[test]
+    fn test_settings_follow_the_config() {
+        let config = ServerConfig {
+            max_players: 50,
+            enable_respawn_screen: false,
+            generator: crate::config::GeneratorKind::Flat,
+            ..ServerConfig::default()
+        };
+        let settings = JoinGameSettings::new(&config, 12345);
+        assert_eq!(
+            settings,
+            JoinGameSettings {
+                hashed_seed:           hashed_seed(12345),
+                max_players:           50,
+                reduced_debug_info:    false,
+                enable_respawn_screen: false,
+                is_debug:              false,
+                is_flat:               true,
+            }
+        );
+    }
+}
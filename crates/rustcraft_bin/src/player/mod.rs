@@ -1,23 +1,42 @@
+mod block_actions;
+mod commands;
 mod configuration;
 mod connection_state;
+mod disconnect;
+mod entity_id;
+mod entity_movement;
+mod health;
+mod inventory;
 mod join_game;
 mod movement_handler;
 mod play_state;
 mod player_data;
+mod player_store;
+mod registry;
+mod spawn_packets;
 
 use std::borrow::{Borrow, BorrowMut};
 use std::fmt::{Debug, Display};
 use std::ops::{Add, Deref};
 
-pub use play_state::PlayStateHandler;
+pub use block_actions::{BlockRequest, BlockRequestSender};
+pub use commands::{seed_message, system_chat_frame};
+pub use connection_state::{ConnectionStage, ConnectionStateTracker, watch_stage_timeouts};
+pub use disconnect::{DisconnectGuard, player_left};
+pub use entity_id::EntityIdAllocator;
+pub use inventory::Inventory;
+pub use play_state::{GameEvent, GameMode, PlayStateHandler, game_event_frame};
 pub use player_data::PlayerData;
+pub use player_store::PlayerStore;
+pub use registry::{Outbound, PlayerRegistry, PlayerSlot, RegisteredPlayer};
+use serde::{Deserialize, Serialize};
 
 pub trait CrossAssign<Rhs = Self> {
     fn cross_assign(&mut self, rhs: Rhs);
 }
 
 #[repr(C)]
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Vec3<N> {
     pub x: N,
     pub y: N,
@@ -173,7 +192,7 @@ impl<N: Display> Display for Vec3<N> {
 }
 
 #[repr(C)]
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Vec2<N> {
     pub yaw:   N,
     pub pitch: N,
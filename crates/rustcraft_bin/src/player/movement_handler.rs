@@ -1,8 +1,8 @@
 use std::fmt::Display;
 
-use anyhow::Result;
+use anyhow::{Result, anyhow};
 
-use crate::network::PacketReader;
+use crate::network::{PacketReader, ServerboundPlay};
 use crate::player::{Vec2, Vec3};
 
 /// Player movement packet types
@@ -39,31 +39,51 @@ where
     pub ground:      bool,
 }
 
+/// Validate a client-sent rotation
+/// Non-finite values are rejected, pitch is clamped to [-90, 90] and yaw wrapped into [-180, 180)
+pub fn validate_rotation(rotation: Vec2<f32>) -> Result<Vec2<f32>> {
+    if !rotation.yaw.is_finite() || !rotation.pitch.is_finite() {
+        return Err(anyhow!("Invalid rotation: yaw {}, pitch {}", rotation.yaw, rotation.pitch));
+    }
+
+    let yaw = (rotation.yaw + 180.0).rem_euclid(360.0) - 180.0;
+    let pitch = rotation.pitch.clamp(-90.0, 90.0);
+
+    Ok(Vec2::new(yaw, pitch))
+}
+
+const SET_PLAYER_POSITION_PACKET_ID: i32 = ServerboundPlay::SetPlayerPosition.id();
+const SET_PLAYER_POSITION_AND_ROTATION_PACKET_ID: i32 = ServerboundPlay::SetPlayerPositionAndRotation.id();
+const SET_PLAYER_ROTATION_PACKET_ID: i32 = ServerboundPlay::SetPlayerRotation.id();
+
+/// Movement flags: bit 0 is on ground, bit 1 is pushing against a wall
+const FLAG_ON_GROUND: u8 = 0x01;
+
 /// Parse movement packets from client
 pub fn parse_movement_packet(packet_id: i32, data: &[u8]) -> Result<Option<MovementPacket>> {
     match packet_id {
-        0x04 => {
+        SET_PLAYER_POSITION_PACKET_ID => {
             // Player Position packet
             let mut reader = PacketReader::new(data);
             let coordinates =
                 Vec3::from((reader.read_double()?, reader.read_double()?, reader.read_double()?));
-            let ground = reader.read_bool()?;
+            let ground = reader.read_byte()? & FLAG_ON_GROUND != 0;
             Ok(Some(MovementPacket::Position(PlayerPosition { coordinates, ground })))
         }
-        0x05 => {
+        SET_PLAYER_ROTATION_PACKET_ID => {
             // Player Look packet
             let mut reader = PacketReader::new(data);
-            let ground = reader.read_bool()?;
             let rotation = Vec2::from((reader.read_float()?, reader.read_float()?));
+            let ground = reader.read_byte()? & FLAG_ON_GROUND != 0;
             Ok(Some(MovementPacket::Look(PlayerLook { rotation, ground })))
         }
-        0x06 => {
+        SET_PLAYER_POSITION_AND_ROTATION_PACKET_ID => {
             // Player Position and Look packet
             let mut reader = PacketReader::new(data);
             let coordinates =
                 Vec3::from((reader.read_double()?, reader.read_double()?, reader.read_double()?));
             let rotation = Vec2::from((reader.read_float()?, reader.read_float()?));
-            let ground = reader.read_bool()?;
+            let ground = reader.read_byte()? & FLAG_ON_GROUND != 0;
 
             Ok(Some(MovementPacket::PositionAndLook(PlayerPositionAndLook {
                 coordinates,
@@ -240,3 +260,49 @@ where
         MovementPacket::new_look(vec.yaw.into(), vec.pitch.into(), false)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_rotation_normalizes() {
+        let cases = [
+            ((0.0, 0.0), (0.0, 0.0)),
+            ((180.0, 45.0), (-180.0, 45.0)),
+            ((-180.0, -45.0), (-180.0, -45.0)),
+            ((270.0, 120.0), (-90.0, 90.0)),
+            ((-190.0, -91.0), (170.0, -90.0)),
+            ((720.5, 0.0), (0.5, 0.0)),
+        ];
+
+        for ((yaw, pitch), (expected_yaw, expected_pitch)) in cases {
+            let rotation = validate_rotation(Vec2::new(yaw, pitch)).unwrap();
+            assert!((rotation.yaw - expected_yaw).abs() < 1e-4, "yaw {} -> {}", yaw, rotation.yaw);
+            assert_eq!(rotation.pitch, expected_pitch);
+        }
+    }
+
+    #[test]
+    fn test_parse_rotation_packet() {
+        let mut data = Vec::new();
+        data.extend_from_slice(&90.0f32.to_be_bytes());
+        data.extend_from_slice(&(-45.0f32).to_be_bytes());
+        data.push(0x03);
+
+        match parse_movement_packet(SET_PLAYER_ROTATION_PACKET_ID, &data).unwrap() {
+            Some(MovementPacket::Look(look)) => {
+                assert_eq!((look.rotation.yaw, look.rotation.pitch), (90.0, -45.0));
+                assert!(look.ground);
+            }
+            _ => panic!("expected a look packet"),
+        }
+    }
+
+    #[test]
+    fn test_validate_rotation_rejects_non_finite() {
+        assert!(validate_rotation(Vec2::new(f32::NAN, 0.0)).is_err());
+        assert!(validate_rotation(Vec2::new(0.0, f32::NAN)).is_err());
+        assert!(validate_rotation(Vec2::new(f32::INFINITY, 0.0)).is_err());
+    }
+}
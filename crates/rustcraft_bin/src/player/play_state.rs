@@ -1,44 +1,156 @@
 #![allow(dead_code)]
 
-use anyhow::Result;
-use tokio::io::AsyncWriteExt;
+use anyhow::{Result, anyhow};
+use serde::Deserialize;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
 use tokio::net::TcpStream;
 
-use crate::network::{ByteWritable, PacketWriter, write_varint};
+use crate::network::{
+    ByteWritable,
+    ClientboundPlay,
+    PacketReader,
+    PacketWriter,
+    pack_block_position,
+    write_varint,
+};
 use crate::player::{Vec2, Vec3};
 
-pub struct PlayStateHandler;
+/// Game Event ids
+/// The meaning of the accompanying float value depends on the event
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum GameEvent {
+    /// Tell the client it cannot respawn at its bed/anchor
+    NoRespawnBlockAvailable = 0,
+    /// Rain starts
+    BeginRaining = 1,
+    /// Rain stops
+    EndRaining = 2,
+    /// Value is the gamemode: 0 survival, 1 creative, 2 adventure, 3 spectator
+    ChangeGameMode = 3,
+    /// Value 0 respawns immediately, 1 rolls the end credits first
+    WinGame = 4,
+    /// Value 0 shows the demo intro, 101-104 the movement/jump/inventory/screenshot hints
+    DemoEvent = 5,
+    /// Plays the arrow hit sound for the shooter
+    ArrowHitPlayer = 6,
+    /// Value is the rain strength, 0.0 to 1.0
+    RainLevelChange = 7,
+    /// Value is the thunder strength, 0.0 to 1.0
+    ThunderLevelChange = 8,
+    PufferfishSting = 9,
+    ElderGuardianAppearance = 10,
+    /// Value 0 enables the respawn screen, 1 skips it
+    EnableRespawnScreen = 11,
+    /// Value 0 disables limited crafting, 1 enables it
+    LimitedCrafting = 12,
+    /// Sent after the initial position sync; the client leaves the loading terrain screen once
+    /// the chunk it stands in has arrived
+    StartWaitingForLevelChunks = 13,
+}
 
-impl PlayStateHandler {
-    /// Send Confirm Teleport/Position packet (0x00 in Play state)
-    /// This acknowledges to the client that their position has been confirmed by the server
-    pub async fn send_confirm_teleport(stream: &mut TcpStream, teleport_id: i32) -> Result<()> {
-        let mut writer = PacketWriter::new();
+impl From<GameEvent> for u8 {
+    fn from(event: GameEvent) -> Self {
+        event as u8
+    }
+}
 
-        // Write the teleport ID (used to match with the client's request)
-        writer.write_varint(teleport_id);
+/// Game Event frame, for queueing on a player's outbound channel
+pub fn game_event_frame<E: Into<u8>>(event: E, value: f32) -> Vec<u8> {
+    let mut writer = PacketWriter::new();
 
-        let packet_data = writer.finish();
-        let packet_id = write_varint(0x00); // Confirm Teleport packet ID in Play state
-        let packet_length = (packet_id.len() + packet_data.len()) as i32;
+    writer.write_byte(event);
+    writer.write_float(value);
 
-        // Write packet: [length][id][data]
-        let mut frame = Vec::new();
-        frame.extend_from_slice(&write_varint(packet_length));
-        frame.extend_from_slice(&packet_id);
-        frame.extend_from_slice(&packet_data);
+    let packet_data = writer.finish();
+    let packet_id = write_varint(ClientboundPlay::GameEvent.id());
+    let packet_length = (packet_id.len() + packet_data.len()) as i32;
 
-        #[cfg(feature = "dev-sdk")]
-        let _ = &crate::LOGGER.log_server_packet(&frame);
+    // Write packet: [length][id][data]
+    let mut frame = Vec::new();
+    frame.extend_from_slice(&write_varint(packet_length));
+    frame.extend_from_slice(&packet_id);
+    frame.extend_from_slice(&packet_data);
+    frame
+}
 
-        stream.write_all(&frame).await?;
-        stream.flush().await?;
+/// Player Abilities flag bits
+pub const ABILITY_INVULNERABLE: u8 = 0x01;
+pub const ABILITY_FLYING: u8 = 0x02;
+pub const ABILITY_ALLOW_FLYING: u8 = 0x04;
+pub const ABILITY_INSTANT_BREAK: u8 = 0x08;
+
+/// Vanilla flying speed and field of view modifier (walking speed)
+pub const DEFAULT_FLY_SPEED: f32 = 0.05;
+pub const DEFAULT_FOV_MODIFIER: f32 = 0.1;
+
+/// Game mode, selected for everyone by the `gamemode` config key
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[repr(u8)]
+pub enum GameMode {
+    #[default]
+    Survival = 0,
+    Creative = 1,
+    Adventure = 2,
+    Spectator = 3,
+}
 
-        Ok(())
+impl GameMode {
+    /// Player Abilities flags a player in this mode starts with
+    pub fn abilities_flags(self) -> u8 {
+        match self {
+            GameMode::Survival | GameMode::Adventure => 0,
+            GameMode::Creative => ABILITY_INVULNERABLE | ABILITY_ALLOW_FLYING | ABILITY_INSTANT_BREAK,
+            // Spectators are always flying
+            GameMode::Spectator => ABILITY_INVULNERABLE | ABILITY_FLYING | ABILITY_ALLOW_FLYING,
+        }
+    }
+
+    pub fn can_fly(self) -> bool {
+        self.abilities_flags() & ABILITY_ALLOW_FLYING != 0
     }
 
-    /// Send Set Default Spawn Position packet (0x4E in Play state)
-    /// Tells the client where to respawn when they die
+    pub fn is_invulnerable(self) -> bool {
+        self.abilities_flags() & ABILITY_INVULNERABLE != 0
+    }
+}
+
+impl From<GameMode> for u8 {
+    fn from(mode: GameMode) -> Self {
+        mode as u8
+    }
+}
+
+/// Slots in the hotbar, numbered from 0 at the left
+pub const HOTBAR_SLOTS: u8 = 9;
+
+/// Hotbar slot the player has selected
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HeldSlot(u8);
+
+impl HeldSlot {
+    pub fn get(self) -> u8 {
+        self.0
+    }
+
+    /// Take the slot from a serverbound Set Held Item, keeping the current one if it is outside the
+    /// hotbar
+    pub fn select_from_packet(&mut self, payload: &[u8]) -> Result<u8> {
+        let slot = PacketReader::new(payload).read_short()?;
+        if !(0..HOTBAR_SLOTS as i16).contains(&slot) {
+            return Err(anyhow!("Held item slot {} is outside the hotbar", slot));
+        }
+        self.0 = slot as u8;
+        Ok(self.0)
+    }
+}
+
+pub struct PlayStateHandler;
+
+impl PlayStateHandler {
+    /// Send Set Default Spawn Position packet
+    /// Tells the client where to respawn when they die, and where the compass points
     pub async fn send_set_default_spawn_position<N: Into<i32>>(
         stream: &mut TcpStream,
         x: N,
@@ -48,17 +160,14 @@ impl PlayStateHandler {
     ) -> Result<()> {
         let mut writer = PacketWriter::new();
 
-        // Position (as a combined int: x << 38 | (z & 0x3FFFFFF) << 12 | (y & 0xFFF))
-        // For 1.21.7, this is sent as X (i32), Y (i32), Z (i32)
-        writer.write_int(x);
-        writer.write_int(y);
-        writer.write_int(z);
+        // Position, packed as x << 38 | (z & 0x3FFFFFF) << 12 | (y & 0xFFF)
+        writer.write_long(pack_block_position(x.into(), y.into(), z.into()));
 
         // Angle (rotation in degrees, 0-360, as a float)
         writer.write_float(angle);
 
         let packet_data = writer.finish();
-        let packet_id = write_varint(0x4E); // Set Default Spawn Position packet ID
+        let packet_id = write_varint(ClientboundPlay::SetDefaultSpawnPosition.id());
         let packet_length = (packet_id.len() + packet_data.len()) as i32;
 
         // Write packet: [length][id][data]
@@ -76,7 +185,7 @@ impl PlayStateHandler {
         Ok(())
     }
 
-    /// Send Player Position And Look packet (0x28 in Play state, server → client)
+    /// Send Player Position And Look packet (Synchronize Player Position, server → client)
     /// This packet tells the client where they should be and how they should look
     pub async fn send_player_position_and_look<N64: Into<f64>, N32: Into<f32>>(
         stream: &mut TcpStream,
@@ -113,7 +222,7 @@ impl PlayStateHandler {
         writer.write_varint(teleport_id);
 
         let packet_data = writer.finish();
-        let packet_id = write_varint(0x28); // Player Position And Look packet ID
+        let packet_id = write_varint(ClientboundPlay::SynchronizePlayerPosition.id());
         let packet_length = (packet_id.len() + packet_data.len()) as i32;
 
         // Write packet: [length][id][data]
@@ -131,7 +240,7 @@ impl PlayStateHandler {
         Ok(())
     }
 
-    /// Send Entity Status packet (0x01 in Play state)
+    /// Send Entity Status packet (Entity Event)
     /// Used to send various entity events
     pub async fn send_entity_status(stream: &mut TcpStream, entity_id: i32, status: u8) -> Result<()> {
         let mut writer = PacketWriter::new();
@@ -143,7 +252,7 @@ impl PlayStateHandler {
         writer.write_byte(status);
 
         let packet_data = writer.finish();
-        let packet_id = write_varint(0x01); // Entity Status packet ID
+        let packet_id = write_varint(ClientboundPlay::EntityEvent.id());
         let packet_length = (packet_id.len() + packet_data.len()) as i32;
 
         // Write packet: [length][id][data]
@@ -161,7 +270,7 @@ impl PlayStateHandler {
         Ok(())
     }
 
-    /// Send Synchronize Player Position packet (0x31 in Play state)
+    /// Send Synchronize Player Position packet
     /// Alternative to Player Position And Look, used for synchronization
     pub async fn send_synchronize_player_position<N64: Into<f64>, N32: Into<f32>>(
         stream: &mut TcpStream,
@@ -189,7 +298,97 @@ impl PlayStateHandler {
         writer.write_varint(teleport_id);
 
         let packet_data = writer.finish();
-        let packet_id = write_varint(0x31); // Synchronize Player Position packet ID
+        let packet_id = write_varint(ClientboundPlay::SynchronizePlayerPosition.id());
+        let packet_length = (packet_id.len() + packet_data.len()) as i32;
+
+        // Write packet: [length][id][data]
+        let mut frame = Vec::new();
+        frame.extend_from_slice(&write_varint(packet_length));
+        frame.extend_from_slice(&packet_id);
+        frame.extend_from_slice(&packet_data);
+
+        #[cfg(feature = "dev-sdk")]
+        let _ = &crate::LOGGER.log_server_packet(&frame);
+
+        stream.write_all(&frame).await?;
+        stream.flush().await?;
+
+        Ok(())
+    }
+
+    /// Send Unload Chunk packet
+    /// Tells the client to drop a chunk that left its view distance
+    pub async fn send_unload_chunk<S>(stream: &mut S, chunk_x: i32, chunk_z: i32) -> Result<()>
+    where
+        S: AsyncWrite + Unpin,
+    {
+        let mut writer = PacketWriter::new();
+
+        // Z comes first
+        writer.write_int(chunk_z);
+        writer.write_int(chunk_x);
+
+        let packet_data = writer.finish();
+        let packet_id = write_varint(ClientboundPlay::UnloadChunk.id());
+        let packet_length = (packet_id.len() + packet_data.len()) as i32;
+
+        let mut frame = Vec::new();
+        frame.extend_from_slice(&write_varint(packet_length));
+        frame.extend_from_slice(&packet_id);
+        frame.extend_from_slice(&packet_data);
+
+        #[cfg(feature = "dev-sdk")]
+        let _ = &crate::LOGGER.log_server_packet(&frame);
+
+        stream.write_all(&frame).await?;
+        stream.flush().await?;
+
+        Ok(())
+    }
+
+    /// Send Set Center Chunk packet
+    /// Tells the client which chunk its view distance is centered on; chunks outside that area are
+    /// discarded by the client, so this must be sent before streaming chunks around a new position
+    pub async fn send_set_center_chunk<S>(stream: &mut S, chunk_x: i32, chunk_z: i32) -> Result<()>
+    where
+        S: AsyncWrite + Unpin,
+    {
+        let mut writer = PacketWriter::new();
+
+        writer.write_varint(chunk_x);
+        writer.write_varint(chunk_z);
+
+        let packet_data = writer.finish();
+        let packet_id = write_varint(ClientboundPlay::SetCenterChunk.id());
+        let packet_length = (packet_id.len() + packet_data.len()) as i32;
+
+        // Write packet: [length][id][data]
+        let mut frame = Vec::new();
+        frame.extend_from_slice(&write_varint(packet_length));
+        frame.extend_from_slice(&packet_id);
+        frame.extend_from_slice(&packet_data);
+
+        #[cfg(feature = "dev-sdk")]
+        let _ = &crate::LOGGER.log_server_packet(&frame);
+
+        stream.write_all(&frame).await?;
+        stream.flush().await?;
+
+        Ok(())
+    }
+
+    /// Send Set Simulation Distance packet
+    /// Chunks further than this from the player are rendered but no longer tick on the client
+    pub async fn send_set_simulation_distance<S>(stream: &mut S, distance: i32) -> Result<()>
+    where
+        S: AsyncWrite + Unpin,
+    {
+        let mut writer = PacketWriter::new();
+
+        writer.write_varint(distance);
+
+        let packet_data = writer.finish();
+        let packet_id = write_varint(ClientboundPlay::SetSimulationDistance.id());
         let packet_length = (packet_id.len() + packet_data.len()) as i32;
 
         // Write packet: [length][id][data]
@@ -207,3 +406,235 @@ impl PlayStateHandler {
         Ok(())
     }
 }
+
+impl PlayStateHandler {
+    /// Send Set Health packet
+    /// Health of 0 or less makes the client show the death screen with its respawn button
+    pub async fn send_set_health<S>(stream: &mut S, health: f32, food: i32, saturation: f32) -> Result<()>
+    where
+        S: AsyncWrite + Unpin,
+    {
+        let mut writer = PacketWriter::new();
+
+        writer.write_float(health);
+        writer.write_varint(food);
+        writer.write_float(saturation);
+
+        let packet_data = writer.finish();
+        let packet_id = write_varint(ClientboundPlay::SetHealth.id());
+        let packet_length = (packet_id.len() + packet_data.len()) as i32;
+
+        // Write packet: [length][id][data]
+        let mut frame = Vec::new();
+        frame.extend_from_slice(&write_varint(packet_length));
+        frame.extend_from_slice(&packet_id);
+        frame.extend_from_slice(&packet_data);
+
+        #[cfg(feature = "dev-sdk")]
+        let _ = &crate::LOGGER.log_server_packet(&frame);
+
+        stream.write_all(&frame).await?;
+        stream.flush().await?;
+
+        Ok(())
+    }
+
+    /// Send Player Abilities packet
+    /// `flags` are the `ABILITY_*` bits; without `ABILITY_ALLOW_FLYING` the client can't take off
+    pub async fn send_player_abilities<S>(
+        stream: &mut S,
+        flags: u8,
+        fly_speed: f32,
+        fov_modifier: f32,
+    ) -> Result<()>
+    where
+        S: AsyncWrite + Unpin,
+    {
+        let mut writer = PacketWriter::new();
+
+        writer.write_byte(flags);
+        writer.write_float(fly_speed);
+        writer.write_float(fov_modifier);
+
+        let packet_data = writer.finish();
+        let packet_id = write_varint(ClientboundPlay::PlayerAbilities.id());
+        let packet_length = (packet_id.len() + packet_data.len()) as i32;
+
+        // Write packet: [length][id][data]
+        let mut frame = Vec::new();
+        frame.extend_from_slice(&write_varint(packet_length));
+        frame.extend_from_slice(&packet_id);
+        frame.extend_from_slice(&packet_data);
+
+        #[cfg(feature = "dev-sdk")]
+        let _ = &crate::LOGGER.log_server_packet(&frame);
+
+        stream.write_all(&frame).await?;
+        stream.flush().await?;
+
+        Ok(())
+    }
+
+    /// Send Set Held Item packet
+    /// Moves the client's hotbar selection to `slot`, 0 to 8
+    pub async fn send_set_held_item<S>(stream: &mut S, slot: u8) -> Result<()>
+    where
+        S: AsyncWrite + Unpin,
+    {
+        let mut writer = PacketWriter::new();
+
+        writer.write_varint(slot as i32);
+
+        let packet_data = writer.finish();
+        let packet_id = write_varint(ClientboundPlay::SetHeldItem.id());
+        let packet_length = (packet_id.len() + packet_data.len()) as i32;
+
+        // Write packet: [length][id][data]
+        let mut frame = Vec::new();
+        frame.extend_from_slice(&write_varint(packet_length));
+        frame.extend_from_slice(&packet_id);
+        frame.extend_from_slice(&packet_data);
+
+        #[cfg(feature = "dev-sdk")]
+        let _ = &crate::LOGGER.log_server_packet(&frame);
+
+        stream.write_all(&frame).await?;
+        stream.flush().await?;
+
+        Ok(())
+    }
+
+    /// Send Game Event packet
+    /// See `GameEvent` for the event ids and what `value` means for each
+    pub async fn send_game_event<S, E>(stream: &mut S, event: E, value: f32) -> Result<()>
+    where
+        S: AsyncWrite + Unpin,
+        E: Into<u8>,
+    {
+        let frame = game_event_frame(event, value);
+
+        #[cfg(feature = "dev-sdk")]
+        let _ = &crate::LOGGER.log_server_packet(&frame);
+
+        stream.write_all(&frame).await?;
+        stream.flush().await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_set_center_chunk_encoding() {
+        let mut out = Vec::new();
+        PlayStateHandler::send_set_center_chunk(&mut out, 3, -1)
+            .await
+            .unwrap();
+
+        // [length][0x57][varint 3][varint -1 (5 bytes)]
+        assert_eq!(out, vec![0x07, 0x57, 0x03, 0xFF, 0xFF, 0xFF, 0xFF, 0x0F]);
+    }
+
+    #[tokio::test]
+    async fn test_unload_chunk_encoding() {
+        let mut out = Vec::new();
+        PlayStateHandler::send_unload_chunk(&mut out, 3, -1)
+            .await
+            .unwrap();
+
+        // [length][0x21][int -1][int 3]
+        assert_eq!(out, vec![0x09, 0x21, 0xFF, 0xFF, 0xFF, 0xFF, 0x00, 0x00, 0x00, 0x03]);
+    }
+
+    #[tokio::test]
+    async fn test_set_simulation_distance_encoding() {
+        let mut out = Vec::new();
+        PlayStateHandler::send_set_simulation_distance(&mut out, 6)
+            .await
+            .unwrap();
+        assert_eq!(out, vec![0x02, 0x68, 0x06]);
+    }
+
+    #[tokio::test]
+    async fn test_game_event_encoding() {
+        let mut out = Vec::new();
+        PlayStateHandler::send_game_event(&mut out, GameEvent::StartWaitingForLevelChunks, 0.0)
+            .await
+            .unwrap();
+        assert_eq!(out, vec![0x06, 0x22, 0x0D, 0x00, 0x00, 0x00, 0x00]);
+
+        let mut out = Vec::new();
+        PlayStateHandler::send_game_event(&mut out, GameEvent::ChangeGameMode, 1.0)
+            .await
+            .unwrap();
+        assert_eq!(out, vec![0x06, 0x22, 0x03, 0x3F, 0x80, 0x00, 0x00]);
+    }
+
+    #[test]
+    fn test_abilities_flags_per_game_mode() {
+        assert_eq!(GameMode::Survival.abilities_flags(), 0x00);
+        assert_eq!(GameMode::Adventure.abilities_flags(), 0x00);
+        // Invulnerable, may fly, instant break
+        assert_eq!(GameMode::Creative.abilities_flags(), 0x0D);
+        // Invulnerable, flying, may fly
+        assert_eq!(GameMode::Spectator.abilities_flags(), 0x07);
+
+        assert!(!GameMode::Survival.can_fly());
+        assert!(GameMode::Creative.can_fly());
+        assert!(GameMode::Spectator.can_fly());
+        assert!(!GameMode::Adventure.is_invulnerable());
+    }
+
+    #[tokio::test]
+    async fn test_player_abilities_encoding() {
+        let mut out = Vec::new();
+        PlayStateHandler::send_player_abilities(
+            &mut out,
+            GameMode::Creative.abilities_flags(),
+            DEFAULT_FLY_SPEED,
+            DEFAULT_FOV_MODIFIER,
+        )
+        .await
+        .unwrap();
+
+        // [length][0x39][flags][f32 0.05][f32 0.1]
+        assert_eq!(out, vec![0x0A, 0x39, 0x0D, 0x3D, 0x4C, 0xCC, 0xCD, 0x3D, 0xCC, 0xCC, 0xCD]);
+    }
+
+    #[test]
+    fn test_held_slot_tracks_hotbar_selection() {
+        let mut held = HeldSlot::default();
+        assert_eq!(held.get(), 0);
+
+        assert_eq!(held.select_from_packet(&4i16.to_be_bytes()).unwrap(), 4);
+        assert_eq!(held.select_from_packet(&8i16.to_be_bytes()).unwrap(), 8);
+        assert_eq!(held.get(), 8);
+
+        // Out of range or truncated selections are refused and the last good slot kept
+        assert!(held.select_from_packet(&9i16.to_be_bytes()).is_err());
+        assert!(held.select_from_packet(&(-1i16).to_be_bytes()).is_err());
+        assert!(held.select_from_packet(&[0x00]).is_err());
+        assert_eq!(held.get(), 8);
+    }
+
+    #[tokio::test]
+    async fn test_set_held_item_encoding() {
+        let mut out = Vec::new();
+        PlayStateHandler::send_set_held_item(&mut out, 5).await.unwrap();
+        assert_eq!(out, vec![0x02, 0x62, 0x05]);
+    }
+
+    #[tokio::test]
+    async fn test_set_health_encoding() {
+        let mut out = Vec::new();
+        PlayStateHandler::send_set_health(&mut out, 20.0, 20, 5.0)
+            .await
+            .unwrap();
+
+        // [length][0x61][f32 20.0][varint 20][f32 5.0]
+        assert_eq!(out, vec![0x0A, 0x61, 0x41, 0xA0, 0x00, 0x00, 0x14, 0x40, 0xA0, 0x00, 0x00]);
+    }
+}
@@ -1,34 +1,97 @@
 #![allow(dead_code)]
 
-use std::io::Cursor;
+use std::collections::HashSet;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use anyhow::Result;
-use tokio::io::AsyncReadExt;
+use parking_lot::RwLock;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
 use tokio::net::TcpStream;
+use tokio::sync::mpsc::{UnboundedReceiver, unbounded_channel};
 use uuid::Uuid;
 
-use crate::chunk::ChunkStorage;
-use crate::core::{ChunkGenThreadPool, HandlerData};
-use crate::error_tracker::{ErrorKey, ErrorTracker};
-use crate::network::{LoginHandler, read_varint};
+use crate::access_control::{AFK_BYPASS_PERMISSION, insufficient_permission};
+use crate::chunk::{ChunkProvider, chunk_data_frame, spiral_chunk_offsets};
+use crate::consts::{DEFAULT_VIEW_DISTANCE, IDLE_CHECK_INTERVAL_MS};
+use crate::core::{Event, EventResult, HandlerData};
+use crate::error_tracker::ErrorKey;
+use crate::network::{
+    ClientboundPlay,
+    KnownPack,
+    LoginHandler,
+    LoginOutcome,
+    PluginMessage,
+    ProtocolVersion,
+    ServerboundPlay,
+    StatusResponse,
+    is_packet_too_large,
+    read_packet_frame,
+};
+use crate::player::block_actions::{BlockAction, BlockRequest};
+use crate::player::commands::{self, PlayerCommand};
 use crate::player::configuration::ConfigurationHandler;
-use crate::player::join_game::JoinGameHandler;
-use crate::player::{CrossAssign, Vec2, Vec3, movement_handler};
+use crate::player::health::{self, FallTracker, Health};
+use crate::player::inventory::{CreativeSlotResult, NOT_CREATIVE_REASON, item_from_name};
+use crate::player::join_game::{JoinGameHandler, JoinGameSettings};
+use crate::player::play_state::{ABILITY_FLYING, DEFAULT_FLY_SPEED, DEFAULT_FOV_MODIFIER, HeldSlot};
+use crate::player::{
+    ConnectionStage,
+    ConnectionStateTracker,
+    CrossAssign,
+    GameEvent,
+    GameMode,
+    Inventory,
+    Outbound,
+    PlayStateHandler,
+    PlayerRegistry,
+    RegisteredPlayer,
+    Vec2,
+    Vec3,
+    movement_handler,
+    player_left,
+};
 use crate::terrain::ChunkPos;
 
+/// Disconnect reason for players idle past `afk_kick_secs`
+pub const AFK_KICK_REASON: &str = "You have been idle for too long (AFK)";
+
 pub struct PlayerData<N64: Into<f64> = f64> {
     pub uuid:         Uuid,
     pub username:     String,
+    /// Allocated from `HandlerData::entity_ids` once login succeeds
+    pub entity_id:    i32,
     pub socket:       TcpStream,
     pub state:        PlayerState,
     // pub x:            f64,
     // pub y:            f64,
     // pub z:            f64,
     pub cooridinates: Vec3<N64>,
+    /// Last validated yaw/pitch reported by the client
+    pub rotation:     Vec2<f32>,
     pub last_chunk_x: i32,
     pub last_chunk_z: i32,
-    loaded_chunks:    std::collections::HashSet<ChunkPos>,
+    pub connection:   Arc<ConnectionStateTracker>,
+    /// Id of the last position sync sent, echoed back by the client's Confirm Teleportation
+    last_teleport_id: i32,
+    /// Shared with this player's registry entry so movement can be routed to players who see it
+    loaded_chunks:    Arc<RwLock<HashSet<ChunkPos>>>,
+    health:           Health,
+    fall:             FallTracker,
+    /// Chunk radius streamed to this client: the server's view distance, capped by the client's
+    view_distance:    i32,
+    /// Negotiated at login
+    protocol:         ProtocolVersion,
+    /// Data packs the client told us it shares with the server during configuration
+    known_packs:      Vec<KnownPack>,
+    /// Last packet that shows someone at the keyboard; see `is_passive_packet`
+    last_activity:    Instant,
+    game_mode:        GameMode,
+    /// Last flight state the client reported through Player Abilities
+    flying:           bool,
+    held_slot:        HeldSlot,
+    /// Shared with this player's registry entry, which keeps it once the player leaves
+    inventory:        Arc<RwLock<Inventory>>,
 }
 
 impl CrossAssign for PlayerData<f64> {
@@ -48,20 +111,60 @@ pub enum PlayerState {
     Handshake,
     Login,
     Play,
+    /// In the world, but nothing but keep-alives and standing-still movement for a while
     Idle,
 }
 
+impl PlayerState {
+    /// `Play` turns `Idle` once the player has been inactive for `timeout`
+    pub fn after_inactivity(self, inactive: Duration, timeout: Duration) -> Self {
+        match self {
+            PlayerState::Play if inactive >= timeout => PlayerState::Idle,
+            state => state,
+        }
+    }
+
+    /// Any input wakes an idle player
+    pub fn after_activity(self) -> Self {
+        match self {
+            PlayerState::Idle => PlayerState::Play,
+            state => state,
+        }
+    }
+}
+
+/// Packets the client sends on its own, so they say nothing about whether anyone is playing
+fn is_passive_packet(packet_id: i32) -> bool {
+    packet_id == ServerboundPlay::KeepAlive.id()
+        || packet_id == ServerboundPlay::ClientTickEnd.id()
+        || packet_id == ServerboundPlay::SetPlayerMovementFlags.id()
+}
+
 impl PlayerData {
     pub async fn new(socket: TcpStream) -> Result<Self> {
         Ok(Self {
             uuid: Uuid::new_v4(),
             username: String::new(),
+            entity_id: 0,
             socket,
             state: PlayerState::Handshake,
             cooridinates: Vec3::from((0.0, 64.0, 0.0)),
+            rotation: Vec2::new(0.0, 0.0),
             last_chunk_x: 0,
             last_chunk_z: 0,
-            loaded_chunks: std::collections::HashSet::new(),
+            connection: Arc::new(ConnectionStateTracker::new()),
+            last_teleport_id: 0,
+            loaded_chunks: Arc::new(RwLock::new(HashSet::new())),
+            health: Health::default(),
+            fall: FallTracker::default(),
+            view_distance: DEFAULT_VIEW_DISTANCE as i32,
+            protocol: ProtocolVersion::newest(),
+            known_packs: Vec::new(),
+            last_activity: Instant::now(),
+            game_mode: GameMode::default(),
+            flying: false,
+            held_slot: HeldSlot::default(),
+            inventory: Arc::new(RwLock::new(Inventory::default())),
         })
     }
 
@@ -71,7 +174,9 @@ impl PlayerData {
         // Wait for world initialization to complete (in blocking task to not block async runtime)
         tracing::debug!("[PLAYER] Waiting for world initialization...");
         let chunk_gen_pool_clone = Arc::clone(&hd.chunk_gen_pool);
+        let span = tracing::Span::current();
         tokio::task::spawn_blocking(move || {
+            let _span = span.enter();
             chunk_gen_pool_clone.wait_for_init_complete();
             tracing::info!("[PLAYER] World initialization complete, accepting players");
         })
@@ -79,18 +184,37 @@ impl PlayerData {
 
         // Handle login flow
         tracing::debug!("[PLAYER] Creating LoginHandler");
-        let mut login_handler = LoginHandler::from(self.socket); // new(self.socket);
+        let mut login_handler =
+            LoginHandler::from(self.socket) // new(self.socket);
+                .with_player_limit(Arc::clone(&hd.players), hd.config.max_players)
+                .with_access_control(Arc::clone(&hd.access))
+                .with_auth(Arc::clone(&hd.auth));
+        if let Some(hook) = &hd.login_plugin {
+            login_handler = login_handler.with_login_plugin(Arc::clone(hook));
+        }
 
         tracing::debug!("[PLAYER] Starting login flow");
-        let player_login = match login_handler.handle_login().await {
-            Ok(login) => {
-                tracing::debug!("[PLAYER] Login successful");
+        let player_login = match login_handler.handle_login(&self.connection).await {
+            Ok(LoginOutcome::Login(login)) => {
+                tracing::debug!("[PLAYER] Login successful ({})", login.protocol.name());
                 login
             }
+            Ok(outcome @ (LoginOutcome::Status | LoginOutcome::LegacyPing)) => {
+                tracing::debug!("[PLAYER] Serving status ping");
+                let response = StatusResponse::from_config(&hd.config)
+                    .with_players(hd.players.len() as u32, hd.players.status_sample());
+                let result = match outcome {
+                    LoginOutcome::LegacyPing => login_handler.handle_legacy_ping(&response).await,
+                    _ => login_handler.handle_status(&response).await,
+                };
+                self.connection.transition(ConnectionStage::Disconnected);
+                return result;
+            }
             Err(e) => {
                 tracing::error!("[LOGIN] Authentication failed: {}", e);
                 let key = ErrorKey::new("LOGIN", format!("auth_failed: {}", e));
                 hd.error_tracker.record_error(key);
+                self.connection.transition(ConnectionStage::Disconnected);
                 return Err(e);
             }
         };
@@ -98,29 +222,110 @@ impl PlayerData {
         tracing::debug!("[PLAYER] Extracting login info");
         self.uuid = player_login.uuid;
         self.username = player_login.username.clone();
+        self.protocol = player_login.protocol;
         self.socket = login_handler.get_stream();
         self.state = PlayerState::Login;
         tracing::debug!("[PLAYER] Player state set to Login (awaiting configuration)");
 
+        // Tag the rest of this connection's logs with who it is
+        let span = tracing::Span::current();
+        span.record("username", self.username.as_str());
+        span.record("uuid", tracing::field::display(self.uuid));
+
+        let saved = hd.players.saved_player(&self.uuid).unwrap_or_default();
+        match saved.position {
+            Some((position, rotation)) => {
+                self.cooridinates = position;
+                self.rotation = rotation;
+            }
+            // First join: the configured spawn, already raised to the surface at startup
+            None => self.cooridinates = hd.world.spawn(),
+        }
         tracing::info!("[PLAYER] '{}' ({}) joined at {}", self.username, self.uuid, self.cooridinates);
+        *self.inventory.write() = saved.inventory;
+
+        self.entity_id = hd.entity_ids.allocate();
+
+        let (outbound, mut outbound_rx) = unbounded_channel();
+        let replaced = hd.players.register(RegisteredPlayer {
+            uuid: self.uuid,
+            username: self.username.clone(),
+            entity_id: self.entity_id,
+            position: self.cooridinates,
+            rotation: self.rotation,
+            in_world: false,
+            connection: Arc::clone(&self.connection),
+            outbound,
+            loaded_chunks: Arc::clone(&self.loaded_chunks),
+            inventory: Arc::clone(&self.inventory),
+        });
+        // Registered players count against max_players themselves, so the held place can go
+        drop(player_login.slot);
+        // The session this login replaced has been kicked; its own guard won't find it any more
+        if let Some(replaced) = replaced {
+            player_left(&hd.players, &hd.entity_ids, &hd.events, replaced);
+        }
+
+        // Unregistering and freeing the entity id is left to the `DisconnectGuard` held by the caller
+        self.play(&hd, &mut outbound_rx).await
+    }
 
+    /// Configuration and Play states, run once the player is registered
+    async fn play(&mut self, hd: &HandlerData, outbound: &mut UnboundedReceiver<Outbound>) -> Result<()> {
         // Handle Configuration phase
         tracing::debug!("[PLAYER] Starting configuration phase");
-        if let Err(e) = ConfigurationHandler::handle_configuration(&mut self.socket).await {
-            tracing::error!("[PLAYER] Configuration phase failed for {}: {}", self.username, e);
-            let key = ErrorKey::new("CONFIG", format!("config_failed: {}", e));
-            hd.error_tracker.record_error(key);
-            return Err(e);
-        }
+        let configuration = ConfigurationHandler::handle_configuration(
+            &mut self.socket,
+            &hd.plugin_channels,
+            self.protocol,
+            &hd.config.dimensions,
+        )
+        .await;
+        let outcome = match configuration {
+            Ok(outcome) => outcome,
+            Err(e) => {
+                tracing::error!("[PLAYER] Configuration phase failed for {}: {}", self.username, e);
+                let key = ErrorKey::new("CONFIG", format!("config_failed: {}", e));
+                hd.error_tracker.record_error(key);
+                return Err(e);
+            }
+        };
         tracing::debug!("[PLAYER] Configuration phase complete");
 
+        self.view_distance = hd
+            .config
+            .view_distance_for(outcome.client_information.map(|info| info.view_distance))
+            as i32;
+        self.known_packs = outcome.shared_packs;
+        let simulation_distance = hd.config.simulation_distance as i32;
+        tracing::debug!(
+            "[PLAYER] View distance {} and simulation distance {} for {}",
+            self.view_distance,
+            simulation_distance,
+            self.username
+        );
+
         // Transition to Play state
         self.state = PlayerState::Play;
+        self.connection.transition(ConnectionStage::InGame);
         tracing::debug!("[PLAYER] Player state set to Play");
 
+        self.game_mode = hd.config.gamemode;
+        self.flying = self.game_mode.abilities_flags() & ABILITY_FLYING != 0;
+
         // Send join game packet
         tracing::debug!("[PLAYER] Sending Join Game packet");
-        if let Err(e) = JoinGameHandler::send_join_game(&mut self.socket, 1, &self.username).await {
+        if let Err(e) = JoinGameHandler::send_join_game(
+            &mut self.socket,
+            self.entity_id,
+            &self.username,
+            self.view_distance,
+            simulation_distance,
+            self.game_mode,
+            &JoinGameSettings::new(&hd.config, hd.world.seed()),
+        )
+        .await
+        {
             tracing::error!("[PLAYER] Failed to send join game packet to {}: {}", self.username, e);
             let key = ErrorKey::new("JOIN_GAME", "send_failed");
             hd.error_tracker.record_error(key);
@@ -128,6 +333,21 @@ impl PlayerData {
         }
         tracing::debug!("[PLAYER] Join Game sent");
 
+        PlayStateHandler::send_set_simulation_distance(&mut self.socket, simulation_distance).await?;
+
+        // Creative and spectator players can only take off once the client knows they may fly
+        if let Err(e) = self.send_abilities().await {
+            tracing::error!("[PLAYER] Failed to send player abilities to {}: {}", self.username, e);
+            let key = ErrorKey::new("ABILITIES", "send_failed");
+            hd.error_tracker.record_error(key);
+            return Err(e);
+        }
+        PlayStateHandler::send_set_held_item(&mut self.socket, self.held_slot.get()).await?;
+
+        // Nothing has changed the inventory on the client yet, so the first state id will do
+        let inventory = self.inventory.read().container_content_frame(0);
+        self.socket.write_all(&inventory).await?;
+
         // Send player info add packet
         tracing::debug!("[PLAYER] Sending Player Info Add packet");
         if let Err(e) =
@@ -142,8 +362,16 @@ impl PlayerData {
 
         // Send spawn position packet
         tracing::debug!("[PLAYER] Sending Spawn Position packet");
-        let spawn = Vec3::from((0, 64, 0));
-        if let Err(e) = JoinGameHandler::send_spawn_position(&mut self.socket, spawn, 0.0).await {
+        let spawn = hd.world.spawn();
+        if let Err(e) = PlayStateHandler::send_set_default_spawn_position(
+            &mut self.socket,
+            spawn.x.floor() as i32,
+            spawn.y.floor() as i32,
+            spawn.z.floor() as i32,
+            0.0,
+        )
+        .await
+        {
             tracing::error!("[PLAYER] Failed to send spawn position: {}", e);
             let key = ErrorKey::new("SPAWN_POS", "send_failed");
             hd.error_tracker.record_error(key);
@@ -151,12 +379,22 @@ impl PlayerData {
         }
         tracing::debug!("[PLAYER] Spawn Position sent");
 
+        // Set the sky before the first chunks arrive; the game loop keeps it in sync after that
+        self.socket.write_all(&hd.world.time().update_frame()).await?;
+        // Clients assume clear skies
+        let weather = hd.world.weather();
+        if weather.is_raining() {
+            for frame in weather.frames() {
+                self.socket.write_all(&frame).await?;
+            }
+        }
+
         // Send synchronize player position to initialize client position
         tracing::debug!("[PLAYER] Sending initial player position sync");
-        if let Err(e) = crate::player::PlayStateHandler::send_synchronize_player_position(
+        if let Err(e) = PlayStateHandler::send_synchronize_player_position(
             &mut self.socket,
             self.cooridinates,
-            Vec2::from((0.0, 0.0)),
+            Vec2::from((0.0_f32, 0.0_f32)),
             0, // teleport_id
         )
         .await
@@ -168,6 +406,37 @@ impl PlayerData {
         }
         tracing::debug!("[PLAYER] Player position sync sent");
 
+        // Let the client leave the loading terrain screen once its chunk arrives
+        if let Err(e) =
+            PlayStateHandler::send_game_event(&mut self.socket, GameEvent::StartWaitingForLevelChunks, 0.0)
+                .await
+        {
+            tracing::error!("[PLAYER] Failed to send start waiting for chunks event: {}", e);
+            let key = ErrorKey::new("GAME_EVENT", "send_failed");
+            hd.error_tracker.record_error(key);
+            return Err(e);
+        }
+
+        if let Err(e) = self.send_health().await {
+            tracing::error!("[PLAYER] Failed to send health: {}", e);
+            let key = ErrorKey::new("HEALTH", "send_failed");
+            hd.error_tracker.record_error(key);
+            return Err(e);
+        }
+
+        // Center the client's view on the spawn chunk before streaming chunks
+        let spawn_chunk = Self::chunk_of(self.cooridinates);
+        self.last_chunk_x = spawn_chunk.x;
+        self.last_chunk_z = spawn_chunk.z;
+        if let Err(e) =
+            PlayStateHandler::send_set_center_chunk(&mut self.socket, spawn_chunk.x, spawn_chunk.z).await
+        {
+            tracing::error!("[PLAYER] Failed to send center chunk: {}", e);
+            let key = ErrorKey::new("CENTER_CHUNK", "send_failed");
+            hd.error_tracker.record_error(key);
+            return Err(e);
+        }
+
         // Load initial chunks around player and send to client
         {
             let socket = &mut self.socket;
@@ -177,8 +446,9 @@ impl PlayerData {
                 // self.x,
                 // self.y,
                 // self.z,
-                &hd.chunk_storage,
-                &mut self.loaded_chunks,
+                hd.world.chunks().as_ref(),
+                &self.loaded_chunks,
+                self.view_distance,
             )
             .await
             {
@@ -189,201 +459,861 @@ impl PlayerData {
             }
         }
 
+        // Show this player to everyone already in the world, and them to this player
+        hd.players.enter_world(&self.uuid);
+        hd.events.emit(&Event::PlayerJoin {
+            uuid:     self.uuid,
+            username: self.username.clone(),
+        });
+
         tracing::info!("[PLAYER] {} ready to play at {}", self.username, self.cooridinates);
         tracing::debug!("[PLAYER] Starting main game loop");
 
         // Main game loop for this player
+        let idle_timeout = hd.config.idle_timeout();
+        let afk_kick = hd.config.afk_kick_timeout();
+        let mut idle_check = tokio::time::interval(Duration::from_millis(IDLE_CHECK_INTERVAL_MS));
+        self.last_activity = Instant::now();
         loop {
-            // Try to read incoming packets from client
-            {
-                let socket = &mut self.socket;
-                // let logger = &self.packet_logger;
-                match Self::handle_incoming_packets_static(
-                    //
-                    socket,
-                    &mut self.cooridinates,
-                    // &mut self.x,
-                    // &mut self.y,
-                    // &mut self.z,
-                )
-                .await
-                {
-                    Ok(_) => {}
-                    Err(e) => {
-                        tracing::error!("[PLAYER] {} packet read error: {}", self.username, e);
-                        return Err(e);
+            tokio::select! {
+                // Waiting for readability is cancel-safe, so a queued packet never interrupts a
+                // half-read frame
+                readable = self.socket.readable() => {
+                    readable?;
+                    self.handle_incoming(hd).await?;
+                }
+                Some(message) = outbound.recv() => {
+                    match message {
+                        Outbound::Packet(frame) => {
+                            #[cfg(feature = "dev-sdk")]
+                            let _ = &crate::LOGGER.log_server_packet(&frame);
+
+                            self.socket.write_all(&frame).await?;
+                            self.socket.flush().await?;
+                        }
+                        Outbound::Kick(reason) => {
+                            tracing::info!("[PLAYER] Kicking {}: {}", self.username, reason);
+                            JoinGameHandler::send_disconnect(&mut self.socket, &reason).await?;
+                            return Ok(());
+                        }
+                    }
+                }
+                _ = idle_check.tick(), if idle_timeout.is_some() || afk_kick.is_some() => {
+                    if let Some(timeout) = idle_timeout {
+                        self.check_idle(timeout);
+                    }
+                    if let Some(limit) = afk_kick {
+                        let bypass = hd.access.permits(self.uuid, AFK_BYPASS_PERMISSION);
+                        let inactive = self.last_activity.elapsed();
+                        if Self::kick_if_afk(&hd.players, &self.uuid, inactive, limit, bypass) {
+                            tracing::info!("[PLAYER] Kicking {} after {}s AFK", self.username, inactive.as_secs());
+                        }
                     }
                 }
             }
+        }
+    }
+
+    /// Note that someone is playing, waking the player if they were idle
+    fn record_activity(&mut self) {
+        self.last_activity = Instant::now();
+        let state = self.state.clone().after_activity();
+        if state != self.state {
+            tracing::info!("[PLAYER] {} is no longer idle", self.username);
+            self.state = state;
+        }
+    }
+
+    /// Queue an AFK kick through the registry once `inactive` reaches `limit`, unless the player
+    /// may `bypass` it; returns whether the kick was queued
+    fn kick_if_afk(
+        players: &PlayerRegistry,
+        uuid: &Uuid,
+        inactive: Duration,
+        limit: Duration,
+        bypass: bool,
+    ) -> bool {
+        if bypass || inactive < limit {
+            return false;
+        }
+        players
+            .get(uuid)
+            .is_some_and(|player| player.kick(AFK_KICK_REASON))
+    }
+
+    /// Mark the player idle once they have gone `timeout` without input
+    fn check_idle(&mut self, timeout: Duration) {
+        let state = self
+            .state
+            .clone()
+            .after_inactivity(self.last_activity.elapsed(), timeout);
+        if state != self.state {
+            tracing::info!("[PLAYER] {} is idle after {}s without input", self.username, timeout.as_secs());
+            self.state = state;
+        }
+    }
+
+    /// Read one packet from the client and react to any movement or command
+    async fn handle_incoming(&mut self, hd: &HandlerData) -> Result<()> {
+        // Read a full frame; partial and merged TCP reads are handled by the frame reader
+        let (packet_id, payload) = match read_packet_frame(&mut self.socket).await {
+            Ok(frame) => frame,
+            Err(e) => {
+                tracing::error!("[PLAYER] {} packet read error: {}", self.username, e);
+                if is_packet_too_large(&e) {
+                    hd.error_tracker
+                        .record_error(ErrorKey::new("NETWORK", "packet_too_large"));
+                }
+                return Err(e);
+            }
+        };
+
+        if let Some(command) = commands::command_from_packet(packet_id, &payload) {
+            self.run_command(hd, &command).await?;
+        }
+
+        if let Some(message) = commands::chat_from_packet(packet_id, &payload) {
+            let chat = Event::Chat {
+                uuid:    self.uuid,
+                message: message.clone(),
+            };
+            if hd.events.emit(&chat) == EventResult::Continue {
+                tracing::info!("[CHAT] <{}> {}", self.username, message);
+            }
+        }
+
+        let before = (self.cooridinates, self.rotation);
+        let movement =
+            Self::handle_movement_packet(packet_id, &payload, &mut self.cooridinates, &mut self.rotation);
+        if let Some(movement) = &movement {
+            hd.players
+                .move_player(&self.uuid, self.cooridinates, self.rotation, movement.is_on_ground());
+            self.apply_fall_damage(movement.is_on_ground()).await?;
+        }
+
+        // Clients repeat their position every second even when standing still
+        let moved = (self.cooridinates, self.rotation) != before;
+        if moved || (movement.is_none() && !is_passive_packet(packet_id)) {
+            self.record_activity();
+        }
+
+        if packet_id == ServerboundPlay::PluginMessage.id() {
+            self.handle_plugin_message(hd, &payload).await?;
+        }
+
+        if packet_id == ServerboundPlay::PlayerAbilities.id() {
+            self.handle_abilities(&payload).await?;
+        }
+
+        if packet_id == ServerboundPlay::SetCreativeModeSlot.id() {
+            self.handle_creative_slot(hd, &payload).await?;
+        }
+
+        if packet_id == ServerboundPlay::PlayerAction.id() || packet_id == ServerboundPlay::UseItemOn.id() {
+            self.handle_block_interaction(hd, packet_id, &payload);
+        }
+
+        if packet_id == ServerboundPlay::SetHeldItem.id() {
+            match self.held_slot.select_from_packet(&payload) {
+                Ok(slot) => tracing::debug!("[PLAYER] {} selected hotbar slot {}", self.username, slot),
+                Err(e) => {
+                    tracing::warn!("[PLAYER] {} sent a bad held item: {}", self.username, e);
+                    // Put the client's selection back where the server has it
+                    PlayStateHandler::send_set_held_item(&mut self.socket, self.held_slot.get()).await?;
+                }
+            }
+        }
+
+        if health::is_respawn_request(packet_id, &payload) {
+            self.respawn(hd).await?;
+        }
+
+        self.refresh_chunks(hd).await
+    }
+
+    /// Answer a plugin message on a channel the server handles
+    async fn handle_plugin_message(&mut self, hd: &HandlerData, payload: &[u8]) -> Result<()> {
+        let message = match PluginMessage::parse(payload) {
+            Ok(message) => message,
+            Err(e) => {
+                tracing::warn!("[PLAYER] Malformed plugin message from {}: {}", self.username, e);
+                return Ok(());
+            }
+        };
+
+        if let Some(reply) = hd.plugin_channels.dispatch(&message) {
+            self.socket
+                .write_all(&reply.frame(ClientboundPlay::PluginMessage.id()))
+                .await?;
+            self.socket.flush().await?;
+        }
+        Ok(())
+    }
+
+    async fn send_abilities(&mut self) -> Result<()> {
+        let mut flags = self.game_mode.abilities_flags();
+        if self.flying {
+            flags |= ABILITY_FLYING;
+        }
+        PlayStateHandler::send_player_abilities(
+            &mut self.socket,
+            flags,
+            DEFAULT_FLY_SPEED,
+            DEFAULT_FOV_MODIFIER,
+        )
+        .await
+    }
+
+    /// Take the flight toggle from a client's Player Abilities, or land a client that isn't allowed
+    /// to fly by resending its abilities
+    async fn handle_abilities(&mut self, payload: &[u8]) -> Result<()> {
+        let Some(&flags) = payload.first() else {
+            tracing::warn!("[PLAYER] Empty player abilities from {}", self.username);
+            return Ok(());
+        };
+
+        let flying = flags & ABILITY_FLYING != 0;
+        if flying && !self.game_mode.can_fly() {
+            tracing::warn!("[PLAYER] {} tried to fly in {:?}", self.username, self.game_mode);
+            return self.send_abilities().await;
+        }
+
+        if flying != self.flying {
+            tracing::debug!(
+                "[PLAYER] {} {} flying",
+                self.username,
+                if flying { "started" } else { "stopped" }
+            );
+        }
+        self.flying = flying;
+        Ok(())
+    }
+
+    /// Put a creative player's item in its inventory; anyone else trying it is kicked
+    async fn handle_creative_slot(&mut self, hd: &HandlerData, payload: &[u8]) -> Result<()> {
+        let result = self
+            .inventory
+            .write()
+            .apply_creative_slot(self.game_mode, payload);
+        match result {
+            CreativeSlotResult::Set(slot) => {
+                tracing::debug!("[INVENTORY] {} set slot {} in creative", self.username, slot);
+            }
+            CreativeSlotResult::Dropped(stack) => {
+                tracing::debug!("[INVENTORY] {} dropped {:?} in creative", self.username, stack);
+            }
+            CreativeSlotResult::Refused(e) => {
+                tracing::warn!("[INVENTORY] Refused creative slot from {}: {}", self.username, e);
+                let inventory = self.inventory.read().container_content_frame(0);
+                self.socket.write_all(&inventory).await?;
+                self.socket.flush().await?;
+            }
+            CreativeSlotResult::NotCreative => {
+                tracing::warn!("[INVENTORY] {} sent a creative slot in {:?}", self.username, self.game_mode);
+                if let Some(player) = hd.players.get(&self.uuid) {
+                    player.kick(NOT_CREATIVE_REASON);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Hand a dig or placement to the game loop, which makes the change and answers the client
+    fn handle_block_interaction(&self, hd: &HandlerData, packet_id: i32, payload: &[u8]) {
+        let request = if packet_id == ServerboundPlay::PlayerAction.id() {
+            BlockRequest::from_player_action(self.uuid, self.game_mode, payload)
+        } else {
+            let inventory = self.inventory.read();
+            BlockRequest::from_use_item_on(
+                self.uuid,
+                self.game_mode,
+                self.held_slot.get(),
+                &inventory,
+                payload,
+            )
+            .map(Some)
+        };
+
+        let mut request = match request {
+            Ok(Some(request)) => request,
+            Ok(None) => return,
+            Err(e) => {
+                tracing::warn!("[PLAYER] {} sent a bad block interaction: {}", self.username, e);
+                return;
+            }
+        };
+        if !request.in_reach(self.cooridinates) {
+            tracing::debug!("[PLAYER] {} tried to reach {}", self.username, request.position);
+            request.action = BlockAction::Refuse;
+        }
+        if hd.block_requests.send(request).is_err() {
+            tracing::warn!(
+                "[PLAYER] Dropped a block interaction from {}; the game loop is gone",
+                self.username
+            );
+        }
+    }
+
+    async fn send_health(&mut self) -> Result<()> {
+        let Health {
+            health,
+            food,
+            saturation,
+        } = self.health;
+        PlayStateHandler::send_set_health(&mut self.socket, health, food, saturation).await
+    }
+
+    /// Hurt the player if this movement landed a fall; reaching 0 health shows the death screen
+    async fn apply_fall_damage(&mut self, on_ground: bool) -> Result<()> {
+        if self.health.is_dead() {
+            return Ok(());
+        }
+
+        let Some(distance) = self.fall.update(self.cooridinates.y, on_ground) else {
+            return Ok(());
+        };
+        if self.game_mode.is_invulnerable() {
+            return Ok(());
+        }
+        let damage = health::fall_damage(distance);
+        if damage <= 0.0 {
+            return Ok(());
+        }
 
-            // Update loaded chunks based on player position
-            if self.check_chunk_changed(&hd.chunk_storage).await? {
-                // Player moved to a different chunk - send new chunks
-                let socket = &mut self.socket;
-                if let Err(e) = Self::send_chunks_around_static(
-                    socket,
-                    &mut self.cooridinates,
-                    &hd.chunk_storage,
-                    &mut self.loaded_chunks,
+        self.health.damage(damage);
+        tracing::debug!(
+            "[PLAYER] {} fell {:.1} blocks, {} damage ({} health left)",
+            self.username,
+            distance,
+            damage,
+            self.health.health
+        );
+        if self.health.is_dead() {
+            tracing::info!("[PLAYER] {} died from a {:.1} block fall", self.username, distance);
+        }
+
+        self.send_health().await
+    }
+
+    /// Answer the death screen's respawn button: full health, back at the world spawn
+    async fn respawn(&mut self, hd: &HandlerData) -> Result<()> {
+        if !self.health.is_dead() {
+            return Ok(());
+        }
+
+        self.health = Health::default();
+        self.fall.reset();
+        self.cooridinates = hd.world.spawn();
+        self.last_teleport_id += 1;
+        PlayStateHandler::send_synchronize_player_position(
+            &mut self.socket,
+            self.cooridinates,
+            self.rotation,
+            self.last_teleport_id,
+        )
+        .await?;
+        hd.players
+            .move_player(&self.uuid, self.cooridinates, self.rotation, false);
+        self.send_health().await?;
+
+        tracing::info!("[PLAYER] {} respawned at {}", self.username, self.cooridinates);
+        Ok(())
+    }
+
+    /// Run a `/` command typed by this player, answering in chat
+    async fn run_command(&mut self, hd: &HandlerData, input: &str) -> Result<()> {
+        tracing::info!("[PLAYER] {} issued command: /{}", self.username, input);
+
+        let name = commands::command_name(input);
+        if !hd.access.permits(self.uuid, &name) {
+            tracing::info!("[PLAYER] {} lacks permission for /{}", self.username, name);
+            let frame = commands::system_chat_frame(&insufficient_permission(&name));
+            self.socket.write_all(&frame).await?;
+            self.socket.flush().await?;
+            return Ok(());
+        }
+
+        let reply = match commands::parse_player_command(input, self.cooridinates) {
+            Ok(PlayerCommand::Teleport(target)) => {
+                self.cooridinates = target;
+                self.fall.reset();
+                self.last_teleport_id += 1;
+                PlayStateHandler::send_synchronize_player_position(
+                    &mut self.socket,
+                    target,
+                    self.rotation,
+                    self.last_teleport_id,
                 )
-                .await
-                {
-                    tracing::warn!("[PLAYER] Failed to send chunks to {}: {}", self.username, e);
+                .await?;
+                hd.players.move_player(&self.uuid, target, self.rotation, false);
+                self.refresh_chunks(hd).await?;
+
+                format!("Teleported to {:.2} {:.2} {:.2}", target.x, target.y, target.z)
+            }
+            Ok(PlayerCommand::Give { item, count }) => {
+                match item_from_name(&item) {
+                    Some(item_id) => {
+                        let left = self.inventory.write().give(item_id, count);
+                        let inventory = self.inventory.read().container_content_frame(0);
+                        self.socket.write_all(&inventory).await?;
+                        match left {
+                            0 => format!("Gave {} x {}", count, item),
+                            _ => format!("Gave {} x {}; {} didn't fit", count - left, item, left),
+                        }
+                    }
+                    None => format!("Unknown item: {}", item),
+                }
+            }
+            Ok(PlayerCommand::Weather(weather)) => {
+                hd.world.set_weather(weather);
+                for frame in weather.frames() {
+                    hd.players.broadcast(&frame);
                 }
+                format!("Set the weather to {}", weather)
+            }
+            Ok(PlayerCommand::Seed) => commands::seed_message(hd.world.seed()),
+            Ok(PlayerCommand::SaveAll { .. }) => {
+                let world = Arc::clone(&hd.world);
+                match tokio::task::spawn_blocking(move || world.save()).await? {
+                    Ok(summary) => format!("Saved the game: {}", summary),
+                    Err(e) => {
+                        tracing::warn!("[PLAYER] Save requested by {} failed: {}", self.username, e);
+                        "Saving failed, see the server log".to_string()
+                    }
+                }
+            }
+            Ok(PlayerCommand::Regen(pos)) => {
+                let chunks = Arc::clone(hd.world.chunks());
+                let chunk = tokio::task::spawn_blocking(move || chunks.regenerate_chunk(pos)).await?;
+                let resent = hd.players.send_to_chunk_watchers(pos, &chunk_data_frame(&chunk));
+                format!("Regenerated chunk {}, resent to {} players", pos, resent)
             }
+            Err(e) => e.to_string(),
+        };
 
-            tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
+        let frame = commands::system_chat_frame(&reply);
+        self.socket.write_all(&frame).await?;
+        self.socket.flush().await?;
+        Ok(())
+    }
+
+    /// Re-center the client and stream chunks if the player crossed a chunk boundary
+    async fn refresh_chunks(&mut self, hd: &HandlerData) -> Result<()> {
+        // Update loaded chunks based on player position
+        if Self::check_chunk_changed(
+            &mut self.socket,
+            self.cooridinates,
+            &mut self.last_chunk_x,
+            &mut self.last_chunk_z,
+        )
+        .await?
+        {
+            // Player moved to a different chunk - send new chunks
+            let socket = &mut self.socket;
+            if let Err(e) = Self::send_chunks_around_static(
+                socket,
+                &mut self.cooridinates,
+                hd.world.chunks().as_ref(),
+                &self.loaded_chunks,
+                self.view_distance,
+            )
+            .await
+            {
+                tracing::warn!("[PLAYER] Failed to send chunks to {}: {}", self.username, e);
+            }
         }
+
+        Ok(())
     }
 
-    async fn check_chunk_changed(&mut self, _chunk_storage: &ChunkStorage) -> Result<bool> {
-        // Calculate current chunk position
-        let current_chunk_x = (self.cooridinates.x / 16.0) as i32;
-        let current_chunk_z = (self.cooridinates.z / 16.0) as i32;
+    /// Chunk containing the given block coordinates
+    fn chunk_of(coords: Vec3<f64>) -> ChunkPos {
+        ChunkPos::from_world(coords.x, coords.z)
+    }
+
+    /// Check whether the player crossed into another chunk, re-centering the client's view if so
+    async fn check_chunk_changed<S>(
+        stream: &mut S,
+        coords: Vec3<f64>,
+        last_chunk_x: &mut i32,
+        last_chunk_z: &mut i32,
+    ) -> Result<bool>
+    where
+        S: AsyncWrite + Unpin,
+    {
+        let current = Self::chunk_of(coords);
 
         // Check if player moved to a different chunk
-        if current_chunk_x != self.last_chunk_x || current_chunk_z != self.last_chunk_z {
-            self.last_chunk_x = current_chunk_x;
-            self.last_chunk_z = current_chunk_z;
+        if current.x != *last_chunk_x || current.z != *last_chunk_z {
+            *last_chunk_x = current.x;
+            *last_chunk_z = current.z;
+            PlayStateHandler::send_set_center_chunk(stream, current.x, current.z).await?;
             Ok(true)
         } else {
             Ok(false)
         }
     }
 
-    async fn send_chunks_around_static<N64>(
-        socket: &mut TcpStream,
+    /// Unload the chunks that fell out of `radius` around the player, then send the ones missing
+    /// Pruning `loaded_chunks` stops movement and chunk updates reaching a player who walked away,
+    /// and lets a chunk be sent again when they come back
+    async fn send_chunks_around_static<S, N64>(
+        socket: &mut S,
         vec_3: &mut Vec3<N64>,
-        chunk_storage: &ChunkStorage,
-        loaded_chunks: &mut std::collections::HashSet<ChunkPos>,
+        chunk_storage: &impl ChunkProvider,
+        loaded_chunks: &RwLock<HashSet<ChunkPos>>,
+        radius: i32,
     ) -> Result<()>
     where
+        S: AsyncWrite + Unpin,
         N64: Into<f64>,
         N64: Copy,
     {
-        let chunk_x = (vec_3.x.into() / 16.0) as i32;
-        let chunk_z = (vec_3.z.into() / 16.0) as i32;
-
-        // Load a 5x5 chunk radius around player
-        for cx in (chunk_x - 2)..=(chunk_x + 2) {
-            for cz in (chunk_z - 2)..=(chunk_z + 2) {
-                let pos = ChunkPos::new(cx, cz);
-
-                if !loaded_chunks.contains(&pos) {
-                    match chunk_storage.get_chunk(pos) {
-                        Ok(chunk) => {
-                            // Send chunk to client
-                            if let Err(e) = &crate::chunk::send_chunk(socket, &chunk).await {
-                                tracing::warn!("[CHUNK] Failed to send chunk {}: {}", pos, e);
-                            } else {
-                                loaded_chunks.insert(pos);
-                                tracing::debug!("[CHUNK] Sent chunk {}", pos);
-                            }
-                        }
-                        Err(e) => {
-                            tracing::warn!("[CHUNK] Failed to load chunk {}: {}", pos, e);
-                        }
+        let center = ChunkPos::from_world(vec_3.x.into(), vec_3.z.into());
+
+        let out_of_view: Vec<_> = loaded_chunks
+            .read()
+            .iter()
+            .filter(|pos| pos.distance_chebyshev(&center) > radius)
+            .copied()
+            .collect();
+        for pos in out_of_view {
+            PlayStateHandler::send_unload_chunk(socket, pos.x, pos.z).await?;
+            loaded_chunks.write().remove(&pos);
+            tracing::debug!("[CHUNK] Unloaded chunk {}", pos);
+        }
+
+        // Queue the missing chunks within the view distance on the generation pool all at once, then
+        // send them spiralling out from the player as they become ready
+        let requests: Vec<_> = spiral_chunk_offsets(radius)
+            .map(|(dx, dz)| ChunkPos::new(center.x + dx, center.z + dz))
+            .filter(|pos| !loaded_chunks.read().contains(pos))
+            .map(|pos| (pos, chunk_storage.get_chunk_async(pos)))
+            .collect();
+
+        for (pos, request) in requests {
+            match request.await {
+                Ok(chunk) => {
+                    // Send chunk to client
+                    if let Err(e) = &crate::chunk::send_chunk(socket, &chunk).await {
+                        tracing::warn!("[CHUNK] Failed to send chunk {}: {}", pos, e);
+                    } else {
+                        loaded_chunks.write().insert(pos);
+                        tracing::debug!("[CHUNK] Sent chunk {}", pos);
                     }
                 }
+                Err(e) => {
+                    tracing::warn!("[CHUNK] Failed to load chunk {}: {}", pos, e);
+                }
             }
         }
 
         Ok(())
     }
 
-    async fn handle_incoming_packets_static(socket: &mut TcpStream, vec_3: &mut Vec3<f64>) -> Result<()> {
-        // Read packet length
-        let mut length_bytes = [0u8; 5];
-        let n = socket.read(&mut length_bytes).await?;
-
-        if n == 0 {
-            // Client disconnected
-            tracing::warn!("[PACKET] Client disconnected (read 0 bytes)");
-            return Err(anyhow::anyhow!("Client disconnected"));
+    /// Store a client rotation if it is valid, keeping the previous one otherwise
+    fn apply_rotation(rotation: &mut Vec2<f32>, incoming: Vec2<f32>) {
+        match movement_handler::validate_rotation(incoming) {
+            Ok(valid) => rotation.cross_assign(valid),
+            Err(e) => tracing::warn!("[PLAYER] Ignoring look update: {}", e),
         }
+    }
 
-        tracing::trace!("[PACKET] Read {} bytes for packet header", n);
+    /// Apply a movement packet to the player's position/rotation, returning it if it was one
+    fn handle_movement_packet(
+        packet_id: i32,
+        payload: &[u8],
+        vec_3: &mut Vec3<f64>,
+        rotation: &mut Vec2<f32>,
+    ) -> Option<movement_handler::MovementPacket> {
+        // Handle movement packets
+        if let Ok(Some(movement)) = movement_handler::parse_movement_packet(packet_id, payload) {
+            match movement {
+                movement_handler::MovementPacket::Position(pos) => {
+                    let pos: Vec3<f64> =
+                        Vec3::from((pos.coordinates.x, pos.coordinates.y, pos.coordinates.z));
 
-        // Parse varint length
-        let mut cursor = Cursor::new(&length_bytes[..n]);
-        let packet_length = match read_varint(&mut cursor) {
-            Ok(len) => {
-                tracing::trace!("[PACKET] Packet length: {}", len);
-                len as usize
-            }
-            Err(e) => {
-                tracing::trace!("[PACKET] Could not parse varint: {}, trying again later", e);
-                return Ok(()); // Incomplete packet, try again later
-            }
-        };
+                    vec_3.cross_assign(pos);
 
-        // Read packet data
-        let mut packet_data = vec![0u8; packet_length];
-        match socket.read_exact(&mut packet_data).await {
-            Ok(_) => {
-                tracing::trace!("[PACKET] Read packet data ({} bytes)", packet_length);
-
-                // Log the full packet (length + data)
-                let mut full_packet = length_bytes[..n].to_vec();
-                full_packet.extend_from_slice(&packet_data);
-                #[cfg(feature = "dev-sdk")]
-                let _ = &crate::LOGGER.log_client_packet(&full_packet);
-
-                // Parse packet ID
-                let mut cursor = Cursor::new(&packet_data[..]);
-                if let Ok(packet_id) = read_varint(&mut cursor) {
-                    let pos = cursor.position() as usize;
-                    let payload = &packet_data[pos..];
-
-                    tracing::trace!(
-                        "[PACKET] Packet ID: 0x{:02x}, payload: {} bytes",
-                        packet_id,
-                        payload.len()
-                    );
-
-                    // Handle movement packets
-                    if let Ok(Some(movement)) = movement_handler::parse_movement_packet(packet_id, payload) {
-                        match movement {
-                            movement_handler::MovementPacket::Position(pos) => {
-                                let pos: Vec3<f64> =
-                                    Vec3::from((pos.coordinates.x, pos.coordinates.y, pos.coordinates.z));
-
-                                let mut v3: Vec3<f64> = Into::into(*vec_3);
-                                CrossAssign::cross_assign(&mut v3, pos);
-
-                                tracing::debug!("[PLAYER] moved to {}", pos);
-                            }
-                            movement_handler::MovementPacket::PositionAndLook(pos) => {
-                                let pos_and_look =
-                                    Vec3::from((pos.coordinates.x, pos.coordinates.y, pos.coordinates.z));
-
-                                let mut v3: Vec3<f64> = Into::into(*vec_3);
-                                CrossAssign::cross_assign(&mut v3, pos_and_look);
-
-                                // where x, y, z are now vec_3.x, vec_3.y, vec_3.z
-                                // *x = pos.x;
-                                // *y = pos.y;
-                                // *z = pos.z;
-                                tracing::debug!("[PLAYER] moved to {}", pos_and_look);
-                            }
-                            movement_handler::MovementPacket::Look(_) => {
-                                // Handle rotation only - no position update
-                            }
-                        }
-                    }
+                    tracing::debug!("[PLAYER] moved to {}", pos);
+                }
+                movement_handler::MovementPacket::PositionAndLook(pos) => {
+                    let pos_and_look = Vec3::from((pos.coordinates.x, pos.coordinates.y, pos.coordinates.z));
+
+                    vec_3.cross_assign(pos_and_look);
+
+                    // where x, y, z are now vec_3.x, vec_3.y, vec_3.z
+                    // *x = pos.x;
+                    // *y = pos.y;
+                    // *z = pos.z;
+                    tracing::debug!("[PLAYER] moved to {}", pos_and_look);
+
+                    Self::apply_rotation(rotation, pos.rotation);
+                }
+                movement_handler::MovementPacket::Look(look) => {
+                    // Handle rotation only - no position update
+                    Self::apply_rotation(rotation, look.rotation);
                 }
             }
-            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
-                // No data available, try again later
-            }
-            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
-                // Client disconnected gracefully
-                tracing::debug!("[PACKET] Client disconnected (unexpected EOF)");
-                return Err(anyhow::anyhow!("Client disconnected"));
+            return Some(movement);
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::InMemoryChunkProvider;
+    use crate::network::PacketReader;
+    use crate::terrain::FlatWorldGenerator;
+
+    #[test]
+    fn test_chunk_of_negative_coordinates() {
+        assert_eq!(PlayerData::<f64>::chunk_of(Vec3::new(15.9, 64.0, 0.0)), ChunkPos::new(0, 0));
+        assert_eq!(PlayerData::<f64>::chunk_of(Vec3::new(16.0, 64.0, -0.5)), ChunkPos::new(1, -1));
+        assert_eq!(PlayerData::<f64>::chunk_of(Vec3::new(-16.0, 64.0, -17.0)), ChunkPos::new(-1, -2));
+    }
+
+    #[test]
+    fn test_invalid_rotation_keeps_previous() {
+        let mut rotation = Vec2::new(10.0, 20.0);
+
+        PlayerData::<f64>::apply_rotation(&mut rotation, Vec2::new(f32::NAN, 0.0));
+        assert_eq!((rotation.yaw, rotation.pitch), (10.0, 20.0));
+
+        PlayerData::<f64>::apply_rotation(&mut rotation, Vec2::new(190.0, -100.0));
+        assert_eq!((rotation.yaw, rotation.pitch), (-170.0, -90.0));
+    }
+
+    #[tokio::test]
+    async fn test_crossing_chunk_boundary_sends_center_chunk() {
+        let mut out = Vec::new();
+        let (mut last_x, mut last_z) = (0, 0);
+
+        // Moving within the same chunk sends nothing
+        let changed = PlayerData::<f64>::check_chunk_changed(
+            &mut out,
+            Vec3::new(8.0, 64.0, 8.0),
+            &mut last_x,
+            &mut last_z,
+        )
+        .await
+        .unwrap();
+        assert!(!changed);
+        assert!(out.is_empty());
+
+        // Crossing into chunk (1, 0)
+        let changed = PlayerData::<f64>::check_chunk_changed(
+            &mut out,
+            Vec3::new(17.0, 64.0, 8.0),
+            &mut last_x,
+            &mut last_z,
+        )
+        .await
+        .unwrap();
+        assert!(changed);
+        assert_eq!((last_x, last_z), (1, 0));
+
+        let mut expected = Vec::new();
+        PlayStateHandler::send_set_center_chunk(&mut expected, 1, 0)
+            .await
+            .unwrap();
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn test_player_goes_idle_after_the_timeout_and_wakes_on_input() {
+        let timeout = Duration::from_secs(300);
+
+        let state = PlayerState::Play.after_inactivity(Duration::from_secs(299), timeout);
+        assert_eq!(state, PlayerState::Play);
+        let state = state.after_inactivity(timeout, timeout);
+        assert_eq!(state, PlayerState::Idle);
+        // Staying away keeps them idle
+        let state = state.after_inactivity(Duration::from_secs(3_600), timeout);
+        assert_eq!(state, PlayerState::Idle);
+
+        assert_eq!(state.after_activity(), PlayerState::Play);
+        assert_eq!(PlayerState::Play.after_activity(), PlayerState::Play);
+
+        // Only players in the world go idle
+        assert_eq!(
+            PlayerState::Login.after_inactivity(Duration::from_secs(3_600), timeout),
+            PlayerState::Login
+        );
+        assert_eq!(PlayerState::Login.after_activity(), PlayerState::Login);
+    }
+
+    #[test]
+    fn test_afk_kick_past_the_threshold_only() {
+        let players = PlayerRegistry::new();
+        let limit = Duration::from_secs(900);
+        let mut outbound = Vec::new();
+        for name in ["Away", "Back", "Op"] {
+            let (player, rx) = RegisteredPlayer::test(name, outbound.len() as i32);
+            let player = RegisteredPlayer {
+                in_world: true,
+                ..player
+            };
+            outbound.push((player.uuid, rx));
+            players.register(player);
+        }
+        let [(away, away_rx), (back, back_rx), (op, op_rx)] = &mut outbound[..] else {
+            unreachable!()
+        };
+
+        assert!(PlayerData::<f64>::kick_if_afk(&players, away, Duration::from_secs(901), limit, false));
+        assert_eq!(away_rx.try_recv().unwrap(), Outbound::Kick(AFK_KICK_REASON.to_string()));
+
+        assert!(!PlayerData::<f64>::kick_if_afk(&players, back, Duration::from_secs(899), limit, false));
+        assert!(back_rx.try_recv().is_err());
+
+        assert!(!PlayerData::<f64>::kick_if_afk(&players, op, Duration::from_secs(3_600), limit, true));
+        assert!(op_rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_passive_packets_are_not_activity() {
+        assert!(is_passive_packet(ServerboundPlay::KeepAlive.id()));
+        assert!(is_passive_packet(ServerboundPlay::ClientTickEnd.id()));
+        assert!(is_passive_packet(ServerboundPlay::SetPlayerMovementFlags.id()));
+        assert!(!is_passive_packet(ServerboundPlay::ChatMessage.id()));
+        assert!(!is_passive_packet(ServerboundPlay::SetPlayerRotation.id()));
+    }
+
+    /// Positions of the packets in `out` with id `packet_id`, in the order they were sent
+    fn chunk_packets(out: &[u8], packet_id: ClientboundPlay) -> Vec<ChunkPos> {
+        let mut reader = PacketReader::new(out);
+        let mut sent = Vec::new();
+        while reader.remaining() > 0 {
+            let length = reader.read_varint().unwrap() as usize;
+            let frame = reader.read_bytes(length).unwrap();
+            let mut packet = PacketReader::new(&frame);
+            if packet.read_varint().unwrap() != packet_id.id() {
+                continue;
             }
-            Err(e) => return Err(e.into()),
+            let (first, second) = (packet.read_int().unwrap(), packet.read_int().unwrap());
+            sent.push(match packet_id {
+                ClientboundPlay::UnloadChunk => ChunkPos::new(second, first),
+                _ => ChunkPos::new(first, second),
+            });
         }
+        sent
+    }
 
-        Ok(())
+    fn sent_chunks(out: &[u8]) -> Vec<ChunkPos> {
+        chunk_packets(out, ClientboundPlay::ChunkDataAndUpdateLight)
+    }
+
+    fn unloaded_chunks(out: &[u8]) -> Vec<ChunkPos> {
+        chunk_packets(out, ClientboundPlay::UnloadChunk)
+    }
+
+    #[tokio::test]
+    async fn test_chunks_are_sent_from_the_provider_nearest_first() {
+        let provider = InMemoryChunkProvider::new(Arc::new(FlatWorldGenerator::default()));
+        let loaded = RwLock::new(HashSet::new());
+        let mut position = Vec3::new(-20.0, 64.0, 40.0);
+        let center = ChunkPos::new(-2, 2);
+        let mut out = Vec::new();
+
+        PlayerData::<f64>::send_chunks_around_static(&mut out, &mut position, &provider, &loaded, 2)
+            .await
+            .unwrap();
+
+        let sent = sent_chunks(&out);
+        assert_eq!(sent.len(), 25);
+        assert_eq!(sent[0], center);
+        assert!(
+            sent.windows(2)
+                .all(|w| { w[0].distance_chebyshev(&center) <= w[1].distance_chebyshev(&center) })
+        );
+        assert_eq!(*loaded.read(), sent.iter().copied().collect::<HashSet<_>>());
+        assert_eq!(provider.len(), 25);
+
+        // Stepping one chunk east only sends the newly uncovered column
+        let mut out = Vec::new();
+        let mut position = Vec3::new(-4.0, 64.0, 40.0);
+        PlayerData::<f64>::send_chunks_around_static(&mut out, &mut position, &provider, &loaded, 2)
+            .await
+            .unwrap();
+        let sent = sent_chunks(&out);
+        assert_eq!(sent.len(), 5);
+        assert!(sent.iter().all(|pos| pos.x == 1));
+
+        // ...and unloads the column left behind
+        let unloaded = unloaded_chunks(&out);
+        assert_eq!(unloaded.len(), 5);
+        assert!(unloaded.iter().all(|pos| pos.x == -4));
+        assert_eq!(loaded.read().len(), 25);
+        assert!(
+            loaded
+                .read()
+                .iter()
+                .all(|pos| pos.distance_chebyshev(&ChunkPos::new(-1, 2)) <= 2)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_loaded_chunks_are_neither_generated_nor_resent() {
+        let provider = InMemoryChunkProvider::new(Arc::new(FlatWorldGenerator::default()));
+        let loaded = RwLock::new(HashSet::from([ChunkPos::new(0, 0), ChunkPos::new(1, 1)]));
+        let mut position = Vec3::new(1.0, 64.0, 1.0);
+        let mut out = Vec::new();
+
+        PlayerData::<f64>::send_chunks_around_static(&mut out, &mut position, &provider, &loaded, 1)
+            .await
+            .unwrap();
+
+        let sent = sent_chunks(&out);
+        assert_eq!(sent.len(), 7);
+        assert!(!sent.contains(&ChunkPos::new(0, 0)));
+        assert!(!sent.contains(&ChunkPos::new(1, 1)));
+        assert_eq!(provider.len(), 7);
+        assert_eq!(loaded.read().len(), 9);
+    }
+
+    #[tokio::test]
+    async fn test_chunks_are_resent_after_walking_out_of_range_and_back() {
+        let provider = InMemoryChunkProvider::new(Arc::new(FlatWorldGenerator::default()));
+        let players = PlayerRegistry::new();
+        let (player, mut rx) = RegisteredPlayer::test("Steve", 1);
+        let (uuid, loaded) = (player.uuid, Arc::clone(&player.loaded_chunks));
+        players.register(player);
+        players.enter_world(&uuid);
+        while rx.try_recv().is_ok() {}
+
+        let spawn = ChunkPos::new(0, 0);
+        let mut out = Vec::new();
+        let mut position = Vec3::new(8.0, 64.0, 8.0);
+        PlayerData::<f64>::send_chunks_around_static(&mut out, &mut position, &provider, &loaded, 1)
+            .await
+            .unwrap();
+        assert!(sent_chunks(&out).contains(&spawn));
+        // A `/regen` of the spawn chunk is resent to the player
+        assert_eq!(players.send_to_chunk_watchers(spawn, &[0x00]), 1);
+
+        let mut out = Vec::new();
+        let mut position = Vec3::new(168.0, 64.0, 168.0);
+        PlayerData::<f64>::send_chunks_around_static(&mut out, &mut position, &provider, &loaded, 1)
+            .await
+            .unwrap();
+        assert_eq!(unloaded_chunks(&out).len(), 9);
+        assert!(unloaded_chunks(&out).contains(&spawn));
+        assert!(!loaded.read().contains(&spawn));
+        // ...but not once they have walked away
+        assert_eq!(players.send_to_chunk_watchers(spawn, &[0x00]), 0);
+
+        // Walking back sends the spawn chunks again rather than skipping them as loaded
+        let mut out = Vec::new();
+        let mut position = Vec3::new(8.0, 64.0, 8.0);
+        PlayerData::<f64>::send_chunks_around_static(&mut out, &mut position, &provider, &loaded, 1)
+            .await
+            .unwrap();
+        assert_eq!(sent_chunks(&out).len(), 9);
+        assert!(sent_chunks(&out).contains(&spawn));
+        assert_eq!(players.send_to_chunk_watchers(spawn, &[0x00]), 1);
     }
 }
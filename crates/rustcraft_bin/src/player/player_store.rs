@@ -0,0 +1,100 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::player::{Inventory, Vec2, Vec3};
+
+/// Folder under the world directory with one file per player that has left the server
+pub const PLAYER_DATA_DIR: &str = "playerdata";
+
+/// What a player keeps from one session to the next
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SavedPlayer {
+    /// Where the player was standing and looking; missing from files saved before it was kept
+    pub position:  Option<(Vec3<f64>, Vec2<f32>)>,
+    pub inventory: Inventory,
+}
+
+/// `SavedPlayer`s as `playerdata/<uuid>.json`, so they survive restarts and move with the world
+/// folder; nothing is kept in memory once a player is gone
+pub struct PlayerStore {
+    dir: PathBuf,
+}
+
+impl PlayerStore {
+    pub fn new(world_dir: &Path) -> Self {
+        Self {
+            dir: world_dir.join(PLAYER_DATA_DIR),
+        }
+    }
+
+    fn path(&self, uuid: Uuid) -> PathBuf {
+        self.dir.join(format!("{}.json", uuid))
+    }
+
+    /// The player's saved data, or `None` if it has never left this world
+    pub fn load(&self, uuid: Uuid) -> Result<Option<SavedPlayer>> {
+        let path = self.path(uuid);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let contents = std::fs::read_to_string(path)?;
+        Ok(Some(serde_json::from_str(&contents)?))
+    }
+
+    pub fn save(&self, uuid: Uuid, player: &SavedPlayer) -> Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        // Write then rename, so a crash mid-save leaves the previous file intact
+        let path = self.path(uuid);
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_string(player)?)?;
+        std::fs::rename(tmp, path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::player::inventory::ItemStack;
+
+    #[test]
+    fn test_saved_player_round_trip() {
+        let world_dir = std::env::temp_dir().join(format!("rustcraft_players_{}", Uuid::new_v4()));
+        let store = PlayerStore::new(&world_dir);
+        let uuid = Uuid::new_v4();
+        assert_eq!(store.load(uuid).unwrap(), None);
+
+        let mut player = SavedPlayer {
+            position: Some((Vec3::new(10.5, -12.0, 4_000_000.25), Vec2::new(-90.0, 45.5))),
+            ..SavedPlayer::default()
+        };
+        player.inventory.add_item(ItemStack::new(1, 70));
+        player.inventory.set(45, Some(ItemStack::new(300, 1))).unwrap();
+        store.save(uuid, &player).unwrap();
+        assert_eq!(store.load(uuid).unwrap(), Some(player.clone()));
+
+        // Saving again replaces the file
+        player.inventory = Inventory::default();
+        store.save(uuid, &player).unwrap();
+        assert_eq!(store.load(uuid).unwrap(), Some(player));
+        assert_eq!(store.load(Uuid::new_v4()).unwrap(), None);
+
+        let _ = std::fs::remove_dir_all(&world_dir);
+    }
+
+    #[test]
+    fn test_inventory_only_file_still_loads() {
+        let saved: SavedPlayer = serde_json::from_str(r#"{ "inventory": [] }"#).unwrap();
+        assert_eq!(saved, SavedPlayer::default());
+    }
+
+    #[test]
+    fn test_bad_slots_are_rejected() {
+        let bad = r#"{ "inventory": [{ "slot": 46, "item_id": 1, "count": 1 }] }"#;
+        assert!(serde_json::from_str::<SavedPlayer>(bad).is_err());
+    }
+}
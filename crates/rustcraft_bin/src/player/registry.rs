@@ -0,0 +1,732 @@
+#![allow(dead_code)]
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use parking_lot::{Mutex, RwLock};
+use tokio::sync::mpsc::UnboundedSender;
+use uuid::Uuid;
+
+use crate::consts::MAX_CHUNK_DISTANCE;
+use crate::network::StatusPlayer;
+use crate::player::connection_state::{ConnectionStateTracker, StateInfo};
+use crate::player::entity_movement::movement_frames;
+use crate::player::inventory::Inventory;
+use crate::player::player_store::{PlayerStore, SavedPlayer};
+use crate::player::spawn_packets::{
+    PlayerSpawn,
+    player_info_add_frame,
+    player_info_remove_frame,
+    remove_entities_frame,
+    spawn_player_frame,
+};
+use crate::player::{Vec2, Vec3};
+use crate::terrain::ChunkPos;
+
+/// Kick reason for a session replaced by a newer login with the same UUID
+pub const DUPLICATE_LOGIN_REASON: &str = "You logged in from another location";
+
+/// Work queued for a player's task, which owns the socket
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outbound {
+    /// A framed packet, written as-is
+    Packet(Vec<u8>),
+    /// Send a Disconnect with this reason and close the connection
+    Kick(String),
+}
+
+pub type PacketSender = UnboundedSender<Outbound>;
+
+/// A player known to the server, shared between the player's own task and diagnostics
+#[derive(Clone)]
+pub struct RegisteredPlayer {
+    pub uuid:          Uuid,
+    pub username:      String,
+    pub entity_id:     i32,
+    pub position:      Vec3<f64>,
+    pub rotation:      Vec2<f32>,
+    /// Set once the player has been spawned for everyone else in the world
+    pub in_world:      bool,
+    pub connection:    Arc<ConnectionStateTracker>,
+    pub outbound:      PacketSender,
+    /// Chunks the player's client has been sent, shared with the player's task
+    pub loaded_chunks: Arc<RwLock<HashSet<ChunkPos>>>,
+    /// Shared with the player's task, and kept when it disconnects
+    pub inventory:     Arc<RwLock<Inventory>>,
+}
+
+impl RegisteredPlayer {
+    pub fn spawn_info(&self) -> PlayerSpawn {
+        PlayerSpawn {
+            entity_id: self.entity_id,
+            uuid:      self.uuid,
+            position:  self.position,
+            rotation:  self.rotation,
+        }
+    }
+
+    /// Tab list entry followed by the player entity
+    fn spawn_frames(&self) -> [Vec<u8>; 2] {
+        [
+            player_info_add_frame([(self.uuid, self.username.as_str())]),
+            spawn_player_frame(&self.spawn_info()),
+        ]
+    }
+
+    /// Queue a framed packet, returning false if the player's task is gone
+    pub fn send(&self, frame: Vec<u8>) -> bool {
+        self.outbound.send(Outbound::Packet(frame)).is_ok()
+    }
+
+    /// Ask the player's task to disconnect it, returning false if it's already gone
+    pub fn kick<S: Into<String>>(&self, reason: S) -> bool {
+        self.outbound.send(Outbound::Kick(reason.into())).is_ok()
+    }
+}
+
+#[cfg(test)]
+impl RegisteredPlayer {
+    /// A player at `(entity_id, 64, 0)`, not yet in the world, along with the receiving end of its
+    /// outbound queue
+    pub fn test(name: &str, entity_id: i32) -> (Self, tokio::sync::mpsc::UnboundedReceiver<Outbound>) {
+        let (outbound, rx) = tokio::sync::mpsc::unbounded_channel();
+        let player = Self {
+            uuid: Uuid::new_v4(),
+            username: name.to_string(),
+            entity_id,
+            position: Vec3::new(entity_id as f64, 64.0, 0.0),
+            rotation: Vec2::new(0.0, 0.0),
+            in_world: false,
+            connection: Arc::new(ConnectionStateTracker::new()),
+            outbound,
+            loaded_chunks: Arc::new(RwLock::new(HashSet::new())),
+            inventory: Arc::new(RwLock::new(Inventory::default())),
+        };
+        (player, rx)
+    }
+}
+
+/// Players standing in each chunk, for proximity queries that skip everyone far away
+/// Only changed while holding the registry's `players` write lock, so the two always agree
+#[derive(Debug, Default)]
+struct ChunkIndex {
+    cells: HashMap<ChunkPos, HashSet<Uuid>>,
+}
+
+impl ChunkIndex {
+    fn insert(&mut self, uuid: Uuid, chunk: ChunkPos) {
+        self.cells.entry(chunk).or_default().insert(uuid);
+    }
+
+    fn remove(&mut self, uuid: &Uuid, chunk: ChunkPos) {
+        if let Some(cell) = self.cells.get_mut(&chunk) {
+            cell.remove(uuid);
+            if cell.is_empty() {
+                self.cells.remove(&chunk);
+            }
+        }
+    }
+
+    fn relocate(&mut self, uuid: Uuid, from: ChunkPos, to: ChunkPos) {
+        if from != to {
+            self.remove(&uuid, from);
+            self.insert(uuid, to);
+        }
+    }
+
+    /// Players within `radius` chunks of `center`, diagonals counting as one
+    /// Probes each cell of the square, or walks the occupied cells when there are fewer of those
+    fn near(&self, center: ChunkPos, radius: i32) -> Vec<Uuid> {
+        let side = 2 * radius.max(0) as i64 + 1;
+        if (side * side) as usize <= self.cells.len() {
+            (-radius..=radius)
+                .flat_map(|dx| (-radius..=radius).map(move |dz| ChunkPos::new(center.x + dx, center.z + dz)))
+                .filter_map(|chunk| self.cells.get(&chunk))
+                .flatten()
+                .copied()
+                .collect()
+        } else {
+            self.cells
+                .iter()
+                .filter(|(chunk, _)| chunk.distance_chebyshev(&center) <= radius)
+                .flat_map(|(_, cell)| cell.iter().copied())
+                .collect()
+        }
+    }
+}
+
+fn chunk_of(position: Vec3<f64>) -> ChunkPos {
+    ChunkPos::from_world(position.x, position.z)
+}
+
+/// Registry of all logged-in players, keyed by UUID
+pub struct PlayerRegistry {
+    players:  RwLock<HashMap<Uuid, RegisteredPlayer>>,
+    /// Where `players` are, by chunk
+    index:    RwLock<ChunkIndex>,
+    /// Where players' data is kept between sessions; without one nothing outlives a logout
+    store:    Option<PlayerStore>,
+    /// `PlayerSlot`s handed out to logins that haven't registered yet
+    reserved: Mutex<usize>,
+}
+
+/// A place under `max_players` held for a player from the login check until it is registered
+/// Dropping it gives the place back
+pub struct PlayerSlot {
+    registry: Arc<PlayerRegistry>,
+}
+
+impl Drop for PlayerSlot {
+    fn drop(&mut self) {
+        *self.registry.reserved.lock() -= 1;
+    }
+}
+
+impl std::fmt::Debug for PlayerSlot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PlayerSlot").finish_non_exhaustive()
+    }
+}
+
+impl PlayerRegistry {
+    pub fn new() -> Self {
+        Self {
+            players:  RwLock::new(HashMap::new()),
+            index:    RwLock::new(ChunkIndex::default()),
+            store:    None,
+            reserved: Mutex::new(0),
+        }
+    }
+
+    /// Save players' data to `store` when they leave, for their next login
+    pub fn with_store(mut self, store: PlayerStore) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    /// Hold a place for one more player, if registered players and places already held leave room
+    /// under `max_players`; the check and the hold happen together, so racing logins can't overfill
+    pub fn reserve(self: &Arc<Self>, max_players: u32) -> Option<PlayerSlot> {
+        let players = self.players.read();
+        let mut reserved = self.reserved.lock();
+        if players.len() + *reserved >= max_players as usize {
+            return None;
+        }
+        *reserved += 1;
+        Some(PlayerSlot {
+            registry: Arc::clone(self),
+        })
+    }
+
+    /// Register a player; an existing session with the same UUID is kicked, despawned and returned,
+    /// since its own cleanup will no longer find it by connection
+    pub fn register(&self, player: RegisteredPlayer) -> Option<RegisteredPlayer> {
+        tracing::debug!("[REGISTRY] Registering '{}' ({})", player.username, player.uuid);
+        let mut players = self.players.write();
+        let replaced = self.remove_locked(&mut players, &player.uuid);
+        if let Some(replaced) = &replaced {
+            tracing::info!("[REGISTRY] '{}' logged in again, closing the old session", replaced.username);
+            replaced.kick(DUPLICATE_LOGIN_REASON);
+        }
+        self.index.write().insert(player.uuid, chunk_of(player.position));
+        players.insert(player.uuid, player);
+        replaced
+    }
+
+    /// Remove a player, despawning it for everyone still in the world
+    pub fn unregister(&self, uuid: &Uuid) -> Option<RegisteredPlayer> {
+        self.remove_locked(&mut self.players.write(), uuid)
+    }
+
+    fn remove_locked(
+        &self,
+        players: &mut HashMap<Uuid, RegisteredPlayer>,
+        uuid: &Uuid,
+    ) -> Option<RegisteredPlayer> {
+        let removed = players.remove(uuid);
+        if let Some(player) = &removed {
+            tracing::debug!("[REGISTRY] Unregistered '{}' ({})", player.username, player.uuid);
+            self.index.write().remove(uuid, chunk_of(player.position));
+
+            if player.in_world {
+                let remove_entity = remove_entities_frame(&[player.entity_id]);
+                let remove_info = player_info_remove_frame(&[player.uuid]);
+                for other in players.values().filter(|p| p.in_world) {
+                    other.send(remove_entity.clone());
+                    other.send(remove_info.clone());
+                }
+            }
+        }
+        removed
+    }
+
+    /// Spawn a player for everyone already in the world, and queue their spawns for it
+    /// Runs under the write lock so two players entering together each see the other exactly once
+    pub fn enter_world(&self, uuid: &Uuid) {
+        let mut players = self.players.write();
+        let Some(newcomer) = players.get(uuid).cloned() else {
+            return;
+        };
+
+        let [newcomer_info, newcomer_spawn] = newcomer.spawn_frames();
+        let others: Vec<&RegisteredPlayer> = players.values().filter(|p| p.in_world).collect();
+        if !others.is_empty() {
+            newcomer.send(player_info_add_frame(others.iter().map(|p| (p.uuid, p.username.as_str()))));
+        }
+        for other in others {
+            newcomer.send(spawn_player_frame(&other.spawn_info()));
+            other.send(newcomer_info.clone());
+            other.send(newcomer_spawn.clone());
+        }
+
+        if let Some(player) = players.get_mut(uuid) {
+            player.in_world = true;
+        }
+    }
+
+    /// Record the latest position reported by a player's client
+    pub fn update_position(&self, uuid: &Uuid, position: Vec3<f64>, rotation: Vec2<f32>) {
+        if let Some(player) = self.players.write().get_mut(uuid) {
+            let old_position = std::mem::replace(&mut player.position, position);
+            player.rotation = rotation;
+            self.index
+                .write()
+                .relocate(*uuid, chunk_of(old_position), chunk_of(position));
+        }
+    }
+
+    /// Record a player's new position and show the move to everyone who has its chunk loaded
+    pub fn move_player(&self, uuid: &Uuid, position: Vec3<f64>, rotation: Vec2<f32>, on_ground: bool) {
+        let mut players = self.players.write();
+        let Some(mover) = players.get_mut(uuid) else {
+            return;
+        };
+
+        let old_position = std::mem::replace(&mut mover.position, position);
+        let old_rotation = std::mem::replace(&mut mover.rotation, rotation);
+        let chunk = chunk_of(position);
+        self.index.write().relocate(*uuid, chunk_of(old_position), chunk);
+        if !mover.in_world {
+            return;
+        }
+
+        let turned = old_rotation != rotation;
+        let frames = movement_frames(mover.entity_id, old_position, position, rotation, turned, on_ground);
+        if frames.is_empty() {
+            return;
+        }
+
+        // Nobody further away than the largest view distance can have the chunk loaded
+        let nearby = self.index.read().near(chunk, MAX_CHUNK_DISTANCE as i32);
+        let watchers = nearby
+            .iter()
+            .filter(|near| *near != uuid)
+            .filter_map(|near| players.get(near))
+            .filter(|p| p.in_world && p.loaded_chunks.read().contains(&chunk));
+        for watcher in watchers {
+            for frame in &frames {
+                watcher.send(frame.clone());
+            }
+        }
+    }
+
+    /// Players standing within `radius_chunks` of `pos`, diagonals counting as one chunk
+    pub fn players_near_chunk(&self, pos: ChunkPos, radius_chunks: i32) -> Vec<Uuid> {
+        self.index.read().near(pos, radius_chunks)
+    }
+
+    /// Remove whichever player owns the given connection tracker
+    pub fn unregister_connection(
+        &self,
+        connection: &Arc<ConnectionStateTracker>,
+    ) -> Option<RegisteredPlayer> {
+        let uuid = self
+            .players
+            .read()
+            .values()
+            .find(|p| Arc::ptr_eq(&p.connection, connection))
+            .map(|p| p.uuid)?;
+        self.unregister(&uuid)
+    }
+
+    /// Write a disconnecting player's data to the store
+    pub fn save_player(&self, player: &RegisteredPlayer) {
+        let Some(store) = &self.store else {
+            return;
+        };
+        let saved = SavedPlayer {
+            position:  Some((player.position, player.rotation)),
+            inventory: player.inventory.read().clone(),
+        };
+        if let Err(e) = store.save(player.uuid, &saved) {
+            tracing::error!("[REGISTRY] Failed to save '{}' ({}): {}", player.username, player.uuid, e);
+        }
+    }
+
+    /// What the player had when it last disconnected, if it has played here before
+    /// Unreadable data is logged and treated as a first join
+    pub fn saved_player(&self, uuid: &Uuid) -> Option<SavedPlayer> {
+        match self.store.as_ref()?.load(*uuid) {
+            Ok(saved) => saved,
+            Err(e) => {
+                tracing::warn!("[REGISTRY] Ignoring unreadable saved data for {}: {}", uuid, e);
+                None
+            }
+        }
+    }
+
+    pub fn get(&self, uuid: &Uuid) -> Option<RegisteredPlayer> {
+        self.players.read().get(uuid).cloned()
+    }
+
+    pub fn get_by_name(&self, username: &str) -> Option<RegisteredPlayer> {
+        self.players
+            .read()
+            .values()
+            .find(|p| p.username.eq_ignore_ascii_case(username))
+            .cloned()
+    }
+
+    pub fn len(&self) -> usize {
+        self.players.read().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.players.read().is_empty()
+    }
+
+    /// Name/UUID pairs for the server list hover sample
+    pub fn status_sample(&self) -> Vec<StatusPlayer> {
+        self.players
+            .read()
+            .values()
+            .map(|p| {
+                StatusPlayer {
+                    name: p.username.clone(),
+                    id:   p.uuid,
+                }
+            })
+            .collect()
+    }
+
+    /// Kick a player by (case-insensitive) name, returning false if nobody matched
+    pub fn kick_by_name(&self, username: &str, reason: &str) -> bool {
+        match self.get_by_name(username) {
+            Some(player) => player.kick(reason),
+            None => false,
+        }
+    }
+
+    /// Send a frame to every player in the world
+    pub fn broadcast(&self, frame: &[u8]) {
+        for player in self.players.read().values().filter(|p| p.in_world) {
+            player.send(frame.to_vec());
+        }
+    }
+
+    /// Send a frame to every player in the world who has `chunk` loaded, returning how many did
+    pub fn send_to_chunk_watchers(&self, chunk: ChunkPos, frame: &[u8]) -> usize {
+        let players = self.players.read();
+        // Nobody further away than the largest view distance can have the chunk loaded
+        let nearby = self.index.read().near(chunk, MAX_CHUNK_DISTANCE as i32);
+        let watchers = nearby
+            .iter()
+            .filter_map(|near| players.get(near))
+            .filter(|p| p.in_world && p.loaded_chunks.read().contains(&chunk));
+
+        let mut sent = 0;
+        for watcher in watchers {
+            watcher.send(frame.to_vec());
+            sent += 1;
+        }
+        sent
+    }
+
+    /// Kick everyone, e.g. when the server stops
+    pub fn kick_all(&self, reason: &str) {
+        for player in self.players.read().values() {
+            player.kick(reason);
+        }
+    }
+
+    /// Chunk each player is standing in
+    pub fn chunk_positions(&self) -> Vec<ChunkPos> {
+        self.players
+            .read()
+            .values()
+            .map(|p| ChunkPos::from_world(p.position.x, p.position.z))
+            .collect()
+    }
+
+    pub fn usernames(&self) -> Vec<String> {
+        self.players.read().values().map(|p| p.username.clone()).collect()
+    }
+
+    /// Connection state snapshot for a single player
+    pub fn connection_state(&self, uuid: &Uuid) -> Option<StateInfo> {
+        self.players.read().get(uuid).map(|p| p.connection.state_info())
+    }
+
+    /// Connection state snapshots for every registered player
+    pub fn connection_states(&self) -> Vec<(Uuid, StateInfo)> {
+        self.players
+            .read()
+            .values()
+            .map(|p| (p.uuid, p.connection.state_info()))
+            .collect()
+    }
+
+    /// Players that have been in a pre-game stage for longer than `max_stage_ms`
+    pub fn stalled(&self, max_stage_ms: u64) -> Vec<(Uuid, StateInfo)> {
+        self.players
+            .read()
+            .values()
+            .filter(|p| p.connection.is_stalled(max_stage_ms))
+            .map(|p| (p.uuid, p.connection.state_info()))
+            .collect()
+    }
+}
+
+impl Default for PlayerRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::mpsc::UnboundedReceiver;
+
+    use super::*;
+    use crate::terrain::ChunkRng;
+
+    fn drain(rx: &mut UnboundedReceiver<Outbound>) -> Vec<Vec<u8>> {
+        std::iter::from_fn(|| rx.try_recv().ok())
+            .map(|message| {
+                match message {
+                    Outbound::Packet(frame) => frame,
+                    Outbound::Kick(reason) => panic!("unexpected kick: {}", reason),
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_reserve_counts_registered_players_and_held_places() {
+        let registry = Arc::new(PlayerRegistry::new());
+        registry.register(RegisteredPlayer::test("Alex", 1).0);
+
+        let slot = registry.reserve(3).expect("room for a second player");
+        let _held = registry.reserve(3).expect("room for a third player");
+        assert!(registry.reserve(3).is_none());
+
+        // A dropped slot frees its place for the next login
+        drop(slot);
+        assert!(registry.reserve(3).is_some());
+    }
+
+    #[test]
+    fn test_second_login_kicks_and_despawns_the_first() {
+        let registry = PlayerRegistry::new();
+        let (first, mut first_rx) = RegisteredPlayer::test("Alex", 1);
+        let (watcher, mut watcher_rx) = RegisteredPlayer::test("Steve", 2);
+        let uuid = first.uuid;
+        registry.register(first);
+        registry.enter_world(&uuid);
+        registry.register(watcher.clone());
+        registry.enter_world(&watcher.uuid);
+        drain(&mut watcher_rx);
+        drain(&mut first_rx);
+
+        let (mut second, _second_rx) = RegisteredPlayer::test("Alex", 3);
+        second.uuid = uuid;
+        let second_connection = Arc::clone(&second.connection);
+        let replaced = registry.register(second).expect("first session replaced");
+
+        assert_eq!(replaced.entity_id, 1);
+        assert_eq!(first_rx.try_recv().unwrap(), Outbound::Kick(DUPLICATE_LOGIN_REASON.to_string()));
+        assert_eq!(
+            drain(&mut watcher_rx),
+            vec![remove_entities_frame(&[1]), player_info_remove_frame(&[uuid])]
+        );
+        // The old session's cleanup finds nothing, the new one is still registered
+        assert!(registry.unregister_connection(&replaced.connection).is_none());
+        assert!(Arc::ptr_eq(&registry.get(&uuid).unwrap().connection, &second_connection));
+        assert_eq!(registry.len(), 2);
+    }
+
+    #[test]
+    fn test_players_spawn_and_despawn_for_each_other() {
+        let registry = PlayerRegistry::new();
+        let (alex, mut alex_rx) = RegisteredPlayer::test("Alex", 1);
+        let (steve, mut steve_rx) = RegisteredPlayer::test("Steve", 2);
+        let (alex_uuid, steve_uuid) = (alex.uuid, steve.uuid);
+        let [alex_info, alex_spawn] = alex.spawn_frames();
+        let [steve_info, steve_spawn] = steve.spawn_frames();
+
+        registry.register(alex);
+        registry.enter_world(&alex_uuid);
+        // Nobody else around yet
+        assert!(drain(&mut alex_rx).is_empty());
+
+        // Registered but not yet in the world: not visible
+        registry.register(steve);
+        assert!(drain(&mut alex_rx).is_empty());
+
+        registry.enter_world(&steve_uuid);
+        assert_eq!(drain(&mut steve_rx), vec![alex_info, alex_spawn]);
+        assert_eq!(drain(&mut alex_rx), vec![steve_info, steve_spawn]);
+
+        registry.unregister(&alex_uuid);
+        assert_eq!(
+            drain(&mut steve_rx),
+            vec![
+                remove_entities_frame(&[1]),
+                player_info_remove_frame(&[alex_uuid])
+            ]
+        );
+        assert!(drain(&mut alex_rx).is_empty());
+    }
+
+    #[test]
+    fn test_spawn_uses_latest_position() {
+        let registry = PlayerRegistry::new();
+        let (alex, _alex_rx) = RegisteredPlayer::test("Alex", 1);
+        let (steve, mut steve_rx) = RegisteredPlayer::test("Steve", 2);
+        let (alex_uuid, steve_uuid) = (alex.uuid, steve.uuid);
+
+        registry.register(alex);
+        registry.enter_world(&alex_uuid);
+        registry.update_position(&alex_uuid, Vec3::new(10.0, 70.0, -5.0), Vec2::new(45.0, 10.0));
+
+        registry.register(steve);
+        registry.enter_world(&steve_uuid);
+
+        let expected = registry.get(&alex_uuid).unwrap().spawn_info();
+        assert_eq!(expected.position, Vec3::new(10.0, 70.0, -5.0));
+        assert_eq!(drain(&mut steve_rx)[1], spawn_player_frame(&expected));
+    }
+
+    #[test]
+    fn test_movement_reaches_only_players_with_the_chunk_loaded() {
+        let registry = PlayerRegistry::new();
+        let (alex, _alex_rx) = RegisteredPlayer::test("Alex", 1);
+        let (steve, mut steve_rx) = RegisteredPlayer::test("Steve", 2);
+        let (herobrine, mut herobrine_rx) = RegisteredPlayer::test("Herobrine", 3);
+        let alex_uuid = alex.uuid;
+        steve.loaded_chunks.write().insert(ChunkPos::new(0, 0));
+        herobrine.loaded_chunks.write().insert(ChunkPos::new(5, 5));
+
+        for p in [alex, steve, herobrine] {
+            let uuid = p.uuid;
+            registry.register(p);
+            registry.enter_world(&uuid);
+        }
+        drain(&mut steve_rx);
+        drain(&mut herobrine_rx);
+
+        let from = registry.get(&alex_uuid).unwrap().position;
+        let to = Vec3::new(2.0, 64.0, 3.0);
+        registry.move_player(&alex_uuid, to, Vec2::new(0.0, 0.0), true);
+
+        assert_eq!(drain(&mut steve_rx), movement_frames(1, from, to, Vec2::new(0.0, 0.0), false, true));
+        assert!(drain(&mut herobrine_rx).is_empty());
+        assert_eq!(registry.get(&alex_uuid).unwrap().position, to);
+    }
+
+    fn near(registry: &PlayerRegistry, pos: ChunkPos, radius: i32) -> HashSet<Uuid> {
+        registry.players_near_chunk(pos, radius).into_iter().collect()
+    }
+
+    #[test]
+    fn test_chunk_index_follows_moves_across_chunk_boundaries() {
+        let registry = PlayerRegistry::new();
+        let (alex, _alex_rx) = RegisteredPlayer::test("Alex", 1);
+        let alex_uuid = alex.uuid;
+        registry.register(alex);
+        registry.enter_world(&alex_uuid);
+        assert_eq!(near(&registry, ChunkPos::new(0, 0), 0), HashSet::from([alex_uuid]));
+
+        // Still chunk (0, 0)
+        registry.move_player(&alex_uuid, Vec3::new(15.9, 64.0, 0.0), Vec2::new(0.0, 0.0), true);
+        assert_eq!(near(&registry, ChunkPos::new(0, 0), 0), HashSet::from([alex_uuid]));
+
+        registry.move_player(&alex_uuid, Vec3::new(16.0, 64.0, -0.5), Vec2::new(0.0, 0.0), true);
+        assert!(near(&registry, ChunkPos::new(0, 0), 0).is_empty());
+        assert_eq!(near(&registry, ChunkPos::new(1, -1), 0), HashSet::from([alex_uuid]));
+        assert_eq!(near(&registry, ChunkPos::new(0, 0), 1), HashSet::from([alex_uuid]));
+
+        // Teleports and logins that skip `move_player` are tracked too
+        registry.update_position(&alex_uuid, Vec3::new(-100.0, 64.0, 200.0), Vec2::new(0.0, 0.0));
+        assert_eq!(near(&registry, ChunkPos::new(-7, 12), 0), HashSet::from([alex_uuid]));
+        assert!(near(&registry, ChunkPos::new(1, -1), 5).is_empty());
+
+        registry.unregister(&alex_uuid);
+        assert!(near(&registry, ChunkPos::new(-7, 12), 0).is_empty());
+        assert!(registry.index.read().cells.is_empty());
+    }
+
+    #[test]
+    fn test_chunk_index_matches_a_full_scan() {
+        let registry = PlayerRegistry::new();
+        let mut rng = ChunkRng::new(42, 0, 0);
+        let mut receivers = Vec::new();
+        for entity_id in 0..60 {
+            let (p, rx) = RegisteredPlayer::test(&format!("Player{}", entity_id), entity_id);
+            let uuid = p.uuid;
+            receivers.push(rx);
+            registry.register(p);
+            registry.enter_world(&uuid);
+            let position = Vec3::new(rng.gen_range(-400..400) as f64, 64.0, rng.gen_range(-400..400) as f64);
+            registry.move_player(&uuid, position, Vec2::new(0.0, 0.0), true);
+        }
+
+        for _ in 0..20 {
+            let center = ChunkPos::new(rng.gen_range(-25..25), rng.gen_range(-25..25));
+            // Small radii probe cells, large ones walk the occupied cells
+            for radius in [0, 1, 3, 8, 32] {
+                let scanned: HashSet<Uuid> = registry
+                    .players
+                    .read()
+                    .values()
+                    .filter(|p| chunk_of(p.position).distance_chebyshev(&center) <= radius)
+                    .map(|p| p.uuid)
+                    .collect();
+                assert_eq!(near(&registry, center, radius), scanned, "{:?} radius {}", center, radius);
+            }
+        }
+    }
+
+    #[test]
+    fn test_send_to_chunk_watchers() {
+        let registry = PlayerRegistry::new();
+        let (steve, mut steve_rx) = RegisteredPlayer::test("Steve", 1);
+        let (alex, mut alex_rx) = RegisteredPlayer::test("Alex", 2);
+        let (steve_uuid, alex_uuid) = (steve.uuid, alex.uuid);
+        steve.loaded_chunks.write().insert(ChunkPos::new(1, 1));
+        alex.loaded_chunks.write().insert(ChunkPos::new(9, 9));
+        registry.register(steve);
+        registry.register(alex);
+        registry.enter_world(&steve_uuid);
+        registry.enter_world(&alex_uuid);
+        drain(&mut steve_rx);
+        drain(&mut alex_rx);
+
+        assert_eq!(registry.send_to_chunk_watchers(ChunkPos::new(1, 1), &[0xAB]), 1);
+        assert_eq!(drain(&mut steve_rx), [vec![0xAB]]);
+        assert!(drain(&mut alex_rx).is_empty());
+
+        assert_eq!(registry.send_to_chunk_watchers(ChunkPos::new(2, 2), &[0xAB]), 0);
+    }
+
+    #[test]
+    fn test_kick_by_name() {
+        let registry = PlayerRegistry::new();
+        let (alex, mut alex_rx) = RegisteredPlayer::test("Alex", 1);
+        registry.register(alex);
+
+        assert!(registry.kick_by_name("alex", "Bye"));
+        assert_eq!(alex_rx.try_recv().unwrap(), Outbound::Kick("Bye".to_string()));
+        assert!(!registry.kick_by_name("Steve", "Bye"));
+    }
+}
@@ -0,0 +1,160 @@
+use uuid::Uuid;
+
+use crate::network::{ByteWritable, ClientboundPlay, PacketWriter, write_varint};
+use crate::player::{Vec2, Vec3};
+
+/// `minecraft:player` in the 1.21.7 entity type registry
+const PLAYER_ENTITY_TYPE: i32 = 149;
+
+/// Player Info Update action bits; entry data is written in bit order
+const ACTION_ADD_PLAYER: u8 = 0x01;
+const ACTION_UPDATE_GAME_MODE: u8 = 0x04;
+const ACTION_UPDATE_LISTED: u8 = 0x08;
+const ACTION_UPDATE_LATENCY: u8 = 0x10;
+
+/// Everything other clients need to show a player
+#[derive(Debug, Clone, Copy)]
+pub struct PlayerSpawn {
+    pub entity_id: i32,
+    pub uuid:      Uuid,
+    pub position:  Vec3<f64>,
+    pub rotation:  Vec2<f32>,
+}
+
+/// Player Info Update adding the given players to the tab list (survival, listed, 0 ping)
+pub fn player_info_add_frame<'a, I>(players: I) -> Vec<u8>
+where
+    I: IntoIterator<Item = (Uuid, &'a str)>,
+{
+    let players: Vec<_> = players.into_iter().collect();
+    let mut writer = PacketWriter::new();
+
+    writer.write_byte(
+        ACTION_ADD_PLAYER | ACTION_UPDATE_GAME_MODE | ACTION_UPDATE_LISTED | ACTION_UPDATE_LATENCY,
+    );
+    writer.write_prefixed_array(&players, |writer, (uuid, username)| {
+        writer.write_uuid(uuid);
+
+        // Add Player: name, then no skin properties
+        writer.write_string(username);
+        writer.write_varint(0);
+
+        // Update Game Mode: survival
+        writer.write_varint(0);
+
+        // Update Listed
+        writer.write_bool(true);
+
+        // Update Latency (milliseconds)
+        writer.write_varint(0);
+    });
+
+    frame(ClientboundPlay::PlayerInfoUpdate.id(), &writer.finish())
+}
+
+pub fn player_info_remove_frame(uuids: &[Uuid]) -> Vec<u8> {
+    let mut writer = PacketWriter::new();
+
+    writer.write_prefixed_array(uuids, |writer, uuid| writer.write_uuid(uuid));
+
+    frame(ClientboundPlay::PlayerInfoRemove.id(), &writer.finish())
+}
+
+/// Spawn Entity for a player; the client pairs it with the tab list entry by UUID
+pub fn spawn_player_frame(player: &PlayerSpawn) -> Vec<u8> {
+    let mut writer = PacketWriter::new();
+
+    writer.write_varint(player.entity_id);
+    writer.write_uuid(player.uuid);
+    writer.write_varint(PLAYER_ENTITY_TYPE);
+
+    writer.write_double(player.position.x);
+    writer.write_double(player.position.y);
+    writer.write_double(player.position.z);
+
+    // Pitch, yaw, head yaw
+    writer.write_byte(to_angle(player.rotation.pitch));
+    writer.write_byte(to_angle(player.rotation.yaw));
+    writer.write_byte(to_angle(player.rotation.yaw));
+
+    // Object data (unused for players)
+    writer.write_varint(0);
+
+    // Velocity
+    writer.write_short(0i16);
+    writer.write_short(0i16);
+    writer.write_short(0i16);
+
+    frame(ClientboundPlay::SpawnEntity.id(), &writer.finish())
+}
+
+pub fn remove_entities_frame(entity_ids: &[i32]) -> Vec<u8> {
+    let mut writer = PacketWriter::new();
+
+    writer.write_prefixed_array(entity_ids, |writer, &id| writer.write_varint(id));
+
+    frame(ClientboundPlay::RemoveEntities.id(), &writer.finish())
+}
+
+/// Protocol angle: a full turn in 256 steps
+pub fn to_angle(degrees: f32) -> u8 {
+    (degrees * 256.0 / 360.0) as i32 as u8
+}
+
+/// Wrap a payload as `[length][id][data]`
+pub(super) fn frame(packet_id: i32, data: &[u8]) -> Vec<u8> {
+    let packet_id = write_varint(packet_id);
+    let packet_length = (packet_id.len() + data.len()) as i32;
+
+    let mut frame = Vec::new();
+    frame.extend_from_slice(&write_varint(packet_length));
+    frame.extend_from_slice(&packet_id);
+    frame.extend_from_slice(data);
+
+    frame
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_angle() {
+        assert_eq!(to_angle(0.0), 0);
+        assert_eq!(to_angle(90.0), 64);
+        assert_eq!(to_angle(-90.0), 192);
+        assert_eq!(to_angle(-180.0), 128);
+    }
+
+    #[test]
+    fn test_spawn_player_frame_layout() {
+        let spawn = PlayerSpawn {
+            entity_id: 7,
+            uuid:      Uuid::from_u128(1),
+            position:  Vec3::new(1.5, 64.0, -2.0),
+            rotation:  Vec2::new(90.0, -90.0),
+        };
+        let frame = spawn_player_frame(&spawn);
+
+        // length, packet id, entity id
+        assert_eq!(frame[0] as usize, frame.len() - 1);
+        assert_eq!(&frame[1..3], &[0x01, 0x07]);
+        assert_eq!(&frame[3..19], Uuid::from_u128(1).as_bytes());
+        assert_eq!(frame[19], PLAYER_ENTITY_TYPE as u8);
+        assert_eq!(frame[20], 0x01); // 149 needs a two byte varint
+        assert_eq!(&frame[21..29], &1.5f64.to_be_bytes());
+        assert_eq!(&frame[29..37], &64.0f64.to_be_bytes());
+        assert_eq!(&frame[37..45], &(-2.0f64).to_be_bytes());
+        assert_eq!(&frame[45..48], &[192, 64, 64]);
+        assert_eq!(&frame[48..], &[0, 0, 0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_remove_frames() {
+        assert_eq!(remove_entities_frame(&[1, 300]), vec![0x05, 0x46, 0x02, 0x01, 0xAC, 0x02]);
+
+        let frame = player_info_remove_frame(&[Uuid::nil()]);
+        assert_eq!(&frame[..3], &[0x12, 0x3E, 0x01]);
+        assert_eq!(frame.len(), 19);
+    }
+}
@@ -0,0 +1,241 @@
+#![allow(dead_code)]
+
+use serde::{Deserialize, Serialize};
+
+use crate::terrain::BlockType;
+
+/// Default 1.21.7 block-state ids for every `BlockType`, with the registry name they belong to
+/// Ids come from the vanilla `blocks.json` report (`--reports` data generator)
+pub const BLOCK_STATE_TABLE: [(BlockType, &str, i32); 13] = [
+    (BlockType::Air, "minecraft:air", 0),
+    (BlockType::Stone, "minecraft:stone", 1),
+    (BlockType::Grass, "minecraft:grass_block", 9), // snowy=false
+    (BlockType::Dirt, "minecraft:dirt", 10),
+    (BlockType::Cobblestone, "minecraft:cobblestone", 14),
+    (BlockType::OakPlanks, "minecraft:oak_planks", 15),
+    (BlockType::Bedrock, "minecraft:bedrock", 85),
+    (BlockType::Water, "minecraft:water", 86), // level=0
+    (BlockType::Lava, "minecraft:lava", 102),  // level=0
+    (BlockType::Sand, "minecraft:sand", 118),
+    (BlockType::Gravel, "minecraft:gravel", 124),
+    (BlockType::OakLog, "minecraft:oak_log", 137),       // axis=y
+    (BlockType::OakLeaves, "minecraft:oak_leaves", 279), // distance=7, persistent=false, waterlogged=false
+];
+
+/// Default 1.21.7 block-state id for the block, as sent in chunk section palettes
+pub fn block_state_id(block: BlockType) -> i32 {
+    BLOCK_STATE_TABLE
+        .iter()
+        .find(|(b, _, _)| *b == block)
+        .map(|(_, _, id)| *id)
+        .expect("every BlockType has an entry in BLOCK_STATE_TABLE")
+}
+
+/// Block with the given registry name; the `minecraft:` namespace may be left off
+pub fn block_from_name(name: &str) -> Option<BlockType> {
+    let name = name.strip_prefix("minecraft:").unwrap_or(name);
+    BLOCK_STATE_TABLE
+        .iter()
+        .find(|(_, registry_name, _)| registry_name.strip_prefix("minecraft:") == Some(name))
+        .map(|(block, _, _)| *block)
+}
+
+/// Registry name of the block, e.g. `minecraft:grass_block`
+pub fn block_name(block: BlockType) -> &'static str {
+    BLOCK_STATE_TABLE
+        .iter()
+        .find(|(b, _, _)| *b == block)
+        .map(|(_, name, _)| *name)
+        .expect("every BlockType has an entry in BLOCK_STATE_TABLE")
+}
+
+/// Direction a pillar block such as a log runs along
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Axis {
+    X,
+    #[default]
+    Y,
+    Z,
+}
+
+impl Axis {
+    const ALL: [Axis; 3] = [Axis::Y, Axis::X, Axis::Z];
+}
+
+/// Highest `level` property of water and lava
+const MAX_FLUID_LEVEL: u8 = 15;
+
+/// A block plus the properties that pick one of its states, e.g. a log's axis
+/// Packed into a `u16` like the ids in region files: the low byte is the `BlockType`, the high
+/// byte its property, where 0 is the default state, so plain `BlockType` ids read back unchanged
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct BlockState(u16);
+
+impl From<BlockType> for BlockState {
+    fn from(block: BlockType) -> Self {
+        Self(block as u16)
+    }
+}
+
+impl BlockState {
+    pub const AIR: BlockState = BlockState(BlockType::Air as u16);
+
+    fn with_property(block: BlockType, property: u8) -> Self {
+        Self(block as u16 | (property as u16) << 8)
+    }
+
+    fn property(self) -> u8 {
+        (self.0 >> 8) as u8
+    }
+
+    pub fn block(self) -> BlockType {
+        BlockType::from_u16(self.0 & 0xFF).expect("BlockState holds a valid BlockType")
+    }
+
+    pub fn is_air(self) -> bool {
+        self == Self::AIR
+    }
+
+    /// A log running along `axis`; other blocks ignore the axis
+    pub fn log(block: BlockType, axis: Axis) -> Self {
+        if !Self::has_axis(block) {
+            return block.into();
+        }
+        let property = Axis::ALL.iter().position(|&a| a == axis).unwrap_or_default();
+        Self::with_property(block, property as u8)
+    }
+
+    /// Water or lava at `level` (0 is a source block), clamped to the highest level; other blocks
+    /// ignore the level
+    pub fn fluid(block: BlockType, level: u8) -> Self {
+        if !Self::has_level(block) {
+            return block.into();
+        }
+        Self::with_property(block, level.min(MAX_FLUID_LEVEL))
+    }
+
+    /// Axis of a log, `None` for blocks without one
+    pub fn axis(self) -> Option<Axis> {
+        Self::has_axis(self.block()).then(|| Axis::ALL[self.property() as usize])
+    }
+
+    /// Level of water or lava, `None` for other blocks
+    pub fn level(self) -> Option<u8> {
+        Self::has_level(self.block()).then(|| self.property())
+    }
+
+    fn has_axis(block: BlockType) -> bool {
+        block == BlockType::OakLog
+    }
+
+    fn has_level(block: BlockType) -> bool {
+        matches!(block, BlockType::Water | BlockType::Lava)
+    }
+
+    /// 1.21.7 block-state id, as sent in chunk section palettes
+    pub fn state_id(self) -> i32 {
+        let default = block_state_id(self.block());
+        match (self.axis(), self.level()) {
+            // Log states run axis=x, y, z around the default y
+            (Some(Axis::X), _) => default - 1,
+            (Some(Axis::Z), _) => default + 1,
+            (_, Some(level)) => default + level as i32,
+            _ => default,
+        }
+    }
+
+    /// Packed form stored in region files
+    pub fn to_u16(self) -> u16 {
+        self.0
+    }
+
+    /// Unpack a stored state; `None` for unknown blocks or out of range properties
+    pub fn from_u16(value: u16) -> Option<Self> {
+        let block = BlockType::from_u16(value & 0xFF)?;
+        let property = (value >> 8) as u8;
+        let valid = if Self::has_axis(block) {
+            (property as usize) < Axis::ALL.len()
+        } else if Self::has_level(block) {
+            property <= MAX_FLUID_LEVEL
+        } else {
+            property == 0
+        };
+        valid.then_some(Self(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known_block_state_ids() {
+        assert_eq!(block_state_id(BlockType::Air), 0);
+        assert_eq!(block_state_id(BlockType::Stone), 1);
+        assert_eq!(block_state_id(BlockType::Grass), 9);
+        assert_eq!(block_state_id(BlockType::Dirt), 10);
+        assert_eq!(block_state_id(BlockType::Water), 86);
+        assert_eq!(block_state_id(BlockType::OakLog), 137);
+        assert_eq!(block_state_id(BlockType::OakLeaves), 279);
+        assert_eq!(block_state_id(BlockType::Bedrock), 85);
+        assert_eq!(block_name(BlockType::Grass), "minecraft:grass_block");
+        assert_eq!(block_from_name("minecraft:grass_block"), Some(BlockType::Grass));
+        assert_eq!(block_from_name("bedrock"), Some(BlockType::Bedrock));
+        assert_eq!(block_from_name("grass"), None);
+    }
+
+    #[test]
+    fn test_log_axis_state_ids() {
+        let default = BlockState::from(BlockType::OakLog);
+        assert_eq!(default.axis(), Some(Axis::Y));
+        assert_eq!(default.state_id(), block_state_id(BlockType::OakLog));
+        assert_eq!(BlockState::log(BlockType::OakLog, Axis::Y), default);
+
+        let horizontal = BlockState::log(BlockType::OakLog, Axis::X);
+        assert_eq!(horizontal.block(), BlockType::OakLog);
+        assert_eq!(horizontal.axis(), Some(Axis::X));
+        assert_eq!(horizontal.state_id(), 136);
+        assert_eq!(BlockState::log(BlockType::OakLog, Axis::Z).state_id(), 138);
+        assert_eq!(BlockState::log(BlockType::Stone, Axis::X), BlockState::from(BlockType::Stone));
+    }
+
+    #[test]
+    fn test_plain_blocks_and_fluids() {
+        for (block, _, id) in BLOCK_STATE_TABLE {
+            let state = BlockState::from(block);
+            assert_eq!(state.state_id(), id);
+            assert_eq!(state.block(), block);
+            assert_eq!(state.to_u16(), block as u16);
+        }
+        assert_eq!(BlockState::from(BlockType::Stone).axis(), None);
+        assert_eq!(BlockState::from(BlockType::Stone).level(), None);
+
+        assert_eq!(BlockState::fluid(BlockType::Water, 3).state_id(), 89);
+        assert_eq!(BlockState::fluid(BlockType::Lava, 99).level(), Some(15));
+    }
+
+    #[test]
+    fn test_packed_round_trip() {
+        for state in [
+            BlockState::AIR,
+            BlockState::from(BlockType::Grass),
+            BlockState::log(BlockType::OakLog, Axis::Z),
+            BlockState::fluid(BlockType::Water, 7),
+        ] {
+            assert_eq!(BlockState::from_u16(state.to_u16()), Some(state));
+        }
+
+        // Properties on blocks that have none, and out of range ones, are rejected
+        assert_eq!(BlockState::from_u16(BlockType::Stone as u16 | 1 << 8), None);
+        assert_eq!(BlockState::from_u16(BlockType::OakLog as u16 | 3 << 8), None);
+        assert_eq!(BlockState::from_u16(99), None);
+    }
+
+    #[test]
+    fn test_table_ids_are_unique() {
+        let mut ids: Vec<i32> = BLOCK_STATE_TABLE.iter().map(|(_, _, id)| *id).collect();
+        ids.sort_unstable();
+        ids.dedup();
+        assert_eq!(ids.len(), BLOCK_STATE_TABLE.len());
+    }
+}
@@ -1,9 +1,19 @@
 #![allow(dead_code)]
+use std::str::FromStr;
+
+use anyhow::{Error, Result, anyhow};
 use serde::{Deserialize, Serialize};
 
 // const CHUNK_SIZE: usize = 16;
 // const CHUNK_HEIGHT: usize = 256;
-use crate::consts::{TERRAIN_CHUNK_HEIGHT, TERRAIN_CHUNK_SIZE};
+use crate::consts::{
+    TERRAIN_CHUNK_HEIGHT,
+    TERRAIN_CHUNK_SIZE,
+    TERRAIN_SECTION_COUNT,
+    TERRAIN_SECTION_HEIGHT,
+    WORLD_MIN_Y,
+};
+use crate::terrain::BlockState;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
 pub struct ChunkPos {
@@ -17,6 +27,32 @@ impl std::fmt::Display for ChunkPos {
     }
 }
 
+/// Chunk coordinates as `x z` or `x,z`, e.g. from a command; `Display`'s `(x:z)` also parses
+impl FromStr for ChunkPos {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (x, z) = parse_xz(s).map_err(|e| anyhow!("Invalid chunk position {:?}: {}", s, e))?;
+        Ok(Self::new(x, z))
+    }
+}
+
+/// A pair of coordinates separated by whitespace, a comma or a colon, optionally in parentheses
+pub fn parse_xz(s: &str) -> Result<(i32, i32)> {
+    let s = s.trim();
+    let s = s
+        .strip_prefix('(')
+        .and_then(|inner| inner.strip_suffix(')'))
+        .unwrap_or(s);
+    let mut parts = s
+        .split(|c: char| c == ',' || c == ':' || c.is_whitespace())
+        .filter(|part| !part.is_empty());
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(x), Some(z), None) => Ok((x.parse()?, z.parse()?)),
+        _ => Err(anyhow!("expected two coordinates")),
+    }
+}
+
 impl ChunkPos {
     pub fn new(x: i32, z: i32) -> Self {
         Self { x, z }
@@ -25,6 +61,50 @@ impl ChunkPos {
     pub fn from_block_pos(x: i32, z: i32) -> Self {
         Self { x: x >> 4, z: z >> 4 }
     }
+
+    /// Chunk containing the world position, flooring so `x = -0.5` is in chunk `-1`
+    pub fn from_world(x: f64, z: f64) -> Self {
+        Self::from_block_pos(x.floor() as i32, z.floor() as i32)
+    }
+
+    /// Block coordinates of the chunk's north-west corner
+    pub fn origin_world(&self) -> (i32, i32) {
+        (self.x * TERRAIN_CHUNK_SIZE as i32, self.z * TERRAIN_CHUNK_SIZE as i32)
+    }
+
+    pub fn contains_world(&self, x: f64, z: f64) -> bool {
+        Self::from_world(x, z) == *self
+    }
+
+    /// The chunks sharing an edge with this one: north (-z), south, east (+x), west
+    pub fn neighbors(&self) -> [ChunkPos; 4] {
+        [
+            Self::new(self.x, self.z - 1),
+            Self::new(self.x, self.z + 1),
+            Self::new(self.x + 1, self.z),
+            Self::new(self.x - 1, self.z),
+        ]
+    }
+
+    /// The ring of chunks around this one, corners included, clockwise from north-west
+    pub fn neighbors_8(&self) -> [ChunkPos; 8] {
+        let (x, z) = (self.x, self.z);
+        [
+            Self::new(x - 1, z - 1),
+            Self::new(x, z - 1),
+            Self::new(x + 1, z - 1),
+            Self::new(x + 1, z),
+            Self::new(x + 1, z + 1),
+            Self::new(x, z + 1),
+            Self::new(x - 1, z + 1),
+            Self::new(x - 1, z),
+        ]
+    }
+
+    /// Distance in chunks when diagonal steps count as one, the shape of a view-distance square
+    pub fn distance_chebyshev(&self, other: &ChunkPos) -> i32 {
+        (self.x - other.x).abs().max((self.z - other.z).abs())
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -42,6 +122,7 @@ pub enum BlockType {
     Sand = 12,
     Gravel = 13,
     OakPlanks = 7,
+    Bedrock = 14,
 }
 
 impl BlockType {
@@ -59,15 +140,54 @@ impl BlockType {
             10 => Some(BlockType::Lava),
             12 => Some(BlockType::Sand),
             13 => Some(BlockType::Gravel),
+            14 => Some(BlockType::Bedrock),
             _ => None,
         }
     }
 }
 
+/// Blocks in a section, indexed `[y][x][z]`
+const SECTION_VOLUME: usize = TERRAIN_CHUNK_SIZE * TERRAIN_CHUNK_SIZE * TERRAIN_SECTION_HEIGHT;
+
+/// A 16x16x16 slice of a chunk that holds at least one non-air block
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Section {
+    blocks:  Vec<BlockState>,
+    non_air: u16,
+}
+
+impl Section {
+    fn new() -> Self {
+        Self {
+            blocks:  vec![BlockState::AIR; SECTION_VOLUME],
+            non_air: 0,
+        }
+    }
+
+    fn index(x: usize, y: usize, z: usize) -> usize {
+        (y * TERRAIN_CHUNK_SIZE + x) * TERRAIN_CHUNK_SIZE + z
+    }
+
+    fn get(&self, x: usize, y: usize, z: usize) -> BlockState {
+        self.blocks[Self::index(x, y, z)]
+    }
+
+    fn set(&mut self, x: usize, y: usize, z: usize, block: BlockState) {
+        let old = std::mem::replace(&mut self.blocks[Self::index(x, y, z)], block);
+        match (old.is_air(), block.is_air()) {
+            (true, false) => self.non_air += 1,
+            (false, true) => self.non_air -= 1,
+            _ => {}
+        }
+    }
+}
+
+/// Sections are stored sparsely: an all-air section is `None` and takes no block storage
+/// Block accessors take chunk-local Y, counted up from `WORLD_MIN_Y`; see `local_y`/`world_y`
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Chunk {
     pub pos:      ChunkPos,
-    blocks:       Vec<Vec<Vec<BlockType>>>, // [y][x][z]
+    sections:     Vec<Option<Section>>, // bottom to top
     pub modified: bool,
 }
 
@@ -75,25 +195,58 @@ impl Chunk {
     pub fn new(pos: ChunkPos) -> Self {
         Self {
             pos,
-            blocks: vec![
-                vec![vec![BlockType::Air; TERRAIN_CHUNK_SIZE]; TERRAIN_CHUNK_SIZE];
-                TERRAIN_CHUNK_HEIGHT
-            ],
+            sections: vec![None; TERRAIN_SECTION_COUNT],
             modified: true,
         }
     }
 
+    /// Chunk-local Y for world height `y`, or `None` above or below the world
+    pub fn local_y(y: i32) -> Option<usize> {
+        usize::try_from(y - WORLD_MIN_Y)
+            .ok()
+            .filter(|&y| y < TERRAIN_CHUNK_HEIGHT)
+    }
+
+    /// World height of chunk-local Y `local_y`
+    pub fn world_y(local_y: usize) -> i32 {
+        local_y as i32 + WORLD_MIN_Y
+    }
+
+    /// Index of the section holding world height `y`, counting from the bottom of the world
+    pub fn section_index(y: i32) -> Option<usize> {
+        Self::local_y(y).map(|y| y / TERRAIN_SECTION_HEIGHT)
+    }
+
+    /// Section Y (world height / 16, rounded down) of section `index`; the bottom one is -4
+    pub fn section_y(index: usize) -> i32 {
+        index as i32 + WORLD_MIN_Y.div_euclid(TERRAIN_SECTION_HEIGHT as i32)
+    }
+
     pub fn get_block(&self, x: usize, y: usize, z: usize) -> Option<BlockType> {
+        self.get_block_state(x, y, z).map(BlockState::block)
+    }
+
+    /// Block and its state properties, e.g. which way a log faces
+    pub fn get_block_state(&self, x: usize, y: usize, z: usize) -> Option<BlockState> {
         if x < TERRAIN_CHUNK_SIZE && y < TERRAIN_CHUNK_HEIGHT && z < TERRAIN_CHUNK_SIZE {
-            Some(self.blocks[y][x][z])
+            let section = &self.sections[y / TERRAIN_SECTION_HEIGHT];
+            Some(
+                section
+                    .as_ref()
+                    .map_or(BlockState::AIR, |s| s.get(x, y % TERRAIN_SECTION_HEIGHT, z)),
+            )
         } else {
             None
         }
     }
 
-    pub fn set_block(&mut self, x: usize, y: usize, z: usize, block: BlockType) -> bool {
+    /// Set a block; a plain `BlockType` places its default state
+    pub fn set_block(&mut self, x: usize, y: usize, z: usize, block: impl Into<BlockState>) -> bool {
+        let block = block.into();
         if x < TERRAIN_CHUNK_SIZE && y < TERRAIN_CHUNK_HEIGHT && z < TERRAIN_CHUNK_SIZE {
-            self.blocks[y][x][z] = block;
+            self.modify_section(y / TERRAIN_SECTION_HEIGHT, block, |section| {
+                section.set(x, y % TERRAIN_SECTION_HEIGHT, z, block)
+            });
             self.modified = true;
             true
         } else {
@@ -101,6 +254,76 @@ impl Chunk {
         }
     }
 
+    /// Set every block in the box between two corners (inclusive, in either order)
+    /// The box is clipped to the chunk; returns how many blocks were set
+    pub fn fill(
+        &mut self,
+        (x0, y0, z0): (usize, usize, usize),
+        (x1, y1, z1): (usize, usize, usize),
+        block: impl Into<BlockState>,
+    ) -> usize {
+        let block = block.into();
+        let clip = |a: usize, b: usize, len: usize| a.min(b)..(a.max(b) + 1).min(len);
+        let (xs, ys, zs) = (
+            clip(x0, x1, TERRAIN_CHUNK_SIZE),
+            clip(y0, y1, TERRAIN_CHUNK_HEIGHT),
+            clip(z0, z1, TERRAIN_CHUNK_SIZE),
+        );
+        if xs.is_empty() || ys.is_empty() || zs.is_empty() {
+            return 0;
+        }
+
+        for index in ys.start / TERRAIN_SECTION_HEIGHT..=(ys.end - 1) / TERRAIN_SECTION_HEIGHT {
+            let base = index * TERRAIN_SECTION_HEIGHT;
+            let local_ys = ys.start.max(base) - base..ys.end.min(base + TERRAIN_SECTION_HEIGHT) - base;
+            self.modify_section(index, block, |section| {
+                for y in local_ys {
+                    for x in xs.clone() {
+                        for z in zs.clone() {
+                            section.set(x, y, z, block);
+                        }
+                    }
+                }
+            });
+        }
+        self.modified = true;
+
+        xs.len() * ys.len() * zs.len()
+    }
+
+    /// Blocks in the column at `x`/`z`, bottom to top, or `None` outside the chunk
+    pub fn get_column(&self, x: usize, z: usize) -> Option<impl Iterator<Item = BlockType> + '_> {
+        (x < TERRAIN_CHUNK_SIZE && z < TERRAIN_CHUNK_SIZE).then(|| {
+            self.sections.iter().flat_map(move |section| {
+                (0..TERRAIN_SECTION_HEIGHT).map(move |y| {
+                    section
+                        .as_ref()
+                        .map_or(BlockType::Air, |s| s.get(x, y, z).block())
+                })
+            })
+        })
+    }
+
+    /// Whether section `index` (counting 16-block slices from the bottom) is all air
+    pub fn is_section_empty(&self, index: usize) -> bool {
+        self.sections.get(index).is_none_or(Option::is_none)
+    }
+
+    /// Run `edit` on a section, allocating it first unless only air is being written to an empty
+    /// one, and drop it again if the edit left it all air
+    fn modify_section(&mut self, index: usize, block: BlockState, edit: impl FnOnce(&mut Section)) {
+        let slot = &mut self.sections[index];
+        if slot.is_none() && block.is_air() {
+            return;
+        }
+
+        let section = slot.get_or_insert_with(Section::new);
+        edit(section);
+        if section.non_air == 0 {
+            *slot = None;
+        }
+    }
+
     pub fn is_modified(&self) -> bool {
         self.modified
     }
@@ -109,3 +332,223 @@ impl Chunk {
         self.modified = false;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunk_pos_from_str() {
+        for (input, expected) in [
+            ("3 -5", ChunkPos::new(3, -5)),
+            ("3,-5", ChunkPos::new(3, -5)),
+            ("  -12,   40 ", ChunkPos::new(-12, 40)),
+            ("-1\t-1", ChunkPos::new(-1, -1)),
+            ("(7:-8)", ChunkPos::new(7, -8)),
+        ] {
+            assert_eq!(input.parse::<ChunkPos>().unwrap(), expected, "{:?}", input);
+        }
+        let pos = ChunkPos::new(-30, 2);
+        assert_eq!(pos.to_string().parse::<ChunkPos>().unwrap(), pos);
+
+        for bad in [
+            "",
+            "3",
+            "3 4 5",
+            "x 4",
+            "3,",
+            "1.5 2",
+            "3 99999999999",
+            "(3 4",
+            "3;4",
+        ] {
+            assert!(bad.parse::<ChunkPos>().is_err(), "{:?}", bad);
+        }
+    }
+
+    #[test]
+    fn test_chunk_pos_neighbors() {
+        let origin = ChunkPos::new(0, 0);
+        assert_eq!(
+            origin.neighbors(),
+            [
+                ChunkPos::new(0, -1),
+                ChunkPos::new(0, 1),
+                ChunkPos::new(1, 0),
+                ChunkPos::new(-1, 0)
+            ]
+        );
+
+        let ring = ChunkPos::new(-1, 0).neighbors_8();
+        assert!(ring.contains(&origin));
+        assert!(ring.contains(&ChunkPos::new(-2, -1)));
+        assert!(!ring.contains(&ChunkPos::new(-1, 0)));
+        for neighbor in ring {
+            assert_eq!(neighbor.distance_chebyshev(&ChunkPos::new(-1, 0)), 1);
+        }
+        // The 4 edge neighbors are part of the 8
+        assert!(
+            origin
+                .neighbors()
+                .iter()
+                .all(|pos| origin.neighbors_8().contains(pos))
+        );
+    }
+
+    #[test]
+    fn test_chunk_pos_distance_chebyshev() {
+        let origin = ChunkPos::new(0, 0);
+        assert_eq!(origin.distance_chebyshev(&origin), 0);
+        assert_eq!(origin.distance_chebyshev(&ChunkPos::new(3, -2)), 3);
+        assert_eq!(ChunkPos::new(-2, 5).distance_chebyshev(&ChunkPos::new(2, 4)), 4);
+        assert_eq!(ChunkPos::new(-3, -3).distance_chebyshev(&ChunkPos::new(1, 2)), 5);
+        assert_eq!(
+            ChunkPos::new(2, 4).distance_chebyshev(&ChunkPos::new(-2, 5)),
+            ChunkPos::new(-2, 5).distance_chebyshev(&ChunkPos::new(2, 4))
+        );
+    }
+
+    #[test]
+    fn test_chunk_pos_from_world() {
+        assert_eq!(ChunkPos::from_world(0.0, 0.0), ChunkPos::new(0, 0));
+        assert_eq!(ChunkPos::from_world(15.99, 15.99), ChunkPos::new(0, 0));
+        assert_eq!(ChunkPos::from_world(16.0, 31.5), ChunkPos::new(1, 1));
+
+        // Truncating would put these in chunk 0
+        assert_eq!(ChunkPos::from_world(-1.0, -0.01), ChunkPos::new(-1, -1));
+        assert_eq!(ChunkPos::from_world(-16.0, -16.01), ChunkPos::new(-1, -2));
+        assert_eq!(ChunkPos::from_world(-15.5, 8.0), ChunkPos::new(-1, 0));
+    }
+
+    #[test]
+    fn test_chunk_pos_origin_and_contains() {
+        assert_eq!(ChunkPos::new(0, 0).origin_world(), (0, 0));
+        assert_eq!(ChunkPos::new(2, -3).origin_world(), (32, -48));
+        assert_eq!(ChunkPos::new(-1, -1).origin_world(), (-16, -16));
+
+        let pos = ChunkPos::new(-1, 0);
+        let (x, z) = pos.origin_world();
+        assert!(pos.contains_world(x as f64, z as f64));
+        assert!(pos.contains_world(-0.001, 15.999));
+        assert!(!pos.contains_world(0.0, 0.0));
+        assert!(!pos.contains_world(-16.001, 0.0));
+        assert!(!pos.contains_world(-8.0, -0.5));
+    }
+
+    #[test]
+    fn test_local_and_world_y() {
+        assert_eq!(Chunk::local_y(WORLD_MIN_Y), Some(0));
+        assert_eq!(Chunk::local_y(0), Some(64));
+        assert_eq!(Chunk::local_y(319), Some(TERRAIN_CHUNK_HEIGHT - 1));
+        assert_eq!(Chunk::local_y(320), None);
+        assert_eq!(Chunk::local_y(WORLD_MIN_Y - 1), None);
+
+        for y in [-64, -10, 0, 300, 319] {
+            assert_eq!(Chunk::world_y(Chunk::local_y(y).unwrap()), y);
+        }
+    }
+
+    #[test]
+    fn test_negative_section_indices() {
+        assert_eq!(Chunk::section_index(WORLD_MIN_Y), Some(0));
+        assert_eq!(Chunk::section_index(-10), Some(3));
+        assert_eq!(Chunk::section_index(-1), Some(3));
+        assert_eq!(Chunk::section_index(0), Some(4));
+        assert_eq!(Chunk::section_index(319), Some(TERRAIN_SECTION_COUNT - 1));
+        assert_eq!(Chunk::section_index(320), None);
+
+        assert_eq!(Chunk::section_y(0), -4);
+        assert_eq!(Chunk::section_y(3), -1);
+        assert_eq!(Chunk::section_y(TERRAIN_SECTION_COUNT - 1), 19);
+
+        let mut chunk = Chunk::new(ChunkPos::new(0, 0));
+        chunk.set_block(2, Chunk::local_y(-10).unwrap(), 2, BlockType::Stone);
+        assert!(!chunk.is_section_empty(3));
+        assert!(
+            (0..TERRAIN_SECTION_COUNT)
+                .filter(|&i| i != 3)
+                .all(|i| chunk.is_section_empty(i))
+        );
+    }
+
+    #[test]
+    fn test_fill_sub_box() {
+        let mut chunk = Chunk::new(ChunkPos::new(0, 0));
+
+        // Corners in any order
+        assert_eq!(chunk.fill((5, 12, 3), (2, 10, 4), BlockType::Stone), 4 * 3 * 2);
+        for x in 0..TERRAIN_CHUNK_SIZE {
+            for y in 8..14 {
+                for z in 0..TERRAIN_CHUNK_SIZE {
+                    let inside = (2..=5).contains(&x) && (10..=12).contains(&y) && (3..=4).contains(&z);
+                    let expected = if inside { BlockType::Stone } else { BlockType::Air };
+                    assert_eq!(chunk.get_block(x, y, z), Some(expected), "({}, {}, {})", x, y, z);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_fill_is_clipped_to_the_chunk() {
+        let mut chunk = Chunk::new(ChunkPos::new(0, 0));
+
+        let top = TERRAIN_CHUNK_HEIGHT - 1;
+        assert_eq!(chunk.fill((14, top - 5, 0), (40, top + 44, 0), BlockType::Dirt), 2 * 6);
+        assert_eq!(chunk.get_block(15, top, 0), Some(BlockType::Dirt));
+        assert_eq!(chunk.fill((16, 0, 0), (20, 0, 0), BlockType::Dirt), 0);
+    }
+
+    #[test]
+    fn test_ground_only_chunk_has_no_upper_sections() {
+        let mut chunk = Chunk::new(ChunkPos::new(0, 0));
+        assert!((0..TERRAIN_SECTION_COUNT).all(|i| chunk.is_section_empty(i)));
+
+        chunk.fill((0, 0, 0), (15, 40, 15), BlockType::Stone);
+        assert!((0..3).all(|i| !chunk.is_section_empty(i)));
+        assert!((3..TERRAIN_SECTION_COUNT).all(|i| chunk.is_section_empty(i)));
+
+        assert_eq!(chunk.get_block(7, 40, 7), Some(BlockType::Stone));
+        assert_eq!(chunk.get_block(7, 41, 7), Some(BlockType::Air));
+        assert_eq!(chunk.get_block(0, 255, 0), Some(BlockType::Air));
+
+        // Writing air to an empty section doesn't allocate it
+        assert!(chunk.set_block(0, 200, 0, BlockType::Air));
+        assert!(chunk.is_section_empty(200 / TERRAIN_SECTION_HEIGHT));
+    }
+
+    #[test]
+    fn test_section_is_freed_once_all_air() {
+        let mut chunk = Chunk::new(ChunkPos::new(0, 0));
+
+        chunk.set_block(3, 100, 3, BlockType::Dirt);
+        chunk.set_block(4, 101, 3, BlockType::Dirt);
+        assert!(!chunk.is_section_empty(6));
+
+        chunk.set_block(3, 100, 3, BlockType::Air);
+        assert!(!chunk.is_section_empty(6));
+        chunk.set_block(4, 101, 3, BlockType::Air);
+        assert!(chunk.is_section_empty(6));
+
+        chunk.fill((0, 96, 0), (15, 111, 15), BlockType::Sand);
+        chunk.fill((0, 96, 0), (15, 111, 15), BlockType::Air);
+        assert!(chunk.is_section_empty(6));
+    }
+
+    #[test]
+    fn test_column_at_chunk_edges() {
+        let mut chunk = Chunk::new(ChunkPos::new(0, 0));
+        chunk.fill((0, 0, 0), (0, 63, 0), BlockType::Stone);
+        chunk.set_block(15, TERRAIN_CHUNK_HEIGHT - 1, 15, BlockType::Sand);
+
+        let column: Vec<BlockType> = chunk.get_column(0, 0).unwrap().collect();
+        assert_eq!(column.len(), TERRAIN_CHUNK_HEIGHT);
+        assert!(column[..64].iter().all(|&b| b == BlockType::Stone));
+        assert!(column[64..].iter().all(|&b| b == BlockType::Air));
+
+        let top = chunk.get_column(15, 15).unwrap().last();
+        assert_eq!(top, Some(BlockType::Sand));
+
+        assert!(chunk.get_column(16, 0).is_none());
+        assert!(chunk.get_column(0, 16).is_none());
+    }
+}
@@ -2,33 +2,39 @@ use std::sync::Arc;
 
 use parking_lot::RwLock;
 
-use crate::terrain::terrain_gen::{Biome, BiomeMap, HeightMap};
-use crate::terrain::{BlockType, Chunk, ChunkPos};
+use crate::consts::WORLD_MIN_Y;
+use crate::terrain::terrain_gen::{Biome, BiomeMap, HeightMap, SEA_LEVEL_ELEVATION};
+use crate::terrain::{BlockType, Chunk, ChunkPos, NoiseSettings, WorldGenerator};
 
 pub struct ChunkGenerator {
     seed:       u64,
+    settings:   NoiseSettings,
     height_map: Arc<RwLock<Option<HeightMap>>>,
     biome_map:  Arc<RwLock<Option<BiomeMap>>>,
 }
 
 impl ChunkGenerator {
-    pub fn new<U>(seed: U) -> Self
+    pub fn new<U>(seed: U, settings: NoiseSettings) -> Self
     where
         U: Into<u64>,
     {
+        let seed = seed.into();
         Self {
-            seed:       seed.into(),
+            seed,
+            settings,
             height_map: Arc::new(RwLock::new(None)),
-            biome_map:  Arc::new(RwLock::new(None)),
+            biome_map: Arc::new(RwLock::new(None)),
         }
     }
 
-    pub fn generate(&self, pos: ChunkPos) -> Chunk {
+    /// Build the height and biome maps now instead of on the first `generate`, so that call isn't
+    /// slower than the rest (e.g. when benchmarking)
+    pub fn prepare(&self) {
         // Lazy initialization of height map
         {
             let mut hm = self.height_map.write();
             if hm.is_none() {
-                *hm = Some(HeightMap::new(512, 512, self.seed));
+                *hm = Some(HeightMap::new(512, 512, self.seed, self.settings));
             }
         }
 
@@ -45,30 +51,6 @@ impl ChunkGenerator {
                 }
             }
         }
-
-        let mut chunk = Chunk::new(pos);
-
-        let hm_lock = self.height_map.read();
-        let bm_lock = self.biome_map.read();
-
-        if let (Some(height_map), Some(biome_map)) = (hm_lock.as_ref(), bm_lock.as_ref()) {
-            // PERF: @nested : Loop moved to thread engine
-            for x in 0..16 {
-                for z in 0..16 {
-                    let world_x = (pos.x * 16 + x as i32) as usize;
-                    let world_z = (pos.z * 16 + z as i32) as usize;
-
-                    let elevation = height_map.get(world_x, world_z);
-                    let biome = biome_map.get(world_x, world_z);
-
-                    let height = self.elevation_to_block_height(elevation);
-
-                    self.fill_column(&mut chunk, x, z, height, biome, elevation);
-                }
-            }
-        }
-
-        chunk
     }
 
     fn elevation_to_block_height(&self, elevation: f64) -> usize {
@@ -86,16 +68,18 @@ impl ChunkGenerator {
         biome: Biome,
         elevation: f64,
     ) {
+        // Heights count up from world Y 0, the chunk from the bottom of the world
+        let base = (-WORLD_MIN_Y) as usize;
         for y in 0..height.min(256) {
             let block = self.get_block_for_biome(y, height, biome, elevation);
-            chunk.set_block(x, y, z, block);
+            chunk.set_block(x, base + y, z, block);
         }
 
-        // Water at sea level (elevation -0.05)
-        let sea_level = self.elevation_to_block_height(-0.05);
+        // Water at sea level, which also fills rivers
+        let sea_level = self.elevation_to_block_height(SEA_LEVEL_ELEVATION);
         if height < sea_level {
             for y in height..sea_level.min(256) {
-                chunk.set_block(x, y, z, BlockType::Water);
+                chunk.set_block(x, base + y, z, BlockType::Water);
             }
         }
     }
@@ -147,3 +131,91 @@ impl ChunkGenerator {
         }
     }
 }
+
+/// Noise-based terrain with biomes and sea level water
+impl WorldGenerator for ChunkGenerator {
+    fn generate(&self, pos: ChunkPos) -> Chunk {
+        self.prepare();
+
+        let mut chunk = Chunk::new(pos);
+
+        let hm_lock = self.height_map.read();
+        let bm_lock = self.biome_map.read();
+
+        if let (Some(height_map), Some(biome_map)) = (hm_lock.as_ref(), bm_lock.as_ref()) {
+            // PERF: @nested : Loop moved to thread engine
+            for x in 0..16 {
+                for z in 0..16 {
+                    let world_x = (pos.x * 16 + x as i32) as usize;
+                    let world_z = (pos.z * 16 + z as i32) as usize;
+
+                    let elevation = height_map.get(world_x, world_z);
+                    let biome = biome_map.get(world_x, world_z);
+
+                    let height = self.elevation_to_block_height(elevation);
+
+                    self.fill_column(&mut chunk, x, z, height, biome, elevation);
+                }
+            }
+        }
+
+        chunk
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consts::TERRAIN_CHUNK_HEIGHT;
+    use crate::terrain::terrain_gen::HeightSampler;
+
+    /// Topmost block of the column and the height of the ground under any water
+    fn column(generator: &ChunkGenerator, x: i32, z: i32) -> (BlockType, usize) {
+        let chunk = generator.generate(ChunkPos::from_block_pos(x, z));
+        let (lx, lz) = (x.rem_euclid(16) as usize, z.rem_euclid(16) as usize);
+        let blocks: Vec<BlockType> = (0..TERRAIN_CHUNK_HEIGHT)
+            .map(|y| chunk.get_block(lx, y, lz).unwrap())
+            .collect();
+        let top = blocks.iter().rposition(|&b| b != BlockType::Air).unwrap();
+        let ground = blocks
+            .iter()
+            .rposition(|&b| b != BlockType::Air && b != BlockType::Water)
+            .unwrap();
+        (blocks[top], ground)
+    }
+
+    #[test]
+    fn test_river_is_water_below_its_banks() {
+        let settings = NoiseSettings::default();
+        let generator = ChunkGenerator::new(12345u64, settings);
+        let sampler = HeightSampler::new(12345, settings);
+        let dry = NoiseSettings {
+            river_width: 0.0,
+            ..settings
+        };
+        let without_rivers = HeightSampler::new(12345, dry);
+
+        // A river through land, with dry land a little way off on every side
+        let offset = 12;
+        let land = |x: i32, z: i32| without_rivers.height(x as f64, z as f64) > 0.1;
+        let dry_land = |x: i32, z: i32| land(x, z) && sampler.river(x as f64, z as f64) == 0.0;
+        let (x, z) = (offset..512 - offset)
+            .flat_map(|x| (offset..512 - offset).map(move |z| (x, z)))
+            .find(|&(x, z)| {
+                sampler.river(x as f64, z as f64) > 0.95
+                    && land(x, z)
+                    && [(offset, 0), (-offset, 0), (0, offset), (0, -offset)]
+                        .iter()
+                        .all(|&(dx, dz)| dry_land(x + dx, z + dz))
+            })
+            .expect("a river crosses land near spawn");
+
+        let (top, river_bed) = column(&generator, x, z);
+        assert_eq!(top, BlockType::Water, "river at ({}, {})", x, z);
+        for (dx, dz) in [(offset, 0), (-offset, 0), (0, offset), (0, -offset)] {
+            let (bank_top, bank) = column(&generator, x + dx, z + dz);
+            assert_ne!(bank_top, BlockType::Water);
+            assert!(bank > river_bed, "bank {} is not above the river bed {}", bank, river_bed);
+        }
+    }
+}
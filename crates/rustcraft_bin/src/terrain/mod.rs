@@ -1,7 +1,18 @@
+mod block_state;
 mod chunk;
 mod chunk_generator;
 mod noise;
+mod noise_settings;
+mod rng;
 mod terrain_gen;
+mod world_generator;
 
-pub use chunk::{BlockType, Chunk, ChunkPos};
+#[cfg(test)]
+pub use block_state::{Axis, block_state_id};
+pub use block_state::{BlockState, block_from_name};
+pub use chunk::{BlockType, Chunk, ChunkPos, parse_xz};
 pub use chunk_generator::ChunkGenerator;
+pub use noise_settings::NoiseSettings;
+pub use rng::ChunkRng;
+pub use terrain_gen::Biome;
+pub use world_generator::{FlatWorldGenerator, VoidWorldGenerator, WorldGenerator};
@@ -1,3 +1,5 @@
+use crate::consts::{NOISE_LACUNARITY, NOISE_PERSISTENCE};
+
 /// Deterministic hash function for 2D coordinates
 pub fn hash2d(x: i32, y: i32, seed: u64) -> f64 {
     let mut hash = seed;
@@ -35,8 +37,16 @@ pub fn perlin_noise(x: f64, y: f64, scale: f64, seed: u64) -> f64 {
     nx0 * (1.0 - v) + nx1 * v
 }
 
-/// Multi-octave Perlin noise (fractional Brownian motion)
+/// Multi-octave Perlin noise (fractional Brownian motion), with each octave at half the
+/// amplitude and twice the frequency of the last
+#[allow(dead_code)]
 pub fn fbm(x: f64, y: f64, octaves: usize, seed: u64) -> f64 {
+    fbm_with(x, y, octaves, NOISE_PERSISTENCE, NOISE_LACUNARITY, seed)
+}
+
+/// Like `fbm`, with each octave's amplitude scaled by `persistence` and its frequency by
+/// `lacunarity`; higher persistence gives rougher terrain, lower gives smoother
+pub fn fbm_with(x: f64, y: f64, octaves: usize, persistence: f64, lacunarity: f64, seed: u64) -> f64 {
     let mut value = 0.0;
     let mut amplitude = 1.0;
     let mut frequency = 1.0;
@@ -47,9 +57,38 @@ pub fn fbm(x: f64, y: f64, octaves: usize, seed: u64) -> f64 {
         value += noise * amplitude;
         max_value += amplitude;
 
-        amplitude *= 0.5;
-        frequency *= 2.0;
+        amplitude *= persistence;
+        frequency *= lacunarity;
     }
 
     value / max_value
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Mean squared change between neighbouring samples, which only the fine octaves contribute to
+    fn roughness(persistence: f64) -> f64 {
+        let step = 0.01;
+        let samples: Vec<f64> = (0..2000)
+            .map(|i| fbm_with(i as f64 * step, 3.7, 6, persistence, NOISE_LACUNARITY, 42))
+            .collect();
+        samples.windows(2).map(|w| (w[1] - w[0]).powi(2)).sum::<f64>() / (samples.len() - 1) as f64
+    }
+
+    #[test]
+    fn test_fbm_matches_default_fbm_with() {
+        for (x, y) in [(0.0, 0.0), (1.25, -3.5), (100.1, 7.9)] {
+            assert_eq!(fbm(x, y, 4, 9), fbm_with(x, y, 4, NOISE_PERSISTENCE, NOISE_LACUNARITY, 9));
+        }
+    }
+
+    #[test]
+    fn test_higher_persistence_is_rougher() {
+        let smooth = roughness(0.25);
+        let default = roughness(NOISE_PERSISTENCE);
+        let rough = roughness(0.8);
+        assert!(smooth < default && default < rough, "{} {} {}", smooth, default, rough);
+    }
+}
@@ -0,0 +1,179 @@
+use anyhow::{Result, anyhow};
+use serde::Deserialize;
+
+use crate::consts::{
+    NOISE_CONTINENT_SCALE,
+    NOISE_CONTINENT_WEIGHT,
+    NOISE_DETAIL_SCALE,
+    NOISE_DETAIL_WEIGHT,
+    NOISE_EROSION_AMOUNT,
+    NOISE_EROSION_ITERATIONS,
+    NOISE_HILL_SCALE,
+    NOISE_HILL_WEIGHT,
+    NOISE_LACUNARITY,
+    NOISE_MAX_EROSION_ITERATIONS,
+    NOISE_MOUNTAIN_STRENGTH,
+    NOISE_PERSISTENCE,
+    NOISE_PLATE_SCALE,
+    NOISE_RIVER_SCALE,
+    NOISE_RIVER_WIDTH,
+    NOISE_SAMPLE_STEP,
+    NOISE_SAMPLE_STEPS_ALLOWED,
+};
+
+/// Knobs of the noise generator's height map, so terrain can be tuned from the config file
+/// Scales are in blocks; heights are in the map's [-1, 1] range
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct NoiseSettings {
+    /// Width of continents; larger means bigger land masses and oceans
+    pub continent_scale:    f64,
+    pub hill_scale:         f64,
+    pub detail_scale:       f64,
+    pub continent_weight:   f64,
+    pub hill_weight:        f64,
+    pub detail_weight:      f64,
+    /// Amplitude kept by each finer octave of the continent and hill noise; higher is rougher
+    pub persistence:        f64,
+    /// Frequency multiplier between those octaves
+    pub lacunarity:         f64,
+    /// Spacing of the plate boundaries that mountain ranges rise along
+    pub plate_scale:        f64,
+    /// Height added along plate boundaries, 0 for no mountain ranges
+    pub mountain_strength:  f64,
+    /// Steps steeper than this are worn down by erosion
+    pub erosion_amount:     f64,
+    pub erosion_iterations: u32,
+    /// Spacing of rivers; larger means fewer, longer ones
+    pub river_scale:        f64,
+    /// Width of rivers as a band of the river noise, between 0 (no rivers) and 1
+    pub river_width:        f64,
+    /// Blocks between height samples in each chunk, bilinearly interpolated in between: 1, 2, 4, 8
+    /// or 16. 4 makes about a tenth of the noise calls for slightly smoother terrain; changing it
+    /// leaves seams against chunks already generated
+    pub sample_step:        u32,
+}
+
+impl Default for NoiseSettings {
+    fn default() -> Self {
+        Self {
+            continent_scale:    NOISE_CONTINENT_SCALE,
+            hill_scale:         NOISE_HILL_SCALE,
+            detail_scale:       NOISE_DETAIL_SCALE,
+            continent_weight:   NOISE_CONTINENT_WEIGHT,
+            hill_weight:        NOISE_HILL_WEIGHT,
+            detail_weight:      NOISE_DETAIL_WEIGHT,
+            persistence:        NOISE_PERSISTENCE,
+            lacunarity:         NOISE_LACUNARITY,
+            plate_scale:        NOISE_PLATE_SCALE,
+            mountain_strength:  NOISE_MOUNTAIN_STRENGTH,
+            erosion_amount:     NOISE_EROSION_AMOUNT,
+            erosion_iterations: NOISE_EROSION_ITERATIONS,
+            river_scale:        NOISE_RIVER_SCALE,
+            river_width:        NOISE_RIVER_WIDTH,
+            sample_step:        NOISE_SAMPLE_STEP,
+        }
+    }
+}
+
+impl NoiseSettings {
+    pub fn validate(&self) -> Result<()> {
+        for (key, scale) in [
+            ("continent_scale", self.continent_scale),
+            ("hill_scale", self.hill_scale),
+            ("detail_scale", self.detail_scale),
+            ("plate_scale", self.plate_scale),
+            ("river_scale", self.river_scale),
+        ] {
+            if !(scale.is_finite() && scale > 0.0) {
+                return Err(anyhow!("noise.{} must be a positive number, got {}", key, scale));
+            }
+        }
+        for (key, value) in [
+            ("continent_weight", self.continent_weight),
+            ("hill_weight", self.hill_weight),
+            ("detail_weight", self.detail_weight),
+            ("mountain_strength", self.mountain_strength),
+            ("erosion_amount", self.erosion_amount),
+            ("persistence", self.persistence),
+        ] {
+            if !(value.is_finite() && value >= 0.0) {
+                return Err(anyhow!("noise.{} must be zero or more, got {}", key, value));
+            }
+        }
+        if !(0.0..=1.0).contains(&self.river_width) {
+            return Err(anyhow!("noise.river_width must be between 0 and 1, got {}", self.river_width));
+        }
+        if !(self.lacunarity.is_finite() && self.lacunarity >= 1.0) {
+            return Err(anyhow!("noise.lacunarity must be at least 1, got {}", self.lacunarity));
+        }
+        if self.erosion_iterations > NOISE_MAX_EROSION_ITERATIONS {
+            return Err(anyhow!(
+                "noise.erosion_iterations must be at most {}, got {}",
+                NOISE_MAX_EROSION_ITERATIONS,
+                self.erosion_iterations
+            ));
+        }
+        if !NOISE_SAMPLE_STEPS_ALLOWED.contains(&self.sample_step) {
+            return Err(anyhow!(
+                "noise.sample_step must be one of {:?}, got {}",
+                NOISE_SAMPLE_STEPS_ALLOWED,
+                self.sample_step
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_partial_settings_keep_defaults() {
+        let settings: NoiseSettings =
+            serde_json::from_str(r#"{ "continent_scale": 1024.0, "erosion_iterations": 0 }"#).unwrap();
+        assert_eq!(settings.continent_scale, 1024.0);
+        assert_eq!(settings.erosion_iterations, 0);
+        assert_eq!(settings.hill_scale, NOISE_HILL_SCALE);
+        assert!(settings.validate().is_ok());
+        assert!(NoiseSettings::default().validate().is_ok());
+
+        for bad in [
+            NoiseSettings {
+                detail_scale: 0.0,
+                ..Default::default()
+            },
+            NoiseSettings {
+                hill_weight: -0.5,
+                ..Default::default()
+            },
+            NoiseSettings {
+                erosion_amount: f64::NAN,
+                ..Default::default()
+            },
+            NoiseSettings {
+                river_width: 1.5,
+                ..Default::default()
+            },
+            NoiseSettings {
+                lacunarity: 0.5,
+                ..Default::default()
+            },
+            NoiseSettings {
+                erosion_iterations: NOISE_MAX_EROSION_ITERATIONS + 1,
+                ..Default::default()
+            },
+            NoiseSettings {
+                sample_step: 3,
+                ..Default::default()
+            },
+            NoiseSettings {
+                sample_step: 0,
+                ..Default::default()
+            },
+        ] {
+            assert!(bad.validate().is_err(), "{:?}", bad);
+        }
+    }
+}
@@ -0,0 +1,101 @@
+#![allow(dead_code)]
+
+use std::ops::Range;
+
+use crate::terrain::ChunkPos;
+
+/// Deterministic random numbers for one chunk (splitmix64)
+/// Decoration (trees, ores, ...) must draw from this rather than a global RNG, so regenerating a
+/// chunk from the same world seed places everything in the same spot
+#[derive(Debug, Clone)]
+pub struct ChunkRng {
+    state: u64,
+}
+
+impl ChunkRng {
+    pub fn new(world_seed: u64, chunk_x: i32, chunk_z: i32) -> Self {
+        // Mix each coordinate in separately so (x, z) and (z, x) don't share a stream
+        let mut state = world_seed;
+        state = mix64(state ^ (chunk_x as u32 as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15));
+        state = mix64(state ^ (chunk_z as u32 as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F));
+        Self { state }
+    }
+
+    pub fn for_chunk(world_seed: u64, pos: ChunkPos) -> Self {
+        Self::new(world_seed, pos.x, pos.z)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        mix64(self.state)
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    /// Uniform in `[0, 1)`
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Uniform in `range`; panics if it is empty
+    pub fn gen_range(&mut self, range: Range<i32>) -> i32 {
+        assert!(!range.is_empty(), "gen_range called with an empty range");
+        let span = (range.end as i64 - range.start as i64) as u64;
+        // Multiply-shift keeps the bias below 2^-32 for spans that fit in an i32
+        let offset = ((self.next_u32() as u64 * span) >> 32) as i64;
+        (range.start as i64 + offset) as i32
+    }
+
+    /// True with probability `p`
+    pub fn chance(&mut self, p: f64) -> bool {
+        self.next_f64() < p
+    }
+}
+
+/// splitmix64 finalizer
+fn mix64(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sequence(mut rng: ChunkRng) -> Vec<u64> {
+        (0..16).map(|_| rng.next_u64()).collect()
+    }
+
+    #[test]
+    fn test_same_seed_and_chunk_repeat() {
+        assert_eq!(
+            sequence(ChunkRng::new(12345, 3, -7)),
+            sequence(ChunkRng::for_chunk(12345, ChunkPos::new(3, -7)))
+        );
+
+        let base = sequence(ChunkRng::new(12345, 3, -7));
+        assert_ne!(base, sequence(ChunkRng::new(12345, 4, -7)));
+        assert_ne!(base, sequence(ChunkRng::new(12345, 3, -6)));
+        assert_ne!(base, sequence(ChunkRng::new(12345, -7, 3)));
+        assert_ne!(base, sequence(ChunkRng::new(54321, 3, -7)));
+    }
+
+    #[test]
+    fn test_ranges_and_chance() {
+        let mut rng = ChunkRng::new(1, 0, 0);
+        for _ in 0..1000 {
+            let value = rng.gen_range(-3..5);
+            assert!((-3..5).contains(&value));
+            assert!((0.0..1.0).contains(&rng.next_f64()));
+        }
+        assert_eq!(rng.gen_range(i32::MIN..i32::MIN + 1), i32::MIN);
+
+        assert!((0..100).all(|_| !rng.chance(0.0)));
+        assert!((0..100).all(|_| rng.chance(1.0)));
+        let hits = (0..10_000).filter(|_| rng.chance(0.25)).count();
+        assert!((2_000..3_000).contains(&hits), "{} hits", hits);
+    }
+}
@@ -1,5 +1,5 @@
 #![allow(dead_code)]
-use crate::terrain::noise;
+use crate::terrain::{NoiseSettings, noise};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Biome {
@@ -13,81 +13,226 @@ pub enum Biome {
     Desert,
 }
 
+impl Biome {
+    pub const ALL: [Biome; 8] = [
+        Biome::Ocean,
+        Biome::Beach,
+        Biome::Plains,
+        Biome::Forest,
+        Biome::Mountain,
+        Biome::Snow,
+        Biome::SnowMountain,
+        Biome::Desert,
+    ];
+
+    /// Vanilla biome this one is synchronized to the client as
+    pub fn key(self) -> &'static str {
+        match self {
+            Biome::Ocean => "minecraft:ocean",
+            Biome::Beach => "minecraft:beach",
+            Biome::Plains => "minecraft:plains",
+            Biome::Forest => "minecraft:forest",
+            Biome::Mountain => "minecraft:windswept_hills",
+            Biome::Snow => "minecraft:snowy_plains",
+            Biome::SnowMountain => "minecraft:snowy_slopes",
+            Biome::Desert => "minecraft:desert",
+        }
+    }
+}
+
+/// Elevation the sea fills up to
+pub const SEA_LEVEL_ELEVATION: f64 = -0.05;
+/// Elevation rivers are carved down to, a few blocks below sea level so they hold water
+const RIVER_BED_ELEVATION: f64 = -0.08;
+
+/// Height of the terrain at any point before erosion, so single points can be looked up without
+/// building a whole `HeightMap`
+#[derive(Debug, Clone, Copy)]
+pub struct HeightSampler {
+    seed:     u64,
+    settings: NoiseSettings,
+}
+
+impl HeightSampler {
+    pub fn new(seed: u64, settings: NoiseSettings) -> Self {
+        Self { seed, settings }
+    }
+
+    /// Elevation in [-1, 1] at map coordinates `(fx, fy)`
+    pub fn height(&self, fx: f64, fy: f64) -> f64 {
+        let height = self.continental_noise(fx, fy).clamp(-1.0, 1.0);
+        let height = self.apply_plate_collision(fx, fy, height);
+        self.carve_river(fx, fy, height)
+    }
+
+    /// Heights of a chunk's columns, indexed `[z][x]`
+    /// Sampled every `sample_step` blocks and bilinearly interpolated in between; the samples sit on
+    /// multiples of the step, so neighbouring chunks agree along the edge they share
+    pub fn chunk_heights(&self, chunk_x: i32, chunk_z: i32) -> [[f64; 16]; 16] {
+        let (origin_x, origin_z) = (chunk_x as f64 * 16.0, chunk_z as f64 * 16.0);
+        let mut heights = [[0.0; 16]; 16];
+
+        let step = self.settings.sample_step.clamp(1, 16) as usize;
+        if step == 1 {
+            for (z, row) in heights.iter_mut().enumerate() {
+                for (x, height) in row.iter_mut().enumerate() {
+                    *height = self.height(origin_x + x as f64, origin_z + z as f64);
+                }
+            }
+            return heights;
+        }
+
+        // Corners of each step-sized cell, including the far edge of the chunk
+        let cells = 16 / step;
+        let corners: Vec<Vec<f64>> = (0..=cells)
+            .map(|cz| {
+                (0..=cells)
+                    .map(|cx| self.height(origin_x + (cx * step) as f64, origin_z + (cz * step) as f64))
+                    .collect()
+            })
+            .collect();
+
+        let lerp = |a: f64, b: f64, t: f64| a + (b - a) * t;
+        for (z, row) in heights.iter_mut().enumerate() {
+            let (cz, tz) = (z / step, (z % step) as f64 / step as f64);
+            for (x, height) in row.iter_mut().enumerate() {
+                let (cx, tx) = (x / step, (x % step) as f64 / step as f64);
+                let near = lerp(corners[cz][cx], corners[cz][cx + 1], tx);
+                let far = lerp(corners[cz + 1][cx], corners[cz + 1][cx + 1], tx);
+                *height = lerp(near, far, tz);
+            }
+        }
+        heights
+    }
+
+    /// How much of a river the point is in: 1 on a river's center line, falling to 0 at its banks
+    /// Rivers follow the zero line of their own noise channel, so they wind across biomes and run
+    /// on until they meet the sea
+    pub fn river(&self, fx: f64, fy: f64) -> f64 {
+        let s = self.settings;
+        if s.river_width <= 0.0 {
+            return 0.0;
+        }
+
+        let noise = noise::fbm_with(
+            fx / s.river_scale,
+            fy / s.river_scale,
+            2,
+            s.persistence,
+            s.lacunarity,
+            self.seed.wrapping_add(3),
+        );
+        // Ridged: distance from the middle of the noise's [0, 1] range
+        let ridge = (noise * 2.0 - 1.0).abs();
+        (1.0 - ridge / s.river_width).max(0.0)
+    }
+
+    /// Lower land inside a river down to its bed, with sloped banks; seas are left alone
+    fn carve_river(&self, fx: f64, fy: f64, height: f64) -> f64 {
+        if height <= RIVER_BED_ELEVATION {
+            return height;
+        }
+        let river = self.river(fx, fy);
+        let depth = river * river * (3.0 - 2.0 * river);
+        height + (RIVER_BED_ELEVATION - height) * depth
+    }
+
+    fn continental_noise(&self, fx: f64, fy: f64) -> f64 {
+        let s = self.settings;
+
+        // Multi-scale noise for continents
+        let large_scale = noise::fbm_with(
+            fx / s.continent_scale,
+            fy / s.continent_scale,
+            3,
+            s.persistence,
+            s.lacunarity,
+            self.seed,
+        );
+        let medium_scale = noise::fbm_with(
+            fx / s.hill_scale,
+            fy / s.hill_scale,
+            2,
+            s.persistence,
+            s.lacunarity,
+            self.seed.wrapping_add(1),
+        );
+        let small_scale =
+            noise::perlin_noise(fx / s.detail_scale, fy / s.detail_scale, 1.0, self.seed.wrapping_add(2));
+
+        // Combine scales with weights
+        large_scale * s.continent_weight + medium_scale * s.hill_weight + small_scale * s.detail_weight
+    }
+
+    /// Simulate plate collisions for mountain ranges
+    fn apply_plate_collision(&self, fx: f64, fy: f64, height: f64) -> f64 {
+        // Create collision zones at regular intervals
+        let plate_scale = self.settings.plate_scale;
+        let collision_strength = self.settings.mountain_strength;
+
+        // rem_euclid, so ranges keep their spacing across 0 instead of mirroring
+        let distance_to_boundary_x =
+            (fx.rem_euclid(plate_scale) - plate_scale / 2.0).abs() / (plate_scale / 8.0);
+        let distance_to_boundary_y =
+            (fy.rem_euclid(plate_scale) - plate_scale / 2.0).abs() / (plate_scale / 8.0);
+
+        if distance_to_boundary_x < 1.0 || distance_to_boundary_y < 1.0 {
+            let boundary_boost =
+                (1.0 - distance_to_boundary_x.min(distance_to_boundary_y)) * collision_strength;
+            (height + boundary_boost).clamp(-1.0, 1.0)
+        } else {
+            height
+        }
+    }
+}
+
 pub struct HeightMap {
-    data:   Vec<Vec<f64>>,
-    width:  usize,
-    height: usize,
-    seed:   u64,
+    data:     Vec<Vec<f64>>,
+    width:    usize,
+    height:   usize,
+    sampler:  HeightSampler,
+    settings: NoiseSettings,
 }
 
 impl HeightMap {
-    pub fn new(width: usize, height: usize, seed: u64) -> Self {
+    pub fn new(width: usize, height: usize, seed: u64, settings: NoiseSettings) -> Self {
         let mut hm = Self {
             data: vec![vec![0.0; width]; height],
             width,
             height,
-            seed,
+            sampler: HeightSampler::new(seed, settings),
+            settings,
         };
         hm.generate();
         hm
     }
 
     fn generate(&mut self) {
-        // Base continental noise
+        // Continental noise with mountain ranges along plate boundaries, a chunk at a time
         // PERF: @nested : Loop moved to thread engine
-        for y in 0..self.height {
-            for x in 0..self.width {
-                let fx = x as f64;
-                let fy = y as f64;
-
-                // Multi-scale noise for continents
-                let large_scale = noise::fbm(fx / 512.0, fy / 512.0, 3, self.seed);
-                let medium_scale = noise::fbm(fx / 128.0, fy / 128.0, 2, self.seed.wrapping_add(1));
-                let small_scale = noise::perlin_noise(fx / 32.0, fy / 32.0, 1.0, self.seed.wrapping_add(2));
-
-                // Combine scales with weights
-                let height = large_scale * 0.6 + medium_scale * 0.3 + small_scale * 0.1;
-                self.data[y][x] = height.clamp(-1.0, 1.0);
+        for chunk_y in 0..self.height.div_ceil(16) {
+            for chunk_x in 0..self.width.div_ceil(16) {
+                let heights = self.sampler.chunk_heights(chunk_x as i32, chunk_y as i32);
+                for (dy, row) in heights.iter().enumerate() {
+                    let y = chunk_y * 16 + dy;
+                    for (dx, &height) in row.iter().enumerate() {
+                        let x = chunk_x * 16 + dx;
+                        if x < self.width && y < self.height {
+                            self.data[y][x] = height;
+                        }
+                    }
+                }
             }
         }
 
-        // Simulate plate collisions for mountain ranges
-        self.apply_plate_collisions();
-
         // Apply erosion
         self.apply_erosion();
     }
 
-    fn apply_plate_collisions(&mut self) {
-        // Simulate collision zones as mountain ridges
-        // PERF: @nested : Loop moved to thread engine
-        for y in 0..self.height {
-            for x in 0..self.width {
-                let fx = x as f64;
-                let fy = y as f64;
-
-                // Create collision zones at regular intervals
-                let plate_scale = 256.0;
-                let collision_strength = 0.15;
-
-                let distance_to_boundary_x =
-                    (fx % plate_scale - plate_scale / 2.0).abs() / (plate_scale / 8.0);
-                let distance_to_boundary_y =
-                    (fy % plate_scale - plate_scale / 2.0).abs() / (plate_scale / 8.0);
-
-                if distance_to_boundary_x < 1.0 || distance_to_boundary_y < 1.0 {
-                    let boundary_boost =
-                        (1.0 - distance_to_boundary_x.min(distance_to_boundary_y)) * collision_strength;
-                    self.data[y][x] = (self.data[y][x] + boundary_boost).clamp(-1.0, 1.0);
-                }
-            }
-        }
-    }
-
     fn apply_erosion(&mut self) {
         // Simple thermal erosion: flatten steep slopes
-        let iterations = 2;
-        let erosion_amount = 0.1;
+        let iterations = self.settings.erosion_iterations;
+        let erosion_amount = self.settings.erosion_amount;
 
         for _ in 0..iterations {
             let mut new_data = self.data.clone();
@@ -187,7 +332,7 @@ impl BiomeMap {
     //     Self { data, width, height }
     // }
 
-    fn determine_biome(elevation: f64, slope: f64) -> Biome {
+    pub fn determine_biome(elevation: f64, slope: f64) -> Biome {
         // Snowline at elevation 0.7
         if elevation > 0.7 {
             if slope > 0.3 {
@@ -215,7 +360,7 @@ impl BiomeMap {
             }
         }
         // Beach/coastal
-        else if elevation > -0.05 {
+        else if elevation > SEA_LEVEL_ELEVATION {
             Biome::Beach
         }
         // Ocean
@@ -232,3 +377,122 @@ impl BiomeMap {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZE: usize = 64;
+
+    fn heights(settings: NoiseSettings) -> Vec<f64> {
+        let map = HeightMap::new(SIZE, SIZE, 12345, settings);
+        (0..SIZE)
+            .flat_map(|y| (0..SIZE).map(move |x| (x, y)))
+            .map(|(x, y)| map.get(x, y))
+            .collect()
+    }
+
+    fn mean_difference(a: &[f64], b: &[f64]) -> f64 {
+        a.iter().zip(b).map(|(a, b)| (a - b).abs()).sum::<f64>() / a.len() as f64
+    }
+
+    #[test]
+    fn test_settings_change_the_height_map() {
+        let default = heights(NoiseSettings::default());
+        assert_eq!(default, heights(NoiseSettings::default()), "same seed and settings are reproducible");
+
+        let small_continents = heights(NoiseSettings {
+            continent_scale: 64.0,
+            ..Default::default()
+        });
+        let mountainous = heights(NoiseSettings {
+            plate_scale: 32.0,
+            mountain_strength: 0.6,
+            ..Default::default()
+        });
+        let uneroded = heights(NoiseSettings {
+            detail_weight: 0.8,
+            erosion_iterations: 0,
+            ..Default::default()
+        });
+
+        for (name, other) in [
+            ("continent_scale", &small_continents),
+            ("mountain_strength", &mountainous),
+            ("erosion", &uneroded),
+        ] {
+            let diff = mean_difference(&default, other);
+            assert!(diff > 0.01, "{} barely changed the terrain: {}", name, diff);
+        }
+
+        // Stronger mountain ranges only ever raise the land
+        let mean = |h: &[f64]| h.iter().sum::<f64>() / h.len() as f64;
+        assert!(mean(&mountainous) > mean(&default));
+    }
+
+    #[test]
+    fn test_plate_boundaries_repeat_across_zero() {
+        let sampler = HeightSampler::new(12345, NoiseSettings::default());
+        let scale = NoiseSettings::default().plate_scale;
+        for fx in [-3.0 * scale, -scale / 2.0, -17.25, -1.0, 0.0, 5.5] {
+            for fy in [-scale / 2.0 - 3.0, 40.0] {
+                let boost = sampler.apply_plate_collision(fx, fy, 0.0);
+                assert_eq!(boost, sampler.apply_plate_collision(fx + scale, fy, 0.0), "({}, {})", fx, fy);
+                assert_eq!(boost, sampler.apply_plate_collision(fx, fy + scale, 0.0), "({}, {})", fx, fy);
+            }
+        }
+        // The middle of each plate is a boundary, on either side of 0
+        assert!(sampler.apply_plate_collision(-scale / 2.0, 1.0, 0.0) > 0.0);
+    }
+
+    #[test]
+    fn test_interpolated_heights_stay_close_to_full_resolution() {
+        let full = HeightSampler::new(12345, NoiseSettings::default());
+        let coarse = HeightSampler::new(
+            12345,
+            NoiseSettings {
+                sample_step: 4,
+                ..Default::default()
+            },
+        );
+        // Block heights as `ChunkGenerator` places them: 190 blocks across [-1, 1]
+        let blocks = |elevation: f64| (elevation + 1.0) * 95.0;
+
+        for (chunk_x, chunk_z) in [(0, 0), (5, 9), (-3, 2), (17, -20)] {
+            let exact = full.chunk_heights(chunk_x, chunk_z);
+            let interpolated = coarse.chunk_heights(chunk_x, chunk_z);
+
+            let mut total = 0.0;
+            let mut worst: f64 = 0.0;
+            for z in 0..16 {
+                for x in 0..16 {
+                    let diff = (blocks(exact[z][x]) - blocks(interpolated[z][x])).abs();
+                    if x % 4 == 0 && z % 4 == 0 {
+                        assert_eq!(diff, 0.0, "sample points are exact");
+                    }
+                    total += diff;
+                    worst = worst.max(diff);
+                }
+            }
+            let mean = total / 256.0;
+            assert!(mean < 0.5, "chunk ({}, {}) is off by {:.2} blocks on average", chunk_x, chunk_z, mean);
+            assert!(worst < 3.0, "chunk ({}, {}) is off by up to {:.2} blocks", chunk_x, chunk_z, worst);
+        }
+
+        // Neighbouring chunks share their edge samples, so there is no step between them
+        let west = coarse.chunk_heights(4, 4);
+        let east = coarse.chunk_heights(5, 4);
+        let (edge, inner) = (full.height(80.0, 64.0), full.height(76.0, 64.0));
+        assert_eq!(east[0][0], edge);
+        assert_eq!(west[0][15], inner + (edge - inner) * 0.75);
+    }
+
+    #[test]
+    fn test_full_resolution_chunks_match_single_samples() {
+        let sampler = HeightSampler::new(7, NoiseSettings::default());
+        let heights = sampler.chunk_heights(-2, 3);
+        for (x, z) in [(0, 0), (15, 15), (7, 3)] {
+            assert_eq!(heights[z][x], sampler.height((-32 + x as i32) as f64, (48 + z as i32) as f64));
+        }
+    }
+}
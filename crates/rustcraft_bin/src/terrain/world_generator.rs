@@ -0,0 +1,183 @@
+#![allow(dead_code)]
+
+use std::str::FromStr;
+
+use anyhow::{Result, anyhow};
+
+use crate::consts::{TERRAIN_CHUNK_HEIGHT, TERRAIN_CHUNK_SIZE};
+use crate::terrain::{BlockType, Chunk, ChunkPos, block_from_name};
+
+/// Produces the blocks of chunks that have never been saved
+/// `ChunkStorage` calls this from its generation pool, so implementations must be thread safe and
+/// return the same chunk every time for a given position
+pub trait WorldGenerator: Send + Sync {
+    fn generate(&self, pos: ChunkPos) -> Chunk;
+}
+
+/// Identical horizontal layers everywhere, listed bottom to top as `(block, thickness)`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FlatWorldGenerator {
+    layers: Vec<(BlockType, u32)>,
+}
+
+impl FlatWorldGenerator {
+    pub fn new(layers: Vec<(BlockType, u32)>) -> Self {
+        Self { layers }
+    }
+
+    pub fn layers(&self) -> &[(BlockType, u32)] {
+        &self.layers
+    }
+}
+
+impl Default for FlatWorldGenerator {
+    /// Bedrock, 2 dirt, 1 grass
+    fn default() -> Self {
+        Self::new(vec![
+            (BlockType::Bedrock, 1),
+            (BlockType::Dirt, 2),
+            (BlockType::Grass, 1),
+        ])
+    }
+}
+
+/// Parse a layer spec such as `"bedrock, 2 dirt, grass_block"`: comma separated layers, bottom to
+/// top, each an optional thickness followed by a block registry name
+impl FromStr for FlatWorldGenerator {
+    type Err = anyhow::Error;
+
+    fn from_str(spec: &str) -> Result<Self> {
+        let layers = spec
+            .split(',')
+            .map(|layer| {
+                let mut parts = layer.split_whitespace();
+                let (thickness, name) = match (parts.next(), parts.next(), parts.next()) {
+                    (Some(name), None, _) => (1, name),
+                    (Some(count), Some(name), None) => {
+                        let count = count
+                            .parse::<u32>()
+                            .map_err(|_| anyhow!("Invalid layer thickness '{}'", count))?;
+                        (count, name)
+                    }
+                    _ => return Err(anyhow!("Invalid flat layer '{}'", layer.trim())),
+                };
+                let block = block_from_name(name).ok_or_else(|| anyhow!("Unknown block '{}'", name))?;
+                Ok((block, thickness))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self::new(layers))
+    }
+}
+
+impl WorldGenerator for FlatWorldGenerator {
+    fn generate(&self, pos: ChunkPos) -> Chunk {
+        let mut chunk = Chunk::new(pos);
+        let last = TERRAIN_CHUNK_SIZE - 1;
+
+        let mut y = 0;
+        for &(block, thickness) in &self.layers {
+            if y >= TERRAIN_CHUNK_HEIGHT {
+                break;
+            }
+            let thickness = thickness as usize;
+            if thickness > 0 && block != BlockType::Air {
+                chunk.fill((0, y, 0), (last, y + thickness - 1, last), block);
+            }
+            y += thickness;
+        }
+
+        chunk
+    }
+}
+
+/// Nothing but air
+#[derive(Debug, Clone, Copy, Default)]
+pub struct VoidWorldGenerator;
+
+impl WorldGenerator for VoidWorldGenerator {
+    fn generate(&self, pos: ChunkPos) -> Chunk {
+        Chunk::new(pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consts::TERRAIN_SECTION_HEIGHT;
+
+    #[test]
+    fn test_flat_generator_layers() {
+        let generator: FlatWorldGenerator = "stone, 2 dirt, grass_block".parse().unwrap();
+
+        for pos in [ChunkPos::new(0, 0), ChunkPos::new(-12, 40)] {
+            let chunk = generator.generate(pos);
+            assert_eq!(chunk.pos, pos);
+
+            for x in 0..TERRAIN_CHUNK_SIZE {
+                for z in 0..TERRAIN_CHUNK_SIZE {
+                    let column: Vec<BlockType> = chunk.get_column(x, z).unwrap().collect();
+                    assert_eq!(
+                        &column[..5],
+                        &[
+                            BlockType::Stone,
+                            BlockType::Dirt,
+                            BlockType::Dirt,
+                            BlockType::Grass,
+                            BlockType::Air
+                        ]
+                    );
+                    assert!(column[4..].iter().all(|&b| b == BlockType::Air));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_flat_layer_spec() {
+        let generator: FlatWorldGenerator = "minecraft:bedrock, 3 stone,2 dirt , 1 grass_block"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            generator.layers(),
+            &[
+                (BlockType::Bedrock, 1),
+                (BlockType::Stone, 3),
+                (BlockType::Dirt, 2),
+                (BlockType::Grass, 1)
+            ]
+        );
+        assert_eq!(
+            "bedrock, 2 dirt, grass_block"
+                .parse::<FlatWorldGenerator>()
+                .unwrap(),
+            FlatWorldGenerator::default()
+        );
+
+        // Every column is the spec stacked from y = 0, with grass on top
+        let chunk = generator.generate(ChunkPos::new(5, -8));
+        for x in 0..TERRAIN_CHUNK_SIZE {
+            for z in 0..TERRAIN_CHUNK_SIZE {
+                let column: Vec<BlockType> = chunk.get_column(x, z).unwrap().collect();
+                let top = column.iter().rposition(|&b| b != BlockType::Air).unwrap();
+                assert_eq!(top, 6);
+                assert_eq!(column[top], BlockType::Grass);
+                assert_eq!(column[0], BlockType::Bedrock);
+                assert_eq!(&column[1..4], &[BlockType::Stone; 3]);
+                assert_eq!(&column[4..6], &[BlockType::Dirt; 2]);
+            }
+        }
+
+        assert!("".parse::<FlatWorldGenerator>().is_err());
+        assert!("2 diamond_block".parse::<FlatWorldGenerator>().is_err());
+        assert!("two dirt".parse::<FlatWorldGenerator>().is_err());
+        assert!("2 dirt grass_block".parse::<FlatWorldGenerator>().is_err());
+    }
+
+    #[test]
+    fn test_void_generator_is_empty() {
+        let chunk = VoidWorldGenerator.generate(ChunkPos::new(3, -3));
+        assert_eq!(chunk.pos, ChunkPos::new(3, -3));
+        assert!((0..TERRAIN_CHUNK_HEIGHT / TERRAIN_SECTION_HEIGHT).all(|i| chunk.is_section_empty(i)));
+    }
+}
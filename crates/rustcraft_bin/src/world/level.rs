@@ -0,0 +1,141 @@
+use std::path::Path;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::player::Vec3;
+use crate::world::minecraft_world::WorldBorder;
+use crate::world::time::WorldTime;
+
+/// World metadata file, next to the region files
+pub const LEVEL_FILE: &str = "level.json";
+/// Layout of `level.json` written by this version; files from before it was versioned read as 0
+pub const LEVEL_VERSION: u32 = 1;
+
+/// World state that isn't stored in chunks, saved so it survives restarts and moves with the
+/// world folder
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LevelData {
+    pub version:     u32,
+    pub seed:        Option<u64>,
+    pub spawn:       Option<Vec3<f64>>,
+    pub border:      Option<WorldBorder>,
+    pub world_age:   u64,
+    pub time_of_day: u64,
+}
+
+impl LevelData {
+    pub fn new(seed: u64, spawn: Vec3<f64>, border: WorldBorder, time: &WorldTime) -> Self {
+        Self {
+            version:     LEVEL_VERSION,
+            seed:        Some(seed),
+            spawn:       Some(spawn),
+            border:      Some(border),
+            world_age:   time.world_age,
+            time_of_day: time.time_of_day,
+        }
+    }
+
+    /// Read `level.json` from `world_dir`, or `None` for a world that has never been saved
+    pub fn load(world_dir: &Path) -> Result<Option<Self>> {
+        let path = world_dir.join(LEVEL_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let contents = std::fs::read_to_string(path)?;
+        let level: Self = serde_json::from_str(&contents)?;
+        if level.version != LEVEL_VERSION {
+            tracing::info!(
+                "[WORLD] Migrating {} from version {} to {}; it is rewritten on the next save",
+                LEVEL_FILE,
+                level.version,
+                LEVEL_VERSION
+            );
+        }
+        Ok(Some(level))
+    }
+
+    pub fn save(&self, world_dir: &Path) -> Result<()> {
+        // Write then rename, so a crash mid-save leaves the previous file intact
+        let path = world_dir.join(LEVEL_FILE);
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_string_pretty(self)?)?;
+        std::fs::rename(tmp, path)?;
+        Ok(())
+    }
+
+    /// The seed to generate with: the saved one, so existing terrain keeps matching, unless the
+    /// world predates saving it
+    pub fn seed_or(&self, configured: u64) -> u64 {
+        match self.seed {
+            Some(saved) if saved != configured => {
+                tracing::warn!(
+                    "[WORLD] Config seed {} differs from the world's seed {}; keeping the world's",
+                    configured,
+                    saved
+                );
+                saved
+            }
+            Some(saved) => saved,
+            None => configured,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir() -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("rustcraft_level_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_level_data_round_trip() {
+        let dir = temp_dir();
+
+        assert_eq!(LevelData::load(&dir).unwrap(), None);
+
+        let time = WorldTime {
+            world_age: 1_234_567,
+            time_of_day: 18_000,
+            ..WorldTime::default()
+        };
+        let border = WorldBorder {
+            center:   (100.0, -50.5),
+            diameter: 2_000.0,
+        };
+        let data = LevelData::new(u64::MAX - 1, Vec3::new(8.5, 70.0, -3.5), border, &time);
+        data.save(&dir).unwrap();
+        assert_eq!(LevelData::load(&dir).unwrap(), Some(data));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_unversioned_level_file_still_loads() {
+        let dir = temp_dir();
+        std::fs::write(dir.join(LEVEL_FILE), r#"{ "world_age": 40, "time_of_day": 13040 }"#).unwrap();
+
+        let level = LevelData::load(&dir).unwrap().unwrap();
+        assert_eq!(level.version, 0);
+        assert_eq!((level.world_age, level.time_of_day), (40, 13_040));
+        assert_eq!((level.seed, level.spawn, level.border), (None, None, None));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_saved_seed_wins_over_config() {
+        let saved = LevelData {
+            seed: Some(7),
+            ..LevelData::default()
+        };
+        assert_eq!(saved.seed_or(7), 7);
+        assert_eq!(saved.seed_or(12_345), 7);
+        assert_eq!(LevelData::default().seed_or(12_345), 12_345);
+    }
+}
@@ -1,23 +1,427 @@
 #![allow(dead_code)]
 
-/// Represents a Minecraft world/dimension
-pub struct MinecraftWorld {
-    name: String,
+use std::sync::Arc;
+
+use anyhow::Result;
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::chunk::{ChunkProvider, ChunkStorage, FlushSummary};
+use crate::consts::WORLD_BORDER_DIAMETER;
+use crate::core::{Event, EventBus, EventResult};
+use crate::player::Vec3;
+use crate::terrain::{BlockState, BlockType, Chunk, ChunkPos};
+use crate::world::level::LevelData;
+use crate::world::time::WorldTime;
+use crate::world::weather::{Weather, WeatherState};
+
+/// Square boundary players are kept inside, centered on `center` (x, z)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct WorldBorder {
+    pub center:   (f64, f64),
+    /// Side length in blocks
+    pub diameter: f64,
 }
 
-impl MinecraftWorld {
-    pub fn new(name: String) -> Self {
-        Self { name }
+impl Default for WorldBorder {
+    fn default() -> Self {
+        Self {
+            center:   (0.0, 0.0),
+            diameter: WORLD_BORDER_DIAMETER,
+        }
     }
 }
 
-impl Default for MinecraftWorld {
-    fn default() -> Self {
-        let name = crate::consts::WORLD_PATH
+impl WorldBorder {
+    pub fn contains(&self, x: f64, z: f64) -> bool {
+        let radius = self.diameter / 2.0;
+        (x - self.center.0).abs() <= radius && (z - self.center.1).abs() <= radius
+    }
+}
+
+/// A Minecraft world/dimension: its chunks and the world-level state around them
+pub struct World {
+    name:    String,
+    chunks:  Arc<ChunkStorage>,
+    seed:    u64,
+    spawn:   RwLock<Vec3<f64>>,
+    border:  RwLock<WorldBorder>,
+    time:    Mutex<WorldTime>,
+    weather: Mutex<WeatherState>,
+}
+
+impl World {
+    pub fn new<S: Into<String>>(name: S, chunks: Arc<ChunkStorage>, seed: u64, spawn: Vec3<f64>) -> Self {
+        Self {
+            name: name.into(),
+            chunks,
+            seed,
+            spawn: RwLock::new(spawn),
+            border: RwLock::new(WorldBorder::default()),
+            time: Mutex::new(WorldTime::default()),
+            weather: Mutex::new(WeatherState::new(seed)),
+        }
+    }
+
+    /// World folder name, e.g. `world`
+    pub fn default_name() -> String {
+        crate::consts::WORLD_PATH
             .split('/')
             .next_back()
             .unwrap_or("world")
-            .to_string();
-        Self { name }
+            .to_string()
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn chunks(&self) -> &Arc<ChunkStorage> {
+        &self.chunks
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn spawn(&self) -> Vec3<f64> {
+        *self.spawn.read()
+    }
+
+    pub fn set_spawn(&self, spawn: Vec3<f64>) {
+        *self.spawn.write() = spawn;
+    }
+
+    pub fn border(&self) -> WorldBorder {
+        *self.border.read()
+    }
+
+    pub fn set_border(&self, border: WorldBorder) {
+        *self.border.write() = border;
+    }
+
+    pub fn time(&self) -> WorldTime {
+        *self.time.lock()
+    }
+
+    /// Ticks into the current day, `0..DAY_LENGTH`
+    pub fn time_of_day(&self) -> u64 {
+        self.time.lock().time_of_day
+    }
+
+    pub fn world_age(&self) -> u64 {
+        self.time.lock().world_age
+    }
+
+    pub fn set_time_of_day(&self, time: u64) {
+        self.time.lock().set_time_of_day(time);
+    }
+
+    pub fn set_daylight_cycle(&self, enabled: bool) {
+        self.time.lock().daylight_cycle = enabled;
+    }
+
+    pub fn weather(&self) -> Weather {
+        self.weather.lock().weather()
+    }
+
+    /// Change the weather now; it runs for a random duration before changing again by itself
+    pub fn set_weather(&self, weather: Weather) {
+        self.weather.lock().set(weather);
+    }
+
+    /// Restore the spawn, border, world age and time of day saved in `level`; the seed is picked up
+    /// earlier, since the generator needs it
+    pub fn apply_level_data(&self, level: &LevelData) {
+        if let Some(spawn) = level.spawn {
+            self.set_spawn(spawn);
+        }
+        if let Some(border) = level.border {
+            self.set_border(border);
+        }
+        let mut time = self.time.lock();
+        time.world_age = level.world_age;
+        time.set_time_of_day(level.time_of_day);
+    }
+
+    /// Write every cached chunk and the world metadata to disk, reporting the chunks written
+    pub fn save(&self) -> Result<FlushSummary> {
+        let summary = self.chunks.flush()?;
+        LevelData::new(self.seed, self.spawn(), self.border(), &self.time()).save(self.chunks.world_dir())?;
+        Ok(summary)
+    }
+
+    /// Block at world coordinates, loading or generating its chunk; air above and below the world
+    pub fn get_block(&self, x: i32, y: i32, z: i32) -> Result<BlockType> {
+        let Some(y) = Chunk::local_y(y) else {
+            return Ok(BlockType::Air);
+        };
+
+        let chunk = self.chunks.get_chunk(ChunkPos::from_block_pos(x, z))?;
+        let (local_x, local_z) = Self::local(x, z);
+        Ok(chunk.get_block(local_x, y, local_z).unwrap_or(BlockType::Air))
+    }
+
+    /// Like `get_block`, with the block's state properties
+    pub fn get_block_state(&self, x: i32, y: i32, z: i32) -> Result<BlockState> {
+        let Some(y) = Chunk::local_y(y) else {
+            return Ok(BlockState::AIR);
+        };
+
+        let chunk = self.chunks.get_chunk(ChunkPos::from_block_pos(x, z))?;
+        let (local_x, local_z) = Self::local(x, z);
+        Ok(chunk
+            .get_block_state(local_x, y, local_z)
+            .unwrap_or(BlockState::AIR))
+    }
+
+    /// Place a block at world coordinates; returns false if `y` is outside the world
+    pub fn set_block(&self, x: i32, y: i32, z: i32, block: impl Into<BlockState>) -> Result<bool> {
+        self.chunks.set_block(x, y, z, block.into())
+    }
+
+    /// Break the block at world coordinates for `player` unless a `BlockBreak` listener vetoes it;
+    /// returns whether the block was removed
+    pub fn break_block(&self, events: &EventBus, player: Uuid, x: i32, y: i32, z: i32) -> Result<bool> {
+        let block = self.get_block_state(x, y, z)?;
+        if block.is_air() {
+            return Ok(false);
+        }
+
+        let event = Event::BlockBreak {
+            player,
+            x,
+            y,
+            z,
+            block,
+        };
+        if events.emit(&event) == EventResult::Cancel {
+            return Ok(false);
+        }
+        self.set_block(x, y, z, BlockState::AIR)
+    }
+
+    /// Place a block for `player` unless a `BlockPlace` listener vetoes it; returns whether it was
+    /// placed
+    pub fn place_block(
+        &self,
+        events: &EventBus,
+        player: Uuid,
+        x: i32,
+        y: i32,
+        z: i32,
+        block: impl Into<BlockState>,
+    ) -> Result<bool> {
+        let block = block.into();
+        let event = Event::BlockPlace {
+            player,
+            x,
+            y,
+            z,
+            block,
+        };
+        if events.emit(&event) == EventResult::Cancel {
+            return Ok(false);
+        }
+        self.set_block(x, y, z, block)
+    }
+
+    /// Advance world state by one game tick; returns the new weather if it changed
+    pub fn tick(&self) -> Option<Weather> {
+        self.time.lock().tick();
+        self.weather.lock().tick()
+    }
+
+    /// Position of the block inside its chunk
+    fn local(x: i32, z: i32) -> (usize, usize) {
+        (x.rem_euclid(16) as usize, z.rem_euclid(16) as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consts::{TERRAIN_CHUNK_HEIGHT, WORLD_MIN_Y};
+    use crate::core::{ChunkGenThreadPool, EventKind};
+    use crate::terrain::{FlatWorldGenerator, VoidWorldGenerator, WorldGenerator};
+    use crate::world::time::DAY_LENGTH;
+
+    fn test_world(generator: Arc<dyn WorldGenerator>) -> (World, std::path::PathBuf) {
+        let world_dir = std::env::temp_dir().join(format!("rustcraft_world_{}", uuid::Uuid::new_v4()));
+        let chunks =
+            ChunkStorage::with_world_dir(world_dir.clone(), generator, Arc::new(ChunkGenThreadPool::new()))
+                .unwrap();
+        let world = World::new("test", Arc::new(chunks), 7, Vec3::new(0.5, 65.0, 0.5));
+        (world, world_dir)
+    }
+
+    #[test]
+    fn test_blocks_route_to_the_owning_chunk() {
+        let (world, world_dir) = test_world(Arc::new(VoidWorldGenerator));
+
+        // Either side of chunk boundaries, including negative coordinates
+        let spots = [
+            (0, 64, 0),
+            (15, 64, 15),
+            (16, 64, 0),
+            (-1, 64, -1),
+            (-16, 10, 31),
+            (-17, 200, -33),
+        ];
+        for &(x, y, z) in &spots {
+            assert!(world.set_block(x, y, z, BlockType::Stone).unwrap());
+        }
+
+        for &(x, y, z) in &spots {
+            assert_eq!(world.get_block(x, y, z).unwrap(), BlockType::Stone, "({}, {}, {})", x, y, z);
+
+            let chunk = world.chunks().get_chunk(ChunkPos::from_block_pos(x, z)).unwrap();
+            let (lx, lz) = (x.rem_euclid(16) as usize, z.rem_euclid(16) as usize);
+            assert_eq!(chunk.get_block(lx, Chunk::local_y(y).unwrap(), lz), Some(BlockType::Stone));
+        }
+
+        // Neighbours across the boundary were left alone
+        assert_eq!(world.get_block(1, 64, 0).unwrap(), BlockType::Air);
+        assert_eq!(world.get_block(-2, 64, -1).unwrap(), BlockType::Air);
+        assert_eq!(world.get_block(17, 64, 0).unwrap(), BlockType::Air);
+
+        // Below zero and above the old 256 block ceiling are inside the world
+        for y in [WORLD_MIN_Y, -1, 300] {
+            assert!(world.set_block(3, y, 3, BlockType::Stone).unwrap());
+            assert_eq!(world.get_block(3, y, 3).unwrap(), BlockType::Stone);
+        }
+
+        // Outside the world's height
+        let top = WORLD_MIN_Y + TERRAIN_CHUNK_HEIGHT as i32;
+        assert!(!world.set_block(0, WORLD_MIN_Y - 1, 0, BlockType::Stone).unwrap());
+        assert!(!world.set_block(0, top, 0, BlockType::Stone).unwrap());
+        assert_eq!(world.get_block(0, WORLD_MIN_Y - 1, 0).unwrap(), BlockType::Air);
+
+        let _ = std::fs::remove_dir_all(&world_dir);
+    }
+
+    #[test]
+    fn test_get_block_reads_generated_terrain() {
+        let (world, world_dir) = test_world(Arc::new(FlatWorldGenerator::default()));
+
+        // Flat layers stack up from the bottom of the world
+        let bottom = WORLD_MIN_Y;
+        for (x, z) in [(0, 0), (-40, 17), (1000, -1000)] {
+            assert_eq!(world.get_block(x, bottom, z).unwrap(), BlockType::Bedrock);
+            assert_eq!(world.get_block(x, bottom + 3, z).unwrap(), BlockType::Grass);
+            assert_eq!(world.get_block(x, bottom + 4, z).unwrap(), BlockType::Air);
+        }
+
+        world.set_block(-40, bottom + 3, 17, BlockType::Sand).unwrap();
+        assert_eq!(world.get_block(-40, bottom + 3, 17).unwrap(), BlockType::Sand);
+        assert_eq!(world.get_block(-39, bottom + 3, 17).unwrap(), BlockType::Grass);
+
+        let _ = std::fs::remove_dir_all(&world_dir);
+    }
+
+    #[test]
+    fn test_time_persists_across_restarts() {
+        let (world, world_dir) = test_world(Arc::new(VoidWorldGenerator));
+        world.set_time_of_day(13_000);
+        for _ in 0..40 {
+            world.tick();
+        }
+        world.save().unwrap();
+
+        let chunks = ChunkStorage::with_world_dir(
+            world_dir.clone(),
+            Arc::new(VoidWorldGenerator),
+            Arc::new(ChunkGenThreadPool::new()),
+        )
+        .unwrap();
+        let reloaded = World::new("test", Arc::new(chunks), 7, Vec3::new(0.5, 65.0, 0.5));
+        assert_eq!(reloaded.time_of_day(), 0);
+        reloaded.apply_level_data(&LevelData::load(&world_dir).unwrap().unwrap());
+        assert_eq!(reloaded.time_of_day(), 13_040);
+        assert_eq!(reloaded.world_age(), 40);
+
+        let _ = std::fs::remove_dir_all(&world_dir);
+    }
+
+    #[test]
+    fn test_spawn_and_border_persist_across_restarts() {
+        let (world, world_dir) = test_world(Arc::new(VoidWorldGenerator));
+        let border = WorldBorder {
+            center:   (32.0, -32.0),
+            diameter: 500.0,
+        };
+        world.set_spawn(Vec3::new(100.5, 80.0, -20.5));
+        world.set_border(border);
+        world.save().unwrap();
+
+        let level = LevelData::load(&world_dir).unwrap().unwrap();
+        assert_eq!(level.seed, Some(7));
+
+        let chunks = ChunkStorage::with_world_dir(
+            world_dir.clone(),
+            Arc::new(VoidWorldGenerator),
+            Arc::new(ChunkGenThreadPool::new()),
+        )
+        .unwrap();
+        let reloaded = World::new("test", Arc::new(chunks), 7, Vec3::new(0.5, 65.0, 0.5));
+        reloaded.apply_level_data(&level);
+        assert_eq!(reloaded.spawn(), Vec3::new(100.5, 80.0, -20.5));
+        assert_eq!(reloaded.border(), border);
+
+        let _ = std::fs::remove_dir_all(&world_dir);
+    }
+
+    #[test]
+    fn test_tick_and_border() {
+        let (world, world_dir) = test_world(Arc::new(VoidWorldGenerator));
+        for _ in 0..DAY_LENGTH + 5 {
+            world.tick();
+        }
+        assert_eq!(world.time_of_day(), 5);
+        assert_eq!(world.world_age(), DAY_LENGTH + 5);
+
+        assert!(world.border().contains(29_999_983.0, -29_999_983.0));
+        world.set_border(WorldBorder {
+            center:   (100.0, 0.0),
+            diameter: 20.0,
+        });
+        assert!(world.border().contains(110.0, -10.0));
+        assert!(!world.border().contains(89.0, 0.0));
+
+        let _ = std::fs::remove_dir_all(&world_dir);
+    }
+
+    #[test]
+    fn test_block_break_veto_keeps_the_block() {
+        let (world, world_dir) = test_world(Arc::new(VoidWorldGenerator));
+        let events = EventBus::new();
+        let player = Uuid::new_v4();
+        world.set_block(0, 64, 0, BlockType::Stone).unwrap();
+        world.set_block(1, 64, 0, BlockType::Dirt).unwrap();
+
+        // Protect stone only
+        events.subscribe(EventKind::BlockBreak, |event| {
+            match event {
+                Event::BlockBreak { block, .. } if block.block() == BlockType::Stone => EventResult::Cancel,
+                _ => EventResult::Continue,
+            }
+        });
+
+        assert!(!world.break_block(&events, player, 0, 64, 0).unwrap());
+        assert_eq!(world.get_block(0, 64, 0).unwrap(), BlockType::Stone);
+
+        assert!(world.break_block(&events, player, 1, 64, 0).unwrap());
+        assert_eq!(world.get_block(1, 64, 0).unwrap(), BlockType::Air);
+
+        // Placing is its own event, so the break listener doesn't stop it
+        assert!(
+            world
+                .place_block(&events, player, 1, 64, 0, BlockType::Sand)
+                .unwrap()
+        );
+        assert_eq!(world.get_block(1, 64, 0).unwrap(), BlockType::Sand);
+
+        let _ = std::fs::remove_dir_all(&world_dir);
     }
 }
@@ -1,4 +1,11 @@
+mod level;
 mod minecraft_world;
 mod region;
+mod time;
+mod weather;
 
+pub use level::LevelData;
+pub use minecraft_world::World;
 pub use region::{Region, RegionPos};
+pub use time::TIME_UPDATE_INTERVAL;
+pub use weather::Weather;
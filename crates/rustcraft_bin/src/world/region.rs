@@ -1,12 +1,19 @@
 #![allow(dead_code)]
 use std::ops::Neg;
+use std::str::FromStr;
 
-use anyhow::Result;
+use anyhow::{Error, Result, anyhow};
 use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 
-use crate::consts::{WORLD_MAX_CHUNKS, WORLD_REGION_SIZE};
-use crate::terrain::{BlockType, Chunk, ChunkPos};
+use crate::consts::{
+    TERRAIN_CHUNK_HEIGHT,
+    TERRAIN_CHUNK_SIZE,
+    WORLD_MAX_CHUNKS,
+    WORLD_MIN_Y,
+    WORLD_REGION_SIZE,
+};
+use crate::terrain::{BlockState, BlockType, Chunk, ChunkPos, parse_xz};
 
 // const WORLD_REGION_SIZE: i32 = 32;
 // const WORLD_MAX_CHUNKS: i32 = 10240;
@@ -26,6 +33,16 @@ impl From<ChunkPos> for RegionPos {
     }
 }
 
+/// Region coordinates as `x z` or `x,z`, the same way `ChunkPos` parses
+impl FromStr for RegionPos {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (x, z) = parse_xz(s).map_err(|e| anyhow!("Invalid region position {:?}: {}", s, e))?;
+        Ok(Self::new(x, z))
+    }
+}
+
 impl RegionPos {
     pub fn new(x: i32, z: i32) -> Self {
         Self { x, z }
@@ -59,6 +76,14 @@ impl RegionPos {
         }
     }
 
+    /// Every chunk position the region covers, in the same z-major order as `chunk_offset`
+    pub fn iter_chunk_positions(&self) -> impl Iterator<Item = ChunkPos> {
+        let (min_x, min_z) = self.min_chunk();
+        (0..WORLD_REGION_SIZE).flat_map(move |local_z| {
+            (0..WORLD_REGION_SIZE).map(move |local_x| ChunkPos::new(min_x + local_x, min_z + local_z))
+        })
+    }
+
     pub fn filename(&self) -> String {
         let (min_x, min_z) = self.min_chunk();
         let (max_x, max_z) = self.max_chunk();
@@ -104,52 +129,136 @@ impl RegionPos {
     }
 }
 
-#[derive(Serialize, Deserialize)]
+/// Region files start with this magic and a big-endian `u32` format version
+const REGION_MAGIC: &[u8; 4] = b"RCRG";
+/// 1: headerless flat block arrays, 2: palette + run-length encoded chunks,
+/// 3: as 2 but `TERRAIN_CHUNK_HEIGHT` tall from `WORLD_MIN_Y`
+pub const REGION_FORMAT_VERSION: u32 = 3;
+
+/// Versions 1 and 2 stored 256 blocks of height from Y 0
+const LEGACY_MIN_Y: i32 = 0;
+const LEGACY_CHUNK_HEIGHT: usize = 256;
+
+/// Blocks per chunk, in the `y`, `x`, `z` order runs are encoded in
+const CHUNK_VOLUME: usize = TERRAIN_CHUNK_HEIGHT * TERRAIN_CHUNK_SIZE * TERRAIN_CHUNK_SIZE;
+
+/// Block `i` of a `y`, `x`, `z` ordered chunk that starts at world `min_y`, as chunk-local
+/// coordinates of the current world height; `None` if it falls outside the world
+fn unflatten(i: usize, min_y: i32) -> Option<(usize, usize, usize)> {
+    let y = (i / (TERRAIN_CHUNK_SIZE * TERRAIN_CHUNK_SIZE)) as i32 + min_y;
+    let local_y = Chunk::local_y(y)?;
+    Some((i / TERRAIN_CHUNK_SIZE % TERRAIN_CHUNK_SIZE, local_y, i % TERRAIN_CHUNK_SIZE))
+}
+
+/// On-disk chunk: the distinct block states (packed by `BlockState::to_u16`), then runs of
+/// `(palette index, length)`
+/// A mostly-air chunk is a handful of runs instead of 64K block ids
+#[derive(Debug, Serialize, Deserialize)]
 pub struct SerializedChunk {
-    pub pos:    (i32, i32),
-    pub blocks: Vec<u16>,
+    pub pos:     (i32, i32),
+    pub palette: Vec<u16>,
+    pub runs:    Vec<(u16, u16)>,
 }
 
 impl SerializedChunk {
     pub fn from_chunk(chunk: &Chunk) -> Self {
-        let mut blocks = Vec::with_capacity(16 * 256 * 16);
+        let mut palette: Vec<u16> = Vec::new();
+        let mut runs: Vec<(u16, u16)> = Vec::new();
+
+        for y in 0..TERRAIN_CHUNK_HEIGHT {
+            for x in 0..TERRAIN_CHUNK_SIZE {
+                for z in 0..TERRAIN_CHUNK_SIZE {
+                    let block = chunk.get_block_state(x, y, z).map_or(0, BlockState::to_u16);
+                    let index = match palette.iter().position(|&id| id == block) {
+                        Some(index) => index as u16,
+                        None => {
+                            palette.push(block);
+                            (palette.len() - 1) as u16
+                        }
+                    };
 
-        for y in 0..256 {
-            for x in 0..16 {
-                for z in 0..16 {
-                    let block = chunk.get_block(x, y, z).map(|b| b as u16).unwrap_or(0);
-                    blocks.push(block);
+                    match runs.last_mut() {
+                        Some((last, len)) if *last == index && *len < u16::MAX => *len += 1,
+                        _ => runs.push((index, 1)),
+                    }
                 }
             }
         }
 
         Self {
             pos: (chunk.pos.x, chunk.pos.z),
-            blocks,
+            palette,
+            runs,
         }
     }
 
     pub fn to_chunk(&self) -> Result<Chunk> {
+        self.to_chunk_from(WORLD_MIN_Y, TERRAIN_CHUNK_HEIGHT)
+    }
+
+    /// Decode runs written for a world `height` blocks tall starting at `min_y`
+    fn to_chunk_from(&self, min_y: i32, height: usize) -> Result<Chunk> {
+        let volume = height * TERRAIN_CHUNK_SIZE * TERRAIN_CHUNK_SIZE;
         let mut chunk = Chunk::new(ChunkPos::new(self.pos.0, self.pos.1));
+        let mut offset = 0;
+
+        for &(index, len) in &self.runs {
+            let id = *self
+                .palette
+                .get(index as usize)
+                .ok_or_else(|| anyhow!("Palette index {} out of range in chunk {:?}", index, self.pos))?;
+            let end = offset + len as usize;
+            if end > volume {
+                return Err(anyhow!("Chunk {:?} has more than {} blocks", self.pos, volume));
+            }
 
-        let mut idx = 0;
-        for y in 0..256 {
-            for x in 0..16 {
-                for z in 0..16 {
-                    if idx < self.blocks.len() {
-                        if let Some(block_type) = BlockType::from_u16(self.blocks[idx]) {
-                            chunk.set_block(x, y, z, block_type);
-                        }
-                        idx += 1;
-                    }
+            // Chunks start as air, and unknown ids load as air like before
+            if let Some(block) = BlockState::from_u16(id).filter(|b| !b.is_air()) {
+                for (x, y, z) in (offset..end).filter_map(|i| unflatten(i, min_y)) {
+                    chunk.set_block(x, y, z, block);
                 }
             }
+            offset = end;
         }
 
+        if offset != volume {
+            return Err(anyhow!("Chunk {:?} has {} of {} blocks", self.pos, offset, volume));
+        }
         Ok(chunk)
     }
 }
 
+/// Version 1 chunk, read so existing worlds still load
+#[derive(Serialize, Deserialize)]
+struct LegacySerializedChunk {
+    pos:    (i32, i32),
+    blocks: Vec<u16>,
+}
+
+impl LegacySerializedChunk {
+    fn to_chunk(&self) -> Chunk {
+        let mut chunk = Chunk::new(ChunkPos::new(self.pos.0, self.pos.1));
+
+        let volume = LEGACY_CHUNK_HEIGHT * TERRAIN_CHUNK_SIZE * TERRAIN_CHUNK_SIZE;
+        for (i, &id) in self.blocks.iter().take(volume).enumerate() {
+            if let Some(block) = BlockType::from_u16(id).filter(|&b| b != BlockType::Air)
+                && let Some((x, y, z)) = unflatten(i, LEGACY_MIN_Y)
+            {
+                chunk.set_block(x, y, z, block);
+            }
+        }
+
+        chunk
+    }
+}
+
+/// Body of a region file after the magic and version
+#[derive(Serialize, Deserialize)]
+struct RegionFile {
+    pos:    (i32, i32),
+    chunks: Vec<SerializedChunk>,
+}
+
 pub struct Region {
     pos:      RegionPos,
     chunks:   Vec<Option<Chunk>>,
@@ -188,6 +297,14 @@ impl Region {
         self.chunks.iter().filter_map(|c| c.as_ref())
     }
 
+    /// Positions of the chunks actually present in this region
+    pub fn occupied_positions(&self) -> impl Iterator<Item = ChunkPos> + '_ {
+        self.pos
+            .iter_chunk_positions()
+            .zip(self.chunks.iter())
+            .filter_map(|(pos, chunk)| chunk.as_ref().map(|_| pos))
+    }
+
     /// `rayon` parallel iterator,
     /// uses chunks.par_iter().filter_map(...)
     pub fn par_chunks_iter(&self) -> impl ParallelIterator<Item = &Chunk> {
@@ -204,20 +321,273 @@ impl Region {
     }
 
     pub fn serialize(&self) -> Vec<u8> {
-        let serialized: Vec<SerializedChunk> =
-            self.par_chunks_iter().map(SerializedChunk::from_chunk).collect();
-        bincode::serialize(&serialized).unwrap_or_default()
+        let file = RegionFile {
+            pos:    (self.pos.x, self.pos.z),
+            chunks: self.par_chunks_iter().map(SerializedChunk::from_chunk).collect(),
+        };
+
+        let mut data = Vec::new();
+        data.extend_from_slice(REGION_MAGIC);
+        data.extend_from_slice(&REGION_FORMAT_VERSION.to_be_bytes());
+        match bincode::serialize(&file) {
+            Ok(body) => data.extend_from_slice(&body),
+            Err(_) => return Vec::new(),
+        }
+        data
     }
 
     pub fn deserialize(data: &[u8]) -> Result<Self> {
-        let serialized: Vec<SerializedChunk> = bincode::deserialize(data)?;
-        let mut region = Self::new(RegionPos::new(0, 0)); // Will be set properly
+        let Some(rest) = data.strip_prefix(REGION_MAGIC) else {
+            return Self::deserialize_legacy(data);
+        };
+        let (version, body) = rest
+            .split_first_chunk::<4>()
+            .ok_or_else(|| anyhow!("Region file truncated before its version"))?;
+        let (min_y, height) = match u32::from_be_bytes(*version) {
+            REGION_FORMAT_VERSION => (WORLD_MIN_Y, TERRAIN_CHUNK_HEIGHT),
+            2 => (LEGACY_MIN_Y, LEGACY_CHUNK_HEIGHT),
+            version => return Err(anyhow!("Unsupported region format version {}", version)),
+        };
+
+        let file: RegionFile = bincode::deserialize(body)?;
+        let mut region = Self::new(RegionPos::new(file.pos.0, file.pos.1));
+        for ser_chunk in file.chunks {
+            region.insert(ser_chunk.to_chunk_from(min_y, height)?);
+        }
+
+        Ok(region)
+    }
+
+    /// Version 1 files are a bare list of flat chunks; the region is found from the chunks
+    fn deserialize_legacy(data: &[u8]) -> Result<Self> {
+        let serialized: Vec<LegacySerializedChunk> = bincode::deserialize(data)?;
+        let pos = serialized
+            .first()
+            .map_or(RegionPos::new(0, 0), |c| RegionPos::from_chunk(c.pos.0, c.pos.1));
+        let mut region = Self::new(pos);
 
         for ser_chunk in serialized {
-            let chunk = ser_chunk.to_chunk()?;
-            region.insert(chunk);
+            region.insert(ser_chunk.to_chunk());
         }
 
         Ok(region)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::terrain::Axis;
+
+    #[test]
+    fn test_region_pos_from_str() {
+        assert_eq!("0 -1".parse::<RegionPos>().unwrap(), RegionPos::new(0, -1));
+        assert_eq!("-4,7".parse::<RegionPos>().unwrap(), RegionPos::new(-4, 7));
+        for bad in ["", "1", "a b", "1 2 3"] {
+            assert!(bad.parse::<RegionPos>().is_err(), "{:?}", bad);
+        }
+    }
+
+    #[test]
+    fn test_iter_chunk_positions_covers_region() {
+        let region_pos = RegionPos::new(-1, 2);
+        let (min_x, min_z) = region_pos.min_chunk();
+        let (max_x, max_z) = region_pos.max_chunk();
+        let positions: Vec<ChunkPos> = region_pos.iter_chunk_positions().collect();
+
+        assert_eq!(positions.len(), 1024);
+        assert!(
+            positions
+                .iter()
+                .all(|p| (min_x..=max_x).contains(&p.x) && (min_z..=max_z).contains(&p.z))
+        );
+        for (idx, pos) in positions.iter().enumerate() {
+            assert_eq!(region_pos.chunk_offset(pos.x, pos.z), Some(idx));
+        }
+    }
+
+    #[test]
+    fn test_occupied_positions() {
+        let region_pos = RegionPos::new(0, 0);
+        let mut region = Region::new(region_pos);
+        assert_eq!(region.occupied_positions().count(), 0);
+
+        region.insert(Chunk::new(ChunkPos::new(3, 1)));
+        region.insert(Chunk::new(ChunkPos::new(31, 31)));
+        let occupied: Vec<ChunkPos> = region.occupied_positions().collect();
+        assert_eq!(occupied, vec![ChunkPos::new(3, 1), ChunkPos::new(31, 31)]);
+    }
+
+    fn sample_chunk(pos: ChunkPos) -> Chunk {
+        let mut chunk = Chunk::new(pos);
+        chunk.fill((0, 0, 0), (15, 59, 15), BlockType::Stone);
+        chunk.fill((0, 60, 0), (15, 62, 15), BlockType::Dirt);
+        chunk.fill((0, 63, 0), (15, 63, 15), BlockType::Grass);
+        chunk.fill((4, 64, 4), (4, 68, 4), BlockType::OakLog);
+        chunk.set_block(9, 63, 2, BlockType::Water);
+        chunk
+    }
+
+    fn assert_same_blocks(a: &Chunk, b: &Chunk) {
+        for x in 0..TERRAIN_CHUNK_SIZE {
+            for z in 0..TERRAIN_CHUNK_SIZE {
+                assert!(a.get_column(x, z).unwrap().eq(b.get_column(x, z).unwrap()), "column ({}, {})", x, z);
+            }
+        }
+    }
+
+    #[test]
+    fn test_serialized_chunk_round_trip() {
+        let chunk = sample_chunk(ChunkPos::new(-3, 7));
+        let serialized = SerializedChunk::from_chunk(&chunk);
+
+        assert_eq!(serialized.palette.len(), 6);
+        let restored = serialized.to_chunk().unwrap();
+        assert_eq!(restored.pos, chunk.pos);
+        assert_same_blocks(&chunk, &restored);
+    }
+
+    #[test]
+    fn test_block_states_survive_serialization() {
+        let mut chunk = sample_chunk(ChunkPos::new(0, 0));
+        let sideways = BlockState::log(BlockType::OakLog, Axis::Z);
+        let flowing = BlockState::fluid(BlockType::Water, 5);
+        chunk.set_block(10, 70, 10, sideways);
+        chunk.set_block(11, 70, 10, flowing);
+
+        let restored = SerializedChunk::from_chunk(&chunk).to_chunk().unwrap();
+        assert_eq!(restored.get_block_state(10, 70, 10), Some(sideways));
+        assert_eq!(restored.get_block_state(11, 70, 10), Some(flowing));
+        assert_eq!(restored.get_block_state(4, 64, 4), Some(BlockState::from(BlockType::OakLog)));
+    }
+
+    #[test]
+    fn test_mostly_air_chunk_is_small() {
+        let bytes =
+            bincode::serialize(&SerializedChunk::from_chunk(&sample_chunk(ChunkPos::new(0, 0)))).unwrap();
+
+        // The flat format spent 2 bytes on each of the 65536 blocks
+        assert!(bytes.len() < 1024, "{} bytes", bytes.len());
+    }
+
+    #[test]
+    fn test_corrupt_runs_are_rejected() {
+        let mut serialized = SerializedChunk::from_chunk(&sample_chunk(ChunkPos::new(0, 0)));
+        serialized.runs.pop();
+        assert!(serialized.to_chunk().is_err());
+
+        let mut serialized = SerializedChunk::from_chunk(&sample_chunk(ChunkPos::new(0, 0)));
+        serialized.runs[0].0 = 99;
+        assert!(serialized.to_chunk().is_err());
+    }
+
+    #[test]
+    fn test_region_round_trip_keeps_position() {
+        let region_pos = RegionPos::new(-2, 1);
+        let (min_x, min_z) = region_pos.min_chunk();
+        let mut region = Region::new(region_pos);
+        region.insert(sample_chunk(ChunkPos::new(min_x + 5, min_z + 31)));
+
+        let data = region.serialize();
+        assert_eq!(&data[..4], REGION_MAGIC);
+        assert_eq!(&data[4..8], &REGION_FORMAT_VERSION.to_be_bytes());
+
+        let restored = Region::deserialize(&data).unwrap();
+        assert_eq!(restored.pos, region_pos);
+        let chunk = restored
+            .get(min_x + 5, min_z + 31)
+            .expect("chunk survives the round trip");
+        assert_same_blocks(chunk, &sample_chunk(chunk.pos));
+    }
+
+    /// `sample_chunk` moved up by `-WORLD_MIN_Y`, so its blocks sit at the world Y they had when
+    /// chunks started at Y 0
+    fn sample_chunk_from_y0(pos: ChunkPos) -> Chunk {
+        let original = sample_chunk(pos);
+        let base = Chunk::local_y(LEGACY_MIN_Y).unwrap();
+        let mut chunk = Chunk::new(pos);
+        for x in 0..TERRAIN_CHUNK_SIZE {
+            for z in 0..TERRAIN_CHUNK_SIZE {
+                for (y, block) in original
+                    .get_column(x, z)
+                    .unwrap()
+                    .enumerate()
+                    .take(LEGACY_CHUNK_HEIGHT)
+                {
+                    chunk.set_block(x, base + y, z, block);
+                }
+            }
+        }
+        chunk
+    }
+
+    #[test]
+    fn test_legacy_region_still_loads() {
+        let chunk = sample_chunk(ChunkPos::new(40, -1));
+        let mut blocks = Vec::new();
+        for y in 0..LEGACY_CHUNK_HEIGHT {
+            for x in 0..TERRAIN_CHUNK_SIZE {
+                for z in 0..TERRAIN_CHUNK_SIZE {
+                    blocks.push(chunk.get_block(x, y, z).unwrap() as u16);
+                }
+            }
+        }
+        let legacy = bincode::serialize(&vec![LegacySerializedChunk {
+            pos: (40, -1),
+            blocks,
+        }])
+        .unwrap();
+
+        let region = Region::deserialize(&legacy).unwrap();
+        assert_eq!(region.pos, RegionPos::new(1, -1));
+        assert_same_blocks(region.get(40, -1).unwrap(), &sample_chunk_from_y0(chunk.pos));
+    }
+
+    #[test]
+    fn test_version_2_region_keeps_world_heights() {
+        // A 256 block tall version 2 chunk: stone up to Y 59, air above
+        let layer = (TERRAIN_CHUNK_SIZE * TERRAIN_CHUNK_SIZE) as u16;
+        let serialized = SerializedChunk {
+            pos:     (2, 3),
+            palette: vec![BlockType::Stone as u16, BlockType::Air as u16],
+            runs:    vec![(0, 60 * layer), (1, (LEGACY_CHUNK_HEIGHT as u16 - 60) * layer)],
+        };
+
+        let mut data = REGION_MAGIC.to_vec();
+        data.extend_from_slice(&2u32.to_be_bytes());
+        data.extend_from_slice(
+            &bincode::serialize(&RegionFile {
+                pos:    (0, 0),
+                chunks: vec![serialized],
+            })
+            .unwrap(),
+        );
+
+        let region = Region::deserialize(&data).unwrap();
+        let chunk = region.get(2, 3).unwrap();
+        for y in [WORLD_MIN_Y, -1, 60, 255, 300] {
+            assert_eq!(chunk.get_block(5, Chunk::local_y(y).unwrap(), 5), Some(BlockType::Air), "y {}", y);
+        }
+        for y in [0, 30, 59] {
+            assert_eq!(chunk.get_block(5, Chunk::local_y(y).unwrap(), 5), Some(BlockType::Stone), "y {}", y);
+        }
+    }
+
+    #[test]
+    fn test_blocks_above_256_round_trip() {
+        let mut chunk = sample_chunk(ChunkPos::new(0, 0));
+        let high = Chunk::local_y(300).unwrap();
+        chunk.set_block(7, high, 8, BlockType::Gravel);
+        chunk.set_block(0, TERRAIN_CHUNK_HEIGHT - 1, 0, BlockType::Sand);
+
+        let restored = SerializedChunk::from_chunk(&chunk).to_chunk().unwrap();
+        assert_eq!(restored.get_block(7, high, 8), Some(BlockType::Gravel));
+        assert_eq!(restored.get_block(0, TERRAIN_CHUNK_HEIGHT - 1, 0), Some(BlockType::Sand));
+        assert_same_blocks(&chunk, &restored);
+
+        let mut region = Region::new(RegionPos::new(0, 0));
+        region.insert(chunk);
+        let region = Region::deserialize(&region.serialize()).unwrap();
+        assert_eq!(region.get(0, 0).unwrap().get_block(7, high, 8), Some(BlockType::Gravel));
+    }
+}
@@ -0,0 +1,112 @@
+use crate::network::{ByteWritable, ClientboundPlay, PacketWriter, write_varint};
+
+/// Ticks in one Minecraft day
+pub const DAY_LENGTH: u64 = 24_000;
+/// Ticks between Update Time packets; clients interpolate the sky in between
+pub const TIME_UPDATE_INTERVAL: u64 = 20;
+
+/// World age and time of day, advanced once per game tick
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WorldTime {
+    /// Ticks the world has existed, never reset
+    pub world_age:      u64,
+    /// Ticks into the current day, `0..DAY_LENGTH`; 0 is sunrise, 6000 noon
+    pub time_of_day:    u64,
+    /// `doDaylightCycle`: when false the sun stays put while the world keeps ageing
+    pub daylight_cycle: bool,
+}
+
+impl Default for WorldTime {
+    fn default() -> Self {
+        Self {
+            world_age:      0,
+            time_of_day:    0,
+            daylight_cycle: true,
+        }
+    }
+}
+
+impl WorldTime {
+    pub fn tick(&mut self) {
+        self.world_age += 1;
+        if self.daylight_cycle {
+            self.time_of_day = (self.time_of_day + 1) % DAY_LENGTH;
+        }
+    }
+
+    pub fn set_time_of_day(&mut self, time: u64) {
+        self.time_of_day = time % DAY_LENGTH;
+    }
+
+    /// Update Time: `[world age: Long][time of day: Long][time of day increasing: Boolean]`
+    pub fn update_frame(&self) -> Vec<u8> {
+        let mut writer = PacketWriter::new();
+        writer.write_long(self.world_age as i64);
+        writer.write_long(self.time_of_day as i64);
+        writer.write_bool(self.daylight_cycle);
+        let data = writer.finish();
+
+        let packet_id = write_varint(ClientboundPlay::UpdateTime.id());
+        let mut frame = write_varint((packet_id.len() + data.len()) as i32);
+        frame.extend_from_slice(&packet_id);
+        frame.extend_from_slice(&data);
+        frame
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::PacketReader;
+
+    #[test]
+    fn test_ticks_advance_and_wrap() {
+        let mut time = WorldTime::default();
+        for _ in 0..6_000 {
+            time.tick();
+        }
+        assert_eq!(time.time_of_day, 6_000);
+        assert_eq!(time.world_age, 6_000);
+
+        for _ in 0..DAY_LENGTH - 6_000 + 250 {
+            time.tick();
+        }
+        assert_eq!(time.time_of_day, 250);
+        assert_eq!(time.world_age, DAY_LENGTH + 250);
+
+        time.set_time_of_day(DAY_LENGTH * 3 + 13_000);
+        assert_eq!(time.time_of_day, 13_000);
+    }
+
+    #[test]
+    fn test_daylight_cycle_off_freezes_the_sun() {
+        let mut time = WorldTime {
+            daylight_cycle: false,
+            time_of_day: 18_000,
+            ..WorldTime::default()
+        };
+        for _ in 0..100 {
+            time.tick();
+        }
+        assert_eq!(time.time_of_day, 18_000);
+        assert_eq!(time.world_age, 100);
+    }
+
+    #[test]
+    fn test_update_time_frame() {
+        let time = WorldTime {
+            world_age:      48_123,
+            time_of_day:    123,
+            daylight_cycle: true,
+        };
+        let frame = time.update_frame();
+        assert_eq!(frame[0] as usize, frame.len() - 1);
+        assert_eq!(frame[1] as i32, ClientboundPlay::UpdateTime.id());
+
+        let mut reader = PacketReader::new(&frame[2..]);
+        assert_eq!(reader.read_long().unwrap(), 48_123);
+        assert_eq!(reader.read_long().unwrap(), 123);
+        assert!(reader.read_bool().unwrap());
+        assert_eq!(reader.remaining(), 0);
+    }
+}
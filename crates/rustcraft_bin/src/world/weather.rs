@@ -0,0 +1,207 @@
+#![allow(dead_code)]
+
+use std::fmt;
+use std::ops::Range;
+use std::str::FromStr;
+
+use anyhow::{Error, anyhow};
+
+use crate::player::{GameEvent, game_event_frame};
+use crate::terrain::ChunkRng;
+
+/// How long each kind of weather lasts before it changes by itself, in ticks (vanilla's ranges)
+const CLEAR_DURATION: Range<i32> = 12_000..180_000;
+const RAIN_DURATION: Range<i32> = 12_000..24_000;
+const THUNDER_DURATION: Range<i32> = 3_600..15_600;
+/// Chance that clear skies break into a thunderstorm rather than plain rain
+const THUNDER_CHANCE: f64 = 0.25;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Weather {
+    #[default]
+    Clear,
+    Rain,
+    Thunder,
+}
+
+impl Weather {
+    pub fn name(self) -> &'static str {
+        match self {
+            Weather::Clear => "clear",
+            Weather::Rain => "rain",
+            Weather::Thunder => "thunder",
+        }
+    }
+
+    pub fn is_raining(self) -> bool {
+        self != Weather::Clear
+    }
+
+    /// Game Event frames that switch a client over to this weather
+    pub fn frames(self) -> Vec<Vec<u8>> {
+        let (start, rain, thunder) = match self {
+            Weather::Clear => (GameEvent::EndRaining, 0.0, 0.0),
+            Weather::Rain => (GameEvent::BeginRaining, 1.0, 0.0),
+            Weather::Thunder => (GameEvent::BeginRaining, 1.0, 1.0),
+        };
+        vec![
+            game_event_frame(start, 0.0),
+            game_event_frame(GameEvent::RainLevelChange, rain),
+            game_event_frame(GameEvent::ThunderLevelChange, thunder),
+        ]
+    }
+
+    fn duration(self) -> Range<i32> {
+        match self {
+            Weather::Clear => CLEAR_DURATION,
+            Weather::Rain => RAIN_DURATION,
+            Weather::Thunder => THUNDER_DURATION,
+        }
+    }
+}
+
+impl fmt::Display for Weather {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Weather {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "clear" => Ok(Weather::Clear),
+            "rain" => Ok(Weather::Rain),
+            "thunder" => Ok(Weather::Thunder),
+            other => Err(anyhow!("Unknown weather: {}", other)),
+        }
+    }
+}
+
+/// Current weather and the ticks left until it changes
+#[derive(Debug, Clone)]
+pub struct WeatherState {
+    weather:   Weather,
+    remaining: u64,
+    rng:       ChunkRng,
+}
+
+impl WeatherState {
+    /// Clear skies for a random while; `seed` makes the forecast repeatable
+    pub fn new(seed: u64) -> Self {
+        let mut state = Self {
+            weather:   Weather::Clear,
+            remaining: 0,
+            rng:       ChunkRng::new(seed, 0, 0),
+        };
+        state.set(Weather::Clear);
+        state
+    }
+
+    pub fn weather(&self) -> Weather {
+        self.weather
+    }
+
+    /// Ticks until the weather changes by itself
+    pub fn remaining(&self) -> u64 {
+        self.remaining
+    }
+
+    /// Switch to `weather` for a random duration
+    pub fn set(&mut self, weather: Weather) {
+        self.weather = weather;
+        self.remaining = self.rng.gen_range(weather.duration()) as u64;
+    }
+
+    /// Count down one tick; returns the new weather when it changes
+    /// Clear turns to rain or thunder, thunder calms to rain and rain clears up
+    pub fn tick(&mut self) -> Option<Weather> {
+        self.remaining = self.remaining.saturating_sub(1);
+        if self.remaining > 0 {
+            return None;
+        }
+
+        let next = match self.weather {
+            Weather::Clear if self.rng.chance(THUNDER_CHANCE) => Weather::Thunder,
+            Weather::Clear => Weather::Rain,
+            Weather::Rain => Weather::Clear,
+            Weather::Thunder => Weather::Rain,
+        };
+        self.set(next);
+        Some(next)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::{ClientboundPlay, PacketReader};
+
+    /// Tick until the weather changes
+    fn run_out(state: &mut WeatherState) -> Weather {
+        let ticks = state.remaining();
+        for _ in 1..ticks {
+            assert_eq!(state.tick(), None);
+        }
+        state
+            .tick()
+            .expect("weather should change once the duration runs out")
+    }
+
+    #[test]
+    fn test_weather_transitions() {
+        let mut state = WeatherState::new(42);
+        assert_eq!(state.weather(), Weather::Clear);
+        assert!(CLEAR_DURATION.contains(&(state.remaining() as i32)));
+
+        state.set(Weather::Rain);
+        assert!(RAIN_DURATION.contains(&(state.remaining() as i32)));
+        assert_eq!(run_out(&mut state), Weather::Clear);
+        assert_eq!(state.weather(), Weather::Clear);
+
+        state.set(Weather::Thunder);
+        assert!(THUNDER_DURATION.contains(&(state.remaining() as i32)));
+        assert_eq!(run_out(&mut state), Weather::Rain);
+        assert_eq!(run_out(&mut state), Weather::Clear);
+
+        // Clear skies always break into some kind of rain, and both kinds turn up
+        let mut seen = Vec::new();
+        for seed in 0..64 {
+            let mut state = WeatherState::new(seed);
+            let next = run_out(&mut state);
+            assert!(next.is_raining());
+            seen.push(next);
+        }
+        assert!(seen.contains(&Weather::Rain));
+        assert!(seen.contains(&Weather::Thunder));
+    }
+
+    #[test]
+    fn test_parse_weather() {
+        for weather in [Weather::Clear, Weather::Rain, Weather::Thunder] {
+            assert_eq!(weather.name().parse::<Weather>().unwrap(), weather);
+        }
+        assert!("snow".parse::<Weather>().is_err());
+    }
+
+    #[test]
+    fn test_weather_change_frames() {
+        let events = |weather: Weather| -> Vec<(u8, f32)> {
+            weather
+                .frames()
+                .iter()
+                .map(|frame| {
+                    assert_eq!(frame[0] as usize, frame.len() - 1);
+                    assert_eq!(frame[1] as i32, ClientboundPlay::GameEvent.id());
+                    let mut reader = PacketReader::new(&frame[2..]);
+                    (reader.read_byte().unwrap(), reader.read_float().unwrap())
+                })
+                .collect()
+        };
+
+        assert_eq!(events(Weather::Thunder), vec![(1, 0.0), (7, 1.0), (8, 1.0)]);
+        assert_eq!(events(Weather::Rain), vec![(1, 0.0), (7, 1.0), (8, 0.0)]);
+        assert_eq!(events(Weather::Clear), vec![(2, 0.0), (7, 0.0), (8, 0.0)]);
+    }
+}